@@ -175,6 +175,21 @@ impl AutoStructField {
         }
     }
 
+    fn serialize_expr(&self) -> proc_macro2::TokenStream {
+        let ident = &self.ident;
+        // Neither `CStr` nor `GenericArray` implement `Serialize` the way we want, so they are
+        // serialized as a string and a slice instead
+        let type_name = match &self.ty {
+            Type::Path(path) => path.path.segments.last().map(|segment| &segment.ident),
+            _ => None,
+        };
+        match type_name {
+            Some(name) if name == "CStr" => quote!(&*self.#ident.to_string_lossy()),
+            Some(name) if name == "GenericArray" => quote!(self.#ident.as_slice()),
+            _ => quote!(&self.#ident),
+        }
+    }
+
     fn write_type(&self) -> Option<proc_macro2::TokenStream> {
         let ty = &self.ty;
         match &self.kind {
@@ -217,6 +232,7 @@ struct DeriveOptions {
     readable: bool,
     writable: bool,
     fixed_size: bool,
+    serialize: bool,
 }
 
 impl Parse for DeriveOptions {
@@ -225,6 +241,7 @@ impl Parse for DeriveOptions {
             readable: false,
             writable: false,
             fixed_size: false,
+            serialize: false,
         };
         let idents = Punctuated::<Ident, Token![,]>::parse_terminated(input)?;
         for ident in idents {
@@ -243,6 +260,11 @@ impl Parse for DeriveOptions {
                     err(ident.span(), format!("Duplicate '{}'", ident))?;
                 }
                 options.fixed_size = true;
+            } else if ident == "Serialize" {
+                if options.serialize {
+                    err(ident.span(), format!("Duplicate '{}'", ident))?;
+                }
+                options.serialize = true;
             } else {
                 err(ident.span(), format!("Unknown option '{}'", ident))?;
             }
@@ -502,6 +524,38 @@ impl AutoStructDecl {
         }
     }
 
+    // Serializes the fields stored in the struct, leaving out the ones derived when writing
+    fn serialize_impl_tokens(&self) -> proc_macro2::TokenStream {
+        let name = &self.struct_.ident;
+        let (impl_gens, type_gens, where_clause) = self.struct_.generics.split_for_impl();
+
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .filter(|field| field.has_storage())
+            .collect();
+        let field_count = fields.len();
+        let field_names = fields.iter().map(|field| field.ident.to_string());
+        let exprs = fields.iter().map(|field| field.serialize_expr());
+
+        quote! {
+            #[automatically_derived]
+            impl #impl_gens serde::Serialize for #name #type_gens
+                #where_clause
+            {
+                fn serialize<S: serde::Serializer>(&self, __serializer__: S)
+                    -> std::result::Result<S::Ok, S::Error>
+                {
+                    use serde::ser::SerializeStruct;
+                    let mut __state__ =
+                        __serializer__.serialize_struct(stringify!(#name), #field_count)?;
+                    #(__state__.serialize_field(#field_names, #exprs)?;)*
+                    __state__.end()
+                }
+            }
+        }
+    }
+
     fn struct_and_impl_tokens(self, options: DeriveOptions) -> proc_macro2::TokenStream {
         let readable_tokens = if options.readable {
             self.readable_impl_tokens(options.fixed_size)
@@ -515,11 +569,18 @@ impl AutoStructDecl {
             proc_macro2::TokenStream::new()
         };
 
+        let serialize_tokens = if options.serialize {
+            self.serialize_impl_tokens()
+        } else {
+            proc_macro2::TokenStream::new()
+        };
+
         let struct_tokens = self.struct_.into_token_stream();
         quote! {
             #struct_tokens
             #readable_tokens
             #writable_tokens
+            #serialize_tokens
         }
     }
 }
@@ -21,6 +21,7 @@ fn main() {
     .unwrap();
 
     let mut resources: Vec<(String, String)> = vec![];
+    let mut resources_by_id = phf_codegen::Map::new();
     for line in resources_file.lines() {
         let line = line.unwrap();
         if line.is_empty() {
@@ -49,17 +50,21 @@ fn main() {
             .collect::<Vec<_>>()
             .join(", ");
 
-        let resource_data = format!(
-            "
-            r#\"resource_info_table::ResourceInfo {{
+        let resource_info = format!(
+            "resource_info_table::ResourceInfo {{
                 long_name: {:?},
                 short_name: {:?},
                 res_id: {},
                 fourcc: reader_writer::FourCC::from_bytes(b\"{}\"),
                 paks: &[{}],
-            }}\"#",
+            }}",
             long_name, short_name, res_id, res_type, pak_names_formatted
         );
+        resources_by_id.entry(
+            u32::from_str_radix(&res_id[2..], 16).unwrap(),
+            &resource_info,
+        );
+        let resource_data = format!("r#\"{}\"#", resource_info);
         if let Some(short_name) = short_name {
             resources.push((short_name.to_string(), resource_data.clone()));
         }
@@ -71,4 +76,12 @@ fn main() {
     }
     write!(&mut output_file, "{}", map_generator.build()).unwrap();
     writeln!(&mut output_file, ";").unwrap();
+
+    // Expanded by `resource_info_by_id_map!` to build the runtime lookup table
+    writeln!(
+        &mut output_file,
+        "static RESOURCES_BY_ID: &str = r##\"{}\"##;",
+        resources_by_id.build()
+    )
+    .unwrap();
 }
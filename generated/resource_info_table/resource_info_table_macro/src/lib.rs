@@ -17,3 +17,11 @@ pub fn resource_info(item: TokenStream) -> TokenStream {
             .into()
     }
 }
+
+/// Expands to a `phf::Map<u32, ResourceInfo>` of every resource, keyed by file id.
+#[proc_macro]
+pub fn resource_info_by_id_map(_item: TokenStream) -> TokenStream {
+    syn::parse_str::<proc_macro2::TokenStream>(RESOURCES_BY_ID)
+        .unwrap()
+        .into()
+}
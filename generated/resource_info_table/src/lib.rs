@@ -1,6 +1,12 @@
+// The generated tables refer to `resource_info_table::ResourceInfo`
+extern crate self as resource_info_table;
+
 use reader_writer::FourCC;
 pub use resource_info_table_macro::resource_info;
 
+static RESOURCES_BY_ID: phf::Map<u32, ResourceInfo> =
+    resource_info_table_macro::resource_info_by_id_map!();
+
 #[derive(Copy, Clone, Debug)]
 pub struct ResourceInfo {
     pub long_name: &'static str,
@@ -10,6 +16,17 @@ pub struct ResourceInfo {
     pub paks: &'static [&'static [u8]],
 }
 
+/// Looks up a resource by its file id at runtime. Prefer the `resource_info!` macro when the
+/// resource is known at compile time.
+pub fn resource_info_by_id(res_id: u32) -> Option<&'static ResourceInfo> {
+    RESOURCES_BY_ID.get(&res_id)
+}
+
+/// Iterates over every known resource, in no particular order.
+pub fn resource_info_iter() -> impl Iterator<Item = &'static ResourceInfo> {
+    RESOURCES_BY_ID.values()
+}
+
 impl<'a, 'b> From<ResourceInfo> for (&'a [&'b [u8]], u32, FourCC) {
     fn from(val: ResourceInfo) -> Self {
         (val.paks, val.res_id, val.fourcc)
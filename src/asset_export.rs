@@ -0,0 +1,578 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Write as _,
+};
+
+use image::codecs::png::PngEncoder;
+use reader_writer::{Reader, Writable};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use structs::{
+    Cmdl, Mrea, Resource, Scan, Scly, SclyProperty, Strg, Txtr, MATERIAL_FLAG_PUNCHTHROUGH,
+    MATERIAL_FLAG_SHORT_UVS, MATERIAL_FLAG_TRANSPARENT,
+};
+
+use crate::{
+    txtr_conversions::{decode_txtr_mipmap, TxtrFormatExt},
+    ResourceData,
+};
+
+/// Converts a resource into a format that can be edited with common tools. Returns the file
+/// extension and contents of the converted resource, or `None` if the resource type has no
+/// conversion.
+pub fn convert_resource(res: &Resource) -> Result<Option<(&'static str, Vec<u8>)>, String> {
+    let bytes = ResourceData::new(res).decompress();
+    let mut reader = Reader::new(&bytes[..]);
+
+    let converted = match res.fourcc().as_bytes() {
+        b"TXTR" => ("png", txtr_to_png(&Txtr::try_read(&mut reader)?)?),
+        b"STRG" => ("json", to_json_bytes(strg_to_json(&reader.read(())))?),
+        b"SCAN" => ("json", to_json_bytes(scan_to_json(&reader.read(())))?),
+        b"MREA" => {
+            let mrea: Mrea = reader.read(());
+            let scly = mrea.scly_section();
+            ("scly.json", to_json_bytes(scly_to_json(&scly)?)?)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(converted))
+}
+
+fn to_json_bytes(value: Value) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(&value).map_err(|e| format!("Failed to serialize JSON: {}", e))
+}
+
+fn txtr_to_png(txtr: &Txtr) -> Result<Vec<u8>, String> {
+    let color_type = txtr.format.color_type();
+    let (pixels, w, h) = decode_txtr_mipmap(txtr, 0)?;
+
+    let mut png = vec![];
+    PngEncoder::new(&mut png)
+        .encode(&pixels[..], w as u32, h as u32, color_type)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+
+    Ok(png)
+}
+
+fn strg_to_json(strg: &Strg) -> Value {
+    let mut tables = serde_json::Map::new();
+    for table in strg.string_tables.iter() {
+        let strings = table
+            .strings
+            .iter()
+            .map(|s| Value::String(s.into_owned().into_string().replace('\u{0}', "")))
+            .collect();
+        tables.insert(table.lang.to_string(), Value::Array(strings));
+    }
+    Value::Object(tables)
+}
+
+fn scan_to_json(scan: &Scan) -> Value {
+    let images: Vec<Value> = scan
+        .images
+        .iter()
+        .map(|image| {
+            json!({
+                "txtr": image.txtr.to_u32(),
+                "appearancePercent": image.appearance_percent,
+                "imagePosition": image.image_position,
+                "width": image.width,
+                "height": image.height,
+                "interval": image.interval,
+                "fadeDuration": image.fade_duration,
+            })
+        })
+        .collect();
+
+    json!({
+        "frme": scan.frme.to_u32(),
+        "strg": scan.strg.to_u32(),
+        "scanSpeed": scan.scan_speed,
+        "category": scan.category,
+        "iconFlag": scan.icon_flag,
+        "images": images,
+    })
+}
+
+fn scly_to_json(scly: &Scly) -> Result<Value, String> {
+    let mut layers = vec![];
+    for layer in scly.layers.iter() {
+        let mut objects = vec![];
+        for obj in layer.objects.iter() {
+            let mut property_data = obj.property_data.clone();
+            property_data.guess_kind();
+
+            let connections: Vec<Value> = obj
+                .connections
+                .iter()
+                .map(|conn| {
+                    // Use the same names as `ConnectionConfig`
+                    json!({
+                        "state": format!("{:?}", conn.state)
+                            .trim_start_matches("ConnectionState::")
+                            .to_string(),
+                        "message": format!("{:?}", conn.message)
+                            .trim_start_matches("ConnectionMsg::")
+                            .to_string(),
+                        "targetId": conn.target_object_id,
+                    })
+                })
+                .collect();
+
+            let mut value = json!({
+                "id": obj.instance_id,
+                "objectType": format!("0x{:02X}", property_data.object_type()),
+                "connections": connections,
+            });
+            if let SclyProperty::Unknown { .. } = property_data {
+                // Object types without a struct definition can only be dumped as raw bytes
+                let mut data = vec![];
+                property_data.write_to(&mut data).unwrap();
+                value["data"] = json!(data
+                    .iter()
+                    .map(|b| format!("{:02X}", b))
+                    .collect::<String>());
+            } else {
+                value["struct"] = json!(property_data.struct_name());
+                value["properties"] = serde_json::to_value(&property_data).map_err(|e| {
+                    format!(
+                        "Failed to serialize object 0x{:08X}: {}",
+                        obj.instance_id, e
+                    )
+                })?;
+            }
+            if property_data.supports_position() {
+                value["position"] = json!(property_data.get_position());
+            }
+            if property_data.supports_rotation() {
+                value["rotation"] = json!(property_data.get_rotation());
+            }
+            if property_data.supports_scale() {
+                value["scale"] = json!(property_data.get_scale());
+            }
+            objects.push(value);
+        }
+
+        layers.push(json!({
+            "unknown": layer.unknown,
+            "objects": objects,
+        }));
+    }
+
+    Ok(json!({ "layers": layers }))
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModelFormat {
+    Gltf,
+    Obj,
+}
+
+struct MeshVertex {
+    position: [f32; 3],
+    normal: Option<[f32; 3]>,
+    uv: Option<[f32; 2]>,
+}
+
+// One per surface of the CMDL
+struct Mesh {
+    material: usize,
+    vertices: Vec<MeshVertex>,
+    triangles: Vec<[u32; 3]>,
+}
+
+// Prime is Z-up while glTF (and most OBJ importers) expect Y-up
+fn to_y_up(v: [f32; 3]) -> [f32; 3] {
+    [v[0], v[2], -v[1]]
+}
+
+fn lookup<T: Copy>(array: &[T], index: u16) -> Result<T, String> {
+    array
+        .get(index as usize)
+        .copied()
+        .ok_or_else(|| format!("Vertex attribute index {} is out of bounds", index))
+}
+
+fn cmdl_meshes(cmdl: &Cmdl, materials: &[structs::CmdlMaterial]) -> Result<Vec<Mesh>, String> {
+    let geometry = cmdl.geometry()?;
+
+    let mut meshes = vec![];
+    for (i, surface) in geometry.surfaces.iter().enumerate() {
+        let material = materials
+            .get(surface.material_index as usize)
+            .ok_or_else(|| {
+                format!(
+                    "Surface {} uses missing material {}",
+                    i, surface.material_index
+                )
+            })?;
+        let uvs = if material.flags & MATERIAL_FLAG_SHORT_UVS != 0 {
+            &geometry.short_uvs
+        } else {
+            &geometry.uvs
+        };
+
+        let mut mesh = Mesh {
+            material: surface.material_index as usize,
+            vertices: vec![],
+            triangles: vec![],
+        };
+        let mut vertex_ids = HashMap::new();
+        for primitive in surface.primitives(material.vertex_attribute_flags)? {
+            let ids = primitive
+                .vertices
+                .iter()
+                .map(|v| {
+                    let position = v.position.ok_or("Vertex has no position")?;
+                    let key = (position, v.normal, v.uvs[0]);
+                    if let Some(id) = vertex_ids.get(&key) {
+                        return Ok(*id);
+                    }
+                    mesh.vertices.push(MeshVertex {
+                        position: to_y_up(lookup(&geometry.positions, position)?),
+                        normal: v
+                            .normal
+                            .map(|n| lookup(&geometry.normals, n).map(to_y_up))
+                            .transpose()?,
+                        uv: v.uvs[0].map(|t| lookup(uvs, t)).transpose()?,
+                    });
+                    let id = mesh.vertices.len() as u32 - 1;
+                    vertex_ids.insert(key, id);
+                    Ok(id)
+                })
+                .collect::<Result<Vec<u32>, String>>()?;
+
+            // Front faces are counter-clockwise in model space, like glTF and OBJ expect
+            mesh.triangles.extend(
+                primitive
+                    .triangles()
+                    .iter()
+                    .map(|[a, b, c]| [ids[*a], ids[*b], ids[*c]]),
+            );
+        }
+        meshes.push(mesh);
+    }
+
+    Ok(meshes)
+}
+
+/// Converts a CMDL and its textures into a glTF 2.0 (with an external buffer) or an OBJ+MTL
+/// model. Each material uses its first texture as the base color; textures missing from
+/// `textures` are left out. Returns the name and contents of every file of the model, which are
+/// named after `name`.
+pub fn export_model(
+    cmdl: &Cmdl,
+    textures: &HashMap<u32, Txtr>,
+    name: &str,
+    format: ModelFormat,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let materials = cmdl.materials(0)?;
+    let meshes = cmdl_meshes(cmdl, &materials)?;
+
+    let texture_ids: Vec<u32> = cmdl
+        .material_sets
+        .iter()
+        .next()
+        .ok_or("CMDL doesn't have a material set")?
+        .texture_ids
+        .iter()
+        .map(|id| id.to_u32())
+        .collect();
+
+    let mut files = vec![];
+
+    // The PNG filename used as the base color of each material
+    let mut images: HashMap<u32, String> = HashMap::new();
+    let mut material_images = vec![];
+    for material in materials.iter() {
+        let txtr_id = material
+            .texture_indices
+            .iter()
+            .next()
+            .and_then(|i| texture_ids.get(*i as usize).copied());
+        let image = match txtr_id.and_then(|id| textures.get(&id).map(|txtr| (id, txtr))) {
+            Some((id, txtr)) => match images.entry(id) {
+                Entry::Occupied(entry) => Some(entry.get().clone()),
+                Entry::Vacant(entry) => {
+                    let filename = format!("{}.{:08X}.png", name, id);
+                    files.push((filename.clone(), txtr_to_png(txtr)?));
+                    Some(entry.insert(filename).clone())
+                }
+            },
+            None => None,
+        };
+        material_images.push(image);
+    }
+
+    match format {
+        ModelFormat::Gltf => {
+            let (gltf, bin) = build_gltf(name, &meshes, &materials, &material_images)?;
+            files.push((format!("{}.gltf", name), gltf));
+            files.push((format!("{}.bin", name), bin));
+        }
+        ModelFormat::Obj => {
+            let (obj, mtl) = build_obj(name, &meshes, &materials, &material_images);
+            files.push((format!("{}.obj", name), obj));
+            files.push((format!("{}.mtl", name), mtl));
+        }
+    }
+
+    Ok(files)
+}
+
+fn f32s_to_le_bytes(values: impl Iterator<Item = f32>) -> Vec<u8> {
+    values.flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn build_gltf(
+    name: &str,
+    meshes: &[Mesh],
+    materials: &[structs::CmdlMaterial],
+    material_images: &[Option<String>],
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+
+    let mut bin: Vec<u8> = vec![];
+    let mut buffer_views = vec![];
+    let mut accessors = vec![];
+    let mut add_accessor = |data: Vec<u8>, kind: &str, count: usize, target: u32, extra: Value| {
+        let offset = bin.len();
+        bin.extend_from_slice(&data);
+        buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": data.len(),
+            "target": target,
+        }));
+        let mut accessor = json!({
+            "bufferView": buffer_views.len() - 1,
+            "componentType": FLOAT,
+            "count": count,
+            "type": kind,
+        });
+        if let Value::Object(extra) = extra {
+            for (k, v) in extra {
+                accessor[k] = v;
+            }
+        }
+        accessors.push(accessor);
+        accessors.len() - 1
+    };
+
+    let mut primitives = vec![];
+    for mesh in meshes.iter() {
+        if mesh.triangles.is_empty() {
+            continue;
+        }
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for v in mesh.vertices.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(v.position[i]);
+                max[i] = max[i].max(v.position[i]);
+            }
+        }
+        let positions = f32s_to_le_bytes(mesh.vertices.iter().flat_map(|v| v.position));
+        let mut attributes = json!({
+            "POSITION": add_accessor(
+                positions,
+                "VEC3",
+                mesh.vertices.len(),
+                ARRAY_BUFFER,
+                json!({ "min": min, "max": max }),
+            ),
+        });
+        if mesh.vertices.iter().all(|v| v.normal.is_some()) {
+            let normals = f32s_to_le_bytes(mesh.vertices.iter().flat_map(|v| v.normal.unwrap()));
+            attributes["NORMAL"] = json!(add_accessor(
+                normals,
+                "VEC3",
+                mesh.vertices.len(),
+                ARRAY_BUFFER,
+                json!({}),
+            ));
+        }
+        if mesh.vertices.iter().all(|v| v.uv.is_some()) {
+            // glTF's UV origin is the top left of the image
+            let uvs = f32s_to_le_bytes(mesh.vertices.iter().flat_map(|v| {
+                let [u, v] = v.uv.unwrap();
+                [u, 1.0 - v]
+            }));
+            attributes["TEXCOORD_0"] = json!(add_accessor(
+                uvs,
+                "VEC2",
+                mesh.vertices.len(),
+                ARRAY_BUFFER,
+                json!({}),
+            ));
+        }
+        let indices = mesh
+            .triangles
+            .iter()
+            .flatten()
+            .flat_map(|i| i.to_le_bytes())
+            .collect();
+        let indices = add_accessor(
+            indices,
+            "SCALAR",
+            mesh.triangles.len() * 3,
+            ELEMENT_ARRAY_BUFFER,
+            json!({ "componentType": UNSIGNED_INT }),
+        );
+
+        primitives.push(json!({
+            "attributes": attributes,
+            "indices": indices,
+            "material": mesh.material,
+            "mode": 4,
+        }));
+    }
+
+    let mut images = vec![];
+    let mut gltf_textures = vec![];
+    let gltf_materials: Vec<Value> = materials
+        .iter()
+        .zip(material_images.iter())
+        .enumerate()
+        .map(|(i, (material, image))| {
+            let mut pbr = json!({ "metallicFactor": 0.0 });
+            if let Some(image) = image {
+                let index = match images.iter().position(|i| i == image) {
+                    Some(index) => index,
+                    None => {
+                        images.push(image.clone());
+                        gltf_textures.push(json!({ "source": images.len() - 1 }));
+                        images.len() - 1
+                    }
+                };
+                pbr["baseColorTexture"] = json!({ "index": index });
+            }
+            let alpha_mode = if material.flags & MATERIAL_FLAG_TRANSPARENT != 0 {
+                "BLEND"
+            } else if material.flags & MATERIAL_FLAG_PUNCHTHROUGH != 0 {
+                "MASK"
+            } else {
+                "OPAQUE"
+            };
+            json!({
+                "name": format!("material_{}", i),
+                "pbrMetallicRoughness": pbr,
+                "alphaMode": alpha_mode,
+            })
+        })
+        .collect();
+
+    let gltf = json!({
+        "asset": { "version": "2.0", "generator": "randomprime" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "name": name, "mesh": 0 }],
+        "meshes": [{ "name": name, "primitives": primitives }],
+        "materials": gltf_materials,
+        "textures": gltf_textures,
+        "images": images.iter().map(|uri| json!({ "uri": uri })).collect::<Vec<_>>(),
+        "buffers": [{ "uri": format!("{}.bin", name), "byteLength": bin.len() }],
+        "bufferViews": buffer_views,
+        "accessors": accessors,
+    });
+
+    Ok((to_json_bytes(gltf)?, bin))
+}
+
+fn build_obj(
+    name: &str,
+    meshes: &[Mesh],
+    materials: &[structs::CmdlMaterial],
+    material_images: &[Option<String>],
+) -> (Vec<u8>, Vec<u8>) {
+    let mut obj = String::new();
+    let mut mtl = String::new();
+
+    writeln!(obj, "mtllib {}.mtl", name).unwrap();
+    // OBJ indices are 1-based and shared by the whole file
+    let mut base = 1;
+    for (i, mesh) in meshes.iter().enumerate() {
+        writeln!(obj, "o surface_{}", i).unwrap();
+        for v in mesh.vertices.iter() {
+            let [x, y, z] = v.position;
+            writeln!(obj, "v {} {} {}", x, y, z).unwrap();
+            let [nx, ny, nz] = v.normal.unwrap_or([0.0; 3]);
+            writeln!(obj, "vn {} {} {}", nx, ny, nz).unwrap();
+            let [tu, tv] = v.uv.unwrap_or([0.0; 2]);
+            writeln!(obj, "vt {} {}", tu, tv).unwrap();
+        }
+        writeln!(obj, "usemtl material_{}", mesh.material).unwrap();
+        for triangle in mesh.triangles.iter() {
+            let [a, b, c] = triangle.map(|i| i as usize + base);
+            writeln!(obj, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c).unwrap();
+        }
+        base += mesh.vertices.len();
+    }
+
+    for (i, (material, image)) in materials.iter().zip(material_images.iter()).enumerate() {
+        writeln!(mtl, "newmtl material_{}", i).unwrap();
+        writeln!(mtl, "Kd 1 1 1").unwrap();
+        if material.flags & MATERIAL_FLAG_TRANSPARENT != 0 {
+            writeln!(mtl, "d 0.5").unwrap();
+        }
+        if let Some(image) = image {
+            writeln!(mtl, "map_Kd {}", image).unwrap();
+        }
+        writeln!(mtl).unwrap();
+    }
+
+    (obj.into_bytes(), mtl.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use reader_writer::CStrConversionExtension;
+    use serde_json::json;
+    use structs::{ResId, SclyProperty};
+
+    #[test]
+    fn scly_property_to_json() {
+        let timer: SclyProperty = structs::Timer {
+            name: b"Timer \"1\"\n\0".as_cstr(),
+            start_time: 0.25,
+            max_random_add: f32::INFINITY,
+            looping: 0,
+            start_immediately: 1,
+            active: 1,
+        }
+        .into();
+        assert_eq!(timer.struct_name(), Some("Timer"));
+        assert_eq!(
+            serde_json::to_value(&timer).unwrap(),
+            json!({
+                "name": "Timer \"1\"\n",
+                "start_time": 0.25,
+                "max_random_add": null,
+                "looping": 0,
+                "start_immediately": 1,
+                "active": 1,
+            })
+        );
+
+        let scan_params = structs::scly_structs::ScannableParameters {
+            scan: ResId::new(0x12345678),
+        };
+        assert_eq!(
+            serde_json::to_value(&scan_params).unwrap(),
+            json!({ "scan": 0x12345678 })
+        );
+        let grapple_params = structs::scly_structs::GrappleParameters {
+            unknowns: [1.5; 11].into(),
+            disable_turning: 1,
+        };
+        assert_eq!(
+            serde_json::to_value(&grapple_params).unwrap(),
+            json!({ "unknowns": vec![1.5; 11], "disable_turning": 1 })
+        );
+    }
+}
@@ -0,0 +1,1068 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use nalgebra::{Matrix3, Matrix4, Point3, Quaternion, UnitQuaternion, Vector3};
+use percent_encoding::percent_decode_str;
+use serde_json::Value;
+use structs::{
+    res_id::TXTR, Cmdl, CmdlGeometry, CmdlMaterial, CmdlPrimitive, CmdlPrimitiveKind, CmdlSurface,
+    CmdlTevStage, CmdlTevStageTextureInput, CmdlVertex, ResId, Txtr, TxtrFormat,
+    MATERIAL_FLAG_PUNCHTHROUGH, MATERIAL_FLAG_TRANSPARENT,
+};
+
+use crate::txtr_conversions::encode_txtr;
+
+// Position, UV and normal indices of an OBJ face vertex
+type ObjVertex = (usize, Option<usize>, Option<usize>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum AlphaMode {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+struct ImportedMaterial {
+    image: RgbaImage,
+    alpha_mode: AlphaMode,
+}
+
+// Already converted to Prime's coordinate system
+struct ImportedVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    uv: [f32; 2],
+}
+
+struct ImportedSurface {
+    material: usize,
+    vertices: Vec<ImportedVertex>,
+    // Counter-clockwise when facing the camera
+    triangles: Vec<[u32; 3]>,
+}
+
+/// A textured mesh loaded from a glTF (`.gltf` or `.glb`) or OBJ file, which can be converted
+/// into a CMDL and one TXTR per material.
+pub struct ImportedModel {
+    materials: Vec<ImportedMaterial>,
+    surfaces: Vec<ImportedSurface>,
+}
+
+// Inverse of the conversion done by `asset_export`, glTF and OBJ are Y-up while Prime is Z-up
+fn from_y_up(v: [f32; 3]) -> [f32; 3] {
+    [v[0], -v[2], v[1]]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len > 0.0 {
+        [v[0] / len, v[1] / len, v[2] / len]
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+// Builds a surface from triangles in the Y-up space of the source file. Missing normals are
+// computed by averaging the normals of the faces around each vertex.
+fn build_surface(
+    material: usize,
+    positions: Vec<[f32; 3]>,
+    normals: Option<Vec<[f32; 3]>>,
+    uvs: Vec<[f32; 2]>,
+    triangles: Vec<[u32; 3]>,
+) -> ImportedSurface {
+    let normals = normals.unwrap_or_else(|| {
+        let mut normals = vec![[0.0; 3]; positions.len()];
+        for [a, b, c] in triangles.iter().map(|t| t.map(|i| i as usize)) {
+            let n = cross(
+                sub(positions[b], positions[a]),
+                sub(positions[c], positions[a]),
+            );
+            for i in [a, b, c] {
+                for j in 0..3 {
+                    normals[i][j] += n[j];
+                }
+            }
+        }
+        normals
+    });
+
+    ImportedSurface {
+        material,
+        vertices: positions
+            .into_iter()
+            .zip(normals)
+            .zip(uvs)
+            .map(|((position, normal), uv)| ImportedVertex {
+                position: from_y_up(position),
+                normal: from_y_up(normalize(normal)),
+                uv,
+            })
+            .collect(),
+        triangles,
+    }
+}
+
+fn solid_color_image(color: [f32; 4]) -> RgbaImage {
+    let pixel = Rgba(color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8));
+    RgbaImage::from_pixel(8, 8, pixel)
+}
+
+fn decode_image(bytes: &[u8], name: &str) -> Result<RgbaImage, String> {
+    image::load_from_memory(bytes)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| format!("Failed to decode image '{}': {}", name, e))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))
+}
+
+impl ImportedModel {
+    /// Loads a model, picking the format from the file extension. Textures are loaded relative
+    /// to the model; only PNG images are supported.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let model = match &extension[..] {
+            "gltf" | "glb" => Self::load_gltf(path)?,
+            "obj" => Self::load_obj(path)?,
+            _ => Err(format!(
+                "Unsupported model format '{}', expected .gltf, .glb or .obj",
+                path.display()
+            ))?,
+        };
+        if model.surfaces.is_empty() {
+            Err(format!(
+                "'{}' doesn't contain any triangles",
+                path.display()
+            ))?
+        }
+        Ok(model)
+    }
+
+    /// The number of TXTRs the model needs, one per material.
+    pub fn texture_count(&self) -> usize {
+        self.materials.len()
+    }
+
+    /// Encodes the texture of every material. Images are resized to power of two dimensions
+    /// between 8 and 1024 pixels. Translucent materials use RGB5A3, the others CMPR.
+    pub fn textures(&self) -> Result<Vec<Txtr<'static>>, String> {
+        self.materials
+            .iter()
+            .map(|material| {
+                let size = |n: u32| n.clamp(8, 1024).next_power_of_two().min(1024);
+                let (w, h) = (size(material.image.width()), size(material.image.height()));
+                let image = if (w, h) != material.image.dimensions() {
+                    image::imageops::resize(&material.image, w, h, FilterType::Triangle)
+                } else {
+                    material.image.clone()
+                };
+                let format = match material.alpha_mode {
+                    AlphaMode::Blend => TxtrFormat::Rgb5A3,
+                    _ => TxtrFormat::Cmpr,
+                };
+                encode_txtr(format, image.into_raw(), w as usize, h as usize, None)
+            })
+            .collect()
+    }
+
+    /// Builds the CMDL, with `texture_ids` giving the id of each texture returned by
+    /// `textures`.
+    pub fn to_cmdl(&self, texture_ids: &[ResId<TXTR>]) -> Result<Cmdl<'static>, String> {
+        if texture_ids.len() != self.materials.len() {
+            Err(format!(
+                "The model needs {} texture ids, got {}",
+                self.materials.len(),
+                texture_ids.len()
+            ))?
+        }
+
+        // Positions, normals and UVs are indexed separately, so identical values are shared
+        // between vertices
+        fn index_of<const N: usize>(
+            values: &mut Vec<[f32; N]>,
+            lookup: &mut HashMap<[u32; N], u16>,
+            value: [f32; N],
+            name: &str,
+        ) -> Result<u16, String> {
+            let key = value.map(|f| f.to_bits());
+            if let Some(index) = lookup.get(&key) {
+                return Ok(*index);
+            }
+            let index = values.len();
+            if index > u16::MAX as usize {
+                Err(format!("The model has more than {} {}", index, name))?
+            }
+            values.push(value);
+            lookup.insert(key, index as u16);
+            Ok(index as u16)
+        }
+
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let (mut position_lookup, mut normal_lookup, mut uv_lookup) =
+            (HashMap::new(), HashMap::new(), HashMap::new());
+        let mut surfaces = vec![];
+        for surface in self.surfaces.iter() {
+            let vertices = surface
+                .vertices
+                .iter()
+                .map(|v| {
+                    let mut vertex = CmdlVertex {
+                        position: Some(index_of(
+                            &mut positions,
+                            &mut position_lookup,
+                            v.position,
+                            "positions",
+                        )?),
+                        normal: Some(index_of(
+                            &mut normals,
+                            &mut normal_lookup,
+                            v.normal,
+                            "normals",
+                        )?),
+                        ..Default::default()
+                    };
+                    vertex.uvs[0] = Some(index_of(&mut uvs, &mut uv_lookup, v.uv, "UVs")?);
+                    Ok(vertex)
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+
+            let triangle_vertices: Vec<CmdlVertex> = surface
+                .triangles
+                .iter()
+                .flatten()
+                .map(|i| vertices[*i as usize])
+                .collect();
+            let primitives: Vec<CmdlPrimitive> = triangle_vertices
+                .chunks(u16::MAX as usize)
+                .map(|chunk| CmdlPrimitive {
+                    kind: CmdlPrimitiveKind::Triangles,
+                    vertex_format: 0,
+                    vertices: chunk.to_vec(),
+                })
+                .collect();
+
+            let count = surface.vertices.len().max(1) as f32;
+            let mut centroid = [0.0; 3];
+            let mut reflection_normal = [0.0; 3];
+            for v in surface.vertices.iter() {
+                for i in 0..3 {
+                    centroid[i] += v.position[i] / count;
+                    reflection_normal[i] += v.normal[i];
+                }
+            }
+
+            let mut cmdl_surface = CmdlSurface {
+                centroid: centroid.into(),
+                material_index: surface.material as u32,
+                mantissa: 0x8000,
+                parent_model_pointer: 0,
+                next_surface_pointer: 0,
+                reflection_normal: normalize(reflection_normal).into(),
+                extra_data: vec![].into(),
+                display_list: vec![].into(),
+            };
+            cmdl_surface.set_primitives(&primitives, VERTEX_ATTRIBUTE_FLAGS)?;
+            surfaces.push(cmdl_surface);
+        }
+
+        let materials = self
+            .materials
+            .iter()
+            .enumerate()
+            .map(|(i, material)| lit_texture_material(i as u32, material.alpha_mode))
+            .collect();
+        let geometry = CmdlGeometry {
+            positions,
+            normals,
+            colors: vec![],
+            uvs,
+            short_uvs: vec![],
+            surfaces,
+        };
+
+        Ok(Cmdl::from_geometry(
+            texture_ids.to_vec(),
+            materials,
+            &geometry,
+        ))
+    }
+
+    fn load_obj(path: &Path) -> Result<Self, String> {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let obj = String::from_utf8_lossy(&read_file(path)?).into_owned();
+
+        let mut positions: Vec<[f32; 3]> = vec![];
+        let mut normals: Vec<[f32; 3]> = vec![];
+        let mut uvs: Vec<[f32; 2]> = vec![];
+        let mut mtl_files = vec![];
+        // Faces grouped by material name
+        let mut groups: Vec<(String, Vec<[ObjVertex; 3]>)> = vec![];
+        let mut current = None;
+
+        let parse_floats = |args: &[&str], n: usize, line: usize| -> Result<Vec<f32>, String> {
+            if args.len() < n {
+                Err(format!("Line {}: expected {} values", line, n))?
+            }
+            args[..n]
+                .iter()
+                .map(|a| {
+                    a.parse::<f32>()
+                        .map_err(|_| format!("Line {}: invalid number '{}'", line, a))
+                })
+                .collect()
+        };
+        // OBJ indices are 1-based, negative indices count back from the last element
+        let parse_index = |s: &str, len: usize, line: usize| -> Result<usize, String> {
+            let index: isize = s
+                .parse()
+                .map_err(|_| format!("Line {}: invalid index '{}'", line, s))?;
+            let index = if index < 0 {
+                len as isize + index
+            } else {
+                index - 1
+            };
+            if index < 0 || index as usize >= len {
+                Err(format!("Line {}: index {} is out of bounds", line, s))?
+            }
+            Ok(index as usize)
+        };
+
+        for (i, line) in obj.lines().enumerate() {
+            let line_number = i + 1;
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let args: Vec<&str> = tokens.collect();
+            match keyword {
+                "v" => {
+                    let v = parse_floats(&args, 3, line_number)?;
+                    positions.push([v[0], v[1], v[2]]);
+                }
+                "vn" => {
+                    let v = parse_floats(&args, 3, line_number)?;
+                    normals.push([v[0], v[1], v[2]]);
+                }
+                "vt" => {
+                    let v = parse_floats(&args, 2, line_number)?;
+                    uvs.push([v[0], v[1]]);
+                }
+                "mtllib" => mtl_files.push(args.join(" ")),
+                "usemtl" => current = Some(args.join(" ")),
+                "f" => {
+                    let face = args
+                        .iter()
+                        .map(|vertex| {
+                            let mut parts = vertex.split('/');
+                            let position =
+                                parse_index(parts.next().unwrap(), positions.len(), line_number)?;
+                            let uv = match parts.next() {
+                                Some(s) if !s.is_empty() => {
+                                    Some(parse_index(s, uvs.len(), line_number)?)
+                                }
+                                _ => None,
+                            };
+                            let normal = match parts.next() {
+                                Some(s) if !s.is_empty() => {
+                                    Some(parse_index(s, normals.len(), line_number)?)
+                                }
+                                _ => None,
+                            };
+                            Ok((position, uv, normal))
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+
+                    let name = current.clone().unwrap_or_default();
+                    let group = match groups.iter().position(|(n, _)| *n == name) {
+                        Some(group) => group,
+                        None => {
+                            groups.push((name, vec![]));
+                            groups.len() - 1
+                        }
+                    };
+                    // Polygons are split into a fan
+                    for j in 1..face.len().saturating_sub(1) {
+                        groups[group].1.push([face[0], face[j], face[j + 1]]);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mtl = Self::load_mtl(dir, &mtl_files)?;
+
+        let mut materials = vec![];
+        let mut surfaces = vec![];
+        for (name, faces) in groups {
+            let material = match mtl.get(&name) {
+                Some((color, texture, alpha)) => {
+                    let image = match texture {
+                        Some(texture) => {
+                            let path = dir.join(texture);
+                            decode_image(&read_file(&path)?, texture)?
+                        }
+                        None => solid_color_image([color[0], color[1], color[2], *alpha]),
+                    };
+                    let alpha_mode = if *alpha < 1.0 {
+                        AlphaMode::Blend
+                    } else {
+                        AlphaMode::Opaque
+                    };
+                    ImportedMaterial { image, alpha_mode }
+                }
+                None => ImportedMaterial {
+                    image: solid_color_image([1.0; 4]),
+                    alpha_mode: AlphaMode::Opaque,
+                },
+            };
+            materials.push(material);
+
+            let mut vertex_ids = HashMap::new();
+            let mut surface_positions = vec![];
+            let mut surface_normals = vec![];
+            let mut surface_uvs = vec![];
+            let has_normals = faces.iter().flatten().all(|(_, _, n)| n.is_some());
+            let triangles = faces
+                .iter()
+                .map(|face| {
+                    face.map(|key| {
+                        *vertex_ids.entry(key).or_insert_with(|| {
+                            let (position, uv, normal) = key;
+                            surface_positions.push(positions[position]);
+                            surface_normals.push(normal.map(|n| normals[n]).unwrap_or([0.0; 3]));
+                            surface_uvs.push(uv.map(|t| uvs[t]).unwrap_or([0.0; 2]));
+                            surface_positions.len() as u32 - 1
+                        })
+                    })
+                })
+                .collect();
+
+            surfaces.push(build_surface(
+                materials.len() - 1,
+                surface_positions,
+                if has_normals {
+                    Some(surface_normals)
+                } else {
+                    None
+                },
+                surface_uvs,
+                triangles,
+            ));
+        }
+
+        Ok(ImportedModel {
+            materials,
+            surfaces,
+        })
+    }
+
+    // Returns the diffuse color, diffuse texture and opacity of every material
+    #[allow(clippy::type_complexity)]
+    fn load_mtl(
+        dir: &Path,
+        files: &[String],
+    ) -> Result<HashMap<String, ([f32; 3], Option<String>, f32)>, String> {
+        let mut materials = HashMap::new();
+        for file in files {
+            let mtl = String::from_utf8_lossy(&read_file(&dir.join(file))?).into_owned();
+            let mut current: Option<String> = None;
+            for line in mtl.lines() {
+                let mut tokens = line.split_whitespace();
+                let keyword = match tokens.next() {
+                    Some(keyword) => keyword,
+                    None => continue,
+                };
+                let args: Vec<&str> = tokens.collect();
+                if keyword == "newmtl" {
+                    let name = args.join(" ");
+                    materials.insert(name.clone(), ([1.0; 3], None, 1.0));
+                    current = Some(name);
+                    continue;
+                }
+                let material = match current.as_ref().and_then(|name| materials.get_mut(name)) {
+                    Some(material) => material,
+                    None => continue,
+                };
+                let float = |i: usize| args.get(i).and_then(|a| a.parse::<f32>().ok());
+                match keyword {
+                    "Kd" => {
+                        if let (Some(r), Some(g), Some(b)) = (float(0), float(1), float(2)) {
+                            material.0 = [r, g, b];
+                        }
+                    }
+                    // Texture options come before the filename
+                    "map_Kd" => material.1 = args.last().map(|s| s.to_string()),
+                    "d" => material.2 = float(0).unwrap_or(1.0),
+                    "Tr" => material.2 = 1.0 - float(0).unwrap_or(0.0),
+                    _ => (),
+                }
+            }
+        }
+        Ok(materials)
+    }
+
+    fn load_gltf(path: &Path) -> Result<Self, String> {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let bytes = read_file(path)?;
+
+        let (json, glb_buffer) = if bytes.starts_with(b"glTF") {
+            parse_glb(&bytes)?
+        } else {
+            (bytes, None)
+        };
+        let gltf: Value = serde_json::from_slice(&json)
+            .map_err(|e| format!("Failed to parse '{}': {}", path.display(), e))?;
+
+        let buffers = gltf["buffers"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| match buffer["uri"].as_str() {
+                Some(uri) => load_uri(dir, uri),
+                None if i == 0 => glb_buffer
+                    .clone()
+                    .ok_or_else(|| "Buffer 0 has no data".to_string()),
+                None => Err(format!("Buffer {} has no data", i)),
+            })
+            .collect::<Result<Vec<Vec<u8>>, String>>()?;
+        let gltf = Gltf {
+            json: gltf,
+            buffers,
+        };
+
+        let mut model = ImportedModel {
+            materials: vec![],
+            surfaces: vec![],
+        };
+        // glTF material index (None for the default material) to index in `model.materials`
+        let mut material_indices: HashMap<Option<usize>, usize> = HashMap::new();
+
+        let scene = gltf.json["scene"].as_u64().unwrap_or(0) as usize;
+        let roots: Vec<usize> = match gltf.json["scenes"][scene]["nodes"].as_array() {
+            Some(nodes) => nodes
+                .iter()
+                .filter_map(|n| n.as_u64().map(|n| n as usize))
+                .collect(),
+            None => Err("The glTF doesn't have a scene")?,
+        };
+        let mut stack: Vec<(usize, Matrix4<f32>)> = roots
+            .into_iter()
+            .map(|n| (n, Matrix4::identity()))
+            .collect();
+        while let Some((node_index, parent)) = stack.pop() {
+            let node = &gltf.json["nodes"][node_index];
+            let transform = parent * node_matrix(node);
+            let normal_transform = normal_matrix(&transform);
+            if let Some(children) = node["children"].as_array() {
+                for child in children.iter().filter_map(|c| c.as_u64()) {
+                    stack.push((child as usize, transform));
+                }
+            }
+            let mesh = match node["mesh"].as_u64() {
+                Some(mesh) => &gltf.json["meshes"][mesh as usize],
+                None => continue,
+            };
+
+            for primitive in mesh["primitives"].as_array().cloned().unwrap_or_default() {
+                let mode = primitive["mode"].as_u64().unwrap_or(4);
+                if !(4..=6).contains(&mode) {
+                    continue; // Points and lines
+                }
+
+                let attributes = &primitive["attributes"];
+                let positions = match attributes["POSITION"].as_u64() {
+                    Some(accessor) => gltf.read_accessor(accessor as usize)?,
+                    None => continue,
+                };
+                let positions: Vec<[f32; 3]> = positions
+                    .iter()
+                    .map(|p| transform_point(&transform, [p[0], p[1], p[2]]))
+                    .collect();
+                let normals = attributes["NORMAL"]
+                    .as_u64()
+                    .map(|accessor| gltf.read_accessor(accessor as usize))
+                    .transpose()?
+                    .map(|normals| {
+                        normals
+                            .iter()
+                            .map(|n| transform_normal(&normal_transform, [n[0], n[1], n[2]]))
+                            .collect()
+                    });
+                // glTF's UV origin is the top left of the image
+                let uvs = match attributes["TEXCOORD_0"].as_u64() {
+                    Some(accessor) => gltf
+                        .read_accessor(accessor as usize)?
+                        .iter()
+                        .map(|uv| [uv[0], 1.0 - uv[1]])
+                        .collect(),
+                    None => vec![[0.0; 2]; positions.len()],
+                };
+
+                let indices: Vec<u32> = match primitive["indices"].as_u64() {
+                    Some(accessor) => gltf
+                        .read_accessor(accessor as usize)?
+                        .iter()
+                        .map(|i| i[0] as u32)
+                        .collect(),
+                    None => (0..positions.len() as u32).collect(),
+                };
+                if let Some(i) = indices.iter().find(|i| **i as usize >= positions.len()) {
+                    Err(format!("Vertex index {} is out of bounds", i))?
+                }
+                let triangles = match mode {
+                    4 => indices
+                        .chunks_exact(3)
+                        .map(|t| [t[0], t[1], t[2]])
+                        .collect(),
+                    5 => (0..indices.len().saturating_sub(2))
+                        .map(|i| {
+                            if i % 2 == 0 {
+                                [indices[i], indices[i + 1], indices[i + 2]]
+                            } else {
+                                [indices[i + 1], indices[i], indices[i + 2]]
+                            }
+                        })
+                        .collect(),
+                    _ => (1..indices.len().saturating_sub(1))
+                        .map(|i| [indices[0], indices[i], indices[i + 1]])
+                        .collect(),
+                };
+
+                let gltf_material = primitive["material"].as_u64().map(|m| m as usize);
+                let material = match material_indices.get(&gltf_material) {
+                    Some(material) => *material,
+                    None => {
+                        model
+                            .materials
+                            .push(gltf.load_material(dir, gltf_material)?);
+                        material_indices.insert(gltf_material, model.materials.len() - 1);
+                        model.materials.len() - 1
+                    }
+                };
+
+                model
+                    .surfaces
+                    .push(build_surface(material, positions, normals, uvs, triangles));
+            }
+        }
+
+        Ok(model)
+    }
+}
+
+// Color = texture * rasterized (lit) color, alpha = texture alpha. This is the material used by
+// most single textured models of the game.
+const VERTEX_ATTRIBUTE_FLAGS: u32 = 0x30F; // Position, normal and UV 0 with 16-bit indices
+
+fn lit_texture_material(index: u32, alpha_mode: AlphaMode) -> CmdlMaterial<'static> {
+    let (flags, blend_dst_factor, blend_src_factor) = match alpha_mode {
+        AlphaMode::Opaque => (0x11083, 0, 1),
+        AlphaMode::Mask => (0x11083 | MATERIAL_FLAG_PUNCHTHROUGH, 0, 1),
+        // GX_BL_INVSRCALPHA, GX_BL_SRCALPHA
+        AlphaMode::Blend => (0x11083 | MATERIAL_FLAG_TRANSPARENT, 5, 4),
+    };
+    CmdlMaterial {
+        flags,
+        texture_indices: vec![index].into(),
+        vertex_attribute_flags: VERTEX_ATTRIBUTE_FLAGS,
+        group_index: index,
+        konst_colors: None,
+        blend_dst_factor,
+        blend_src_factor,
+        indirect_texture_index: None,
+        color_channel_flags: vec![0x3000].into(),
+        tev_stages: vec![CmdlTevStage {
+            color_input_flags: 0x7A14F,
+            alpha_input_flags: 0x21CE7,
+            color_combine_flags: 0x100,
+            alpha_combine_flags: 0x100,
+            padding: 0,
+            konst_alpha_input: 0,
+            konst_color_input: 0,
+            rasterized_color_input: 4,
+        }]
+        .into(),
+        tev_stage_texture_inputs: vec![CmdlTevStageTextureInput {
+            padding: 0,
+            texture_index: 0,
+            texcoord_index: 0,
+        }]
+        .into(),
+        texgen_flags: vec![0x1EBC40].into(),
+        uv_animations: vec![].into(),
+    }
+}
+
+struct Gltf {
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Gltf {
+    // Returns the elements of an accessor, with normalized integers converted to floats
+    fn read_accessor(&self, index: usize) -> Result<Vec<Vec<f32>>, String> {
+        let accessor = &self.json["accessors"][index];
+        if accessor.get("sparse").is_some() {
+            Err(format!(
+                "Accessor {} is sparse, which isn't supported",
+                index
+            ))?
+        }
+        let count = accessor["count"].as_u64().unwrap_or(0) as usize;
+        let components = match accessor["type"].as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            ty => Err(format!("Accessor {} has unsupported type {:?}", index, ty))?,
+        };
+        let component_type = accessor["componentType"].as_u64().unwrap_or(0);
+        let component_size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => Err(format!(
+                "Accessor {} has unsupported component type {}",
+                index, component_type
+            ))?,
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+
+        let view = match accessor["bufferView"].as_u64() {
+            Some(view) => &self.json["bufferViews"][view as usize],
+            None => return Ok(vec![vec![0.0; components]; count]),
+        };
+        let buffer = self
+            .buffers
+            .get(view["buffer"].as_u64().unwrap_or(0) as usize)
+            .ok_or_else(|| format!("Accessor {} uses a missing buffer", index))?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize
+            + accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let stride = view["byteStride"]
+            .as_u64()
+            .map(|s| s as usize)
+            .unwrap_or(components * component_size);
+
+        (0..count)
+            .map(|i| {
+                (0..components)
+                    .map(|c| {
+                        let start = offset + i * stride + c * component_size;
+                        let bytes = buffer
+                            .get(start..start + component_size)
+                            .ok_or_else(|| format!("Accessor {} is out of bounds", index))?;
+                        let (value, max) = match component_type {
+                            5120 => (bytes[0] as i8 as f32, 127.0),
+                            5121 => (bytes[0] as f32, 255.0),
+                            5122 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32, 32767.0),
+                            5123 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f32, 65535.0),
+                            5125 => (
+                                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+                                1.0,
+                            ),
+                            _ => (
+                                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                                1.0,
+                            ),
+                        };
+                        Ok(if normalized {
+                            (value / max).max(-1.0)
+                        } else {
+                            value
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn load_material(&self, dir: &Path, index: Option<usize>) -> Result<ImportedMaterial, String> {
+        let index = match index {
+            Some(index) => index,
+            None => {
+                return Ok(ImportedMaterial {
+                    image: solid_color_image([1.0; 4]),
+                    alpha_mode: AlphaMode::Opaque,
+                })
+            }
+        };
+        let material = &self.json["materials"][index];
+        let pbr = &material["pbrMetallicRoughness"];
+
+        let mut factor = [1.0; 4];
+        if let Some(values) = pbr["baseColorFactor"].as_array() {
+            for (f, v) in factor.iter_mut().zip(values.iter()) {
+                *f = v.as_f64().unwrap_or(1.0) as f32;
+            }
+        }
+        let alpha_mode = match material["alphaMode"].as_str() {
+            Some("BLEND") => AlphaMode::Blend,
+            Some("MASK") => AlphaMode::Mask,
+            _ => AlphaMode::Opaque,
+        };
+
+        let texture = pbr["baseColorTexture"]["index"].as_u64();
+        let image = match texture.and_then(|t| self.json["textures"][t as usize]["source"].as_u64())
+        {
+            Some(source) => {
+                let mut image = self.load_image(dir, source as usize)?;
+                for pixel in image.pixels_mut() {
+                    for (c, f) in pixel.0.iter_mut().zip(factor.iter()) {
+                        *c = (*c as f32 * f.clamp(0.0, 1.0)).round() as u8;
+                    }
+                }
+                image
+            }
+            None => solid_color_image(factor),
+        };
+
+        Ok(ImportedMaterial { image, alpha_mode })
+    }
+
+    fn load_image(&self, dir: &Path, index: usize) -> Result<RgbaImage, String> {
+        let image = &self.json["images"][index];
+        let bytes = match (image["uri"].as_str(), image["bufferView"].as_u64()) {
+            (Some(uri), _) => load_uri(dir, uri)?,
+            (None, Some(view)) => {
+                let view = &self.json["bufferViews"][view as usize];
+                let buffer = &self.buffers[view["buffer"].as_u64().unwrap_or(0) as usize];
+                let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+                let length = view["byteLength"].as_u64().unwrap_or(0) as usize;
+                buffer
+                    .get(offset..offset + length)
+                    .ok_or_else(|| format!("Image {} is out of bounds", index))?
+                    .to_vec()
+            }
+            _ => Err(format!("Image {} has no data", index))?,
+        };
+        decode_image(&bytes, &format!("image {}", index))
+    }
+}
+
+// Splits a binary glTF into its JSON and BIN chunks
+fn parse_glb(bytes: &[u8]) -> Result<(Vec<u8>, Option<Vec<u8>>), String> {
+    let u32_at = |offset: usize| -> Result<u32, String> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| "Truncated GLB file".to_string())
+    };
+    if u32_at(4)? != 2 {
+        Err("Only glTF 2.0 is supported".to_string())?
+    }
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let length = u32_at(offset)? as usize;
+        let kind = u32_at(offset + 4)?;
+        let data = bytes
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| "Truncated GLB chunk".to_string())?
+            .to_vec();
+        match kind {
+            0x4E4F534A => json = Some(data), // "JSON"
+            0x004E4942 => bin = Some(data),  // "BIN\0"
+            _ => (),
+        }
+        offset += 8 + length;
+    }
+
+    Ok((json.ok_or("GLB file doesn't have a JSON chunk")?, bin))
+}
+
+fn load_uri(dir: &Path, uri: &str) -> Result<Vec<u8>, String> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        return match data.split_once(";base64,") {
+            Some((_, data)) => decode_base64(data),
+            None => Err("Only base64 data URIs are supported".to_string()),
+        };
+    }
+    let path: PathBuf = dir.join(&*percent_decode_str(uri).decode_utf8_lossy());
+    read_file(&path)
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>, String> {
+    base64::engine::general_purpose::STANDARD
+        .decode(s)
+        .map_err(|e| format!("Invalid base64 data: {}", e))
+}
+
+fn node_matrix(node: &Value) -> Matrix4<f32> {
+    let floats = |key: &str| -> Option<Vec<f32>> {
+        node[key].as_array().map(|values| {
+            values
+                .iter()
+                .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+                .collect()
+        })
+    };
+    if let Some(m) = floats("matrix") {
+        if m.len() != 16 {
+            return Matrix4::identity();
+        }
+        // Column major, like nalgebra
+        return Matrix4::from_column_slice(&m);
+    }
+
+    let t = floats("translation").unwrap_or_else(|| vec![0.0; 3]);
+    let q = floats("rotation").unwrap_or_else(|| vec![0.0, 0.0, 0.0, 1.0]);
+    let s = floats("scale").unwrap_or_else(|| vec![1.0; 3]);
+    if t.len() < 3 || q.len() < 4 || s.len() < 3 {
+        return Matrix4::identity();
+    }
+    let rotation = UnitQuaternion::from_quaternion(Quaternion::new(q[3], q[0], q[1], q[2]));
+    Matrix4::new_translation(&Vector3::new(t[0], t[1], t[2]))
+        * rotation.to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(&Vector3::new(s[0], s[1], s[2]))
+}
+
+// Normals are transformed by the inverse transpose, so that they stay perpendicular to their
+// surface under non-uniform scales
+fn normal_matrix(m: &Matrix4<f32>) -> Matrix3<f32> {
+    let m: Matrix3<f32> = m.fixed_view::<3, 3>(0, 0).into();
+    m.try_inverse().map(|inv| inv.transpose()).unwrap_or(m)
+}
+
+fn transform_point(m: &Matrix4<f32>, p: [f32; 3]) -> [f32; 3] {
+    m.transform_point(&Point3::new(p[0], p[1], p[2]))
+        .coords
+        .into()
+}
+
+fn transform_normal(m: &Matrix3<f32>, n: [f32; 3]) -> [f32; 3] {
+    normalize((m * Vector3::new(n[0], n[1], n[2])).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use reader_writer::{Reader, Writable};
+    use structs::{res_id::TXTR, Cmdl, ResId};
+
+    use super::ImportedModel;
+
+    #[test]
+    fn obj_to_cmdl_round_trip() {
+        let dir = std::env::temp_dir().join(format!("randomprime-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("quad.obj");
+        std::fs::write(
+            &path,
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+             vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             vn 0 0 1\n\
+             f 1/1/1 2/2/1 3/3/1 4/4/1\n",
+        )
+        .unwrap();
+        let model = ImportedModel::load(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let model = model.unwrap();
+
+        assert_eq!(model.texture_count(), 1);
+        assert_eq!(model.textures().unwrap().len(), 1);
+        assert!(model.to_cmdl(&[]).is_err());
+        let texture_id: ResId<TXTR> = ResId::new(0x12345678);
+        let cmdl = model.to_cmdl(&[texture_id]).unwrap();
+
+        let mut bytes = vec![];
+        cmdl.write_to(&mut bytes).unwrap();
+        let cmdl: Cmdl = Reader::new(&bytes[..]).read(());
+
+        let materials = cmdl.materials(0).unwrap();
+        assert_eq!(materials.len(), 1);
+        let geometry = cmdl.geometry().unwrap();
+        assert_eq!(geometry.surfaces.len(), 1);
+        let primitives = geometry.surfaces[0]
+            .primitives(materials[0].vertex_attribute_flags)
+            .unwrap();
+
+        // The quad is split into two triangles, converted from Y-up to Z-up
+        let mut triangles = vec![];
+        for primitive in primitives.iter() {
+            for triangle in primitive.triangles() {
+                triangles.push(triangle.map(|i| {
+                    let vertex = primitive.vertices[i];
+                    assert_eq!(
+                        geometry.normals[vertex.normal.unwrap() as usize],
+                        [0.0, -1.0, 0.0]
+                    );
+                    geometry.positions[vertex.position.unwrap() as usize]
+                }));
+            }
+        }
+        assert_eq!(
+            triangles,
+            [
+                [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0]],
+                [[0.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0]],
+            ]
+        );
+    }
+
+    #[test]
+    fn node_transforms() {
+        // Scaled along X, then rotated 90 degrees around Z and moved
+        let node = serde_json::json!({
+            "translation": [1.0, 2.0, 3.0],
+            "rotation": [0.0, 0.0, std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2],
+            "scale": [2.0, 1.0, 1.0],
+        });
+        let m = super::node_matrix(&node);
+        let close = |a: [f32; 3], b: [f32; 3]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5);
+        assert!(close(
+            super::transform_point(&m, [1.0, 0.0, 0.0]),
+            [1.0, 4.0, 3.0]
+        ));
+
+        // The normal of the plane x + y = 0 stays perpendicular to it after the scale
+        let n = super::transform_normal(&super::normal_matrix(&m), [1.0, 1.0, 0.0]);
+        let along_plane = super::transform_point(&m, [1.0, -1.0, 0.0]);
+        let origin = super::transform_point(&m, [0.0; 3]);
+        let tangent = [
+            along_plane[0] - origin[0],
+            along_plane[1] - origin[1],
+            along_plane[2] - origin[2],
+        ];
+        assert!((n[0] * tangent[0] + n[1] * tangent[1] + n[2] * tangent[2]).abs() < 1e-5);
+    }
+
+    #[test]
+    fn data_uris() {
+        let dir = std::env::temp_dir();
+        assert_eq!(
+            super::load_uri(&dir, "data:application/octet-stream;base64,AAEC/w==").unwrap(),
+            vec![0, 1, 2, 255]
+        );
+        assert!(super::load_uri(&dir, "data:application/octet-stream;base64,A*").is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::Path};
+
+use clap::{clap_app, crate_authors, crate_version};
+use randomprime::dsp_encoder::{channel_path, encode_audio_file};
+
+fn encode(
+    input: &Path,
+    output: &str,
+    loop_start: Option<u32>,
+    loop_end: Option<u32>,
+) -> Result<(), String> {
+    for (suffix, dsp) in encode_audio_file(input, loop_start, loop_end)? {
+        let path = channel_path(output, suffix);
+        fs::write(&path, dsp).map_err(|e| format!("Failed to write '{}': {}", path, e))?;
+        println!("Wrote {}", path);
+    }
+    Ok(())
+}
+
+fn main() {
+    let app = clap_app!(app =>
+        (version: crate_version!())
+        (author: crate_authors!())
+        (about: "Encodes a WAV or FLAC file as GameCube DSP ADPCM. Stereo input is split into \
+                 an L/R pair of files, as used by streamed audio.")
+        (@setting ArgRequiredElseHelp)
+        (@arg input: -i --input +takes_value +required "Input WAV or FLAC file to encode.")
+        (@arg output: -o --output +takes_value +required
+            "Output path of the .dsp file. For stereo input, L and R are appended to the file \
+             name.")
+        (@arg loop_start: --loop_start +takes_value
+            { |s| s.parse::<u32>()
+                .map(|_| ())
+                    .map_err(|_| "Expected integer for loop start".into()) }
+            "First sample of the loop. The file only loops if a loop start or end is given."
+        )
+        (@arg loop_end: --loop_end +takes_value
+            { |s| s.parse::<u32>()
+                .map(|_| ())
+                    .map_err(|_| "Expected integer for loop end".into()) }
+            "Last sample of the loop. Defaults to the last sample of the file."
+        )
+    );
+    let matches = app.get_matches();
+
+    let res = encode(
+        matches.value_of("input").unwrap().as_ref(),
+        matches.value_of("output").unwrap(),
+        matches.value_of("loop_start").map(|s| s.parse().unwrap()),
+        matches.value_of("loop_end").map(|s| s.parse().unwrap()),
+    );
+    if let Err(s) = res {
+        eprintln!("{} {}", clap::Format::Error("error:"), s);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use clap::{clap_app, crate_authors, crate_version};
+use randomprime::{asset_import::ImportedModel, ResourceData};
+use reader_writer::{Reader, Writable};
+use resource_info_table::resource_info_by_id;
+use structs::{Pak, ResId, ResourceKind};
+
+fn parse_res_id(s: &str) -> Result<u32, String> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u32::from_str_radix(s, 16).map_err(|_| format!("Invalid resource id \"{}\"", s))
+}
+
+fn map_input(input: &Path) -> Result<memmap::Mmap, String> {
+    let input_file = File::open(input).map_err(|e| format!("Failed to open input file: {}", e))?;
+    unsafe { memmap::Mmap::map(&input_file) }
+        .map_err(|e| format!("Failed to map input file: {}", e))
+}
+
+fn list(input: &Path) -> Result<(), String> {
+    let mmap = map_input(input)?;
+    let pak: Pak = Reader::new(&mmap[..]).read(());
+
+    println!(
+        "{:<10} {:<6} {:>10} {:>12} {:<24} Path",
+        "ID", "Type", "Stored", "Decompressed", "Name"
+    );
+    for res in pak.resources.iter() {
+        let name = pak
+            .named_resources
+            .iter()
+            .find(|named| named.file_id == res.file_id && named.fourcc == res.fourcc())
+            .map(|named| {
+                String::from_utf8_lossy(&named.name.iter().collect::<Vec<_>>()).into_owned()
+            })
+            .unwrap_or_default();
+        let path = resource_info_by_id(res.file_id)
+            .map(|info| info.long_name)
+            .unwrap_or("");
+        let res_data = ResourceData::new(&res);
+        let decompressed_size = if res.compressed {
+            // The first word of a compressed resource is its decompressed size
+            res_data.data.clone().read::<u32>(()) as usize
+        } else {
+            res_data.data.len()
+        };
+        println!(
+            "{:08X}   {:<6} {:>10} {:>12} {:<24} {}",
+            res.file_id,
+            res.fourcc().to_string(),
+            res_data.data.len(),
+            decompressed_size,
+            name.trim_end_matches('\0'),
+            path,
+        );
+    }
+
+    Ok(())
+}
+
+fn extract(input: &Path, output_dir: &Path, res_id: Option<u32>) -> Result<(), String> {
+    let mmap = map_input(input)?;
+    let pak: Pak = Reader::new(&mmap[..]).read(());
+
+    if !output_dir.is_dir() {
+        fs::create_dir_all(output_dir)
+            .map_err(|e| format!("Failed to create output dir: {}", e))?;
+    }
+
+    let mut found = false;
+    for res in pak.resources.iter() {
+        if res_id.map(|id| id != res.file_id).unwrap_or(false) {
+            continue;
+        }
+        found = true;
+
+        let bytes = ResourceData::new(&res).decompress();
+        let filename = output_dir.join(format!("{:08X}.{}", res.file_id, res.fourcc()));
+        let mut file = File::create(&filename)
+            .map_err(|e| format!("Failed to create {}: {}", filename.display(), e))?;
+        file.write_all(&bytes)
+            .map_err(|e| format!("Failed to write {}: {}", filename.display(), e))?;
+    }
+
+    if let Some(res_id) = res_id {
+        if !found {
+            Err(format!("Resource {:08X} not found in PAK", res_id))?
+        }
+    }
+
+    Ok(())
+}
+
+fn replace(input: &Path, output: &Path, res_id: u32, file: &Path) -> Result<(), String> {
+    let mmap = map_input(input)?;
+    let mut pak: Pak = Reader::new(&mmap[..]).read(());
+
+    let bytes = fs::read(file).map_err(|e| format!("Failed to read replacement file: {}", e))?;
+
+    let mut replaced = false;
+    let mut cursor = pak.resources.cursor();
+    while cursor.peek().is_some() {
+        let mut cursor = cursor.cursor_advancer();
+        if cursor.peek().unwrap().file_id != res_id {
+            continue;
+        }
+        let res = cursor.value().unwrap();
+        res.kind = ResourceKind::External(bytes.clone(), res.fourcc());
+        res.compressed = false;
+        replaced = true;
+    }
+
+    if !replaced {
+        Err(format!("Resource {:08X} not found in PAK", res_id))?
+    }
+
+    let output_file =
+        File::create(output).map_err(|e| format!("Failed to open output file: {}", e))?;
+    pak.write_to(&mut &output_file)
+        .map_err(|e| format!("Error writing PAK: {}", e))?;
+
+    Ok(())
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut file =
+        File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    file.write_all(bytes)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn import_model(input: &Path, output_dir: &Path, cmdl_id: u32, txtr_id: u32) -> Result<(), String> {
+    let model = ImportedModel::load(input)?;
+
+    if !output_dir.is_dir() {
+        fs::create_dir_all(output_dir)
+            .map_err(|e| format!("Failed to create output dir: {}", e))?;
+    }
+
+    let mut texture_ids = vec![];
+    for (i, txtr) in model.textures()?.into_iter().enumerate() {
+        let id = txtr_id + i as u32;
+        let mut bytes = vec![];
+        txtr.write_to(&mut bytes).unwrap();
+        write_file(&output_dir.join(format!("{:08X}.TXTR", id)), &bytes)?;
+        texture_ids.push(ResId::new(id));
+    }
+
+    let cmdl = model.to_cmdl(&texture_ids)?;
+    let mut bytes = vec![];
+    cmdl.write_to(&mut bytes).unwrap();
+    write_file(&output_dir.join(format!("{:08X}.CMDL", cmdl_id)), &bytes)?;
+
+    println!(
+        "Wrote {:08X}.CMDL and {} textures starting at {:08X}",
+        cmdl_id,
+        texture_ids.len(),
+        txtr_id
+    );
+    Ok(())
+}
+
+fn main() {
+    let app = clap_app!(app =>
+        (version: crate_version!())
+        (author: crate_authors!())
+        (about: "Inspects and edits the resources of a PAK file.")
+        (@setting ArgRequiredElseHelp)
+        (@subcommand list =>
+            (about: "Lists the resources contained in a PAK.")
+            (@arg input: -i --input +takes_value +required "Input PAK file.")
+        )
+        (@subcommand extract =>
+            (about: "Extracts one or all resources of a PAK, decompressed.")
+            (@arg input: -i --input +takes_value +required "Input PAK file.")
+            (@arg output: -o --output +takes_value +required
+                "Directory to write the resources to. Files are named <ID>.<TYPE>.")
+            (@arg id: --id +takes_value
+                { |s| parse_res_id(&s).map(|_| ()) }
+                "Hex id of the resource to extract. Defaults to every resource."
+            )
+        )
+        (@subcommand replace =>
+            (about: "Replaces a resource with the contents of a file and writes a new PAK.")
+            (@arg input: -i --input +takes_value +required "Input PAK file.")
+            (@arg output: -o --output +takes_value +required "Output path to write the PAK to.")
+            (@arg id: --id +takes_value +required
+                { |s| parse_res_id(&s).map(|_| ()) }
+                "Hex id of the resource to replace."
+            )
+            (@arg file: -f --file +takes_value +required
+                "File containing the new (uncompressed) resource data."
+            )
+        )
+        (@subcommand import_model =>
+            (name: "import-model")
+            (about: "Converts a glTF or OBJ model into a CMDL and one TXTR per material.")
+            (@arg input: -i --input +takes_value +required "Input .gltf, .glb or .obj file.")
+            (@arg output: -o --output +takes_value +required
+                "Directory to write the resources to. Files are named <ID>.<TYPE>.")
+            (@arg cmdl_id: --("cmdl-id") +takes_value +required
+                { |s| parse_res_id(&s).map(|_| ()) }
+                "Hex id of the new CMDL."
+            )
+            (@arg txtr_id: --("txtr-id") +takes_value +required
+                { |s| parse_res_id(&s).map(|_| ()) }
+                "Hex id of the first TXTR, the following textures use consecutive ids."
+            )
+        )
+    );
+    let matches = app.get_matches();
+
+    let res = match matches.subcommand() {
+        ("list", Some(matches)) => list(matches.value_of("input").unwrap().as_ref()),
+        ("extract", Some(matches)) => extract(
+            matches.value_of("input").unwrap().as_ref(),
+            matches.value_of("output").unwrap().as_ref(),
+            matches.value_of("id").map(|s| parse_res_id(s).unwrap()),
+        ),
+        ("replace", Some(matches)) => replace(
+            matches.value_of("input").unwrap().as_ref(),
+            matches.value_of("output").unwrap().as_ref(),
+            parse_res_id(matches.value_of("id").unwrap()).unwrap(),
+            matches.value_of("file").unwrap().as_ref(),
+        ),
+        ("import-model", Some(matches)) => import_model(
+            matches.value_of("input").unwrap().as_ref(),
+            matches.value_of("output").unwrap().as_ref(),
+            parse_res_id(matches.value_of("cmdl_id").unwrap()).unwrap(),
+            parse_res_id(matches.value_of("txtr_id").unwrap()).unwrap(),
+        ),
+        _ => return,
+    };
+    if let Err(s) = res {
+        eprintln!("{} {}", clap::Format::Error("error:"), s);
+        std::process::exit(1);
+    }
+}
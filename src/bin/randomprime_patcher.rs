@@ -47,6 +47,10 @@ impl structs::ProgressNotifier for ProgressNotifier {
         }
         println!("Flushing written data to the disk...");
     }
+
+    fn notify_warning(&mut self, msg: &str) {
+        println!("Warning, {}", msg);
+    }
 }
 
 #[cfg(windows)]
@@ -0,0 +1,62 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use clap::{clap_app, crate_authors, crate_version};
+use randomprime::save_file::{build_gci, SaveFileConfig};
+use reader_writer::Reader;
+use structs::GcDisc;
+
+fn generate(input: &Path, config: &Path, output: &Path) -> Result<(), String> {
+    let config_json =
+        fs::read_to_string(config).map_err(|e| format!("Failed to read config file: {}", e))?;
+    let config: SaveFileConfig =
+        serde_json::from_str(&config_json).map_err(|e| format!("JSON parse failed: {}", e))?;
+
+    let input_file = File::open(input).map_err(|e| format!("Failed to open input ISO: {}", e))?;
+    let mmap = unsafe { memmap::Mmap::map(&input_file) }
+        .map_err(|e| format!("Failed to map input ISO: {}", e))?;
+    let gc_disc: GcDisc = Reader::new(&mmap[..]).read(());
+
+    let (gci_name, gci) = build_gci(&gc_disc, &config)?;
+
+    // Writing into a directory uses the file name memory card managers expect
+    let output = if output.is_dir() {
+        output.join(gci_name)
+    } else {
+        output.to_path_buf()
+    };
+    let mut file = File::create(&output)
+        .map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    file.write_all(&gci)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+
+    Ok(())
+}
+
+fn main() {
+    let app = clap_app!(app =>
+        (version: crate_version!())
+        (author: crate_authors!())
+        (about: "Generates a Metroid Prime memory card save (GCI) for the game on an ISO.")
+        (@arg input: -i --input +takes_value +required
+            "Input ISO. Item capacities, hints and world data are read from it, so use the \
+             patched ISO the save will be played with.")
+        (@arg config: -c --config +takes_value +required "JSON description of the save.")
+        (@arg output: -o --output +takes_value +required
+            "Output GCI file, or a directory (e.g. a Dolphin GCI folder) to write it to.")
+    );
+    let matches = app.get_matches();
+
+    let res = generate(
+        matches.value_of("input").unwrap().as_ref(),
+        matches.value_of("config").unwrap().as_ref(),
+        matches.value_of("output").unwrap().as_ref(),
+    );
+    if let Err(s) = res {
+        eprintln!("{} {}", clap::Format::Error("error:"), s);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,49 @@
+use std::{fs, path::Path};
+
+use clap::{clap_app, crate_authors, crate_version};
+use randomprime::thp_encoder::encode_thp_file;
+
+fn encode(
+    frames: &Path,
+    audio: Option<&Path>,
+    output: &str,
+    quality: Option<u8>,
+) -> Result<(), String> {
+    let thp = encode_thp_file(frames, audio, quality)?;
+    fs::write(output, thp).map_err(|e| format!("Failed to write '{}': {}", output, e))?;
+    println!("Wrote {}", output);
+    Ok(())
+}
+
+fn main() {
+    let app = clap_app!(app =>
+        (version: crate_version!())
+        (author: crate_authors!())
+        (about: "Encodes a video as a GameCube THP file, played at 29.97 frames per second.")
+        (@setting ArgRequiredElseHelp)
+        (@arg input: -i --input +takes_value +required
+            "Directory of PNG or JPEG frames, in order of their file names, or a Motion JPEG \
+             file. Frames must be a multiple of 16 pixels wide and tall.")
+        (@arg audio: -a --audio +takes_value "Mono or stereo WAV or FLAC file to play alongside \
+                                               the video.")
+        (@arg output: -o --output +takes_value +required "Output path of the .thp file.")
+        (@arg quality: -q --quality +takes_value
+            { |s| s.parse::<u8>()
+                .map(|_| ())
+                    .map_err(|_| "Expected integer for quality".into()) }
+            "JPEG quality of the frames, from 1 to 100. Defaults to 85."
+        )
+    );
+    let matches = app.get_matches();
+
+    let res = encode(
+        matches.value_of("input").unwrap().as_ref(),
+        matches.value_of("audio").map(Path::new),
+        matches.value_of("output").unwrap(),
+        matches.value_of("quality").map(|s| s.parse().unwrap()),
+    );
+    if let Err(s) = res {
+        eprintln!("{} {}", clap::Format::Error("error:"), s);
+        std::process::exit(1);
+    }
+}
@@ -93,6 +93,15 @@ impl structs::ProgressNotifier for ProgressNotifier {
             CbMessage::progress_json(100., "Flushing written data to the disk").as_ptr(),
         );
     }
+
+    fn notify_warning(&mut self, msg: &str) {
+        let percent = self.bytes_so_far as f64 / self.total_size.max(1) as f64 * 100.;
+        let msg = format!("Warning, {}", msg);
+        (self.cb)(
+            self.cb_data,
+            CbMessage::progress_json(percent, &msg).as_ptr(),
+        );
+    }
 }
 
 fn inner(
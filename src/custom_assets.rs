@@ -1,6 +1,7 @@
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
+    fs,
 };
 
 use reader_writer::{FourCC, Reader, Writable};
@@ -11,9 +12,10 @@ use crate::{
     door_meta::{BlastShieldType, DoorType},
     elevators::{SpawnRoomData, World},
     extern_assets::ExternPickupModel,
-    patch_config::{GenericTexture, PatchConfig, Version},
-    patches::WaterType,
+    patch_config::{GenericTexture, PatchConfig, ScanImageConfig, Version},
+    patches::{gui_frame_dependencies, WaterType},
     pickup_meta::{self, PickupModel, PickupType},
+    txtr_conversions::{png_to_txtr, TxtrFormatExt},
     GcDiscLookupExtensions, ResourceData,
 };
 
@@ -328,7 +330,10 @@ pub mod custom_asset_ids {
         DEFAULT_PICKUP_SCANS: SCAN = DEFAULT_PICKUP_SCAN_STRGS.to_u32() + 50,
         DEFAULT_PICKUP_HUDMEMO_STRGS: STRG = DEFAULT_PICKUP_SCANS.to_u32() + 50,
 
-        EXTRA_IDS_START: STRG = DEFAULT_PICKUP_HUDMEMO_STRGS.to_u32() + 50,
+        // Popup and map text of configured hints
+        HINT_STRGS: STRG = DEFAULT_PICKUP_HUDMEMO_STRGS.to_u32() + 50,
+
+        EXTRA_IDS_START: STRG = HINT_STRGS.to_u32() + 200,
     }
 }
 
@@ -786,8 +791,8 @@ pub fn custom_assets<'r>(
                 for custom_scan in room.extra_scans.as_ref().unwrap().iter() {
                     let contents = &custom_scan.text;
 
-                    // Check if this string already has a scan_id //
-                    if string_to_scan_strg.contains_key(contents) {
+                    // Check if this string already has a scan_id, unless it has images //
+                    if custom_scan.images.is_none() && string_to_scan_strg.contains_key(contents) {
                         let (scan_id, strg_id) = string_to_scan_strg.get(contents).unwrap();
 
                         // Add this scan_id as a dep of this world if it wasn't already //
@@ -875,14 +880,23 @@ pub fn custom_assets<'r>(
                             .insert(scan_id.to_u32(), custom_scan.logbook_category.unwrap());
                     }
 
-                    assets.extend_from_slice(&create_item_scan_strg_pair_2(
+                    let mut scan_strg = create_item_scan_strg_pair_2(
                         scan_id,
                         strg_id,
                         strings,
                         is_red,
                         *custom_scan.logbook_category.as_ref().unwrap_or(&0),
                         config.version,
-                    ));
+                    );
+                    if let Some(images) = custom_scan.images.as_ref() {
+                        set_scan_images(
+                            &mut scan_strg[0],
+                            images,
+                            &mut custom_asset_offset,
+                            &mut assets,
+                        )?;
+                    }
+                    assets.extend_from_slice(&scan_strg);
 
                     // Map for easy lookup when patching //
                     let key = PickupHashKey::from_location(level_name, room_name, extra_scans_idx);
@@ -890,7 +904,9 @@ pub fn custom_assets<'r>(
                     local_savw_scans_to_add[world as usize].push(scan_id);
 
                     // Cache this scan/strg pair for re-use //
-                    string_to_scan_strg.insert(contents, (scan_id, strg_id));
+                    if custom_scan.images.is_none() {
+                        string_to_scan_strg.insert(contents, (scan_id, strg_id));
+                    }
 
                     extra_scans_idx += 1;
                 }
@@ -1040,12 +1056,19 @@ pub fn custom_assets<'r>(
                     pickup_hudmemos.insert(key, strg_id);
                 }
 
+                if pickup.scan_images.is_some() && pickup.scan_text.is_none() {
+                    return Err(format!(
+                        "Pickup {} in {} has scan images but no scan text",
+                        pickup_idx, room_name
+                    ));
+                }
+
                 // Custom scan string
                 if pickup.scan_text.is_some() {
                     let scan_text = pickup.scan_text.as_ref().unwrap();
 
-                    // Check if this string already has a scan_id //
-                    if string_to_scan_strg.contains_key(scan_text) {
+                    // Check if this string already has a scan_id, unless it has images //
+                    if pickup.scan_images.is_none() && string_to_scan_strg.contains_key(scan_text) {
                         let (scan_id, strg_id) = string_to_scan_strg.get(scan_text).unwrap();
 
                         // Add this scan_id as a dep of this world if it wasn't already //
@@ -1068,25 +1091,34 @@ pub fn custom_assets<'r>(
                         custom_asset_offset += 1;
 
                         // Build resource //
-                        if room_name.trim().to_lowercase() == "research core"
+                        let mut scan_strg = if room_name.trim().to_lowercase() == "research core"
                         // make the research core scan red because it goes on the terminal
                         {
-                            assets.extend_from_slice(&create_item_scan_strg_pair_2(
+                            create_item_scan_strg_pair_2(
                                 scan_id,
                                 strg_id,
                                 vec![format!("{}\0", scan_text)],
                                 1,
                                 0,
                                 config.version,
-                            ));
+                            )
                         } else {
-                            assets.extend_from_slice(&create_item_scan_strg_pair(
+                            create_item_scan_strg_pair(
                                 scan_id,
                                 strg_id,
                                 format!("{}\0", scan_text),
                                 config.version,
-                            ));
+                            )
+                        };
+                        if let Some(images) = pickup.scan_images.as_ref() {
+                            set_scan_images(
+                                &mut scan_strg[0],
+                                images,
+                                &mut custom_asset_offset,
+                                &mut assets,
+                            )?;
                         }
+                        assets.extend_from_slice(&scan_strg);
 
                         // Map for easy lookup when patching //
                         let key = PickupHashKey::from_location(level_name, room_name, pickup_idx);
@@ -1094,7 +1126,9 @@ pub fn custom_assets<'r>(
                         local_savw_scans_to_add[world as usize].push(scan_id);
 
                         // Cache this scan/strg pair for re-use //
-                        string_to_scan_strg.insert(scan_text.to_string(), (scan_id, strg_id));
+                        if pickup.scan_images.is_none() {
+                            string_to_scan_strg.insert(scan_text.to_string(), (scan_id, strg_id));
+                        }
                     }
                 }
 
@@ -1376,6 +1410,25 @@ pub fn collect_game_resources<'r>(
     ];
     looking_for.extend(player_freeze_deps);
 
+    // Replacement textures of recolored particles
+    looking_for.extend(
+        config
+            .particle_colors
+            .iter()
+            .flat_map(|particle_colors| particle_colors.textures.iter().flatten())
+            .map(|(_, replacement)| (*replacement, FourCC::from_bytes(b"TXTR"))),
+    );
+
+    let scan_txtrs: HashSet<(u32, FourCC)> = scan_image_txtrs(config).collect();
+    looking_for.extend(scan_txtrs.iter().cloned());
+
+    // Fonts and textures of edited GUI frames
+    let mut gui_frame_deps = HashSet::new();
+    for frame_config in config.gui_frames.values() {
+        gui_frame_deps.extend(gui_frame_dependencies(gc_disc, frame_config)?);
+    }
+    looking_for.extend(gui_frame_deps.iter().cloned());
+
     // Dependencies read from paks and custom assets will go here //
     let mut found = HashMap::with_capacity(looking_for.len());
 
@@ -1406,6 +1459,13 @@ pub fn collect_game_resources<'r>(
         }
     }
 
+    if let Some((id, _)) = scan_txtrs.iter().find(|key| looking_for.contains(key)) {
+        return Err(format!("Scan image TXTR 0x{:X} does not exist", id));
+    }
+    if let Some((id, fourcc)) = gui_frame_deps.iter().find(|key| looking_for.contains(key)) {
+        return Err(format!("GUI frame {} 0x{:X} does not exist", fourcc, id));
+    }
+
     // Maps pickup location to STRG to use
     let mut pickup_hudmemos = HashMap::<PickupHashKey, ResId<res_id::STRG>>::new();
     let mut pickup_scans =
@@ -2055,3 +2115,72 @@ fn create_item_scan_strg_pair_2<'r>(
 
     [scan, strg]
 }
+
+/// Fills the image slots of a custom scan, adding a new TXTR for each image read from a PNG.
+fn set_scan_images<'r>(
+    scan: &mut structs::Resource<'r>,
+    images: &[ScanImageConfig],
+    custom_asset_offset: &mut u32,
+    assets: &mut Vec<structs::Resource<'r>>,
+) -> Result<(), String> {
+    if images.len() > 4 {
+        return Err(format!(
+            "A scan can have at most 4 images, got {}",
+            images.len()
+        ));
+    }
+
+    let scan = scan.kind.as_scan_mut().unwrap();
+    for (slot, image) in scan.images.iter_mut().zip(images) {
+        slot.txtr = match (&image.png, image.txtr) {
+            (Some(png_path), None) => {
+                let format_name = image.format.as_deref().unwrap_or("CMPR");
+                let format = structs::TxtrFormat::from_str(format_name)?;
+                let png = fs::read(png_path)
+                    .map_err(|e| format!("Failed to read scan image '{}': {}", png_path, e))?;
+                let txtr = png_to_txtr(&png, format, Some(1))
+                    .map_err(|e| format!("Failed to convert scan image '{}': {}", png_path, e))?;
+
+                let txtr_id = ResId::<res_id::TXTR>::new(
+                    custom_asset_ids::EXTRA_IDS_START.to_u32() + *custom_asset_offset,
+                );
+                *custom_asset_offset += 1;
+                let mut bytes = vec![];
+                txtr.write_to(&mut bytes).unwrap();
+                assets.push(build_resource(
+                    txtr_id,
+                    ResourceKind::External(bytes, b"TXTR".into()),
+                ));
+                txtr_id
+            }
+            (None, Some(txtr)) => ResId::new(txtr),
+            _ => return Err("Scan images need exactly one of png or txtr".to_string()),
+        };
+        slot.image_position = image.pane;
+        slot.appearance_percent = image.appearance_percent.unwrap_or(slot.appearance_percent);
+        slot.width = image.width.unwrap_or(0);
+        slot.height = image.height.unwrap_or(0);
+        slot.interval = image.interval.unwrap_or(0.0);
+        slot.fade_duration = image.fade_duration.unwrap_or(0.0);
+    }
+
+    Ok(())
+}
+
+/// Existing textures referenced by custom scan images, which need to be copied into the areas
+/// that use them.
+fn scan_image_txtrs(config: &PatchConfig) -> impl Iterator<Item = (u32, FourCC)> + '_ {
+    config
+        .level_data
+        .values()
+        .flat_map(|level| level.rooms.values())
+        .flat_map(|room| {
+            let extra_scans = room.extra_scans.iter().flatten();
+            let pickups = room.pickups.iter().flatten();
+            extra_scans
+                .flat_map(|scan| scan.images.iter().flatten())
+                .chain(pickups.flat_map(|pickup| pickup.scan_images.iter().flatten()))
+        })
+        .filter_map(|image| image.txtr)
+        .map(|txtr| (txtr, FourCC::from_bytes(b"TXTR")))
+}
@@ -1,11 +1,27 @@
 use reader_writer::FourCC;
-use structs::{res_id, scly_structs::TypeVulnerability, ResId};
+use structs::{res_id, scly_structs::TypeVulnerability, MarkupBuilder, ResId};
 
 use crate::{
     custom_assets::custom_asset_ids,
     structs::scly_props::structs::{BeamCombos, ChargedBeams, DamageVulnerability},
 };
 
+const SCAN_HIGHLIGHT_COLOR: [u8; 4] = [0xD9, 0x18, 0x18, 0xFF];
+
+// Scan text which highlights what is needed to get through
+fn requirement_scan_text(before: &str, requirement: &str, after: &str) -> Vec<String> {
+    vec![
+        "Analysis complete.\0".to_string(),
+        "\0".to_string(),
+        MarkupBuilder::new()
+            .text(before)
+            .colored(SCAN_HIGHLIGHT_COLOR, requirement)
+            .text(after)
+            .text("\0")
+            .build(),
+    ]
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum DoorType {
     Blue,
@@ -297,21 +313,21 @@ impl DoorType {
         }
     }
 
-    pub fn map_object_type(&self) -> u32 {
+    pub fn map_object_type(&self) -> structs::MapaObjectType {
         let door = self.to_horizontal();
 
         match door {
-            DoorType::Blue => structs::MapaObjectType::DoorNormal as u32,
-            DoorType::PowerOnly => structs::MapaObjectType::DoorNormal as u32,
-            DoorType::Charge => structs::MapaObjectType::DoorNormal as u32,
-            DoorType::Bomb => structs::MapaObjectType::DoorNormal as u32,
-            DoorType::Purple => structs::MapaObjectType::DoorWave as u32,
-            DoorType::Wavebuster => structs::MapaObjectType::DoorWave as u32,
-            DoorType::White => structs::MapaObjectType::DoorIce as u32,
-            DoorType::Icespreader => structs::MapaObjectType::DoorIce as u32,
-            DoorType::Red => structs::MapaObjectType::DoorPlasma as u32,
-            DoorType::Flamethrower => structs::MapaObjectType::DoorPlasma as u32,
-            _ => structs::MapaObjectType::DoorShield as u32, // everything else is non-vanilla and thus shield
+            DoorType::Blue => structs::MapaObjectType::DoorNormal,
+            DoorType::PowerOnly => structs::MapaObjectType::DoorNormal,
+            DoorType::Charge => structs::MapaObjectType::DoorNormal,
+            DoorType::Bomb => structs::MapaObjectType::DoorNormal,
+            DoorType::Purple => structs::MapaObjectType::DoorWave,
+            DoorType::Wavebuster => structs::MapaObjectType::DoorWave,
+            DoorType::White => structs::MapaObjectType::DoorIce,
+            DoorType::Icespreader => structs::MapaObjectType::DoorIce,
+            DoorType::Red => structs::MapaObjectType::DoorPlasma,
+            DoorType::Flamethrower => structs::MapaObjectType::DoorPlasma,
+            _ => structs::MapaObjectType::DoorShield, // everything else is non-vanilla and thus shield
         }
     }
 
@@ -492,80 +508,47 @@ impl DoorType {
         let door = self.to_horizontal();
 
         match door {
-            DoorType::PowerOnly => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will only open with &push;&main-color=#D91818;Power Beam&pop;.\0"
-                    .to_string(),
-            ],
-            DoorType::Boost => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Boost Ball&pop;.\0".to_string(),
-            ],
-            DoorType::PowerBomb => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Power Bombs&pop;.\0"
-                    .to_string(),
-            ],
-            DoorType::Bomb => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Morph Ball Bombs&pop;.\0"
-                    .to_string(),
-            ],
-            DoorType::Missile => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Missiles&pop;.\0".to_string(),
-            ],
-            DoorType::Charge => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Charge Beam&pop;.\0"
-                    .to_string(),
-            ],
-            DoorType::Super => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Super Missiles&pop;.\0"
-                    .to_string(),
-            ],
-            DoorType::Wavebuster => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Wavebuster&pop;.\0".to_string(),
-            ],
-            DoorType::Icespreader => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Ice Spreader&pop;.\0"
-                    .to_string(),
-            ],
-            DoorType::Flamethrower => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Flamethrower&pop;.\0"
-                    .to_string(),
-            ],
+            DoorType::PowerOnly => {
+                requirement_scan_text("This door will only open with ", "Power Beam", ".")
+            }
+            DoorType::Boost => {
+                requirement_scan_text("This door will open with ", "Boost Ball", ".")
+            }
+            DoorType::PowerBomb => {
+                requirement_scan_text("This door will open with ", "Power Bombs", ".")
+            }
+            DoorType::Bomb => {
+                requirement_scan_text("This door will open with ", "Morph Ball Bombs", ".")
+            }
+            DoorType::Missile => {
+                requirement_scan_text("This door will open with ", "Missiles", ".")
+            }
+            DoorType::Charge => {
+                requirement_scan_text("This door will open with ", "Charge Beam", ".")
+            }
+            DoorType::Super => {
+                requirement_scan_text("This door will open with ", "Super Missiles", ".")
+            }
+            DoorType::Wavebuster => {
+                requirement_scan_text("This door will open with ", "Wavebuster", ".")
+            }
+            DoorType::Icespreader => {
+                requirement_scan_text("This door will open with ", "Ice Spreader", ".")
+            }
+            DoorType::Flamethrower => {
+                requirement_scan_text("This door will open with ", "Flamethrower", ".")
+            }
             DoorType::Disabled => vec![
                 "Analysis complete.\0".to_string(),
                 "\0".to_string(),
                 "This door cannot be opened.\0".to_string(),
             ],
-            DoorType::Ai => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with the &push;&main-color=#D91818;help of an enemy&pop;.\0"
-                    .to_string(),
-            ],
-            DoorType::Phazon => vec![
-                "Analysis complete.\0".to_string(),
-                "\0".to_string(),
-                "This door will open with &push;&main-color=#D91818;Phazon Beam&pop;.\0"
-                    .to_string(),
-            ],
+            DoorType::Ai => {
+                requirement_scan_text("This door will open with the ", "help of an enemy", ".")
+            }
+            DoorType::Phazon => {
+                requirement_scan_text("This door will open with ", "Phazon Beam", ".")
+            }
             _ => vec!["Task failed successfully\0".to_string()], // Vanilla doors do not need a scan point
         }
     }
@@ -1104,50 +1087,61 @@ impl BlastShieldType {
 
     pub fn scan_text(&self) -> Vec<String> {
         match self {
-            BlastShieldType::PowerBomb    => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "There is an Advanced Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is reinforced with &push;&main-color=#D91818;Bendezium&pop;, rendering it invulnerable to most weapons.\0".to_string(),
-                                                 ],
-            BlastShieldType::Super        => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "There is an Advanced Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is reinforced with &push;&main-color=#D91818;Cordite&pop;, rendering it invulnerable to most weapons.\0".to_string(),
-                                                 ],
-            BlastShieldType::Wavebuster   => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "There is an Elemental Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is invulnerable to standard Beam fire. Continuous exposure to &push;&main-color=#D91818;Extreme Amperage&pop; may damage it.\0".to_string(),
-                                                 ],
-            BlastShieldType::Icespreader  => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "There is an Elemental Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is invulnerable to standard Beam fire. A concussive blast augmented with &push;&main-color=#D91818;Extreme Cold&pop; may damage it.\0".to_string(),
-                                                 ],
-            BlastShieldType::Flamethrower => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "There is an Elemental Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is invulnerable to standard Beam fire. Continuous exposure to &push;&main-color=#D91818;Extreme Heat&pop; may damage it.\0".to_string(),
-                                                 ],
-            BlastShieldType::Charge       => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "This Blast Shield can be destroyed with a &push;&main-color=#D91818;Concussive Blast&pop;.\0".to_string(),
-                                                 ],
-            BlastShieldType::Grapple      => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "This Blast Shield can be removed with a &push;&main-color=#D91818;Strong Grip&pop;.\0".to_string(),
-                                                 ],
-            BlastShieldType::Bomb         => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "There is a Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is reinforced with &push;&main-color=#D91818;Sandstone&pop;, rendering it invulnerable to most weapons.\0".to_string(),
-                                                 ],
-            BlastShieldType::Phazon       => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "This Blast Shield can be made to malfunction when exposed to &push;&main-color=#D91818;Extreme Radiation&pop;.\0".to_string(),
-                                                 ],
-            BlastShieldType::Thermal      => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "This Blast Shield can be unlocked with &push;&main-color=#D91818;Thermal Visor&pop;.\0".to_string(),
-                                                 ],
-            BlastShieldType::XRay         => vec!["Analysis complete.\0".to_string(),
-                                                  "\0".to_string(),
-                                                  "This Blast Shield can be unlocked with &push;&main-color=#D91818;X-Ray Visor&pop;.\0".to_string(),
-                                                 ],
+            BlastShieldType::PowerBomb => requirement_scan_text(
+                "There is an Advanced Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is reinforced with ",
+                "Bendezium",
+                ", rendering it invulnerable to most weapons.",
+            ),
+            BlastShieldType::Super => requirement_scan_text(
+                "There is an Advanced Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is reinforced with ",
+                "Cordite",
+                ", rendering it invulnerable to most weapons.",
+            ),
+            BlastShieldType::Wavebuster => requirement_scan_text(
+                "There is an Elemental Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is invulnerable to standard Beam fire. Continuous exposure to ",
+                "Extreme Amperage",
+                " may damage it.",
+            ),
+            BlastShieldType::Icespreader => requirement_scan_text(
+                "There is an Elemental Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is invulnerable to standard Beam fire. A concussive blast augmented with ",
+                "Extreme Cold",
+                " may damage it.",
+            ),
+            BlastShieldType::Flamethrower => requirement_scan_text(
+                "There is an Elemental Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is invulnerable to standard Beam fire. Continuous exposure to ",
+                "Extreme Heat",
+                " may damage it.",
+            ),
+            BlastShieldType::Charge => requirement_scan_text(
+                "This Blast Shield can be destroyed with a ",
+                "Concussive Blast",
+                ".",
+            ),
+            BlastShieldType::Grapple => requirement_scan_text(
+                "This Blast Shield can be removed with a ",
+                "Strong Grip",
+                ".",
+            ),
+            BlastShieldType::Bomb => requirement_scan_text(
+                "There is a Blast Shield on the door blocking access. Analysis indicates that the Blast Shield is reinforced with ",
+                "Sandstone",
+                ", rendering it invulnerable to most weapons.",
+            ),
+            BlastShieldType::Phazon => requirement_scan_text(
+                "This Blast Shield can be made to malfunction when exposed to ",
+                "Extreme Radiation",
+                ".",
+            ),
+            BlastShieldType::Thermal => requirement_scan_text(
+                "This Blast Shield can be unlocked with ",
+                "Thermal Visor",
+                ".",
+            ),
+            BlastShieldType::XRay => requirement_scan_text(
+                "This Blast Shield can be unlocked with ",
+                "X-Ray Visor",
+                ".",
+            ),
             _ => vec!["\0".to_string()], // Vanilla missile locks do not have scans associated with the actor
         }
     }
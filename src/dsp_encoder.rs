@@ -0,0 +1,688 @@
+// GameCube DSP ADPCM encoder, used to add custom streamed music.
+// The coefficient search and frame encoding follow Nintendo's reference encoder, by way of the
+// public domain DSPADPCM implementation by Jack Andersen.
+
+// The matrix math is easier to check against the reference with explicit indices
+#![allow(clippy::needless_range_loop)]
+
+use std::{fs::File, io::BufReader, path::Path};
+
+const SAMPLES_PER_FRAME: usize = 14;
+const BYTES_PER_FRAME: usize = 8;
+const HEADER_SIZE: usize = 0x60;
+
+type Vector = [f64; 3];
+
+/// PCM samples decoded from a WAV or FLAC file, one vector per channel.
+#[derive(Clone, Debug)]
+pub struct PcmAudio {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<i16>>,
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open audio file '{}': {}", path.display(), e))?;
+    Ok(BufReader::new(file))
+}
+
+// Scales a sample of any bit depth to 16 bits
+fn to_i16(sample: i32, bits: u32) -> i16 {
+    if bits > 16 {
+        (sample >> (bits - 16)) as i16
+    } else {
+        (sample << (16 - bits)) as i16
+    }
+}
+
+fn deinterleave(samples: Vec<i16>, channel_count: usize) -> Vec<Vec<i16>> {
+    let mut channels = vec![Vec::with_capacity(samples.len() / channel_count); channel_count];
+    for frame in samples.chunks_exact(channel_count) {
+        for (channel, sample) in channels.iter_mut().zip(frame) {
+            channel.push(*sample);
+        }
+    }
+    channels
+}
+
+fn read_wav(path: &Path) -> Result<PcmAudio, String> {
+    let error = |e: hound::Error| format!("Failed to read '{}': {}", path.display(), e);
+    let mut reader = hound::WavReader::new(open(path)?).map_err(error)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Int => reader
+            .samples::<i32>()
+            .map(|s| s.map(|s| to_i16(s, spec.bits_per_sample as u32)))
+            .collect::<Result<Vec<_>, _>>(),
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .map(|s| s.map(|s| (s.clamp(-1.0, 1.0) * 32767.0) as i16))
+            .collect::<Result<Vec<_>, _>>(),
+    }
+    .map_err(error)?;
+    Ok(PcmAudio {
+        sample_rate: spec.sample_rate,
+        channels: deinterleave(samples, spec.channels as usize),
+    })
+}
+
+fn read_flac(path: &Path) -> Result<PcmAudio, String> {
+    let error = |e: claxon::Error| format!("Failed to read '{}': {}", path.display(), e);
+    let mut reader = claxon::FlacReader::new(open(path)?).map_err(error)?;
+    let info = reader.streaminfo();
+    let samples = reader
+        .samples()
+        .map(|s| s.map(|s| to_i16(s, info.bits_per_sample)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(error)?;
+    Ok(PcmAudio {
+        sample_rate: info.sample_rate,
+        channels: deinterleave(samples, info.channels as usize),
+    })
+}
+
+/// Reads a WAV or FLAC file, depending on its extension.
+pub fn read_audio_file(path: &Path) -> Result<PcmAudio, String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let audio = match &extension[..] {
+        "wav" => read_wav(path)?,
+        "flac" => read_flac(path)?,
+        _ => {
+            return Err(format!(
+                "Unsupported audio file '{}', expected a .wav or .flac file",
+                path.display()
+            ))
+        }
+    };
+    if audio.channels.is_empty() || audio.channels.len() > 2 {
+        return Err(format!(
+            "'{}' has {} channels, only mono and stereo are supported",
+            path.display(),
+            audio.channels.len()
+        ));
+    }
+    Ok(audio)
+}
+
+/*** Coefficients ***/
+
+fn inner_product_merge(pcm: &[i16; 28]) -> Vector {
+    let mut out = [0.0; 3];
+    for (i, out) in out.iter_mut().enumerate() {
+        for x in 0..14 {
+            *out -= pcm[14 + x - i] as f64 * pcm[14 + x] as f64;
+        }
+    }
+    out
+}
+
+fn outer_product_merge(pcm: &[i16; 28]) -> [Vector; 3] {
+    let mut mtx = [[0.0; 3]; 3];
+    for x in 1..=2 {
+        for y in 1..=2 {
+            for z in 0..14 {
+                mtx[x][y] += pcm[14 + z - x] as f64 * pcm[14 + z - y] as f64;
+            }
+        }
+    }
+    mtx
+}
+
+// LU decomposition with partial pivoting. Returns None if the matrix is singular.
+fn analyze_ranges(mtx: &mut [Vector; 3]) -> Option<[usize; 3]> {
+    let mut recips = [0.0; 3];
+    for x in 1..=2 {
+        let val = mtx[x][1].abs().max(mtx[x][2].abs());
+        if val < f64::EPSILON {
+            return None;
+        }
+        recips[x] = 1.0 / val;
+    }
+
+    let mut indices = [0; 3];
+    let mut max_index = 0;
+    for i in 1..=2 {
+        for x in 1..i {
+            let mut tmp = mtx[x][i];
+            for y in 1..x {
+                tmp -= mtx[x][y] * mtx[y][i];
+            }
+            mtx[x][i] = tmp;
+        }
+
+        let mut val = 0.0;
+        for x in i..=2 {
+            let mut tmp = mtx[x][i];
+            for y in 1..i {
+                tmp -= mtx[x][y] * mtx[y][i];
+            }
+            mtx[x][i] = tmp;
+            let tmp = tmp.abs() * recips[x];
+            if tmp >= val {
+                val = tmp;
+                max_index = x;
+            }
+        }
+
+        if max_index != i {
+            mtx.swap(max_index, i);
+            recips[max_index] = recips[i];
+        }
+        indices[i] = max_index;
+
+        if mtx[i][i] == 0.0 {
+            return None;
+        }
+        if i != 2 {
+            let tmp = 1.0 / mtx[i][i];
+            for row in mtx.iter_mut().skip(i + 1) {
+                row[i] *= tmp;
+            }
+        }
+    }
+
+    let mut min = 1.0e10f64;
+    let mut max = 0.0f64;
+    for i in 1..=2 {
+        let tmp = mtx[i][i].abs();
+        min = min.min(tmp);
+        max = max.max(tmp);
+    }
+    if min / max < 1.0e-10 {
+        return None;
+    }
+    Some(indices)
+}
+
+fn bidirectional_filter(mtx: &[Vector; 3], indices: &[usize; 3], vec: &mut Vector) {
+    let mut x = 0;
+    for i in 1..=2 {
+        let index = indices[i];
+        let mut tmp = vec[index];
+        vec[index] = vec[i];
+        if x != 0 {
+            for y in x..i {
+                tmp -= vec[y] * mtx[i][y];
+            }
+        } else if tmp != 0.0 {
+            x = i;
+        }
+        vec[i] = tmp;
+    }
+
+    for i in (1..=2).rev() {
+        let mut tmp = vec[i];
+        for y in (i + 1)..=2 {
+            tmp -= vec[y] * mtx[i][y];
+        }
+        vec[i] = tmp / mtx[i][i];
+    }
+    vec[0] = 1.0;
+}
+
+// Returns false if the result is unstable
+fn quadratic_merge(vec: &mut Vector) -> bool {
+    let v2 = vec[2];
+    let tmp = 1.0 - v2 * v2;
+    if tmp == 0.0 {
+        return false;
+    }
+    vec[0] = (vec[0] - v2 * v2) / tmp;
+    vec[1] = (vec[1] - vec[1] * v2) / tmp;
+    vec[1].abs() <= 1.0
+}
+
+fn finish_record(mut vec: Vector) -> Vector {
+    for v in vec.iter_mut().skip(1) {
+        *v = v.clamp(-0.9999999999, 0.9999999999);
+    }
+    [1.0, vec[2] * vec[1] + vec[1], vec[2]]
+}
+
+fn matrix_filter(src: &Vector) -> Vector {
+    let mut mtx = [[0.0; 3]; 3];
+    mtx[2][0] = 1.0;
+    for i in 1..=2 {
+        mtx[2][i] = -src[i];
+    }
+    for i in (1..=2).rev() {
+        let val = 1.0 - mtx[i][i] * mtx[i][i];
+        for y in 1..=i {
+            mtx[i - 1][y] = (mtx[i][i] * mtx[i][y] + mtx[i][y]) / val;
+        }
+    }
+
+    let mut dst = [1.0, 0.0, 0.0];
+    for i in 1..=2 {
+        for y in 1..=i {
+            dst[i] += mtx[i][y] * dst[i - y];
+        }
+    }
+    dst
+}
+
+fn merge_finish_record(src: &Vector) -> Vector {
+    let mut dst = [1.0, 0.0, 0.0];
+    let mut tmp = [0.0; 3];
+    let mut val = src[0];
+    for i in 1..=2 {
+        let mut v2 = 0.0;
+        for y in 1..i {
+            v2 += dst[y] * src[i - y];
+        }
+        dst[i] = if val > 0.0 { -(v2 + src[i]) / val } else { 0.0 };
+        tmp[i] = dst[i];
+        for y in 1..i {
+            dst[y] += dst[i] * dst[i - y];
+        }
+        val *= 1.0 - dst[i] * dst[i];
+    }
+    finish_record(tmp)
+}
+
+fn contrast_vectors(source1: &Vector, source2: &Vector) -> f64 {
+    let val = (source2[2] * source2[1] - source2[1]) / (1.0 - source2[2] * source2[2]);
+    let val1 = source1[0] * source1[0] + source1[1] * source1[1] + source1[2] * source1[2];
+    let val2 = source1[0] * source1[1] + source1[1] * source1[2];
+    let val3 = source1[0] * source1[2];
+    val1 + 2.0 * val * val2 + 2.0 * (-source2[1] * val - source2[2]) * val3
+}
+
+fn filter_records(best: &mut [Vector; 8], exp: usize, records: &[Vector]) {
+    for _ in 0..2 {
+        let mut counts = [0usize; 8];
+        let mut sums = [[0.0; 3]; 8];
+        for record in records {
+            let mut index = 0;
+            let mut value = 1.0e30;
+            for (i, best) in best.iter().enumerate().take(exp) {
+                let tmp = contrast_vectors(best, record);
+                if tmp < value {
+                    value = tmp;
+                    index = i;
+                }
+            }
+            counts[index] += 1;
+            let filtered = matrix_filter(record);
+            for y in 0..3 {
+                sums[index][y] += filtered[y];
+            }
+        }
+
+        for i in 0..exp {
+            if counts[i] > 0 {
+                for sum in sums[i].iter_mut() {
+                    *sum /= counts[i] as f64;
+                }
+            }
+            best[i] = merge_finish_record(&sums[i]);
+        }
+    }
+}
+
+/// Finds the 8 pairs of prediction coefficients which fit the samples best.
+pub fn correlate_coefs(samples: &[i16]) -> [[i16; 2]; 8] {
+    let mut records = vec![];
+    let mut history = [0i16; 28];
+    for frame in samples.chunks(SAMPLES_PER_FRAME) {
+        history.copy_within(14.., 0);
+        history[14..].fill(0);
+        history[14..14 + frame.len()].copy_from_slice(frame);
+
+        let mut vec = inner_product_merge(&history);
+        if vec[0].abs() > 10.0 {
+            let mut mtx = outer_product_merge(&history);
+            if let Some(indices) = analyze_ranges(&mut mtx) {
+                bidirectional_filter(&mtx, &indices, &mut vec);
+                if quadratic_merge(&mut vec) {
+                    records.push(finish_record(vec));
+                }
+            }
+        }
+    }
+
+    // Silence has nothing to predict
+    if records.is_empty() {
+        return [[0; 2]; 8];
+    }
+
+    let mut vec = [1.0, 0.0, 0.0];
+    for record in records.iter() {
+        let filtered = matrix_filter(record);
+        vec[1] += filtered[1];
+        vec[2] += filtered[2];
+    }
+    vec[1] /= records.len() as f64;
+    vec[2] /= records.len() as f64;
+
+    let mut best = [[0.0; 3]; 8];
+    best[0] = merge_finish_record(&vec);
+
+    let mut exp = 1;
+    for w in 0..3 {
+        for i in 0..exp {
+            best[exp + i] = [best[i][0], best[i][1] - 0.01, best[i][2]];
+        }
+        exp = 1 << (w + 1);
+        filter_records(&mut best, exp, &records);
+    }
+
+    let mut coefs = [[0i16; 2]; 8];
+    for (coefs, best) in coefs.iter_mut().zip(best.iter()) {
+        for (coef, value) in coefs.iter_mut().zip(&best[1..]) {
+            *coef = (-value * 2048.0).round().clamp(-32768.0, 32767.0) as i16;
+        }
+    }
+    coefs
+}
+
+/*** Frames ***/
+
+// Encodes up to 14 samples in `pcm[2..]`, with `pcm[0..2]` holding the two previously decoded
+// samples. The samples are replaced by what the frame decodes to.
+fn encode_frame(pcm: &mut [i32; 16], sample_count: usize, coefs: &[[i16; 2]; 8]) -> [u8; 8] {
+    let mut in_samples = [[0i32; 16]; 8];
+    let mut out_samples = [[0i32; 14]; 8];
+    let mut scales = [0i32; 8];
+    let mut dist_accum = [0.0f64; 8];
+
+    for (i, coef) in coefs.iter().enumerate() {
+        let (c1, c2) = (coef[0] as i32, coef[1] as i32);
+        in_samples[i][0] = pcm[0];
+        in_samples[i][1] = pcm[1];
+
+        let mut distance = 0i32;
+        for s in 0..sample_count {
+            let v1 = (pcm[s] * c2 + pcm[s + 1] * c1) / 2048;
+            in_samples[i][s + 2] = v1;
+            let v3 = (pcm[s + 2] - v1).clamp(-32768, 32767);
+            if v3.abs() > distance.abs() {
+                distance = v3;
+            }
+        }
+
+        let mut scale = 0;
+        while scale <= 12 && !(-8..=7).contains(&distance) {
+            scale += 1;
+            distance /= 2;
+        }
+        scale = if scale <= 1 { -1 } else { scale - 2 };
+
+        loop {
+            scale += 1;
+            dist_accum[i] = 0.0;
+            let mut index = 0;
+
+            for s in 0..sample_count {
+                let v1 = in_samples[i][s] * c2 + in_samples[i][s + 1] * c1;
+                let v2 = ((pcm[s + 2] << 11) - v1) / 2048;
+                let scaled = v2 as f64 / (1 << scale) as f64;
+                let mut v3 = if v2 > 0 {
+                    (scaled + 0.4999999) as i32
+                } else {
+                    (scaled - 0.4999999) as i32
+                };
+
+                if v3 < -8 {
+                    index = index.max(-8 - v3);
+                    v3 = -8;
+                } else if v3 > 7 {
+                    index = index.max(v3 - 7);
+                    v3 = 7;
+                }
+                out_samples[i][s] = v3;
+
+                let decoded =
+                    ((v1 + ((v3 * (1 << scale)) << 11) + 1024) >> 11).clamp(-32768, 32767);
+                in_samples[i][s + 2] = decoded;
+                let error = (pcm[s + 2] - decoded) as f64;
+                dist_accum[i] += error * error;
+            }
+
+            let mut x = index + 8;
+            while x > 256 {
+                scale += 1;
+                if scale >= 12 {
+                    scale = 11;
+                }
+                x >>= 1;
+            }
+            if scale >= 12 || index <= 1 {
+                break;
+            }
+        }
+        scales[i] = scale;
+    }
+
+    let mut best = 0;
+    for i in 1..8 {
+        if dist_accum[i] < dist_accum[best] {
+            best = i;
+        }
+    }
+
+    pcm[2..2 + sample_count].copy_from_slice(&in_samples[best][2..2 + sample_count]);
+
+    let mut frame = [0u8; 8];
+    frame[0] = ((best as u8) << 4) | (scales[best] as u8 & 0xF);
+    for y in 0..7 {
+        // Samples past `sample_count` were never set, so they are zero
+        let (high, low) = (out_samples[best][y * 2], out_samples[best][y * 2 + 1]);
+        frame[y + 1] = ((high << 4) as u8) | (low as u8 & 0xF);
+    }
+    frame
+}
+
+/// Encodes samples as consecutive ADPCM frames, the last of which is padded with silence.
+/// `history` holds the two samples decoded before the first one, oldest first, and is updated
+/// to the last two decoded samples. Returns the frames and the decoded samples.
+pub fn encode_adpcm(
+    samples: &[i16],
+    coefs: &[[i16; 2]; 8],
+    history: &mut [i16; 2],
+) -> (Vec<u8>, Vec<i16>) {
+    let mut data = Vec::with_capacity(samples.len().div_ceil(SAMPLES_PER_FRAME) * BYTES_PER_FRAME);
+    let mut decoded = Vec::with_capacity(samples.len());
+    let mut pcm = [0i32; 16];
+    pcm[0] = history[0] as i32;
+    pcm[1] = history[1] as i32;
+    for frame in samples.chunks(SAMPLES_PER_FRAME) {
+        for (dst, src) in pcm[2..].iter_mut().zip(frame) {
+            *dst = *src as i32;
+        }
+        data.extend_from_slice(&encode_frame(&mut pcm, frame.len(), coefs));
+        decoded.extend(pcm[2..2 + frame.len()].iter().map(|s| *s as i16));
+        pcm[0] = pcm[14];
+        pcm[1] = pcm[15];
+    }
+    if let [.., older, last] = decoded[..] {
+        *history = [older, last];
+    } else if let [last] = decoded[..] {
+        *history = [history[1], last];
+    }
+    (data, decoded)
+}
+
+fn sample_to_nibble_address(sample: u32) -> u32 {
+    let frame = sample / SAMPLES_PER_FRAME as u32;
+    let offset = sample % SAMPLES_PER_FRAME as u32;
+    frame * 16 + offset + 2
+}
+
+/// Encodes one channel as a standard .dsp file. `loop_points` are the first and last sample of
+/// the loop; samples after the loop are dropped since they are never played.
+pub fn encode_dsp(samples: &[i16], sample_rate: u32, loop_points: Option<(u32, u32)>) -> Vec<u8> {
+    let samples = match loop_points {
+        Some((_, end)) => &samples[..samples.len().min(end as usize + 1)],
+        None => samples,
+    };
+    let coefs = correlate_coefs(samples);
+    let (data, decoded) = encode_adpcm(samples, &coefs, &mut [0, 0]);
+
+    let sample_count = samples.len() as u32;
+    let nibble_count = match sample_count % SAMPLES_PER_FRAME as u32 {
+        0 => sample_count / SAMPLES_PER_FRAME as u32 * 16,
+        rem => sample_count / SAMPLES_PER_FRAME as u32 * 16 + rem + 2,
+    };
+    let (loop_start, loop_end) = loop_points.unwrap_or((0, sample_count.saturating_sub(1)));
+    let loop_end = loop_end.min(sample_count.saturating_sub(1));
+
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(&sample_count.to_be_bytes());
+    header.extend_from_slice(&nibble_count.to_be_bytes());
+    header.extend_from_slice(&sample_rate.to_be_bytes());
+    header.extend_from_slice(&(loop_points.is_some() as u16).to_be_bytes());
+    header.extend_from_slice(&0u16.to_be_bytes()); // format, always ADPCM
+    header.extend_from_slice(&sample_to_nibble_address(loop_start).to_be_bytes());
+    header.extend_from_slice(&sample_to_nibble_address(loop_end).to_be_bytes());
+    header.extend_from_slice(&sample_to_nibble_address(0).to_be_bytes());
+    for coef in coefs.iter().flatten() {
+        header.extend_from_slice(&coef.to_be_bytes());
+    }
+    header.extend_from_slice(&0u16.to_be_bytes()); // gain
+    header.extend_from_slice(&(data.first().copied().unwrap_or(0) as u16).to_be_bytes());
+    header.extend_from_slice(&0u16.to_be_bytes()); // initial history
+    header.extend_from_slice(&0u16.to_be_bytes());
+
+    // Decoder state at the start of the loop
+    let history = |back: u32| {
+        if loop_start >= back {
+            decoded[(loop_start - back) as usize]
+        } else {
+            0
+        }
+    };
+    let loop_ps = match loop_points {
+        Some(_) => data[loop_start as usize / SAMPLES_PER_FRAME * BYTES_PER_FRAME],
+        None => 0,
+    };
+    header.extend_from_slice(&(loop_ps as u16).to_be_bytes());
+    header.extend_from_slice(&history(1).to_be_bytes());
+    header.extend_from_slice(&history(2).to_be_bytes());
+    header.resize(HEADER_SIZE, 0);
+
+    header.extend_from_slice(&data);
+    header
+}
+
+/// Appends `suffix` to the file name of `path`, before its extension, e.g. "L" turns
+/// "/audio/song.dsp" into "/audio/songL.dsp".
+pub fn channel_path(path: &str, suffix: &str) -> String {
+    match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') => {
+            format!("{}{}{}", &path[..dot], suffix, &path[dot..])
+        }
+        _ => format!("{}{}", path, suffix),
+    }
+}
+
+/// Encodes a WAV or FLAC file into one .dsp file per channel, paired with the suffix streamed
+/// audio expects for the channel ("L" and "R" for stereo, nothing for mono).
+pub fn encode_audio_file(
+    path: &Path,
+    loop_start: Option<u32>,
+    loop_end: Option<u32>,
+) -> Result<Vec<(&'static str, Vec<u8>)>, String> {
+    let audio = read_audio_file(path)?;
+    let sample_count = audio.channels[0].len() as u32;
+    if sample_count == 0 {
+        return Err(format!("'{}' has no samples", path.display()));
+    }
+
+    let loop_points = match (loop_start, loop_end) {
+        (None, None) => None,
+        (start, end) => {
+            let start = start.unwrap_or(0);
+            let end = end.unwrap_or(sample_count - 1);
+            if start >= end || end >= sample_count {
+                return Err(format!(
+                    "Invalid loop points {}..{} for '{}', which has {} samples",
+                    start,
+                    end,
+                    path.display(),
+                    sample_count
+                ));
+            }
+            Some((start, end))
+        }
+    };
+
+    let suffixes: &[&'static str] = if audio.channels.len() == 2 {
+        &["L", "R"]
+    } else {
+        &[""]
+    };
+    Ok(suffixes
+        .iter()
+        .zip(audio.channels.iter())
+        .map(|(suffix, samples)| (*suffix, encode_dsp(samples, audio.sample_rate, loop_points)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_dsp, HEADER_SIZE, SAMPLES_PER_FRAME};
+
+    // Reference decoder for the frames of a .dsp file
+    fn decode_dsp(dsp: &[u8]) -> Vec<i16> {
+        let be_u32 = |i: usize| u32::from_be_bytes(dsp[i..i + 4].try_into().unwrap());
+        let be_i16 = |i: usize| i16::from_be_bytes(dsp[i..i + 2].try_into().unwrap());
+        let sample_count = be_u32(0) as usize;
+        let coefs: Vec<[i32; 2]> = (0..8)
+            .map(|i| [be_i16(0x1C + i * 4) as i32, be_i16(0x1E + i * 4) as i32])
+            .collect();
+
+        let mut samples = Vec::with_capacity(sample_count);
+        let (mut hist1, mut hist2) = (0i32, 0i32);
+        for frame in dsp[HEADER_SIZE..].chunks(8) {
+            let scale = 1 << (frame[0] & 0xF);
+            let [coef1, coef2] = coefs[(frame[0] >> 4) as usize];
+            for i in 0..SAMPLES_PER_FRAME {
+                if samples.len() == sample_count {
+                    break;
+                }
+                let byte = frame[1 + i / 2];
+                let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+                let nibble = ((nibble as i32) << 28) >> 28;
+                let sample = ((nibble * scale) << 11) + 1024 + coef1 * hist1 + coef2 * hist2;
+                let sample = (sample >> 11).clamp(i16::MIN as i32, i16::MAX as i32);
+                samples.push(sample as i16);
+                hist2 = hist1;
+                hist1 = sample;
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn dsp_round_trip() {
+        // A chord with a fade in, so that frames need different scales and coefficients
+        let samples: Vec<i16> = (0..4000)
+            .map(|i| {
+                let t = i as f64 / 32000.0;
+                let wave = (t * 440.0 * std::f64::consts::TAU).sin() * 0.6
+                    + (t * 1250.0 * std::f64::consts::TAU).sin() * 0.3;
+                (wave * 20000.0 * (i as f64 / 4000.0)) as i16
+            })
+            .collect();
+        let dsp = encode_dsp(&samples, 32000, None);
+        assert_eq!(
+            dsp.len(),
+            HEADER_SIZE + samples.len().div_ceil(SAMPLES_PER_FRAME) * 8
+        );
+
+        let decoded = decode_dsp(&dsp);
+        assert_eq!(decoded.len(), samples.len());
+        let max_error = samples
+            .iter()
+            .zip(decoded.iter())
+            .map(|(a, b)| (*a as i32 - *b as i32).abs())
+            .max()
+            .unwrap();
+        assert!(max_error <= 128, "max error {}", max_error);
+    }
+}
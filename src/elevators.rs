@@ -63,6 +63,19 @@ impl World {
         }
     }
 
+    pub fn mapw(&self) -> u32 {
+        match self {
+            World::FrigateOrpheon => 0x6fec3d56,
+            World::ChozoRuins => 0xf9943c2e,
+            World::PhendranaDrifts => 0xd2dca1d0,
+            World::TallonOverworld => 0x43901d69,
+            World::PhazonMines => 0xcbce8e24,
+            World::MagmoorCaverns => 0x449ae03d,
+            World::ImpactCrater => 0xbb59ca90,
+            World::EndCinema => 0x69b55224,
+        }
+    }
+
     pub fn to_str(&self) -> &'static str {
         match self {
             World::FrigateOrpheon => "Frigate Orpheon",
@@ -90,18 +103,17 @@ impl World {
     }
 
     pub fn from_json_key(string: &str) -> Self {
-        for world in World::iter() {
-            if string.trim().to_lowercase() == world.to_json_key().to_lowercase()
+        World::try_from_json_key(string).unwrap_or_else(|| panic!("Unknown World - '{}'", string))
+    }
+
+    pub fn try_from_json_key(string: &str) -> Option<Self> {
+        World::iter().find(|world| {
+            string.trim().to_lowercase() == world.to_json_key().to_lowercase()
                 || world
                     .to_str()
                     .to_lowercase()
                     .starts_with(&string.trim().to_lowercase())
-            {
-                return world;
-            }
-        }
-
-        panic!("Unknown World - '{}'", string);
+        })
     }
 }
 
@@ -617,35 +629,41 @@ macro_rules! decl_spawn_rooms {
 impl SpawnRoomData {
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(dest_name: &str) -> Self {
+        SpawnRoomData::try_from_str(dest_name).unwrap_or_else(|e| panic!("Error - {}", e))
+    }
+
+    pub fn try_from_str(dest_name: &str) -> Result<Self, String> {
         let dest_name = dest_name.to_lowercase();
 
         // Handle special destinations //
         if dest_name == "credits" {
-            return *SpawnRoom::EndingCinematic.spawn_room_data();
+            return Ok(*SpawnRoom::EndingCinematic.spawn_room_data());
         }
 
         if dest_name == "frigate" || dest_name == "frigate escape cutscene" {
-            return *SpawnRoom::FrigateExteriorDockingHangar.spawn_room_data();
+            return Ok(*SpawnRoom::FrigateExteriorDockingHangar.spawn_room_data());
         }
 
         // Handle elevator destinations //
         if let Some(elevator) = Elevator::from_str(&dest_name) {
-            return *elevator.spawn_room_data();
+            return Ok(*elevator.spawn_room_data());
         }
 
         // Handle specific room destinations //
         let vec: Vec<&str> = dest_name.split(':').collect();
         if vec.len() != 2 {
-            panic!("Error - Could not find destination '{}'", dest_name);
+            return Err(format!("Could not find destination '{}'", dest_name));
         }
         let world_name = vec[0].trim();
         let room_name = vec[1].trim();
+        let world_filter = World::try_from_json_key(world_name)
+            .ok_or_else(|| format!("Unknown World - '{}'", world_name))?;
 
         for (pak_name, rooms) in pickup_meta::ROOM_INFO.iter() {
             // for each pak
             let world = World::from_pak(pak_name).unwrap();
 
-            if world != World::from_json_key(world_name) {
+            if world != world_filter {
                 continue;
             }
 
@@ -653,19 +671,19 @@ impl SpawnRoomData {
                 // for each room in the pak
                 if room_info.name().to_lowercase().trim() == room_name {
                     // trim both because "west tower " has an extra space in it
-                    return SpawnRoomData {
+                    return Ok(SpawnRoomData {
                         pak_name,
                         mlvl: world.mlvl(),
                         mrea: room_info.room_id.to_u32(),
                         mrea_idx: idx,
                         room_id: 0,
                         name: room_info.name(),
-                    };
+                    });
                 }
             }
         }
 
-        panic!("Error - Could not find room '{}'", dest_name)
+        Err(format!("Could not find room '{}'", dest_name))
     }
 }
 
@@ -0,0 +1,365 @@
+use image::RgbaImage;
+use structs::{Font, FontGlyph, FontKerning, Txtr, TxtrFormat, FONT_NO_KERNING};
+
+use crate::patch_config::{FontConfig, FontGlyphConfig};
+
+// Fonts are never drawn from textures larger than this
+const MAX_FONT_TEXTURE_SIZE: usize = 1024;
+
+// Empty texels kept around each glyph so that filtering doesn't bleed into its neighbours
+const GLYPH_MARGIN: usize = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Rect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Rect {
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.x + other.w + GLYPH_MARGIN
+            && other.x < self.x + self.w + GLYPH_MARGIN
+            && self.y < other.y + other.h + GLYPH_MARGIN
+            && other.y < self.y + self.h + GLYPH_MARGIN
+    }
+}
+
+// The first mipmap of a font texture, as palette indices for paletted formats so that the
+// meaning the font renderer gives to each index is preserved, and as RGBA otherwise
+struct Canvas {
+    format: TxtrFormat,
+    texels: Vec<u8>,
+    texel_size: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Canvas {
+    fn from_txtr(txtr: &Txtr) -> Result<Canvas, String> {
+        if txtr.format.is_paletted() {
+            let w = txtr.width as usize;
+            let h = txtr.height as usize;
+            let mipmap_data: Vec<u8> = match txtr.pixel_data.iter().next() {
+                Some(mipmap_data) => mipmap_data.iter().map(|byte| *byte).collect(),
+                None => Err("The font texture has no mipmaps".to_string())?,
+            };
+            Ok(Canvas {
+                format: txtr.format.clone(),
+                texels: txtr
+                    .format
+                    .decode_mipmap_indices(&mipmap_data[..], w, h)
+                    .ok_or("Failed to decode the font texture's palette indices")?,
+                texel_size: 1,
+                w,
+                h,
+            })
+        } else {
+            let (texels, w, h) = txtr.to_rgba(0)?;
+            Ok(Canvas {
+                format: txtr.format.clone(),
+                texels,
+                texel_size: 4,
+                w,
+                h,
+            })
+        }
+    }
+
+    fn to_txtr(&self) -> Result<Txtr<'static>, String> {
+        if self.format.is_paletted() {
+            let mipmap = self
+                .format
+                .encode_mipmap_indices(&self.texels[..], self.w, self.h)
+                .ok_or("Failed to encode the font texture's palette indices")?;
+            Ok(Txtr {
+                format: self.format.clone(),
+                width: self.w as u16,
+                height: self.h as u16,
+                pixel_data: vec![mipmap.into()].into(),
+            })
+        } else {
+            Txtr::from_rgba(
+                self.format.clone(),
+                vec![(self.texels.clone(), self.w, self.h)],
+            )
+        }
+    }
+
+    // Non-paletted textures are stored bottom row first, so their V coordinates are flipped
+    // relative to the decoded rows
+    fn flipped(&self) -> bool {
+        !self.format.is_paletted()
+    }
+
+    fn glyph_rect(&self, glyph: &FontGlyph) -> Rect {
+        let (mut top, mut bottom) = (glyph.top_uv_coordinate, glyph.bottom_uv_coordinate);
+        if self.flipped() {
+            top = 1.0 - top;
+            bottom = 1.0 - bottom;
+        }
+        let x0 = (glyph.left_uv_coordinate.min(glyph.right_uv_coordinate) * self.w as f32).round();
+        let x1 = (glyph.left_uv_coordinate.max(glyph.right_uv_coordinate) * self.w as f32).round();
+        let y0 = (top.min(bottom) * self.h as f32).round();
+        let y1 = (top.max(bottom) * self.h as f32).round();
+        Rect {
+            x: x0.max(0.0) as usize,
+            y: y0.max(0.0) as usize,
+            w: (x1 - x0).max(0.0) as usize,
+            h: (y1 - y0).max(0.0) as usize,
+        }
+    }
+
+    fn set_glyph_rect(&self, glyph: &mut FontGlyph, rect: Rect) {
+        let w = self.w as f32;
+        let h = self.h as f32;
+        glyph.left_uv_coordinate = rect.x as f32 / w;
+        glyph.right_uv_coordinate = (rect.x + rect.w) as f32 / w;
+        glyph.top_uv_coordinate = rect.y as f32 / h;
+        glyph.bottom_uv_coordinate = (rect.y + rect.h) as f32 / h;
+        if self.flipped() {
+            glyph.top_uv_coordinate = 1.0 - glyph.top_uv_coordinate;
+            glyph.bottom_uv_coordinate = 1.0 - glyph.bottom_uv_coordinate;
+        }
+    }
+
+    fn clear(&mut self, rect: Rect) {
+        for y in rect.y..(rect.y + rect.h).min(self.h) {
+            let start = (y * self.w + rect.x) * self.texel_size;
+            let end = (y * self.w + (rect.x + rect.w).min(self.w)) * self.texel_size;
+            self.texels[start..end].fill(0);
+        }
+    }
+
+    fn palette_colors(&self) -> Option<Vec<[u8; 4]>> {
+        let (fmt, palette) = match &self.format {
+            TxtrFormat::C4(fmt, palette) => (fmt, &palette[..]),
+            TxtrFormat::C8(fmt, palette) => (fmt, &palette[..]),
+            _ => return None,
+        };
+        Some(
+            palette
+                .chunks(2)
+                .map(|entry| fmt.decode([entry[0], entry[1]]))
+                .collect(),
+        )
+    }
+
+    // Maps an atlas pixel to the closest color of the texture's palette
+    fn atlas_texel(palette: Option<&[[u8; 4]]>, pixel: [u8; 4]) -> Vec<u8> {
+        let palette = match palette {
+            Some(palette) => palette,
+            None => return pixel.to_vec(),
+        };
+        let distance = |color: &[u8; 4]| -> u32 {
+            color
+                .iter()
+                .zip(pixel.iter())
+                .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+                .sum()
+        };
+        let index = (0..palette.len())
+            .min_by_key(|i| distance(&palette[*i]))
+            .unwrap();
+        vec![index as u8]
+    }
+
+    fn blit(&mut self, atlas: &RgbaImage, src: Rect, dst: Rect) {
+        let palette = self.palette_colors();
+        for y in 0..src.h {
+            for x in 0..src.w {
+                let pixel = atlas.get_pixel((src.x + x) as u32, (src.y + y) as u32).0;
+                let texel = Canvas::atlas_texel(palette.as_deref(), pixel);
+                let start = ((dst.y + y) * self.w + dst.x + x) * self.texel_size;
+                self.texels[start..start + self.texel_size].copy_from_slice(&texel[..]);
+            }
+        }
+    }
+
+    // Doubles the height of the texture, keeping the existing texels at the top
+    fn grow(&mut self) -> Result<(), String> {
+        if self.h * 2 > MAX_FONT_TEXTURE_SIZE {
+            Err(format!(
+                "There is not enough room for the new glyphs in a {}x{} font texture",
+                self.w, MAX_FONT_TEXTURE_SIZE
+            ))?
+        }
+        self.texels.resize(self.w * self.h * 2 * self.texel_size, 0);
+        self.h *= 2;
+        Ok(())
+    }
+
+    fn find_space(&self, w: usize, h: usize, used: &[Rect]) -> Option<Rect> {
+        if w > self.w || h > self.h {
+            return None;
+        }
+        for y in 0..=(self.h - h) {
+            for x in 0..=(self.w - w) {
+                let rect = Rect { x, y, w, h };
+                if used.iter().all(|other| !rect.overlaps(other)) {
+                    return Some(rect);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn utf16_char(c: char) -> Result<u16, String> {
+    let mut buf = [0u16; 2];
+    match c.encode_utf16(&mut buf) {
+        [c] => Ok(*c),
+        _ => Err(format!(
+            "Character '{}' can't be used in a font, only characters up to U+FFFF are supported",
+            c
+        )),
+    }
+}
+
+fn glyph_metrics(font: &Font, glyph: &mut FontGlyph, config: &FontGlyphConfig, replaced: bool) {
+    if let Some(left_padding) = config.left_padding {
+        glyph.left_padding = left_padding;
+    } else if !replaced {
+        glyph.left_padding = 0;
+    }
+    if let Some(advance) = config.advance {
+        glyph.print_head_advance = advance;
+    } else if !replaced {
+        glyph.print_head_advance = config.width;
+    }
+    if let Some(right_padding) = config.right_padding {
+        glyph.right_padding = right_padding;
+    } else if !replaced {
+        glyph.right_padding = 0;
+    }
+    if let Some(vertical_offset) = config.vertical_offset {
+        glyph.vertical_offset = vertical_offset;
+    } else if !replaced {
+        glyph.vertical_offset = font.line_height.saturating_sub(config.height);
+    }
+    glyph.width = config.width;
+    glyph.height = config.height;
+}
+
+/// Adds or replaces the glyphs of `font` with images from `atlas` and updates its kerning table.
+/// Returns the font's texture `txtr` with the new glyphs, only its first mipmap is kept.
+pub fn edit_font(
+    font: &mut Font,
+    txtr: &Txtr,
+    atlas: Option<&RgbaImage>,
+    config: &FontConfig,
+) -> Result<Txtr<'static>, String> {
+    let mut canvas = Canvas::from_txtr(txtr)?;
+
+    let mut glyphs: Vec<FontGlyph> = font.glyphs.iter().map(|g| g.into_owned()).collect();
+    let mut rects: Vec<Rect> = glyphs.iter().map(|g| canvas.glyph_rect(g)).collect();
+
+    for glyph_config in &config.glyphs {
+        let atlas = atlas.ok_or("An atlas is required to add glyphs to a font")?;
+        let src = Rect {
+            x: glyph_config.x as usize,
+            y: glyph_config.y as usize,
+            w: glyph_config.width as usize,
+            h: glyph_config.height as usize,
+        };
+        if src.w == 0
+            || src.h == 0
+            || src.x + src.w > atlas.width() as usize
+            || src.y + src.h > atlas.height() as usize
+        {
+            Err(format!(
+                "Glyph '{}' at ({}, {}) with size {}x{} is outside of the {}x{} atlas",
+                glyph_config.character,
+                src.x,
+                src.y,
+                src.w,
+                src.h,
+                atlas.width(),
+                atlas.height()
+            ))?
+        }
+
+        let utf16_char = utf16_char(glyph_config.character)?;
+        let existing = glyphs.iter().position(|g| g.utf16_char == utf16_char);
+
+        // Reuse the spot of the glyph being replaced if the new one fits, otherwise pack it
+        // into the first free space, growing the texture when there is none
+        let mut glyph = match existing {
+            Some(i) => {
+                canvas.clear(rects[i]);
+                glyphs.remove(i)
+            }
+            None => FontGlyph {
+                utf16_char,
+                left_uv_coordinate: 0.0,
+                top_uv_coordinate: 0.0,
+                right_uv_coordinate: 0.0,
+                bottom_uv_coordinate: 0.0,
+                left_padding: 0,
+                print_head_advance: 0,
+                right_padding: 0,
+                width: 0,
+                height: 0,
+                vertical_offset: 0,
+                kerning_start_index: FONT_NO_KERNING,
+            },
+        };
+        let old_rect = existing.map(|i| rects.remove(i));
+        let dst = loop {
+            if let Some(old_rect) = old_rect {
+                if src.w <= old_rect.w && src.h <= old_rect.h {
+                    break Rect {
+                        w: src.w,
+                        h: src.h,
+                        ..old_rect
+                    };
+                }
+            }
+            if let Some(rect) = canvas.find_space(src.w, src.h, &rects[..]) {
+                break rect;
+            }
+            canvas.grow()?;
+        };
+
+        canvas.blit(atlas, src, dst);
+        glyph_metrics(font, &mut glyph, glyph_config, existing.is_some());
+        glyphs.push(glyph);
+        rects.push(dst);
+    }
+
+    // Growing the texture changes the size of a texel in UV space
+    for (glyph, rect) in glyphs.iter_mut().zip(rects.iter()) {
+        canvas.set_glyph_rect(glyph, *rect);
+    }
+
+    let mut kernings: Vec<FontKerning> = font.kernings.iter().map(|k| k.into_owned()).collect();
+    for kerning_config in &config.kernings {
+        let char1 = utf16_char(kerning_config.first)?;
+        let char2 = utf16_char(kerning_config.second)?;
+        kernings.retain(|k| !(k.char1 == char1 && k.char2 == char2));
+        if kerning_config.adjust != 0 {
+            kernings.push(FontKerning {
+                char1,
+                char2,
+                kerning_adjust: kerning_config.adjust,
+            });
+        }
+    }
+
+    // The kerning pairs of a glyph are looked up starting from its kerning index for as long as
+    // the first character matches, so pairs need to be grouped by their first character
+    kernings.sort_by_key(|k| k.char1);
+    glyphs.sort_by_key(|g| g.utf16_char);
+    for glyph in glyphs.iter_mut() {
+        glyph.kerning_start_index = kernings
+            .iter()
+            .position(|k| k.char1 == glyph.utf16_char)
+            .map(|i| i as u32)
+            .unwrap_or(FONT_NO_KERNING);
+    }
+
+    font.glyphs = glyphs.into();
+    font.kernings = kernings.into();
+    canvas.to_txtr()
+}
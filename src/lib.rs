@@ -9,13 +9,17 @@ use reader_writer::{LCow, Reader};
 pub use structs;
 
 pub mod add_modify_obj_patches;
+pub mod asset_export;
+pub mod asset_import;
 pub mod c_interface;
 pub mod ciso_writer;
 pub mod custom_assets;
 pub mod dol_patcher;
 pub mod door_meta;
+pub mod dsp_encoder;
 pub mod elevators;
 pub mod extern_assets;
+pub mod font_edit;
 pub mod gcz_writer;
 pub mod generic_edit;
 pub mod mlvl_wrapper;
@@ -24,7 +28,11 @@ pub mod patcher;
 pub mod patches;
 pub mod pickup_meta;
 pub mod room_lookup;
+pub mod save_file;
 pub mod starting_items;
+pub mod strg_translation;
+pub mod text_validation;
+pub mod thp_encoder;
 pub mod txtr_conversions;
 
 pub trait GcDiscLookupExtensions<'a> {
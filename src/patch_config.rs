@@ -10,15 +10,21 @@ use clap::{crate_version, App, Arg};
 use json_data::*;
 use json_strip::strip_jsonc_comments;
 use reader_writer::{FourCC, Reader};
+use resource_info_table::{resource_info_iter, ResourceInfo};
 use serde::{
     de::{Error, Visitor},
     Deserialize, Deserializer, Serialize,
 };
-use structs::{res_id, MapaObjectVisibilityMode, ResId};
+use structs::{
+    res_id, CtwkValue, Justification, MapaObjectType, MapaObjectVisibilityMode, ResId,
+    VerticalJustification,
+};
 
 use crate::{
+    asset_export::ModelFormat,
     custom_assets::custom_asset_ids, door_meta::DoorType, elevators::World,
     pickup_meta::PickupType, room_lookup::ROOM_BY_INTERNAL_ID, starting_items::StartingItems,
+    txtr_conversions::{DOOR_TEXTURES, HUD_TEXTURES, VISOR_TEXTURES},
 };
 
 /*** Parsed Config (fn patch_iso) ***/
@@ -29,6 +35,7 @@ pub enum RunMode {
     CreateIso,
     ExportLogbook,
     ExportAssets,
+    ExportStrings,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -84,6 +91,18 @@ pub struct GameBanner {
     pub developer: Option<String>,
     pub developer_full: Option<String>,
     pub description: Option<String>,
+    pub image: Option<String>, // 96x32 PNG shown by loaders and the IPL
+    pub save_banner: Option<String>, // 96x32 PNG shown on the memory card screen
+    pub save_icon: Option<String>, // 32x32 PNG shown on the memory card screen
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DiscIdentity {
+    pub game_id: Option<String>, // e.g. "GM8E01", used by emulators/loaders and the memory card
+    pub disc_number: Option<u8>,
+    pub internal_name: Option<String>,
+    pub save_file_name: Option<String>, // Name of the file on the memory card, without " A"/" B"
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -96,9 +115,11 @@ pub struct PickupConfig {
     pub max_increase: Option<i32>,
     pub model: Option<String>,
     pub scan_text: Option<String>,
+    pub scan_images: Option<Vec<ScanImageConfig>>, // Requires scan_text
     pub hudmemo_text: Option<String>,
     pub respawn: Option<bool>,
     pub position: Option<[f32; 3]>,
+    pub snap_to_floor: Option<bool>, // Move `position` down onto the floor below it
     pub modal_hudmemo: Option<bool>,
     pub jumbo_scan: Option<bool>,
     pub destination: Option<String>,
@@ -121,6 +142,21 @@ pub struct ScanConfig {
     pub logbook_category: Option<u32>,
     pub logbook_title: Option<String>,
     pub text: String,
+    pub images: Option<Vec<ScanImageConfig>>, // Up to 4
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ScanImageConfig {
+    pub png: Option<String>,             // Image to add as a new texture
+    pub format: Option<String>,          // Same names as txtr_converter, defaults to CMPR
+    pub txtr: Option<u32>,               // Existing texture to use instead of a PNG
+    pub pane: u32,                       // Which pane of the scan screen to show the image in
+    pub appearance_percent: Option<f32>, // Scan progress at which the image appears
+    pub width: Option<u32>,              // Number of panes the image spans
+    pub height: Option<u32>,
+    pub interval: Option<f32>,
+    pub fade_duration: Option<f32>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -148,6 +184,81 @@ pub struct SuitColors {
     pub phazon_deg: Option<i16>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextureGroup {
+    Beams,
+    Visors,
+    Doors,
+    Hud,
+}
+
+impl TextureGroup {
+    pub fn textures(&self) -> Vec<ResourceInfo> {
+        match self {
+            // The projectile and muzzle effect textures are unnamed, but they're the only
+            // textures which are only found in SamGunFx.pak
+            TextureGroup::Beams => resource_info_iter()
+                .filter(|res_info| {
+                    res_info.fourcc == b"TXTR".into() && res_info.paks == [&b"SamGunFx.pak"[..]]
+                })
+                .copied()
+                .collect(),
+            TextureGroup::Visors => VISOR_TEXTURES.to_vec(),
+            TextureGroup::Doors => DOOR_TEXTURES.to_vec(),
+            TextureGroup::Hud => HUD_TEXTURES.to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TextureColors {
+    pub textures: Option<Vec<u32>>, // TXTR asset IDs
+    pub groups: Option<Vec<TextureGroup>>,
+    pub hue_deg: Option<i16>,
+    pub saturation: Option<f32>, // 0.0 is grayscale, 1.0 is unchanged
+    pub brightness: Option<f32>, // 1.0 is unchanged
+    pub tint: Option<[f32; 3]>,  // multiplied with each channel
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParticleGroup {
+    Beams,
+    Charge,
+}
+
+impl ParticleGroup {
+    pub fn particles(&self) -> Vec<ResourceInfo> {
+        resource_info_iter()
+            .filter(|res_info| res_info.fourcc == b"PART".into())
+            .filter(|res_info| match self {
+                ParticleGroup::Beams => {
+                    res_info.long_name.starts_with("SamGunFx")
+                        && !res_info.long_name.contains("Bomb")
+                        && !res_info.long_name.contains("grapple")
+                }
+                ParticleGroup::Charge => {
+                    res_info.long_name.starts_with("SamGunFx")
+                        && res_info.long_name.contains("Charge")
+                }
+            })
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ParticleColors {
+    pub particles: Option<Vec<u32>>, // PART asset IDs
+    pub groups: Option<Vec<ParticleGroup>>,
+    pub hue_deg: Option<i16>,
+    pub saturation: Option<f32>, // 0.0 is grayscale, 1.0 is unchanged
+    pub brightness: Option<f32>, // 1.0 is unchanged
+    pub tint: Option<[f32; 3]>,  // multiplied with each channel
+    pub textures: Option<HashMap<String, u32>>, // e.g. "1234":5678 replaces TXTR 1234 with TXTR 5678
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DefaultGameOptions {
@@ -1074,6 +1185,81 @@ pub struct WorldLightFaderConfig {
     pub fade_speed: Option<f32>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum LightType {
+    LocalAmbient,
+    Directional,
+    Custom,
+    Spot,
+}
+
+impl LightType {
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            LightType::LocalAmbient => 0,
+            LightType::Directional => 1,
+            LightType::Custom => 2,
+            LightType::Spot => 3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum LightFalloffType {
+    Constant,
+    Linear,
+    Quadratic,
+}
+
+impl LightFalloffType {
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            LightFalloffType::Constant => 0,
+            LightFalloffType::Linear => 1,
+            LightFalloffType::Quadratic => 2,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LightConfig {
+    pub index: Option<u32>, // index into the room's lights, a new light is added if unspecified
+    pub remove: Option<bool>,
+    pub light_type: Option<LightType>,
+    pub color: Option<[f32; 3]>,
+    pub brightness: Option<f32>,
+    pub position: Option<[f32; 3]>,
+    pub direction: Option<[f32; 3]>,
+    pub spot_cutoff: Option<f32>, // degrees
+    pub falloff_type: Option<LightFalloffType>,
+    pub cast_shadows: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MapObjectConfig {
+    #[serde(alias = "type")]
+    pub object_type: MapaObjectType,
+    pub position: [f32; 3],
+    pub rotation: Option<[f32; 3]>,
+    pub editor_id: Option<u32>, // instance id of the script object this icon represents
+    pub visibility_mode: Option<MapaObjectVisibilityMode>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RoomMapConfig {
+    pub add_objects: Option<Vec<MapObjectConfig>>,
+    pub remove_objects: Option<Vec<u32>>,          // editor ids
+    pub door_colors: Option<HashMap<u32, String>>, // key=door instance id, value=door type
+    pub add_surfaces: Option<Vec<Vec<[f32; 3]>>>,  // polygons in winding order
+    pub hidden_surfaces: Option<Vec<u32>>,
+    pub regenerate_outline: Option<bool>,
+}
+
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct RoomConfig {
@@ -1081,6 +1267,7 @@ pub struct RoomConfig {
     pub remove_water: Option<bool>,
     pub submerge: Option<bool>,
     pub map_default_state: Option<MapaObjectVisibilityMode>,
+    pub map: Option<RoomMapConfig>,
     pub liquids: Option<Vec<WaterConfig>>,
     pub pickups: Option<Vec<PickupConfig>>,
     pub extra_scans: Option<Vec<ScanConfig>>,
@@ -1094,6 +1281,7 @@ pub struct RoomConfig {
     pub lock_on_points: Option<Vec<LockOnPoint>>,
     pub fog: Option<FogConfig>,
     pub ambient_lighting_scale: Option<f32>, // 1.0 is default lighting
+    pub lights: Option<Vec<LightConfig>>,
     pub enviornmental_effect: Option<EnviornmentalEffect>,
     pub initial_enviornmental_effect: Option<f32>,
     pub initial_thermal_heat_level: Option<f32>,
@@ -1214,6 +1402,9 @@ pub struct CtwkConfig {
 
     // GuiColors.CTWK
     pub hud_color: Option<[f32; 3]>, // RGB, 0 - 1.0
+
+    // Any tweak field, by tweak name (e.g. "Targeting") and field name
+    pub fields: Option<HashMap<String, HashMap<String, CtwkValue>>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -1271,6 +1462,23 @@ pub enum Version {
     PalTrilogy,
 }
 
+impl Version {
+    pub fn from_disc_header(header: &structs::GcDiscHeader) -> Option<Self> {
+        match (&header.game_identifier(), header.disc_id, header.version) {
+            (b"GM8E01", 0, 0) => Some(Version::NtscU0_00),
+            (b"GM8E01", 0, 1) => Some(Version::NtscU0_01),
+            (b"GM8E01", 0, 2) => Some(Version::NtscU0_02),
+            (b"GM8E01", 0, 48) => Some(Version::NtscK),
+            (b"GM8J01", 0, 0) => Some(Version::NtscJ),
+            (b"GM8P01", 0, 0) => Some(Version::Pal),
+            (b"R3ME01", 0, 0) => Some(Version::NtscUTrilogy),
+            (b"R3IJ01", 0, 0) => Some(Version::NtscJTrilogy),
+            (b"R3MP01", 0, 0) => Some(Version::PalTrilogy),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq, Copy, Clone)]
 pub enum DoorOpenMode {
     Original,           // Doors always stay the same color after opening
@@ -1359,11 +1567,180 @@ impl fmt::Display for Version {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TextureReplacement {
+    pub png: String,              // Path to the replacement image
+    pub format: Option<String>,   // Same names as txtr_converter, defaults to CMPR
+    pub mipmap_count: Option<u8>, // Defaults to as many as the image and format allow
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CustomAudio {
+    pub file: String, // WAV or FLAC to encode, stereo is split into an L/R pair
+    pub loop_start: Option<u32>, // First sample of the loop
+    pub loop_end: Option<u32>, // Last sample of the loop, defaults to the end of the file
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CustomVideo {
+    pub frames: String, // Directory of PNG/JPEG frames sorted by name, or a Motion JPEG file
+    pub audio: Option<String>, // WAV or FLAC to play alongside the video
+    pub quality: Option<u8>, // JPEG quality from 1 to 100, defaults to 85
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FontGlyphConfig {
+    pub character: char,
+    pub x: u32, // Top left corner of the glyph in the atlas
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // Layout metrics in pixels, the glyph being replaced keeps its values if these are unspecified
+    pub left_padding: Option<u32>,
+    pub advance: Option<u32>, // Defaults to the width
+    pub right_padding: Option<u32>,
+    pub vertical_offset: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FontKerningConfig {
+    pub first: char,
+    pub second: char,
+    pub adjust: i32, // Pixels added between the two characters, 0 removes the pair
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FontConfig {
+    pub atlas: Option<String>, // Path to a PNG containing the glyphs
+    #[serde(default)]
+    pub glyphs: Vec<FontGlyphConfig>,
+    #[serde(default)]
+    pub kernings: Vec<FontKerningConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GuiTextPaneConfig {
+    pub size: Option<[f32; 2]>, // Width and height, required for new text panes
+    pub font: Option<u32>,      // Required for new text panes
+    pub jpn_font: Option<u32>,  // Only used by frames with Japanese fonts, defaults to `font`
+    pub word_wrap: Option<bool>,
+    pub justification: Option<Justification>,
+    pub vertical_justification: Option<VerticalJustification>,
+    pub fill_color: Option<[f32; 4]>,
+    pub outline_color: Option<[f32; 4]>,
+    pub block_extent: Option<[f32; 2]>, // Text area in font pixels, defaults to match `size`
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GuiImageConfig {
+    pub texture: Option<u32>,               // Required for new images
+    pub size: Option<[f32; 2]>,             // Sets the quad to a centered rectangle
+    pub quad_coords: Option<Vec<[f32; 3]>>, // Corners in triangle strip order, overrides `size`
+    pub uv_coords: Option<Vec<[f32; 2]>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GuiWidgetConfig {
+    pub name: Option<String>, // Renames an existing widget, required for new widgets
+    pub parent: Option<String>, // New widgets default to the root widget
+    pub visible: Option<bool>,
+    pub active: Option<bool>,
+    pub color: Option<[f32; 4]>,
+    pub origin: Option<[f32; 3]>,
+    pub basis: Option<[f32; 9]>,
+    pub scale: Option<[f32; 3]>, // Applied after `basis`
+    pub text_pane: Option<GuiTextPaneConfig>,
+    pub image: Option<GuiImageConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GuiFrameConfig {
+    #[serde(default)]
+    pub widgets: HashMap<String, GuiWidgetConfig>, // "<widget name>": <changes>
+    #[serde(default)]
+    pub add_widgets: Vec<GuiWidgetConfig>, // Each needs a text pane or an image
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WorldMapConfig {
+    // "<area index>": <decimal MAPA asset ID>, the next index past the end adds an area
+    #[serde(default)]
+    pub area_maps: HashMap<u32, u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UniverseWorldConfig {
+    pub label: Option<String>,
+    pub mlvl: Option<u32>, // required when adding a world
+    pub position: Option<[f32; 3]>,
+    pub hexagons: Option<Vec<[f32; 3]>>, // hexagon positions relative to the world
+    pub color: Option<[f32; 4]>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UniverseMapConfig {
+    pub hexagon_mapa: Option<u32>,
+    // "<world name or label>": <changes>, unknown names add a new world
+    #[serde(default)]
+    pub worlds: HashMap<String, UniverseWorldConfig>,
+    pub world_order: Option<Vec<String>>, // worlds which aren't listed keep their order at the end
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HintConfig {
+    pub remove: Option<bool>,
+    pub item: Option<String>, // point at every room this pickup type was placed in
+    pub rooms: Option<Vec<String>>, // "<world>:<room>"
+    pub popup_text: Option<String>,
+    pub map_text: Option<String>,
+    pub intermediate_time: Option<f32>,
+    pub normal_time: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HintsConfig {
+    pub remove_vanilla_hints: Option<bool>, // remove all hints which aren't listed below
+    // "<hint name>": <changes>, unknown names add a new hint
+    #[serde(default)]
+    pub hints: HashMap<String, HintConfig>,
+}
+
+/// Selects which vanilla resources are written out by the `ExportAssets` run mode. A resource is
+/// exported if it matches every filter which is specified.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExportResourcesConfig {
+    pub ids: Option<Vec<u32>>,
+    pub fourccs: Option<Vec<String>>,
+    pub paks: Option<Vec<String>>,
+    pub rooms: Option<Vec<String>>, // "World:Room", exports the room and its dependencies
+    pub convert: Option<bool>,      // TXTR -> PNG, STRG/SCAN/SCLY -> JSON, CMDL -> model
+    pub model_format: Option<ModelFormat>,
+    pub include_custom_assets: Option<bool>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PatchConfig {
     pub run_mode: RunMode,
     pub logbook_filename: Option<String>,
+    pub strings_filename: Option<String>,
     pub export_asset_dir: Option<String>,
+    pub export_resources: Option<ExportResourcesConfig>,
     pub extern_assets_dir: Option<String>,
     pub seed: u64,
     pub uuid: Option<[u8; 16]>,
@@ -1406,6 +1783,15 @@ pub struct PatchConfig {
     pub level_data: HashMap<String, LevelConfig>,
 
     pub strg: HashMap<String, Vec<String>>, // "<decimal asset ID>": <non-null terminated table of strings>
+    pub textures: HashMap<String, TextureReplacement>, // "<decimal asset ID>": <replacement PNG>
+    pub fonts: HashMap<String, FontConfig>, // "<decimal asset ID>": <glyphs to add or replace>
+    pub gui_frames: HashMap<String, GuiFrameConfig>, // "<decimal asset ID>": <widget changes>
+    pub world_maps: HashMap<String, WorldMapConfig>, // "<world name>": <area map changes>
+    pub universe_map: Option<UniverseMapConfig>,
+    pub hints: Option<HintsConfig>,
+    pub custom_audio: HashMap<String, CustomAudio>, // "<path on disc>": <audio to encode as .dsp>
+    pub custom_videos: HashMap<String, CustomVideo>, // "<path on disc>": <video to encode as .thp>
+    pub translations: HashMap<String, String>, // "<language>": <PO or CSV of translated strings>
 
     pub starting_room: String,
     pub starting_memo: Option<String>,
@@ -1417,6 +1803,7 @@ pub struct PatchConfig {
     pub automatic_crash_screen: bool,
     pub etank_capacity: u32,
     pub shuffle_pickup_position: bool,
+    pub snap_shuffled_pickup_position: bool,
     pub shuffle_pickup_pos_all_rooms: bool,
     pub remove_vanilla_blast_shields: bool,
     pub nonvaria_heat_damage: bool,
@@ -1451,6 +1838,8 @@ pub struct PatchConfig {
     pub skip_splash_screens: bool,
     pub default_game_options: Option<DefaultGameOptions>,
     pub suit_colors: Option<SuitColors>,
+    pub texture_colors: Vec<TextureColors>,
+    pub particle_colors: Vec<ParticleColors>,
     pub force_fusion: bool,
     pub cache_dir: String,
 
@@ -1458,6 +1847,7 @@ pub struct PatchConfig {
     pub quickpatch: bool,
 
     pub game_banner: GameBanner,
+    pub disc_identity: DiscIdentity,
     pub comment: String,
     pub main_menu_message: String,
 
@@ -1466,6 +1856,7 @@ pub struct PatchConfig {
 
     pub results_string: Option<String>,
     pub artifact_hints: Option<HashMap<String, String>>, // e.g. "Strength":"This item can be found in Ruined Fountain"
+    pub auto_wrap_text: bool, // Insert line breaks into custom strings which don't fit their text pane
     pub required_artifact_count: Option<u32>,
     pub artifact_temple_layer_overrides: Option<HashMap<String, bool>>,
     pub no_doors: bool,
@@ -1485,6 +1876,8 @@ struct Preferences {
     skip_splash_screens: Option<bool>,
     default_game_options: Option<DefaultGameOptions>,
     suit_colors: Option<SuitColors>,
+    texture_colors: Option<Vec<TextureColors>>,
+    particle_colors: Option<Vec<ParticleColors>>,
     force_fusion: Option<bool>,
     cache_dir: Option<String>,
 
@@ -1518,6 +1911,7 @@ struct GameConfig {
     warp_to_start_delay_s: Option<f32>,
 
     shuffle_pickup_position: Option<bool>,
+    snap_shuffled_pickup_position: Option<bool>,
     shuffle_pickup_pos_all_rooms: Option<bool>,
     remove_vanilla_blast_shields: Option<bool>,
     nonvaria_heat_damage: Option<bool>,
@@ -1560,12 +1954,14 @@ struct GameConfig {
     hall_of_the_elders_bomb_slot_covers: Option<HallOfTheEldersBombSlotCoversConfig>,
 
     game_banner: Option<GameBanner>,
+    disc_identity: Option<DiscIdentity>,
     comment: Option<String>,
     main_menu_message: Option<String>,
 
     credits_string: Option<String>,
     results_string: Option<String>,
     artifact_hints: Option<HashMap<String, String>>, // e.g. "Strength":"This item can be found in Ruined Fountain"
+    auto_wrap_text: Option<bool>,
     artifact_temple_layer_overrides: Option<HashMap<String, bool>>,
     required_artifact_count: Option<u32>,
     no_doors: Option<bool>, // Remove every door from the game
@@ -1584,7 +1980,9 @@ struct PatchConfigPrivate {
 
     run_mode: Option<String>,
     logbook_filename: Option<String>,
+    strings_filename: Option<String>,
     export_asset_dir: Option<String>,
+    export_resources: Option<ExportResourcesConfig>,
     input_iso: Option<String>,
     output_iso: Option<String>,
     force_vanilla_layout: Option<bool>,
@@ -1606,6 +2004,31 @@ struct PatchConfigPrivate {
 
     #[serde(default)]
     strg: HashMap<String, Vec<String>>, // "<decimal asset ID>": <non-null terminated table of strings>
+
+    #[serde(default)]
+    textures: HashMap<String, TextureReplacement>, // "<decimal asset ID>": <replacement PNG>
+
+    #[serde(default)]
+    fonts: HashMap<String, FontConfig>, // "<decimal asset ID>": <glyphs to add or replace>
+
+    #[serde(default)]
+    gui_frames: HashMap<String, GuiFrameConfig>, // "<decimal asset ID>": <widget changes>
+
+    #[serde(default)]
+    world_maps: HashMap<String, WorldMapConfig>, // "<world name>": <area map changes>
+
+    universe_map: Option<UniverseMapConfig>,
+
+    hints: Option<HintsConfig>,
+
+    #[serde(default)]
+    custom_audio: HashMap<String, CustomAudio>, // "<path on disc>": <audio to encode as .dsp>
+
+    #[serde(default)]
+    custom_videos: HashMap<String, CustomVideo>, // "<path on disc>": <video to encode as .thp>
+
+    #[serde(default)]
+    translations: HashMap<String, String>, // "<language>": <PO or CSV of translated strings>
 }
 
 /*** Parse Patcher Input ***/
@@ -2031,6 +2454,7 @@ impl PatchConfigPrivate {
                 extend_option_vec!(controller_actions, self_room_config, other_room_config);
                 extend_option_vec!(player_actors, self_room_config, other_room_config);
                 extend_option_vec!(world_light_faders, self_room_config, other_room_config);
+                extend_option_vec!(lights, self_room_config, other_room_config);
                 extend_option_vec!(cameras, self_room_config, other_room_config);
                 extend_option_vec!(camera_waypoints, self_room_config, other_room_config);
                 extend_option_vec!(camera_filter_keyframes, self_room_config, other_room_config);
@@ -2060,6 +2484,37 @@ impl PatchConfigPrivate {
                     }
                 }
 
+                if let Some(other_map) = &other_room_config.map {
+                    let self_map = self_room_config.map.get_or_insert_with(Default::default);
+                    extend_option_vec!(add_objects, self_map, other_map);
+                    extend_option_vec!(remove_objects, self_map, other_map);
+                    extend_option_vec!(add_surfaces, self_map, other_map);
+                    extend_option_vec!(hidden_surfaces, self_map, other_map);
+
+                    if let Some(other_door_colors) = &other_map.door_colors {
+                        let self_door_colors =
+                            self_map.door_colors.get_or_insert_with(HashMap::new);
+                        for (id, other_color) in other_door_colors {
+                            match self_door_colors.get(id) {
+                                Some(self_color) => {
+                                    if DoorType::from_string(self_color.to_string())
+                                        != DoorType::from_string(other_color.to_string())
+                                    {
+                                        panic!("Conflict in {}'s map doorColors", room_name);
+                                    }
+                                }
+                                None => {
+                                    self_door_colors.insert(*id, other_color.to_string());
+                                }
+                            }
+                        }
+                    }
+
+                    if other_map.regenerate_outline == Some(true) {
+                        self_map.regenerate_outline = Some(true);
+                    }
+                }
+
                 if let Some(other_edit_objs) = &other_room_config.edit_objs {
                     if self_room_config.edit_objs.is_none() {
                         self_room_config.edit_objs = Some(HashMap::new());
@@ -2182,21 +2637,10 @@ impl PatchConfigPrivate {
             let mut reader = Reader::new(&input_iso[..]);
             let gc_disc: structs::GcDisc = reader.read(());
 
-            match (&gc_disc.header.game_identifier(), gc_disc.header.disc_id, gc_disc.header.version) {
-                (b"GM8E01", 0, 0)  => Version::NtscU0_00,
-                (b"GM8E01", 0, 1)  => Version::NtscU0_01,
-                (b"GM8E01", 0, 2)  => Version::NtscU0_02,
-                (b"GM8E01", 0, 48) => Version::NtscK,
-                (b"GM8J01", 0, 0)  => Version::NtscJ,
-                (b"GM8P01", 0, 0)  => Version::Pal,
-                (b"R3ME01", 0, 0)  => Version::NtscUTrilogy,
-                (b"R3IJ01", 0, 0)  => Version::NtscJTrilogy,
-                (b"R3MP01", 0, 0)  => Version::PalTrilogy,
-                _ => Err(concat!(
-                        "The input ISO doesn't appear to be NTSC-US, NTSC-J, NTSC-K, PAL Metroid Prime, ",
-                        "or NTSC-US, NTSC-J, PAL Metroid Prime Trilogy."
-                    ))?
-            }
+            Version::from_disc_header(&gc_disc.header).ok_or(concat!(
+                "The input ISO doesn't appear to be NTSC-US, NTSC-J, NTSC-K, PAL Metroid Prime, ",
+                "or NTSC-US, NTSC-J, PAL Metroid Prime Trilogy."
+            ))?
         };
 
         let force_vanilla_layout = self.force_vanilla_layout.unwrap_or(false);
@@ -2249,6 +2693,7 @@ impl PatchConfigPrivate {
                     "create_iso" => RunMode::CreateIso,
                     "export_logbook" => RunMode::ExportLogbook,
                     "export_assets" => RunMode::ExportAssets,
+                    "export_strings" => RunMode::ExportStrings,
                     _ => panic!("Unsupported run mode: {}", self.run_mode.as_ref().unwrap()),
                 }
             } else {
@@ -2593,7 +3038,9 @@ impl PatchConfigPrivate {
         let result = PatchConfig {
             run_mode,
             logbook_filename: self.logbook_filename.clone(),
+            strings_filename: self.strings_filename.clone(),
             export_asset_dir: self.export_asset_dir.clone(),
+            export_resources: self.export_resources.clone(),
             version,
             input_iso,
             iso_format,
@@ -2606,6 +3053,15 @@ impl PatchConfigPrivate {
 
             level_data: self.level_data.clone(),
             strg: self.strg.clone(),
+            textures: self.textures.clone(),
+            fonts: self.fonts.clone(),
+            gui_frames: self.gui_frames.clone(),
+            world_maps: self.world_maps.clone(),
+            universe_map: self.universe_map.clone(),
+            hints: self.hints.clone(),
+            custom_audio: self.custom_audio.clone(),
+            custom_videos: self.custom_videos.clone(),
+            translations: self.translations.clone(),
 
             qol_game_breaking,
             qol_cosmetic,
@@ -2660,6 +3116,8 @@ impl PatchConfigPrivate {
             artifact_hint_behavior,
             flaahgra_music_files,
             suit_colors: self.preferences.suit_colors.clone(),
+            texture_colors: self.preferences.texture_colors.clone().unwrap_or_default(),
+            particle_colors: self.preferences.particle_colors.clone().unwrap_or_default(),
             force_fusion: self.preferences.force_fusion.unwrap_or(false),
             cache_dir: self
                 .preferences
@@ -2680,6 +3138,10 @@ impl PatchConfigPrivate {
             warp_to_start_delay_s: self.game_config.warp_to_start_delay_s.unwrap_or(0.0),
 
             shuffle_pickup_position: self.game_config.shuffle_pickup_position.unwrap_or(false),
+            snap_shuffled_pickup_position: self
+                .game_config
+                .snap_shuffled_pickup_position
+                .unwrap_or(false),
             shuffle_pickup_pos_all_rooms: self
                 .game_config
                 .shuffle_pickup_pos_all_rooms
@@ -2734,12 +3196,14 @@ impl PatchConfigPrivate {
             item_max_capacity,
 
             game_banner: self.game_config.game_banner.clone().unwrap_or_default(),
+            disc_identity: self.game_config.disc_identity.clone().unwrap_or_default(),
             comment: self.game_config.comment.clone().unwrap_or_default(),
             main_menu_message,
 
             credits_string,
             results_string,
             artifact_hints: self.game_config.artifact_hints.clone(),
+            auto_wrap_text: self.game_config.auto_wrap_text.unwrap_or(false),
             required_artifact_count: self.game_config.required_artifact_count,
 
             ctwk_config: self.tweaks.clone(),
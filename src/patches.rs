@@ -1,6 +1,7 @@
 use std::{
     borrow::Cow,
-    collections::{hash_map::DefaultHasher, HashMap},
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
     convert::TryInto,
     ffi::CString,
     fs::{self, File},
@@ -19,32 +20,39 @@ use reader_writer::{
     generic_array::GenericArray, typenum::U3, CStr, CStrConversionExtension, FourCC, Reader,
     Writable,
 };
-use resource_info_table::{resource_info, ResourceInfo};
+use resource_info_table::{resource_info, resource_info_by_id, resource_info_iter, ResourceInfo};
 use structs::{
     res_id,
     scly_structs::{DamageInfo, TypeVulnerability},
-    Languages, MapaObjectVisibilityMode, ResId, SclyLayer, SclyPropertyData,
+    Justification, Languages, MapaObjectVisibilityMode, MarkupBuilder, MarkupTag, PartElement,
+    PartElementKind, PartKeys, ResId, SclyLayer, SclyPropertyData, TxtrFormat,
 };
 
 use crate::{
     add_modify_obj_patches::*,
+    asset_export::{self, ModelFormat},
     ciso_writer::CisoWriter,
     custom_assets::{
         collect_game_resources, custom_asset_filename, custom_asset_ids, PickupHashKey,
     },
     dol_patcher::DolPatcher,
     door_meta::{BlastShieldType, DoorType},
+    dsp_encoder,
     elevators::{is_elevator, Elevator, SpawnRoom, SpawnRoomData, World},
     extern_assets::ExternPickupModel,
+    font_edit,
     gcz_writer::GczWriter,
     generic_edit::patch_edit_objects,
     mlvl_wrapper,
     patch_config::{
         ArtifactHintBehavior, BlockConfig, BombSlotCover, ConnectionConfig, ConnectionMsg,
-        ConnectionState, CtwkConfig, CutsceneMode, DifficultyBehavior, DoorConfig, DoorOpenMode,
-        FogConfig, GameBanner, GenericTexture, HallOfTheEldersBombSlotCoversConfig, IsoFormat,
-        LevelConfig, PatchConfig, PhazonDamageModifier, PickupConfig, PlatformConfig, PlatformType,
-        RoomConfig, RunMode, SpecialFunctionType, SuitDamageReduction, TimerConfig, Version, Visor,
+        ConnectionState, CtwkConfig, CutsceneMode, DifficultyBehavior, DiscIdentity, DoorConfig,
+        DoorOpenMode, ExportResourcesConfig, FogConfig, FontConfig, GameBanner, GenericTexture,
+        GuiFrameConfig, GuiImageConfig, GuiTextPaneConfig, GuiWidgetConfig,
+        HallOfTheEldersBombSlotCoversConfig, HintsConfig, IsoFormat, LevelConfig, LightConfig,
+        PatchConfig, PhazonDamageModifier, PickupConfig, PlatformConfig, PlatformType, RoomConfig,
+        RoomMapConfig, RunMode, SpecialFunctionType, SuitDamageReduction, TimerConfig,
+        UniverseMapConfig, Version, Visor, WorldMapConfig,
     },
     patcher::{PatcherState, PrimePatcher},
     pickup_meta::{
@@ -52,10 +60,15 @@ use crate::{
         PickupModel, PickupType, ScriptObjectLocation,
     },
     starting_items::StartingItems,
+    strg_translation::{self, StrgString, TranslationFormat},
     structs::LightLayer,
+    text_validation::{TextContext, TextValidator},
+    thp_encoder,
     txtr_conversions::{
-        cmpr_compress, cmpr_decompress, huerotate_color, huerotate_in_place, huerotate_matrix,
-        GRAVITY_SUIT_TEXTURES, PHAZON_SUIT_TEXTURES, POWER_SUIT_TEXTURES, VARIA_SUIT_TEXTURES,
+        cmpr_compress, cmpr_decompress, encode_txtr, huerotate_color, huerotate_in_place,
+        huerotate_matrix, png_to_txtr, recolor_mipmap, recolor_palette, ColorAdjustment,
+        TxtrFormatExt, GRAVITY_SUIT_TEXTURES, PHAZON_SUIT_TEXTURES, POWER_SUIT_TEXTURES,
+        VARIA_SUIT_TEXTURES,
     },
     GcDiscLookupExtensions,
 };
@@ -282,7 +295,7 @@ fn patch_artifact_totem_scan_strg(
 ) -> Result<(), String> {
     let mut string = text.to_string();
     if version == Version::NtscJ {
-        string = format!("&line-extra-space=4;&font=C29C51F1;{}", string);
+        string = MarkupBuilder::new().jpn_font().text(&string).build();
     }
     let strg = res.kind.as_strg_mut().unwrap();
     for st in strg.string_tables.as_mut_vec().iter_mut() {
@@ -292,10 +305,43 @@ fn patch_artifact_totem_scan_strg(
     Ok(())
 }
 
-fn patch_save_banner_txtr(res: &mut structs::Resource) -> Result<(), String> {
+fn read_banner_png(path: &str, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let image = image::open(path)
+        .map_err(|e| format!("Failed to read banner image '{}': {}", path, e))?
+        .to_rgba8();
+    if image.width() != width || image.height() != height {
+        return Err(format!(
+            "Banner image '{}' must be {}x{}, found {}x{}",
+            path,
+            width,
+            height,
+            image.width(),
+            image.height()
+        ));
+    }
+    Ok(image.into_raw())
+}
+
+// The card banner and icon use the same format as the vanilla save banner
+fn encode_save_banner_txtr(path: &str, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let pixels = read_banner_png(path, width, height)?;
+    let format = TxtrFormat::from_str("C8(RGB565)").unwrap();
+    let txtr = encode_txtr(format, pixels, width as usize, height as usize, Some(1))?;
+    let mut bytes = vec![];
+    txtr.write_to(&mut bytes).unwrap();
+    Ok(bytes)
+}
+
+fn patch_save_banner_txtr(
+    res: &mut structs::Resource,
+    txtr_bytes: Option<&[u8]>,
+) -> Result<(), String> {
     const TXTR_BYTES: &[u8] = include_bytes!("../extra_assets/save_banner.txtr");
     res.compressed = false;
-    res.kind = structs::ResourceKind::Unknown(Reader::new(TXTR_BYTES), b"TXTR".into());
+    res.kind = match txtr_bytes {
+        Some(bytes) => structs::ResourceKind::External(bytes.to_vec(), b"TXTR".into()),
+        None => structs::ResourceKind::Unknown(Reader::new(TXTR_BYTES), b"TXTR".into()),
+    };
     Ok(())
 }
 
@@ -473,29 +519,28 @@ fn patch_add_scans_to_savw(
 fn patch_map_door_icon(
     res: &mut structs::Resource,
     door: ModifiableDoorLocation,
-    map_object_type: u32,
+    map_object_type: structs::MapaObjectType,
     mrea_id: u32,
 ) -> Result<(), String> {
-    if door.door_location.is_none() {
-        println!("Warning, no door location to patch map for");
-        return Ok(());
-    }
-
-    let mapa = res.kind.as_mapa_mut().unwrap();
+    let door_id = match door.door_location {
+        Some(door_location) => door_location.instance_id,
+        None => {
+            println!("Warning, no door location to patch map for");
+            return Ok(());
+        }
+    };
 
-    let door_id = door.door_location.as_ref().unwrap().instance_id;
+    let mapa = res
+        .kind
+        .as_mapa_mut()
+        .ok_or_else(|| format!("Failed to read the map of room 0x{:X}", mrea_id))?;
 
-    let door_icon = mapa
-        .objects
-        .iter_mut()
-        .find(|obj| obj.editor_id == door_id)
-        .unwrap_or_else(|| {
-            panic!(
-                "Failed to find door 0x{:X} in room 0x{:X}",
-                door_id, mrea_id
-            )
-        });
-    door_icon.type_ = map_object_type;
+    if !mapa.set_door_type(door_id, map_object_type) {
+        return Err(format!(
+            "Failed to find door 0x{:X} in room 0x{:X}",
+            door_id, mrea_id
+        ));
+    }
 
     Ok(())
 }
@@ -2374,6 +2419,7 @@ fn patch_add_item<'r>(
     skip_hudmemos: bool,
     extern_models: &HashMap<String, ExternPickupModel>,
     shuffle_position: bool,
+    snap_shuffled_position: bool,
     seed: u64,
     _no_starting_visor: bool,
     version: Version,
@@ -2490,6 +2536,9 @@ fn patch_add_item<'r>(
             let strg_dep: structs::Dependency = strg.into();
             area.add_dependencies(game_resources, new_layer_idx, iter::once(strg_dep));
 
+            let image_deps = scan_image_dependencies(game_resources, scan);
+            area.add_dependencies(game_resources, new_layer_idx, image_deps.into_iter());
+
             scan
         } else {
             let scan_dep: structs::Dependency = pickup_type.scan().into();
@@ -2547,7 +2596,7 @@ fn patch_add_item<'r>(
 
     let mut pickup_position = {
         if shuffle_position {
-            get_shuffled_position(area, &mut rng)
+            get_shuffled_position(area, &mut rng, snap_shuffled_position)?
         } else {
             if pickup_config.position.is_none() {
                 panic!(
@@ -2556,7 +2605,12 @@ fn patch_add_item<'r>(
                 );
             }
 
-            pickup_config.position.unwrap()
+            let position = pickup_config.position.unwrap();
+            if pickup_config.snap_to_floor.unwrap_or(false) {
+                snap_position_to_floor(area, position)?
+            } else {
+                position
+            }
         }
     };
 
@@ -3658,9 +3712,28 @@ fn patch_add_poi<'r>(
     let frme_dep: structs::Dependency = frme_id.into();
     area.add_dependencies(game_resources, 0, iter::once(frme_dep));
 
+    let image_deps = scan_image_dependencies(game_resources, scan_id);
+    area.add_dependencies(game_resources, 0, image_deps.into_iter());
+
     Ok(())
 }
 
+/// The textures shown by a custom scan, which have to be added to the area alongside it.
+fn scan_image_dependencies(
+    game_resources: &HashMap<(u32, FourCC), structs::Resource>,
+    scan_id: ResId<res_id::SCAN>,
+) -> Vec<structs::Dependency> {
+    let scan = game_resources[&(scan_id.to_u32(), b"SCAN".into())]
+        .kind
+        .as_scan()
+        .unwrap();
+    scan.images
+        .iter()
+        .filter(|image| image.txtr != ResId::invalid())
+        .map(|image| image.txtr.into())
+        .collect()
+}
+
 fn patch_add_scan_actor<'r>(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
@@ -3789,10 +3862,13 @@ where
     closest
 }
 
+// When `snap_to_floor` is set, positions are re-rolled until one lands on a walkable floor.
+// Otherwise a single position is rolled, which keeps the results of existing seeds.
 fn get_shuffled_position<R>(
     area: &mut mlvl_wrapper::MlvlArea<'_, '_, '_, '_>,
     rng: &mut R,
-) -> [f32; 3]
+    snap_to_floor: bool,
+) -> Result<[f32; 3], String>
 where
     R: Rng,
 {
@@ -3848,18 +3924,73 @@ where
         offset_max_z = -0.3;
     }
 
-    // Pick the relative position inside the bounding box
-    let x_factor: f32 = gen_n_pick_closest(2, rng, 0.15 + offset_xy, 0.85 - offset_xy, 0.5);
-    let y_factor: f32 = gen_n_pick_closest(2, rng, 0.15 + offset_xy, 0.85 - offset_xy, 0.5);
-    let z_factor: f32 = gen_n_pick_closest(2, rng, 0.1, 0.8 + offset_max_z, 0.35);
+    let collision = if snap_to_floor {
+        Some(area.mrea().collision_section()?)
+    } else {
+        None
+    };
+
+    // Keep picking positions until one lands on a walkable floor, falling back to the first one
+    let mut fallback = None;
+    for _ in 0..SHUFFLE_POSITION_ATTEMPTS {
+        // Pick the relative position inside the bounding box
+        let x_factor: f32 = gen_n_pick_closest(2, rng, 0.15 + offset_xy, 0.85 - offset_xy, 0.5);
+        let y_factor: f32 = gen_n_pick_closest(2, rng, 0.15 + offset_xy, 0.85 - offset_xy, 0.5);
+        let z_factor: f32 = gen_n_pick_closest(2, rng, 0.1, 0.8 + offset_max_z, 0.35);
+
+        // Pick a bounding box if multiple are available
+        let bounding_box = *bounding_boxes.choose(rng).unwrap();
+        let position = [
+            bounding_box[0] + (bounding_box[3] - bounding_box[0]) * x_factor,
+            bounding_box[1] + (bounding_box[4] - bounding_box[1]) * y_factor,
+            bounding_box[2] + (bounding_box[5] - bounding_box[2]) * z_factor,
+        ];
+
+        let collision = match collision {
+            Some(ref collision) => collision,
+            None => return Ok(position),
+        };
+        let max_drop = position[2] - bounding_box[2] + PICKUP_FLOOR_OFFSET;
+        if let Some(position) = snap_position(&collision.collision, position, max_drop) {
+            return Ok(position);
+        }
+        fallback.get_or_insert(position);
+    }
+
+    Ok(fallback.unwrap())
+}
 
-    // Pick a bounding box if multiple are available
-    let bounding_box = *bounding_boxes.choose(rng).unwrap();
-    [
-        bounding_box[0] + (bounding_box[3] - bounding_box[0]) * x_factor,
-        bounding_box[1] + (bounding_box[4] - bounding_box[1]) * y_factor,
-        bounding_box[2] + (bounding_box[5] - bounding_box[2]) * z_factor,
-    ]
+const SHUFFLE_POSITION_ATTEMPTS: usize = 32;
+
+// Height above the floor at which snapped pickups are placed
+const PICKUP_FLOOR_OFFSET: f32 = 1.0;
+
+// Moves a position down onto the walkable floor below it, as long as there is enough room above
+// the floor for a pickup
+fn snap_position(
+    collision: &structs::CollisionIndexData,
+    position: [f32; 3],
+    max_drop: f32,
+) -> Option<[f32; 3]> {
+    let floor = collision.find_floor(position, max_drop)?;
+    let above_floor = [floor[0], floor[1], floor[2] + 0.01];
+    if collision
+        .raycast(above_floor, [0.0, 0.0, 1.0], PICKUP_FLOOR_OFFSET * 2.0)
+        .is_some()
+    {
+        return None;
+    }
+    Some([floor[0], floor[1], floor[2] + PICKUP_FLOOR_OFFSET])
+}
+
+fn snap_position_to_floor(
+    area: &mut mlvl_wrapper::MlvlArea<'_, '_, '_, '_>,
+    position: [f32; 3],
+) -> Result<[f32; 3], String> {
+    let (bounding_box_min, _, _, _) = derrive_bounding_box_measurements(area);
+    let collision = area.mrea().collision_section()?;
+    let max_drop = position[2] - bounding_box_min[2] + PICKUP_FLOOR_OFFSET;
+    Ok(snap_position(&collision.collision, position, max_drop).unwrap_or(position))
 }
 
 fn set_room_map_default_state(
@@ -3872,6 +4003,82 @@ fn set_room_map_default_state(
     Ok(())
 }
 
+fn patch_room_map(
+    res: &mut structs::Resource,
+    map: Option<&RoomMapConfig>,
+    bounding_box: Option<([f32; 3], [f32; 3])>,
+    regenerate_outline: bool,
+    mrea_id: u32,
+) -> Result<(), String> {
+    let mapa = res
+        .kind
+        .as_mapa_mut()
+        .ok_or_else(|| format!("Failed to read the map of room 0x{:X}", mrea_id))?;
+
+    if let Some(map) = map {
+        for editor_id in map.remove_objects.iter().flatten() {
+            if mapa.remove_objects(*editor_id) == 0 {
+                return Err(format!(
+                    "Failed to find map object 0x{:X} in room 0x{:X}",
+                    editor_id, mrea_id
+                ));
+            }
+        }
+
+        for object in map.add_objects.iter().flatten() {
+            mapa.add_object(
+                object.object_type,
+                object
+                    .visibility_mode
+                    .unwrap_or(MapaObjectVisibilityMode::MapStationOrVisit),
+                object.editor_id.unwrap_or(0xFFFFFFFF),
+                object.position,
+                object.rotation.unwrap_or([0.0, 0.0, 0.0]),
+            );
+        }
+
+        for (door_id, door_type) in map.door_colors.iter().flatten() {
+            let map_object_type = match DoorType::from_string(door_type.to_string()) {
+                Some(door_type) => door_type.map_object_type(),
+                None => return Err(format!("Unexpected door type '{}' in map", door_type)),
+            };
+            if !mapa.set_door_type(*door_id, map_object_type) {
+                return Err(format!(
+                    "Failed to find door 0x{:X} in the map of room 0x{:X}",
+                    door_id, mrea_id
+                ));
+            }
+        }
+
+        // Remove from the back so that the indices stay valid
+        let mut hidden_surfaces = map.hidden_surfaces.clone().unwrap_or_default();
+        hidden_surfaces.sort();
+        hidden_surfaces.dedup();
+        for index in hidden_surfaces.iter().rev() {
+            mapa.remove_surface(*index as usize)
+                .map_err(|e| format!("{} in room 0x{:X}", e, mrea_id))?;
+        }
+
+        for polygon in map.add_surfaces.iter().flatten() {
+            mapa.add_surface(polygon)
+                .map_err(|e| format!("{} in room 0x{:X}", e, mrea_id))?;
+        }
+    }
+
+    if let Some((offset, scale)) = bounding_box {
+        let bb = mapa.aabb;
+        mapa.aabb =
+            transform_bounding_box([bb[0], bb[1], bb[2], bb[3], bb[4], bb[5]], offset, scale)
+                .into();
+    }
+
+    if regenerate_outline {
+        mapa.regenerate_outline();
+    }
+
+    Ok(())
+}
+
 fn add_player_freeze_assets<'r>(
     file: &mut structs::FstEntryFile<'r>,
     resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
@@ -3948,6 +4155,7 @@ fn modify_pickups_in_mrea<'r>(
     qol_pickup_scans: bool,
     extern_models: &HashMap<String, ExternPickupModel>,
     shuffle_position: bool,
+    snap_shuffled_position: bool,
     seed: u64,
     _no_starting_visor: bool,
     version: Version,
@@ -4021,7 +4229,15 @@ fn modify_pickups_in_mrea<'r>(
 
     let mut position_override: Option<[f32; 3]> = None;
     if shuffle_position {
-        position_override = Some(get_shuffled_position(area, &mut rng));
+        position_override = Some(get_shuffled_position(
+            area,
+            &mut rng,
+            snap_shuffled_position,
+        )?);
+    } else if let Some(position) = pickup_config.position {
+        if pickup_config.snap_to_floor.unwrap_or(false) {
+            position_override = Some(snap_position_to_floor(area, position)?);
+        }
     }
 
     // Pickup to use for game functionality //
@@ -4118,6 +4334,9 @@ fn modify_pickups_in_mrea<'r>(
             let strg_dep: structs::Dependency = strg.into();
             area.add_dependencies(game_resources, 0, iter::once(strg_dep));
 
+            let image_deps = scan_image_dependencies(game_resources, scan);
+            area.add_dependencies(game_resources, 0, image_deps.into_iter());
+
             scan
         } else {
             let scan_dep: structs::Dependency = pickup_type.scan().into();
@@ -5060,7 +5279,7 @@ fn make_elevators_patch(
                 move |res| {
                     let mut string = format!("Transport to {}\u{0}", room_dest_name);
                     if version == Version::NtscJ {
-                        string = format!("&line-extra-space=4;&font=C29C51F1;{}", string);
+                        string = MarkupBuilder::new().jpn_font().text(&string).build();
                     }
                     let strg = structs::Strg::from_strings(vec![string]);
                     res.kind = structs::ResourceKind::Strg(strg);
@@ -5073,7 +5292,7 @@ fn make_elevators_patch(
                     hologram_name,
                 );
                 if version == Version::NtscJ {
-                    string = format!("&line-extra-space=4;&font=C29C51F1;{}", string);
+                    string = MarkupBuilder::new().jpn_font().text(&string).build();
                 }
                 let strg = structs::Strg::from_strings(vec![string]);
                 res.kind = structs::ResourceKind::Strg(strg);
@@ -5087,7 +5306,7 @@ fn make_elevators_patch(
                         control_name,
                     );
                     if version == Version::NtscJ {
-                        string = format!("&line-extra-space=4;&font=C29C51F1;{}", string);
+                        string = MarkupBuilder::new().jpn_font().text(&string).build();
                     }
                     let strg = structs::Strg::from_strings(vec![string]);
                     res.kind = structs::ResourceKind::Strg(strg);
@@ -5817,7 +6036,7 @@ fn patch_ambient_lighting(
             brightness: scale,
             spot_cutoff: 0.0,
             unknown0: 0.0,
-            unknown1: 0,
+            cast_shadows: 0,
             unknown2: 0.0,
             falloff_type: 0, // constant
             unknown3: 0.0,
@@ -5827,6 +6046,100 @@ fn patch_ambient_lighting(
     Ok(())
 }
 
+fn patch_lights(
+    _ps: &mut PatcherState,
+    area: &mut mlvl_wrapper::MlvlArea<'_, '_, '_, '_>,
+    config: Vec<LightConfig>,
+) -> Result<(), String> {
+    let room_id = area.mlvl_area.mrea.to_u32();
+    let lights = area.mrea().lights_section_mut();
+    let lights = lights.light_layers.as_mut_vec();
+    let original_count = lights.len();
+
+    let update_light = |light: &mut LightLayer, config: &LightConfig| {
+        if let Some(light_type) = config.light_type {
+            light.light_type = light_type.to_u32();
+        }
+        if let Some(color) = config.color {
+            light.color = color.into();
+        }
+        if let Some(brightness) = config.brightness {
+            light.brightness = brightness;
+        }
+        if let Some(position) = config.position {
+            light.position = position.into();
+        }
+        if let Some(direction) = config.direction {
+            light.direction = direction.into();
+        }
+        if let Some(spot_cutoff) = config.spot_cutoff {
+            light.spot_cutoff = spot_cutoff;
+        }
+        if let Some(falloff_type) = config.falloff_type {
+            light.falloff_type = falloff_type.to_u32();
+        }
+        if let Some(cast_shadows) = config.cast_shadows {
+            light.cast_shadows = cast_shadows as u8;
+        }
+    };
+
+    // Indices always refer to the vanilla order of lights, so edit existing lights first, then
+    // remove, then add
+    let mut remove_indices = Vec::new();
+    for light_config in config.iter() {
+        let index = match light_config.index {
+            Some(index) => index as usize,
+            None => continue,
+        };
+
+        if index >= original_count {
+            return Err(format!(
+                "Light index {} is out of range in room 0x{:X}, which only has {} lights",
+                index, room_id, original_count,
+            ));
+        }
+
+        if light_config.remove.unwrap_or(false) {
+            remove_indices.push(index);
+        } else {
+            update_light(&mut lights[index], light_config);
+        }
+    }
+
+    remove_indices.sort_unstable();
+    remove_indices.dedup();
+    for index in remove_indices.into_iter().rev() {
+        lights.remove(index);
+    }
+
+    for light_config in config.iter().filter(|c| c.index.is_none()) {
+        if light_config.remove.unwrap_or(false) {
+            return Err(format!(
+                "Cannot remove a light without an index in room 0x{:X}",
+                room_id
+            ));
+        }
+
+        let mut light = LightLayer {
+            light_type: 2, // custom
+            color: [1.0, 1.0, 1.0].into(),
+            position: [0.0, 0.0, 0.0].into(),
+            direction: [0.0, 0.0, -1.0].into(),
+            brightness: 1.0,
+            spot_cutoff: 0.0,
+            unknown0: 0.0,
+            cast_shadows: 0,
+            unknown2: 0.0,
+            falloff_type: 1, // linear
+            unknown3: 0.0,
+        };
+        update_light(&mut light, light_config);
+        lights.push(light);
+    }
+
+    Ok(())
+}
+
 // fn patch_add_orange_light<'r>(
 //     ps: &mut PatcherState,
 //     area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
@@ -7603,6 +7916,23 @@ fn patch_remove_doors(
     Ok(())
 }
 
+fn transform_bounding_box(bb: [f32; 6], offset: [f32; 3], scale: [f32; 3]) -> [f32; 6] {
+    let size: [f32; 3] = [
+        (bb[3] - bb[0]).abs(),
+        (bb[4] - bb[1]).abs(),
+        (bb[5] - bb[2]).abs(),
+    ];
+
+    [
+        bb[0] + offset[0] + (size[0] * 0.5 - (size[0] * 0.5) * scale[0]),
+        bb[1] + offset[1] + (size[1] * 0.5 - (size[1] * 0.5) * scale[1]),
+        bb[2] + offset[2] + (size[2] * 0.5 - (size[2] * 0.5) * scale[2]),
+        bb[3] + offset[0] - (size[0] * 0.5 - (size[0] * 0.5) * scale[0]),
+        bb[4] + offset[1] - (size[1] * 0.5 - (size[1] * 0.5) * scale[1]),
+        bb[5] + offset[2] - (size[2] * 0.5 - (size[2] * 0.5) * scale[2]),
+    ]
+}
+
 fn patch_transform_bounding_box(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'_, '_, '_, '_>,
@@ -7610,24 +7940,8 @@ fn patch_transform_bounding_box(
     scale: [f32; 3],
 ) -> Result<(), String> {
     let bb = area.mlvl_area.area_bounding_box;
-    let size: [f32; 3] = [
-        (bb[3] - bb[0]).abs(),
-        (bb[4] - bb[1]).abs(),
-        (bb[5] - bb[2]).abs(),
-    ];
-
-    area.mlvl_area.area_bounding_box[0] =
-        bb[0] + offset[0] + (size[0] * 0.5 - (size[0] * 0.5) * scale[0]);
-    area.mlvl_area.area_bounding_box[1] =
-        bb[1] + offset[1] + (size[1] * 0.5 - (size[1] * 0.5) * scale[1]);
-    area.mlvl_area.area_bounding_box[2] =
-        bb[2] + offset[2] + (size[2] * 0.5 - (size[2] * 0.5) * scale[2]);
-    area.mlvl_area.area_bounding_box[3] =
-        bb[3] + offset[0] - (size[0] * 0.5 - (size[0] * 0.5) * scale[0]);
-    area.mlvl_area.area_bounding_box[4] =
-        bb[4] + offset[1] - (size[1] * 0.5 - (size[1] * 0.5) * scale[1]);
-    area.mlvl_area.area_bounding_box[5] =
-        bb[5] + offset[2] - (size[2] * 0.5 - (size[2] * 0.5) * scale[2]);
+    area.mlvl_area.area_bounding_box =
+        transform_bounding_box([bb[0], bb[1], bb[2], bb[3], bb[4], bb[5]], offset, scale).into();
 
     Ok(())
 }
@@ -9265,15 +9579,35 @@ fn patch_no_hud(res: &mut structs::Resource) -> Result<(), String> {
     Ok(())
 }
 
-fn patch_main_menu(res: &mut structs::Resource) -> Result<(), String> {
-    let frme = res.kind.as_frme_mut().unwrap();
-
-    let (jpn_font, jpn_point_scale) = if frme.version == 0 {
+// The text pane which displays the main menu message
+fn main_menu_message_text_pane(frme_version: u32) -> structs::TextPaneWidget {
+    let (jpn_font, jpn_point_scale) = if frme_version == 0 {
         (None, None)
     } else {
         (Some(ResId::new(0xC29C51F1)), Some([237, 35].into()))
     };
 
+    structs::TextPaneWidget {
+        x_dim: 10.455326,
+        z_dim: 1.813613,
+        scale_center: [-5.227663, 0.0, -0.51].into(),
+        font: resource_info!("Deface14B_O.FONT").try_into().unwrap(),
+        word_wrap: 0,
+        horizontal: 1,
+        justification: 0,
+        vertical_justification: 0,
+        fill_color: [1.0, 1.0, 1.0, 1.0].into(),
+        outline_color: [0.0, 0.0, 0.0, 1.0].into(),
+        block_extent: [213.0, 38.0].into(),
+        jpn_font,
+        jpn_point_scale,
+    }
+}
+
+fn patch_main_menu(res: &mut structs::Resource) -> Result<(), String> {
+    let frme = res.kind.as_frme_mut().unwrap();
+
+    let text_pane = main_menu_message_text_pane(frme.version);
     frme.widgets.as_mut_vec().push(structs::FrmeWidget {
         name: b"textpane_identifier\0".as_cstr(),
         parent: b"kGSYS_HeadWidgetID\0".as_cstr(),
@@ -9283,21 +9617,7 @@ fn patch_main_menu(res: &mut structs::Resource) -> Result<(), String> {
         cull_faces: 0,
         color: [1.0, 1.0, 1.0, 1.0].into(),
         model_draw_flags: 2,
-        kind: structs::FrmeWidgetKind::TextPane(structs::TextPaneWidget {
-            x_dim: 10.455326,
-            z_dim: 1.813613,
-            scale_center: [-5.227663, 0.0, -0.51].into(),
-            font: resource_info!("Deface14B_O.FONT").try_into().unwrap(),
-            word_wrap: 0,
-            horizontal: 1,
-            justification: 0,
-            vertical_justification: 0,
-            fill_color: [1.0, 1.0, 1.0, 1.0].into(),
-            outline_color: [0.0, 0.0, 0.0, 1.0].into(),
-            block_extent: [213.0, 38.0].into(),
-            jpn_font,
-            jpn_point_scale,
-        }),
+        kind: structs::FrmeWidgetKind::TextPane(text_pane),
         worker_id: None,
         origin: [9.25, 1.500001, 0.0].into(),
         basis: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
@@ -9323,103 +9643,875 @@ fn patch_main_menu(res: &mut structs::Resource) -> Result<(), String> {
     Ok(())
 }
 
-fn patch_credits(
-    res: &mut structs::Resource,
-    version: Version,
-    config: &PatchConfig,
-    level_data: &HashMap<String, LevelConfig>,
-) -> Result<(), String> {
-    let mut output = "\n\n\n\n\n\n\n".to_string();
-
-    if version == Version::NtscJ {
-        output = format!(
-            "&line-extra-space=8;&font=5D696116;{}",
-            &output[..output.len() - 2]
-        );
-    }
-
-    if config.credits_string.is_some() {
-        output = format!("{}{}", output, config.credits_string.as_ref().unwrap());
+// Size of a font pixel in the vanilla text panes, which is their size divided by their block
+// extent. New text panes derive their block extent from their size with it, so their text is drawn
+// at the same scale as the game's instead of being stretched to fit the pane.
+const TEXT_PANE_PIXEL_SIZE: f32 = 0.049;
+
+fn gui_text_pane(
+    config: &GuiTextPaneConfig,
+    frme_version: u32,
+) -> Result<structs::TextPaneWidget, String> {
+    let size = config.size.ok_or("New text panes need a size")?;
+    let font = config.font.ok_or("New text panes need a font")?;
+    let block_extent = config.block_extent.unwrap_or([
+        size[0] / TEXT_PANE_PIXEL_SIZE,
+        size[1] / TEXT_PANE_PIXEL_SIZE,
+    ]);
+    let (jpn_font, jpn_point_scale) = if frme_version == 0 {
+        (None, None)
     } else {
-        output = format!(
-            "{}{}",
-            output,
-            concat!(
-                "&push;&font=C29C51F1;&main-color=#89D6FF;",
-                "Major Item Locations",
-                "&pop;",
-            )
-            .to_owned()
-        );
-
-        use std::fmt::Write;
-        const PICKUPS_TO_PRINT: &[PickupType] = &[
-            PickupType::ScanVisor,
-            PickupType::ThermalVisor,
-            PickupType::XRayVisor,
-            PickupType::VariaSuit,
-            PickupType::GravitySuit,
-            PickupType::PhazonSuit,
-            PickupType::MorphBall,
-            PickupType::BoostBall,
-            PickupType::SpiderBall,
-            PickupType::MorphBallBomb,
-            PickupType::PowerBomb,
-            PickupType::ChargeBeam,
-            PickupType::SpaceJumpBoots,
-            PickupType::GrappleBeam,
-            PickupType::SuperMissile,
-            PickupType::Wavebuster,
-            PickupType::IceSpreader,
-            PickupType::Flamethrower,
-            PickupType::WaveBeam,
-            PickupType::IceBeam,
-            PickupType::PlasmaBeam,
-        ];
-
-        for pickup_type in PICKUPS_TO_PRINT {
-            let room_name = {
-                let mut _room_name = String::new();
-                for (_, level) in level_data.iter() {
-                    for (room_name, room) in level.rooms.iter() {
-                        if room.pickups.is_none() {
-                            continue;
-                        };
-                        for pickup_info in room.pickups.as_ref().unwrap().iter() {
-                            if PickupType::from_str(pickup_type.name())
-                                == PickupType::from_str(&pickup_info.pickup_type)
-                            {
-                                _room_name = room_name.to_string();
-                                break;
-                            }
-                        }
-                    }
-                }
+        (
+            Some(ResId::new(config.jpn_font.unwrap_or(font))),
+            Some([block_extent[0] as u32, block_extent[1] as u32].into()),
+        )
+    };
 
-                if _room_name.is_empty() {
-                    _room_name = "<Not Present>".to_string();
-                }
+    let mut text_pane = structs::TextPaneWidget {
+        x_dim: size[0],
+        z_dim: size[1],
+        scale_center: [-size[0] / 2.0, 0.0, 0.0].into(),
+        font: ResId::new(font),
+        word_wrap: 1,
+        horizontal: 1,
+        justification: 0,
+        vertical_justification: 0,
+        fill_color: [1.0, 1.0, 1.0, 1.0].into(),
+        outline_color: [0.0, 0.0, 0.0, 1.0].into(),
+        block_extent: block_extent.into(),
+        jpn_font,
+        jpn_point_scale,
+    };
+    edit_gui_text_pane(&mut text_pane, config);
+    Ok(text_pane)
+}
 
-                _room_name
-            };
-            let pickup_name = pickup_type.name();
-            write!(output, "\n\n{}: {}", pickup_name, room_name).unwrap();
+fn edit_gui_text_pane(text_pane: &mut structs::TextPaneWidget, config: &GuiTextPaneConfig) {
+    if let Some(size) = config.size {
+        text_pane.x_dim = size[0];
+        text_pane.z_dim = size[1];
+    }
+    if let Some(font) = config.font {
+        text_pane.font = ResId::new(font);
+    }
+    if let Some(jpn_font) = config.jpn_font {
+        if text_pane.jpn_font.is_some() {
+            text_pane.jpn_font = Some(ResId::new(jpn_font));
         }
     }
-    output = format!("{}{}", output, "\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\0");
-    if version == Version::NtscJ {
-        res.kind
-            .as_strg_mut()
-            .unwrap()
-            .add_strings(&[output.to_string()], Languages::Some(&[b"ENGL", b"JAPN"]));
-    } else {
-        res.kind
-            .as_strg_mut()
-            .unwrap()
-            .add_strings(&[output.to_string()], Languages::All);
+    if let Some(word_wrap) = config.word_wrap {
+        text_pane.word_wrap = word_wrap as u8;
+    }
+    if let Some(justification) = config.justification {
+        text_pane.justification = justification as u32;
+    }
+    if let Some(vertical_justification) = config.vertical_justification {
+        text_pane.vertical_justification = vertical_justification as u32;
+    }
+    if let Some(fill_color) = config.fill_color {
+        text_pane.fill_color = fill_color.into();
     }
+    if let Some(outline_color) = config.outline_color {
+        text_pane.outline_color = outline_color.into();
+    }
+    if let Some(block_extent) = config.block_extent {
+        text_pane.block_extent = block_extent.into();
+    }
+}
 
-    /* We are who we choose to be */
+/// Serialized quad and UV coordinates for an image widget, which its `RoArray`s are read from.
+fn gui_image_coords(config: &GuiImageConfig) -> Option<(Vec<u8>, Vec<u8>)> {
+    let quad_coords = match (&config.quad_coords, config.size) {
+        (Some(quad_coords), _) => quad_coords.clone(),
+        (None, Some([w, h])) => vec![
+            [-w / 2.0, 0.0, h / 2.0],
+            [-w / 2.0, 0.0, -h / 2.0],
+            [w / 2.0, 0.0, h / 2.0],
+            [w / 2.0, 0.0, -h / 2.0],
+        ],
+        (None, None) if config.uv_coords.is_some() => vec![],
+        (None, None) => return None,
+    };
+    let uv_coords = config
+        .uv_coords
+        .clone()
+        .unwrap_or_else(|| vec![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+
+    let quad_bytes = quad_coords
+        .iter()
+        .flatten()
+        .flat_map(|f| f.to_be_bytes())
+        .collect();
+    let uv_bytes = uv_coords
+        .iter()
+        .flatten()
+        .flat_map(|f| f.to_be_bytes())
+        .collect();
+    Some((quad_bytes, uv_bytes))
+}
+
+fn edit_gui_image<'r>(
+    image: &mut structs::ImageWidget<'r>,
+    config: &GuiImageConfig,
+    coords: Option<&'r (Vec<u8>, Vec<u8>)>,
+) {
+    if let Some(texture) = config.texture {
+        image.texture = ResId::new(texture);
+    }
+    if let Some((quad_bytes, uv_bytes)) = coords {
+        if config.quad_coords.is_some() || config.size.is_some() {
+            image.quad_coords = Reader::new(quad_bytes).read((quad_bytes.len() / 12, ()));
+        }
+        image.uv_coords = Reader::new(uv_bytes).read((uv_bytes.len() / 8, ()));
+    }
+}
+
+fn edit_gui_widget<'r>(
+    widget: &mut structs::FrmeWidget<'r>,
+    config: &GuiWidgetConfig,
+    coords: Option<&'r (Vec<u8>, Vec<u8>)>,
+) -> Result<(), String> {
+    let widget_name = widget.name.to_string_lossy().into_owned();
+    if let Some(name) = &config.name {
+        widget.name = string_to_cstr(name.clone());
+    }
+    if let Some(parent) = &config.parent {
+        widget.parent = string_to_cstr(parent.clone());
+    }
+    if let Some(visible) = config.visible {
+        widget.default_visible = visible as u8;
+    }
+    if let Some(active) = config.active {
+        widget.default_active = active as u8;
+    }
+    if let Some(color) = config.color {
+        widget.color = color.into();
+    }
+    if let Some(origin) = config.origin {
+        widget.origin = origin.into();
+    }
+    if let Some(basis) = config.basis {
+        widget.basis = basis.into();
+    }
+    if let Some(scale) = config.scale {
+        for (i, value) in widget.basis.iter_mut().enumerate() {
+            *value *= scale[i % 3];
+        }
+    }
+
+    if let Some(text_pane_config) = &config.text_pane {
+        match &mut widget.kind {
+            structs::FrmeWidgetKind::TextPane(text_pane) => {
+                edit_gui_text_pane(text_pane, text_pane_config)
+            }
+            _ => return Err(format!("Widget \"{}\" is not a text pane", widget_name)),
+        }
+    }
+    if let Some(image_config) = &config.image {
+        match &mut widget.kind {
+            structs::FrmeWidgetKind::Image(image) => edit_gui_image(image, image_config, coords),
+            _ => return Err(format!("Widget \"{}\" is not an image", widget_name)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Fonts and textures set by a GUI frame edit, which have to be loaded along with the frame. Each
+/// font is followed by the texture of its glyphs.
+pub fn gui_frame_dependencies(
+    gc_disc: &structs::GcDisc,
+    config: &GuiFrameConfig,
+) -> Result<Vec<(u32, FourCC)>, String> {
+    let mut deps = vec![];
+    for widget in config.widgets.values().chain(config.add_widgets.iter()) {
+        if let Some(text_pane) = &widget.text_pane {
+            for font_id in text_pane.font.iter().chain(text_pane.jpn_font.iter()) {
+                let res_info = match resource_info_by_id(*font_id) {
+                    Some(res_info) if res_info.fourcc == b"FONT".into() => res_info,
+                    _ => Err(format!("{} is not a known FONT", font_id))?,
+                };
+                let font_bytes = read_vanilla_resource(gc_disc, res_info)?;
+                let font = structs::Font::try_read(&mut Reader::new(&font_bytes[..]))
+                    .map_err(|e| format!("Failed to read font {}: {}", font_id, e))?;
+                deps.push((*font_id, b"FONT".into()));
+                deps.push((font.txtr.to_u32(), b"TXTR".into()));
+            }
+        }
+        if let Some(texture) = widget.image.as_ref().and_then(|image| image.texture) {
+            deps.push((texture, b"TXTR".into()));
+        }
+    }
+    deps.sort();
+    deps.dedup();
+    Ok(deps)
+}
+
+fn add_gui_frame_dependencies<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    frme_id: u32,
+    deps: &[(u32, FourCC)],
+) {
+    let layers: Vec<usize> = area
+        .mlvl_area
+        .dependencies
+        .deps
+        .iter()
+        .enumerate()
+        .filter(|(_, layer_deps)| {
+            layer_deps
+                .iter()
+                .any(|dep| dep.asset_id == frme_id && dep.asset_type == b"FRME".into())
+        })
+        .map(|(layer, _)| layer)
+        .collect();
+
+    for layer in layers {
+        let deps_iter = deps
+            .iter()
+            .map(|&(asset_id, asset_type)| structs::Dependency {
+                asset_id,
+                asset_type,
+            });
+        area.add_dependencies(game_resources, layer, deps_iter);
+    }
+}
+
+fn add_gui_frame_resources<'r>(
+    file: &mut structs::FstEntryFile<'r>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    deps: &[(u32, FourCC)],
+) -> Result<(), String> {
+    let pak = match file {
+        structs::FstEntryFile::Pak(pak) => pak,
+        _ => Err("Expected a PAK when adding GUI frame resources")?,
+    };
+
+    let missing: Vec<&(u32, FourCC)> = deps
+        .iter()
+        .filter(|(id, fourcc)| {
+            !pak.resources
+                .iter()
+                .any(|res| res.file_id == *id && res.fourcc() == *fourcc)
+        })
+        .collect();
+
+    // append at the end of the pak
+    let mut cursor = pak.resources.cursor();
+    while cursor.cursor_advancer().peek().is_some() {}
+    for key in missing {
+        cursor.insert_after(iter::once(game_resources[key].clone()));
+    }
+    Ok(())
+}
+
+fn patch_gui_frame(res: &mut structs::Resource, config: &GuiFrameConfig) -> Result<(), String> {
+    // The edited frame borrows image coordinates from these buffers, so it is written out before
+    // they are dropped
+    let edit_coords: HashMap<&String, (Vec<u8>, Vec<u8>)> = config
+        .widgets
+        .iter()
+        .filter_map(|(name, widget)| {
+            let coords = gui_image_coords(widget.image.as_ref()?)?;
+            Some((name, coords))
+        })
+        .collect();
+    let add_coords: Vec<Option<(Vec<u8>, Vec<u8>)>> = config
+        .add_widgets
+        .iter()
+        .map(|widget| gui_image_coords(widget.image.as_ref()?))
+        .collect();
+
+    let original = match &res.kind {
+        structs::ResourceKind::Frme(frme) => {
+            let mut bytes = vec![];
+            frme.write_to(&mut bytes)
+                .map_err(|e| format!("Failed to write FRME: {}", e))?;
+            bytes
+        }
+        structs::ResourceKind::Unknown(_, _) => {
+            crate::ResourceData::new(res).decompress().into_owned()
+        }
+        structs::ResourceKind::External(_, _) => crate::ResourceData::new_external(res)
+            .decompress()
+            .into_owned(),
+        _ => Err("Resource is not a FRME")?,
+    };
+    let mut frme = structs::Frme::try_read(&mut Reader::new(&original))?;
+    let version = frme.version;
+    let widgets = frme.widgets.as_mut_vec();
+
+    for (name, widget_config) in &config.widgets {
+        let widget = widgets
+            .iter_mut()
+            .find(|widget| widget.name.to_bytes() == name.as_bytes())
+            .ok_or_else(|| format!("There is no widget named \"{}\"", name))?;
+        edit_gui_widget(widget, widget_config, edit_coords.get(name))?;
+    }
+
+    for (widget_config, coords) in config.add_widgets.iter().zip(add_coords.iter()) {
+        let name = widget_config
+            .name
+            .as_ref()
+            .ok_or("New widgets need a name")?;
+        if widgets.iter().any(|w| w.name.to_bytes() == name.as_bytes()) {
+            return Err(format!("There is already a widget named \"{}\"", name));
+        }
+        let parent = widget_config
+            .parent
+            .as_deref()
+            .unwrap_or("kGSYS_HeadWidgetID");
+        if !widgets
+            .iter()
+            .any(|w| w.name.to_bytes() == parent.as_bytes())
+        {
+            return Err(format!("There is no widget named \"{}\"", parent));
+        }
+
+        let kind = match (&widget_config.text_pane, &widget_config.image) {
+            (Some(text_pane), None) => {
+                structs::FrmeWidgetKind::TextPane(gui_text_pane(text_pane, version)?)
+            }
+            (None, Some(image)) => {
+                let texture = image.texture.ok_or("New images need a texture")?;
+                let (quad_bytes, uv_bytes) = coords
+                    .as_ref()
+                    .ok_or("New images need a size or quad coordinates")?;
+                structs::FrmeWidgetKind::Image(structs::ImageWidget {
+                    texture: ResId::new(texture),
+                    unknown0: 0,
+                    unknown1: 0,
+                    quad_coords: Reader::new(quad_bytes).read((quad_bytes.len() / 12, ())),
+                    uv_coords: Reader::new(uv_bytes).read((uv_bytes.len() / 8, ())),
+                })
+            }
+            _ => {
+                return Err(format!(
+                    "New widget \"{}\" needs either a text pane or an image",
+                    name
+                ))
+            }
+        };
+
+        let mut widget = structs::FrmeWidget {
+            name: string_to_cstr(name.clone()),
+            parent: string_to_cstr(parent.to_string()),
+            use_anim_controller: 0,
+            default_visible: 1,
+            default_active: 1,
+            cull_faces: 0,
+            color: [1.0, 1.0, 1.0, 1.0].into(),
+            model_draw_flags: 2,
+            kind,
+            worker_id: None,
+            origin: [0.0, 0.0, 0.0].into(),
+            basis: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+            rotation_center: [0.0, 0.0, 0.0].into(),
+            unknown0: 0,
+            unknown1: 0,
+        };
+        let edits = GuiWidgetConfig {
+            name: None,
+            parent: None,
+            text_pane: None,
+            image: None,
+            ..widget_config.clone()
+        };
+        edit_gui_widget(&mut widget, &edits, None)?;
+        widgets.push(widget);
+    }
+
+    // Config edits are registered after the built-in HUD patches, so nothing else needs to read
+    // the frame once it is external
+    let mut bytes = vec![];
+    frme.write_to(&mut bytes).unwrap();
+    res.kind = structs::ResourceKind::External(bytes, b"FRME".into());
+    res.compressed = false;
+
+    Ok(())
+}
+
+fn patch_world_map(
+    res: &mut structs::Resource,
+    world_map: &WorldMapConfig,
+    world: World,
+) -> Result<(), String> {
+    let mapw = res.kind.as_mapw_mut().unwrap();
+    let area_maps = mapw.area_maps.as_mut_vec();
+
+    // Go in order of index so that several new areas can be appended at once
+    let mut indices: Vec<u32> = world_map.area_maps.keys().copied().collect();
+    indices.sort();
+    for index in indices {
+        let mapa_id = world_map.area_maps[&index];
+        match (index as usize).cmp(&area_maps.len()) {
+            Ordering::Less => area_maps[index as usize] = mapa_id,
+            Ordering::Equal => area_maps.push(mapa_id),
+            Ordering::Greater => {
+                return Err(format!(
+                    "Area map index {} is past the end of {}'s {} area maps",
+                    index,
+                    world.to_json_key(),
+                    area_maps.len()
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn universe_world_index(mapu: &structs::Mapu, name: &str) -> Option<usize> {
+    let name = name.trim();
+    if let Some(world) = World::iter().find(|w| w.to_json_key().eq_ignore_ascii_case(name)) {
+        return mapu.world_index(world.mlvl());
+    }
+
+    mapu.worlds.iter().position(|world| {
+        world
+            .label
+            .to_str()
+            .map(|label| label.eq_ignore_ascii_case(name))
+            .unwrap_or(false)
+    })
+}
+
+fn patch_universe_map(
+    res: &mut structs::Resource,
+    universe_map: &UniverseMapConfig,
+) -> Result<(), String> {
+    let mapu = res.kind.as_mapu_mut().unwrap();
+
+    if let Some(hexagon_mapa) = universe_map.hexagon_mapa {
+        mapu.hexagon_mapa = ResId::new(hexagon_mapa);
+    }
+
+    let mut names: Vec<&String> = universe_map.worlds.keys().collect();
+    names.sort();
+    for name in names {
+        let world_config = &universe_map.worlds[name];
+
+        let index = match universe_world_index(mapu, name) {
+            Some(index) => index,
+            None => {
+                let mlvl = world_config.mlvl.ok_or_else(|| {
+                    format!(
+                        "'{}' is not on the universe map, an mlvl is required to add it",
+                        name
+                    )
+                })?;
+                mapu.worlds.as_mut_vec().push(structs::MapuWorld {
+                    label: string_to_cstr(name.trim().to_string()),
+                    mlvl: ResId::new(mlvl),
+                    transform: [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+                    hexagon_transforms: vec![].into(),
+                    color: [1.0, 1.0, 1.0, 1.0].into(),
+                });
+                mapu.worlds.len() - 1
+            }
+        };
+
+        let world = &mut mapu.worlds.as_mut_vec()[index];
+        if let Some(label) = world_config.label.as_ref() {
+            world.label = string_to_cstr(label.to_string());
+        }
+        if let Some(mlvl) = world_config.mlvl {
+            world.mlvl = ResId::new(mlvl);
+        }
+        if let Some(position) = world_config.position {
+            world.transform[3] = position[0];
+            world.transform[7] = position[1];
+            world.transform[11] = position[2];
+        }
+        if let Some(hexagons) = world_config.hexagons.as_ref() {
+            // New hexagons keep the rotation and scale of the existing ones
+            let mut template: [f32; 12] = world
+                .hexagon_transforms
+                .iter()
+                .next()
+                .map(|xf| xf.as_slice().try_into().unwrap())
+                .unwrap_or([1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+            let hexagon_transforms = hexagons
+                .iter()
+                .map(|position| {
+                    template[3] = position[0];
+                    template[7] = position[1];
+                    template[11] = position[2];
+                    GenericArray::from(template)
+                })
+                .collect::<Vec<_>>();
+            world.hexagon_transforms = hexagon_transforms.into();
+        }
+        if let Some(color) = world_config.color {
+            world.color = color.into();
+        }
+    }
+
+    if let Some(world_order) = universe_map.world_order.as_ref() {
+        let mut order = Vec::new();
+        for name in world_order {
+            let index = universe_world_index(mapu, name)
+                .ok_or_else(|| format!("'{}' is not on the universe map", name))?;
+            if order.contains(&index) {
+                return Err(format!("'{}' is listed twice in worldOrder", name));
+            }
+            order.push(index);
+        }
+        let unlisted: Vec<usize> = (0..mapu.worlds.len())
+            .filter(|i| !order.contains(i))
+            .collect();
+        order.extend(unlisted);
+
+        let worlds = mapu.worlds.as_mut_vec();
+        let mut old_worlds: Vec<Option<structs::MapuWorld>> = worlds.drain(..).map(Some).collect();
+        worlds.extend(order.into_iter().map(|i| old_worlds[i].take().unwrap()));
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct HintEdit {
+    name: String,
+    remove: bool,
+    intermediate_time: Option<f32>,
+    normal_time: Option<f32>,
+    popup_text_strg: Option<u32>,
+    locations: Option<Vec<structs::HintLocation>>,
+}
+
+fn hint_strg<'r>(strg_id: u32, text: &str, version: Version) -> structs::Resource<'r> {
+    let contents = vec![format!("{}\0", text)];
+    let strg = if version == Version::Pal {
+        structs::Strg::from_strings_pal(contents)
+    } else if version == Version::NtscJ {
+        structs::Strg::from_strings_jpn(contents)
+    } else {
+        structs::Strg::from_strings(contents)
+    };
+    let mut res =
+        crate::custom_assets::build_resource_raw(strg_id, structs::ResourceKind::Strg(strg));
+    res.compressed = false;
+    res
+}
+
+// Resolves the configured hints into edits of the HINT resource and the STRGs they need
+fn resolve_hints<'r>(
+    hints: &HintsConfig,
+    level_data: &HashMap<String, LevelConfig>,
+    version: Version,
+) -> Result<(Vec<HintEdit>, Vec<structs::Resource<'r>>), String> {
+    let mut edits = Vec::new();
+    let mut strgs = Vec::new();
+    let mut next_strg_id = custom_asset_ids::HINT_STRGS.to_u32();
+    let mut new_strg =
+        |text: &str, strgs: &mut Vec<structs::Resource<'r>>| -> Result<u32, String> {
+            if next_strg_id >= custom_asset_ids::EXTRA_IDS_START.to_u32() {
+                return Err("Too many hint strings".to_string());
+            }
+            strgs.push(hint_strg(next_strg_id, text, version));
+            next_strg_id += 1;
+            Ok(next_strg_id - 1)
+        };
+
+    let mut names: Vec<&String> = hints.hints.keys().collect();
+    names.sort();
+    for name in names {
+        let hint = &hints.hints[name];
+
+        // Find where the hinted pickup was placed
+        let mut rooms: Vec<SpawnRoomData> = Vec::new();
+        for room in hint.rooms.iter().flatten() {
+            rooms.push(
+                SpawnRoomData::try_from_str(room).map_err(|e| format!("Hint '{}': {}", name, e))?,
+            );
+        }
+        let item = match hint.item.as_ref() {
+            Some(item) => Some(
+                PickupType::try_from_str(item).map_err(|e| format!("Hint '{}': {}", name, e))?,
+            ),
+            None => None,
+        };
+        if let Some(item) = item {
+            let mut world_names: Vec<&String> = level_data.keys().collect();
+            world_names.sort();
+            for world_name in world_names {
+                let mut room_names: Vec<&String> = level_data[world_name].rooms.keys().collect();
+                room_names.sort();
+                for room_name in room_names {
+                    let room = &level_data[world_name].rooms[room_name];
+                    let has_item = room.pickups.iter().flatten().any(|pickup| {
+                        PickupType::try_from_str(&pickup.pickup_type).ok() == Some(item)
+                    });
+                    let room =
+                        SpawnRoomData::try_from_str(&format!("{}:{}", world_name, room_name))
+                            .map_err(|e| format!("Hint '{}': {}", name, e))?;
+                    if has_item && !rooms.contains(&room) {
+                        rooms.push(room);
+                    }
+                }
+            }
+
+            if rooms.is_empty() {
+                return Err(format!(
+                    "Hint '{}' is for {} which isn't placed anywhere",
+                    name,
+                    item.name()
+                ));
+            }
+        }
+
+        let room_names = rooms
+            .iter()
+            .map(|room| room.name.replace('\0', " "))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let popup_text = match (hint.popup_text.as_ref(), item) {
+            (Some(text), _) => Some(text.to_string()),
+            (None, Some(item)) => Some(
+                MarkupBuilder::new()
+                    .just(Justification::Center)
+                    .text("The ")
+                    .literal(item.name())
+                    .text(" can be found in ")
+                    .literal(&room_names)
+                    .text(".")
+                    .build(),
+            ),
+            (None, None) => None,
+        };
+        let map_text = match (hint.map_text.as_ref(), item) {
+            (Some(text), _) => Some(text.to_string()),
+            (None, Some(item)) => Some(MarkupBuilder::new().literal(item.name()).build()),
+            (None, None) => None,
+        };
+
+        let popup_text_strg = match popup_text {
+            Some(text) => Some(new_strg(&text, &mut strgs)?),
+            None => None,
+        };
+        let locations = if rooms.is_empty() {
+            None
+        } else {
+            let map_text_strg = match map_text {
+                Some(text) => new_strg(&text, &mut strgs)?,
+                None => 0xFFFFFFFF,
+            };
+            Some(
+                rooms
+                    .iter()
+                    .map(|room| structs::HintLocation {
+                        mlvl: ResId::new(room.mlvl),
+                        mrea: ResId::new(room.mrea),
+                        target_room_index: room.mrea_idx,
+                        map_text_strg: ResId::new(map_text_strg),
+                    })
+                    .collect(),
+            )
+        };
+
+        edits.push(HintEdit {
+            name: name.trim().to_string(),
+            remove: hint.remove.unwrap_or(false),
+            intermediate_time: hint.intermediate_time,
+            normal_time: hint.normal_time,
+            popup_text_strg,
+            locations,
+        });
+    }
+
+    Ok((edits, strgs))
+}
+
+fn patch_hints(
+    res: &mut structs::Resource,
+    edits: &[HintEdit],
+    remove_vanilla_hints: bool,
+) -> Result<(), String> {
+    let hint = res.kind.as_hint_mut().unwrap();
+    let hints = hint.hints.as_mut_vec();
+
+    let is_named = |details: &structs::HintDetails, name: &str| {
+        details
+            .hint_name
+            .to_str()
+            .map(|hint_name| hint_name.eq_ignore_ascii_case(name))
+            .unwrap_or(false)
+    };
+
+    // New hints take their timing from the vanilla ones
+    let template = hints.first().cloned();
+
+    if remove_vanilla_hints {
+        hints.retain(|details| edits.iter().any(|edit| is_named(details, &edit.name)));
+    }
+
+    for edit in edits {
+        let index = hints
+            .iter()
+            .position(|details| is_named(details, &edit.name));
+        if edit.remove {
+            if let Some(index) = index {
+                hints.remove(index);
+            }
+            continue;
+        }
+
+        let details = match index {
+            Some(index) => &mut hints[index],
+            None => {
+                let template = template
+                    .as_ref()
+                    .ok_or_else(|| format!("Cannot add hint '{}' to an empty HINT", edit.name))?;
+                if edit.popup_text_strg.is_none() || edit.locations.is_none() {
+                    return Err(format!(
+                        "New hint '{}' needs an item or rooms and popup text",
+                        edit.name
+                    ));
+                }
+                hints.push(structs::HintDetails {
+                    hint_name: string_to_cstr(edit.name.clone()),
+                    intermediate_time: template.intermediate_time,
+                    normal_time: template.normal_time,
+                    popup_text_strg: 0xFFFFFFFF,
+                    text_time: template.text_time,
+                    locations: vec![].into(),
+                });
+                hints.last_mut().unwrap()
+            }
+        };
+
+        if let Some(intermediate_time) = edit.intermediate_time {
+            details.intermediate_time = intermediate_time;
+        }
+        if let Some(normal_time) = edit.normal_time {
+            details.normal_time = normal_time;
+        }
+        if let Some(popup_text_strg) = edit.popup_text_strg {
+            details.popup_text_strg = popup_text_strg;
+        }
+        if let Some(locations) = edit.locations.as_ref() {
+            details.locations = locations.clone().into();
+        }
+    }
+
+    Ok(())
+}
+
+fn add_hint_strgs<'r>(
+    file: &mut structs::FstEntryFile<'r>,
+    strgs: &[structs::Resource<'r>],
+) -> Result<(), String> {
+    let pak = match file {
+        structs::FstEntryFile::Pak(pak) => pak,
+        _ => Err("Expected a PAK when adding the hint strings")?,
+    };
+
+    // append at the end of the pak
+    let mut cursor = pak.resources.cursor();
+    while cursor.cursor_advancer().peek().is_some() {}
+    cursor.insert_after(strgs.iter().cloned());
+    Ok(())
+}
+
+fn patch_credits(
+    res: &mut structs::Resource,
+    version: Version,
+    config: &PatchConfig,
+    level_data: &HashMap<String, LevelConfig>,
+) -> Result<(), String> {
+    let mut output = "\n\n\n\n\n\n\n".to_string();
+
+    if version == Version::NtscJ {
+        output = format!(
+            "&line-extra-space=8;&font=5D696116;{}",
+            &output[..output.len() - 2]
+        );
+    }
+
+    if config.credits_string.is_some() {
+        output = format!("{}{}", output, config.credits_string.as_ref().unwrap());
+    } else {
+        output = format!(
+            "{}{}",
+            output,
+            concat!(
+                "&push;&font=C29C51F1;&main-color=#89D6FF;",
+                "Major Item Locations",
+                "&pop;",
+            )
+            .to_owned()
+        );
+
+        use std::fmt::Write;
+        const PICKUPS_TO_PRINT: &[PickupType] = &[
+            PickupType::ScanVisor,
+            PickupType::ThermalVisor,
+            PickupType::XRayVisor,
+            PickupType::VariaSuit,
+            PickupType::GravitySuit,
+            PickupType::PhazonSuit,
+            PickupType::MorphBall,
+            PickupType::BoostBall,
+            PickupType::SpiderBall,
+            PickupType::MorphBallBomb,
+            PickupType::PowerBomb,
+            PickupType::ChargeBeam,
+            PickupType::SpaceJumpBoots,
+            PickupType::GrappleBeam,
+            PickupType::SuperMissile,
+            PickupType::Wavebuster,
+            PickupType::IceSpreader,
+            PickupType::Flamethrower,
+            PickupType::WaveBeam,
+            PickupType::IceBeam,
+            PickupType::PlasmaBeam,
+        ];
+
+        for pickup_type in PICKUPS_TO_PRINT {
+            let room_name = {
+                let mut _room_name = String::new();
+                for (_, level) in level_data.iter() {
+                    for (room_name, room) in level.rooms.iter() {
+                        if room.pickups.is_none() {
+                            continue;
+                        };
+                        for pickup_info in room.pickups.as_ref().unwrap().iter() {
+                            if PickupType::from_str(pickup_type.name())
+                                == PickupType::from_str(&pickup_info.pickup_type)
+                            {
+                                _room_name = room_name.to_string();
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                if _room_name.is_empty() {
+                    _room_name = "<Not Present>".to_string();
+                }
+
+                _room_name
+            };
+            let pickup_name = pickup_type.name();
+            write!(output, "\n\n{}: {}", pickup_name, room_name).unwrap();
+        }
+    }
+    output = format!("{}{}", output, "\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\0");
+    if version == Version::NtscJ {
+        res.kind
+            .as_strg_mut()
+            .unwrap()
+            .add_strings(&[output.to_string()], Languages::Some(&[b"ENGL", b"JAPN"]));
+    } else {
+        res.kind
+            .as_strg_mut()
+            .unwrap()
+            .add_strings(&[output.to_string()], Languages::All);
+    }
+
+    /* We are who we choose to be */
     /* https://mobile.twitter.com/ZoidCTF/status/1542699504041750528 */
     res.kind.as_strg_mut().unwrap().edit_strings(
         ("David 'Zoid' Kirsch".to_string(), "Zoid Kirsch".to_string()),
@@ -9439,7 +10531,10 @@ fn patch_completion_screen(
     version: Version,
 ) -> Result<(), String> {
     if version == Version::NtscJ {
-        results_string = format!("&line-extra-space=4;&font=C29C51F1;{}", results_string);
+        results_string = MarkupBuilder::new()
+            .jpn_font()
+            .text(&results_string)
+            .build();
     }
     results_string += "\nPercentage Complete\0";
 
@@ -9685,20 +10780,32 @@ fn patch_dol(
         dol_patcher.patch(build_info_address, uuid.to_vec().clone().into())?;
     }
 
+    // The strings are patched in place, "MetroidPrime A" leaves room for 12 characters
+    let save_file_name = config
+        .disc_identity
+        .save_file_name
+        .as_deref()
+        .unwrap_or("randomprime");
+    if save_file_name.is_empty() || save_file_name.len() > 12 || !save_file_name.is_ascii() {
+        Err(format!(
+            "Save file name \"{}\" must be 1 to 12 ASCII characters",
+            save_file_name
+        ))?
+    }
     if version == Version::Pal || version == Version::NtscJ {
         dol_patcher.patch(
             symbol_addr!("aMetroidprime", version),
-            b"randomprime\0"[..].into(),
+            format!("{}\0", save_file_name).into_bytes().into(),
         )?;
     } else {
         dol_patcher
             .patch(
                 symbol_addr!("aMetroidprimeA", version),
-                b"randomprime A\0"[..].into(),
+                format!("{} A\0", save_file_name).into_bytes().into(),
             )?
             .patch(
                 symbol_addr!("aMetroidprimeB", version),
-                b"randomprime B\0"[..].into(),
+                format!("{} B\0", save_file_name).into_bytes().into(),
             )?;
     }
 
@@ -11788,10 +12895,10 @@ fn empty_frigate_pak(file: &mut structs::FstEntryFile) -> Result<(), String> {
 }
 
 fn patch_ctwk_game(res: &mut structs::Resource, ctwk_config: &CtwkConfig) -> Result<(), String> {
-    let mut ctwk = res.kind.as_ctwk_mut().unwrap();
-    let ctwk_game = match &mut ctwk {
-        structs::Ctwk::Game(i) => i,
-        _ => panic!("Failed to map res=0x{:X} as CtwkGame", res.file_id),
+    let file_id = res.file_id;
+    let ctwk_game = match res.kind.as_ctwk_kind_mut(structs::CtwkKind::Game) {
+        Some(structs::Ctwk::Game(i)) => i,
+        _ => Err(format!("Failed to map res=0x{:X} as CtwkGame", file_id))?,
     };
 
     ctwk_game.press_start_delay = 0.001;
@@ -11820,10 +12927,10 @@ fn patch_ctwk_game(res: &mut structs::Resource, ctwk_config: &CtwkConfig) -> Res
 }
 
 fn patch_ctwk_player(res: &mut structs::Resource, ctwk_config: &CtwkConfig) -> Result<(), String> {
-    let mut ctwk = res.kind.as_ctwk_mut().unwrap();
-    let ctwk_player = match &mut ctwk {
-        structs::Ctwk::Player(i) => i,
-        _ => panic!("Failed to map res=0x{:X} as CtwkPlayer", res.file_id),
+    let file_id = res.file_id;
+    let ctwk_player = match res.kind.as_ctwk_kind_mut(structs::CtwkKind::Player) {
+        Some(structs::Ctwk::Player(i)) => i,
+        _ => Err(format!("Failed to map res=0x{:X} as CtwkPlayer", file_id))?,
     };
 
     if ctwk_config.player_size.is_some() {
@@ -12029,10 +13136,13 @@ fn patch_ctwk_player_gun(
     res: &mut structs::Resource,
     ctwk_config: &CtwkConfig,
 ) -> Result<(), String> {
-    let mut ctwk = res.kind.as_ctwk_mut().unwrap();
-    let ctwk_player_gun = match &mut ctwk {
-        structs::Ctwk::PlayerGun(i) => i,
-        _ => panic!("Failed to map res=0x{:X} as CtwkPlayerGun", res.file_id),
+    let file_id = res.file_id;
+    let ctwk_player_gun = match res.kind.as_ctwk_kind_mut(structs::CtwkKind::PlayerGun) {
+        Some(structs::Ctwk::PlayerGun(i)) => i,
+        _ => Err(format!(
+            "Failed to map res=0x{:X} as CtwkPlayerGun",
+            file_id
+        ))?,
     };
 
     if ctwk_config.gun_position.is_some() {
@@ -12062,11 +13172,10 @@ fn patch_ctwk_player_gun(
 }
 
 fn patch_ctwk_ball(res: &mut structs::Resource, ctwk_config: &CtwkConfig) -> Result<(), String> {
-    let mut ctwk = res.kind.as_ctwk_mut().unwrap();
-
-    let ctwk_ball = match &mut ctwk {
-        structs::Ctwk::Ball(i) => i,
-        _ => panic!("Failed to map res=0x{:X} as CtwkBall", res.file_id),
+    let file_id = res.file_id;
+    let ctwk_ball = match res.kind.as_ctwk_kind_mut(structs::CtwkKind::Ball) {
+        Some(structs::Ctwk::Ball(i)) => i,
+        _ => Err(format!("Failed to map res=0x{:X} as CtwkBall", file_id))?,
     };
 
     if ctwk_config.max_translation_accel.is_some() {
@@ -13012,10 +14121,13 @@ fn patch_ctwk_gui_colors(
     res: &mut structs::Resource,
     ctwk_config: &CtwkConfig,
 ) -> Result<(), String> {
-    let mut ctwk = res.kind.as_ctwk_mut().unwrap();
-    let ctwk_gui_colors = match &mut ctwk {
-        structs::Ctwk::GuiColors(i) => i,
-        _ => panic!("Failed to map res=0x{:X} as CtwkGuiColors", res.file_id),
+    let file_id = res.file_id;
+    let ctwk_gui_colors = match res.kind.as_ctwk_kind_mut(structs::CtwkKind::GuiColors) {
+        Some(structs::Ctwk::GuiColors(i)) => i,
+        _ => Err(format!(
+            "Failed to map res=0x{:X} as CtwkGuiColors",
+            file_id
+        ))?,
     };
 
     if ctwk_config.hud_color.is_some() {
@@ -13128,6 +14240,92 @@ fn patch_ctwk_gui_colors(
     Ok(())
 }
 
+// A tweak's file id and kind, along with the fields to set in it
+type CtwkFieldEdit<'a> = (
+    u32,
+    structs::CtwkKind,
+    &'a HashMap<String, structs::CtwkValue>,
+);
+
+// Finds the tweak resources which the configured field edits apply to
+fn resolve_ctwk_fields<'a>(
+    gc_disc: &structs::GcDisc,
+    fields: &'a HashMap<String, HashMap<String, structs::CtwkValue>>,
+) -> Result<Vec<CtwkFieldEdit<'a>>, String> {
+    let mut edits = HashMap::new();
+    for (name, tweak_fields) in fields {
+        let kind = structs::CtwkKind::from_name(name.as_bytes())
+            .ok_or_else(|| format!("Unknown tweak \"{}\"", name))?;
+        edits.insert(kind, tweak_fields);
+    }
+
+    let file_entry = gc_disc
+        .find_file("Tweaks.Pak")
+        .ok_or("Could not find Tweaks.Pak")?;
+    let pak = match *file_entry.file().unwrap() {
+        structs::FstEntryFile::Pak(ref pak) => pak.clone(),
+        structs::FstEntryFile::Unknown(ref reader) => reader.clone().read(()),
+        _ => Err("Tweaks.Pak is not a PAK")?,
+    };
+
+    // The tweaks are looked up by name, which also works for versions with different file ids
+    let mut kinds = HashMap::new();
+    for named in pak.named_resources.iter() {
+        if named.fourcc != b"CTWK".into() {
+            continue;
+        }
+        let name = named.name.iter().collect::<Vec<u8>>();
+        if let Some(kind) = structs::CtwkKind::from_name(&name) {
+            kinds.insert(named.file_id, kind);
+        }
+    }
+
+    let mut resolved = Vec::new();
+    for res in pak.resources.iter() {
+        if res.fourcc() != b"CTWK".into() {
+            continue;
+        }
+        let kind = kinds.get(&res.file_id).copied().or_else(|| {
+            resource_info_by_id(res.file_id)
+                .and_then(|info| info.short_name)
+                .and_then(|name| structs::CtwkKind::from_name(name.as_bytes()))
+        });
+        let kind = match kind {
+            Some(kind) => kind,
+            None => continue,
+        };
+        if let Some(tweak_fields) = edits.remove(&kind) {
+            resolved.push((res.file_id, kind, tweak_fields));
+        }
+    }
+
+    if let Some(kind) = edits.keys().next() {
+        Err(format!(
+            "Tweak \"{}\" was not found in Tweaks.Pak",
+            kind.name()
+        ))?;
+    }
+
+    Ok(resolved)
+}
+
+fn patch_ctwk_fields(
+    res: &mut structs::Resource,
+    kind: structs::CtwkKind,
+    fields: &HashMap<String, structs::CtwkValue>,
+) -> Result<(), String> {
+    let file_id = res.file_id;
+    let ctwk = res
+        .kind
+        .as_ctwk_kind_mut(kind)
+        .ok_or_else(|| format!("Failed to map res=0x{:X} as a tweak", file_id))?;
+    for (field, value) in fields {
+        ctwk.set_field(field, value)
+            .map_err(|e| format!("Failed to set {}.{}: {}", kind.name(), field, e))?;
+    }
+    Ok(())
+}
+
 fn patch_move_item_loss_scan(
     _ps: &mut PatcherState,
     area: &mut mlvl_wrapper::MlvlArea<'_, '_, '_, '_>,
@@ -14364,11 +15562,22 @@ fn patch_garbeetle_scale(
 fn patch_bnr(file: &mut structs::FstEntryFile, banner: &GameBanner) -> Result<(), String> {
     let bnr = match file {
         structs::FstEntryFile::Bnr(bnr) => bnr,
-        _ => panic!(),
+        _ => Err("opening.bnr isn't a banner file")?,
     };
 
-    bnr.pixels
-        .clone_from_slice(include_bytes!("../extra_assets/banner_image.bin"));
+    match &banner.image {
+        Some(path) => {
+            let pixels = read_banner_png(
+                path,
+                structs::BNR_IMAGE_WIDTH as u32,
+                structs::BNR_IMAGE_HEIGHT as u32,
+            )?;
+            bnr.set_image(&pixels);
+        }
+        None => bnr
+            .pixels
+            .clone_from_slice(include_bytes!("../extra_assets/banner_image.bin")),
+    }
 
     fn write_encoded_str(field: &str, s: &Option<String>, slice: &mut [u8]) -> Result<(), String> {
         if let Some(s) = s {
@@ -14389,32 +15598,81 @@ fn patch_bnr(file: &mut structs::FstEntryFile, banner: &GameBanner) -> Result<()
         Ok(())
     }
 
-    write_encoded_str(
-        "game_name",
-        &banner.game_name,
-        &mut bnr.english_fields.game_name,
-    )?;
-    write_encoded_str(
-        "developer",
-        &banner.developer,
-        &mut bnr.english_fields.developer,
-    )?;
-    write_encoded_str(
-        "game_name_full",
-        &banner.game_name_full,
-        &mut bnr.english_fields.game_name_full,
-    )?;
-    write_encoded_str(
-        "developer_full",
-        &banner.developer_full,
-        &mut bnr.english_fields.developer_full,
-    )?;
-    write_encoded_str(
-        "description",
-        &banner.description,
-        &mut bnr.english_fields.description,
-    )?;
+    // BNR2 discs have the same fields for each of the other PAL languages
+    let mut fields: Vec<&mut structs::BnrMetadata> = vec![&mut bnr.english_fields];
+    if let Some(other_lang_fields) = &mut bnr.other_lang_fields {
+        fields.extend(other_lang_fields.iter_mut());
+    }
+    for fields in fields {
+        write_encoded_str("game_name", &banner.game_name, &mut fields.game_name)?;
+        write_encoded_str("developer", &banner.developer, &mut fields.developer)?;
+        write_encoded_str(
+            "game_name_full",
+            &banner.game_name_full,
+            &mut fields.game_name_full,
+        )?;
+        write_encoded_str(
+            "developer_full",
+            &banner.developer_full,
+            &mut fields.developer_full,
+        )?;
+        write_encoded_str("description", &banner.description, &mut fields.description)?;
+    }
+
+    Ok(())
+}
+
+fn patch_disc_header(
+    header: &mut structs::GcDiscHeader,
+    identity: &DiscIdentity,
+) -> Result<(), String> {
+    if let Some(game_id) = &identity.game_id {
+        let id = game_id.as_bytes();
+        if id.len() != 6
+            || !id
+                .iter()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            Err(format!(
+                "Game ID \"{}\" must be 6 uppercase letters or digits",
+                game_id
+            ))?
+        }
+        header.console_id = id[0];
+        header.game_code.clone_from_slice(&id[1..3]);
+        header.country_code = id[3];
+        header.maker_code.clone_from_slice(&id[4..6]);
+    }
+
+    if let Some(disc_number) = identity.disc_number {
+        header.disc_id = disc_number;
+    }
+
+    if let Some(internal_name) = &identity.internal_name {
+        let mut bytes = internal_name.as_bytes().to_vec();
+        if !internal_name.is_ascii() || bytes.len() >= header.game_name.len() {
+            Err(format!(
+                "Internal name must be at most {} ASCII characters",
+                header.game_name.len() - 1
+            ))?
+        }
+        bytes.resize(header.game_name.len(), 0);
+        header.game_name.clone_from_slice(&bytes);
+    }
+
+    Ok(())
+}
 
+// Keep the memory card messages in line with the renamed game
+fn patch_memorycard_game_name(res: &mut structs::Resource, name: &str) -> Result<(), String> {
+    let strg = res
+        .kind
+        .as_strg_mut()
+        .ok_or("Failed to read the memory card strings")?;
+    // The Japanese version spells the name in katakana, in both of its string tables
+    for game_name in ["Metroid Prime", "メトロイドプライム"] {
+        strg.edit_strings((game_name.to_string(), name.to_string()), Languages::All);
+    }
     Ok(())
 }
 
@@ -14686,7 +15944,12 @@ fn patch_qol_logical(patcher: &mut PrimePatcher, config: &PatchConfig, version:
     }
 }
 
-fn patch_qol_cosmetic(patcher: &mut PrimePatcher, skip_ending_cinematic: bool, quick_patch: bool) {
+fn patch_qol_cosmetic(
+    patcher: &mut PrimePatcher,
+    skip_ending_cinematic: bool,
+    quick_patch: bool,
+    custom_audio: &[String],
+) {
     if quick_patch {
         // Replace all non-critical files with empty ones to speed up patching
         const FILENAMES: &[&[u8]] = &[
@@ -14847,6 +16110,10 @@ fn patch_qol_cosmetic(patcher: &mut PrimePatcher, skip_ending_cinematic: bool, q
         ];
         const EMPTY: &[u8] = include_bytes!("../extra_assets/attract_mode.thp"); // empty file
         for name in FILENAMES {
+            // Keep the audio which the config replaces
+            if custom_audio.iter().any(|path| path.as_bytes() == *name) {
+                continue;
+            }
             patcher.add_file_patch(name, |file| {
                 *file = structs::FstEntryFile::ExternalFile(Box::new(EMPTY));
                 Ok(())
@@ -15702,7 +16969,7 @@ fn patch_arboretum_sandstone(patcher: &mut PrimePatcher<'_, '_>) {
     });
 }
 
-pub fn patch_iso<T>(config: PatchConfig, mut pn: T) -> Result<(), String>
+pub fn patch_iso<T>(mut config: PatchConfig, mut pn: T) -> Result<(), String>
 where
     T: structs::ProgressNotifier,
 {
@@ -15761,6 +17028,21 @@ where
         ))?
     }
 
+    // The disc only borrows the input ISO, so the texts are borrowed field by field. The other
+    // run modes don't write the strings to a disc, so they are left alone.
+    if config.run_mode == RunMode::CreateIso {
+        let texts = config_texts(
+            &mut config.level_data,
+            &mut config.artifact_hints,
+            &mut config.main_menu_message,
+            &mut config.credits_string,
+            &mut config.results_string,
+            &mut config.strg,
+            &mut config.hints,
+        );
+        validate_config_text(texts, &gc_disc, config.auto_wrap_text, &mut pn)?;
+    }
+
     if config.run_mode == RunMode::ExportLogbook {
         export_logbook(&mut gc_disc, &config)?;
         return Ok(());
@@ -15769,7 +17051,17 @@ where
         return Ok(());
     }
 
-    build_and_run_patches(&mut gc_disc, &config, audio_override_patches)?;
+    patch_disc_header(&mut gc_disc.header, &config.disc_identity)?;
+
+    build_and_run_patches(&mut gc_disc, &config, audio_override_patches, &mut pn)?;
+
+    // Exported after patching so that the strings the patcher adds are included
+    if config.run_mode == RunMode::ExportStrings {
+        export_strings(&mut gc_disc, &config)?;
+        return Ok(());
+    }
+
+    apply_translations(&mut gc_disc, &config, &mut pn)?;
 
     println!("Created patches in {:?}", start_time.elapsed());
 
@@ -15916,6 +17208,81 @@ fn export_logbook(gc_disc: &mut structs::GcDisc, config: &PatchConfig) -> Result
     Ok(())
 }
 
+// The contents of a STRG resource, whether or not it has been patched
+fn strg_resource_bytes(res: &structs::Resource) -> Vec<u8> {
+    match res.kind {
+        structs::ResourceKind::Unknown(..) => {
+            crate::ResourceData::new(res).decompress().into_owned()
+        }
+        structs::ResourceKind::External(..) => crate::ResourceData::new_external(res)
+            .decompress()
+            .into_owned(),
+        _ => {
+            let mut bytes = vec![];
+            res.kind.as_strg().unwrap().write_to(&mut bytes).unwrap();
+            bytes
+        }
+    }
+}
+
+fn export_strings(gc_disc: &mut structs::GcDisc, config: &PatchConfig) -> Result<(), String> {
+    let mut strgs = BTreeMap::<u32, Vec<StrgString>>::new();
+    for (name, fst_entry) in gc_disc.file_system_root.dir_files_iter_mut() {
+        if !name.to_ascii_lowercase().ends_with(b".pak") {
+            continue;
+        }
+        fst_entry.guess_kind();
+        let pak = match fst_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(pak) => pak,
+            _ => continue,
+        };
+
+        for res in pak.resources.iter() {
+            if res.fourcc() != b"STRG".into() || strgs.contains_key(&res.file_id) {
+                continue;
+            }
+            let bytes = strg_resource_bytes(&res);
+            let strg: structs::Strg = Reader::new(&bytes).read(());
+
+            let mut strings = vec![];
+            for table in strg.string_tables.iter() {
+                let lang = table.lang.to_string();
+                for (index, string) in table.strings.iter().enumerate() {
+                    if strings.len() <= index {
+                        strings.push(StrgString {
+                            asset_id: res.file_id,
+                            index,
+                            pak_name: String::from_utf8_lossy(&name).into_owned(),
+                            texts: vec![],
+                        });
+                    }
+                    let text = string.into_owned().into_string();
+                    let text = text.trim_end_matches('\0').to_string();
+                    strings[index].texts.push((lang.clone(), text));
+                }
+            }
+            strgs.insert(res.file_id, strings);
+        }
+    }
+
+    let default_filename = "strings.po".to_string();
+    let filename = config
+        .strings_filename
+        .as_ref()
+        .unwrap_or(&default_filename);
+    let strings: Vec<StrgString> = strgs.into_values().flatten().collect();
+    let contents = match TranslationFormat::from_path(filename) {
+        TranslationFormat::Po => strg_translation::write_po(&strings),
+        TranslationFormat::Csv => strg_translation::write_csv(&strings),
+    };
+    let mut file =
+        File::create(filename).map_err(|e| format!("Failed to create strings file: {}", e))?;
+    file.write_all(contents.as_bytes())
+        .map_err(|e| format!("Failed to write strings file: {}", e))?;
+
+    Ok(())
+}
+
 fn export_asset(asset_dir: &str, filename: String, bytes: Vec<u8>) -> Result<(), String> {
     let mut file = File::create(format!("{}/{}", asset_dir, filename))
         .map_err(|e| format!("Failed to create asset file: {}", e))?;
@@ -15942,25 +17309,226 @@ fn export_assets(gc_disc: &mut structs::GcDisc, config: &PatchConfig) -> Result<
         }
     }
 
-    let (_, _, _, _, _, _, _, _, custom_assets) = collect_game_resources(gc_disc, None, config)?;
+    let include_custom_assets = config
+        .export_resources
+        .as_ref()
+        .and_then(|c| c.include_custom_assets)
+        .unwrap_or(true);
+    if include_custom_assets {
+        let (_, _, _, _, _, _, _, _, custom_assets) =
+            collect_game_resources(gc_disc, None, config)?;
 
-    for resource in custom_assets {
-        let mut bytes = vec![];
-        resource.write_to(&mut bytes).unwrap();
+        for resource in custom_assets {
+            let mut bytes = vec![];
+            resource.write_to(&mut bytes).unwrap();
+
+            let filename = custom_asset_filename(resource.resource_info(0));
+
+            export_asset(asset_dir, filename, bytes)?;
+        }
+    }
+
+    if let Some(export_config) = config.export_resources.as_ref() {
+        export_vanilla_resources(gc_disc, asset_dir, export_config)?;
+    }
+
+    Ok(())
+}
+
+fn export_vanilla_resources(
+    gc_disc: &structs::GcDisc,
+    asset_dir: &str,
+    export_config: &ExportResourcesConfig,
+) -> Result<(), String> {
+    let fourccs: Option<Vec<FourCC>> = match export_config.fourccs.as_ref() {
+        Some(fourccs) => Some(
+            fourccs
+                .iter()
+                .map(|fourcc| {
+                    let bytes: [u8; 4] = fourcc
+                        .to_ascii_uppercase()
+                        .as_bytes()
+                        .try_into()
+                        .map_err(|_| format!("Invalid resource type '{}'", fourcc))?;
+                    Ok(FourCC::from_bytes(&bytes))
+                })
+                .collect::<Result<_, String>>()?,
+        ),
+        None => None,
+    };
+    let rooms: Option<Vec<SpawnRoomData>> = match export_config.rooms.as_ref() {
+        Some(rooms) => Some(
+            rooms
+                .iter()
+                .map(|room| SpawnRoomData::try_from_str(room))
+                .collect::<Result<_, String>>()?,
+        ),
+        None => None,
+    };
+    let convert = export_config.convert.unwrap_or(true);
+    let model_format = export_config.model_format.unwrap_or(ModelFormat::Gltf);
 
-        let filename = custom_asset_filename(resource.resource_info(0));
+    let pak_names: Vec<String> = gc_disc
+        .file_system_root
+        .dir_entries()
+        .unwrap()
+        .iter()
+        .filter(|entry| !entry.is_folder())
+        .map(|entry| entry.name().to_str().unwrap().to_string())
+        .filter(|name| name.to_lowercase().ends_with(".pak"))
+        .collect();
+
+    let mut exported = HashSet::new();
+    for pak_name in pak_names {
+        if let Some(paks) = export_config.paks.as_ref() {
+            if !paks.iter().any(|p| p.eq_ignore_ascii_case(&pak_name)) {
+                continue;
+            }
+        }
+
+        let file_entry = gc_disc.find_file(&pak_name).unwrap();
+        let pak = match *file_entry.file().unwrap() {
+            structs::FstEntryFile::Pak(ref pak) => pak.clone(),
+            structs::FstEntryFile::Unknown(ref reader) => reader.clone().read(()),
+            _ => Err(format!("{} is not a PAK", pak_name))?,
+        };
+
+        // Every resource needed by the requested rooms of this pak
+        let room_resources: Option<HashSet<u32>> = rooms.as_ref().map(|rooms| {
+            let room_mreas: Vec<u32> = rooms
+                .iter()
+                .filter(|room| room.pak_name.eq_ignore_ascii_case(&pak_name))
+                .map(|room| room.mrea)
+                .collect();
+            let mut ids = HashSet::new();
+            if room_mreas.is_empty() {
+                return ids;
+            }
+            let mlvl = pak
+                .resources
+                .iter()
+                .find(|res| res.fourcc() == b"MLVL".into())
+                .and_then(|res| res.kind.as_mlvl().map(|mlvl| mlvl.into_owned()));
+            if let Some(mlvl) = mlvl {
+                for area in mlvl.areas.iter() {
+                    if !room_mreas.contains(&area.mrea.to_u32()) {
+                        continue;
+                    }
+                    ids.insert(area.mrea.to_u32());
+                    ids.insert(area.area_name_strg.to_u32());
+                    for layer_deps in area.dependencies.deps.iter() {
+                        ids.extend(layer_deps.iter().map(|dep| dep.asset_id));
+                    }
+                }
+            }
+            ids
+        });
+
+        for res in pak.resources.iter() {
+            if let Some(ids) = export_config.ids.as_ref() {
+                if !ids.contains(&res.file_id) {
+                    continue;
+                }
+            }
+            if let Some(fourccs) = fourccs.as_ref() {
+                if !fourccs.contains(&res.fourcc()) {
+                    continue;
+                }
+            }
+            if let Some(room_resources) = room_resources.as_ref() {
+                if !room_resources.contains(&res.file_id) {
+                    continue;
+                }
+            }
+            if !exported.insert((res.file_id, res.fourcc())) {
+                continue; // Already exported from another pak
+            }
+
+            if convert && res.fourcc() == b"CMDL".into() {
+                let files = export_vanilla_model(&pak, &res, model_format)
+                    .map_err(|e| format!("Failed to convert {:08X}: {}", res.file_id, e))?;
+                for (filename, bytes) in files {
+                    export_asset(asset_dir, filename, bytes)?;
+                }
+                continue;
+            }
 
-        export_asset(asset_dir, filename, bytes)?;
+            let converted = if convert {
+                asset_export::convert_resource(&res)
+                    .map_err(|e| format!("Failed to convert {:08X}: {}", res.file_id, e))?
+            } else {
+                None
+            };
+            let filename = format!("{:08X}.{}", res.file_id, res.fourcc());
+            // The SCLY dump only covers part of an MREA, so the room itself is kept as well
+            let keep_raw = match converted {
+                Some((ext, bytes)) => {
+                    export_asset(asset_dir, format!("{}.{}", filename, ext), bytes)?;
+                    res.fourcc() == b"MREA".into()
+                }
+                None => true,
+            };
+            if keep_raw {
+                let bytes = crate::ResourceData::new(&res).decompress().into_owned();
+                export_asset(asset_dir, filename, bytes)?;
+            }
+        }
     }
 
     Ok(())
 }
 
-fn build_and_run_patches<'r>(
+// Exports a CMDL as a model, along with the textures it uses that are in the same pak
+fn export_vanilla_model(
+    pak: &structs::Pak,
+    res: &structs::Resource,
+    model_format: ModelFormat,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let bytes = crate::ResourceData::new(res).decompress().into_owned();
+    let cmdl = structs::Cmdl::try_read(&mut Reader::new(&bytes[..]))
+        .map_err(|e| format!("Failed to read model {:08X}: {}", res.file_id, e))?;
+
+    let texture_ids: HashSet<u32> = cmdl
+        .material_sets
+        .iter()
+        .flat_map(|set| {
+            set.texture_ids
+                .iter()
+                .map(|id| id.to_u32())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let texture_bytes: Vec<(u32, Vec<u8>)> = pak
+        .resources
+        .iter()
+        .filter(|r| r.fourcc() == b"TXTR".into() && texture_ids.contains(&r.file_id))
+        .map(|r| {
+            (
+                r.file_id,
+                crate::ResourceData::new(&r).decompress().into_owned(),
+            )
+        })
+        .collect();
+    let mut textures: HashMap<u32, structs::Txtr> = HashMap::new();
+    for (id, bytes) in texture_bytes.iter() {
+        let txtr = structs::Txtr::try_read(&mut Reader::new(&bytes[..]))
+            .map_err(|e| format!("Failed to read texture {:08X}: {}", id, e))?;
+        textures.insert(*id, txtr);
+    }
+
+    let name = format!("{:08X}.CMDL", res.file_id);
+    asset_export::export_model(&cmdl, &textures, &name, model_format)
+}
+
+fn build_and_run_patches<'r, N>(
     gc_disc: &mut structs::GcDisc<'r>,
     config: &PatchConfig,
     audio_override_patches: &'r Vec<AudioOverridePatch>,
-) -> Result<(), String> {
+    pn: &mut N,
+) -> Result<(), String>
+where
+    N: structs::ProgressNotifier,
+{
     let morph_ball_size = config.ctwk_config.morph_ball_size.unwrap_or(1.0);
     let player_size = config.ctwk_config.player_size.unwrap_or(1.0);
 
@@ -16081,9 +17649,11 @@ fn build_and_run_patches<'r>(
                         max_increase: None,
                         model: None,
                         scan_text: None,
+                        scan_images: None,
                         hudmemo_text: None,
                         respawn: None,
                         position: None,
+                        snap_to_floor: None,
                         modal_hudmemo: None,
                         jumbo_scan: None,
                         destination: None,
@@ -16299,6 +17869,12 @@ fn build_and_run_patches<'r>(
 
     patcher.add_file_patch(b"opening.bnr", |file| patch_bnr(file, &config.game_banner));
 
+    if let Some(internal_name) = &config.disc_identity.internal_name {
+        patcher.add_resource_patch(resource_info!("STRG_MemoryCard.STRG").into(), move |res| {
+            patch_memorycard_game_name(res, internal_name)
+        });
+    }
+
     if let Some(flaahgra_music_files) = &config.flaahgra_music_files {
         const MUSIC_FILE_NAME: &[&[u8]] = &[b"Audio/rui_flaaghraR.dsp", b"Audio/rui_flaaghraL.dsp"];
         for (file_name, music_file) in MUSIC_FILE_NAME.iter().zip(flaahgra_music_files.iter()) {
@@ -16310,6 +17886,10 @@ fn build_and_run_patches<'r>(
     }
 
     // Patch Tweaks.pak
+    let ctwk_fields = match &config.ctwk_config.fields {
+        Some(fields) => resolve_ctwk_fields(gc_disc, fields)?,
+        None => Vec::new(),
+    };
     if config.version == Version::NtscK {
         patcher.add_resource_patch(
             (&[b"Tweaks.Pak"], 0x37CE7FD6, FourCC::from_bytes(b"CTWK")), // Game.CTWK
@@ -16347,24 +17927,13 @@ fn build_and_run_patches<'r>(
         patcher.add_resource_patch(resource_info!("GuiColors.CTWK").into(), |res| {
             patch_ctwk_gui_colors(res, &config.ctwk_config)
         });
-
-        /* TODO: add more tweaks
-        953a7c63.CTWK -> Game.CTWK
-        264a4972.CTWK -> Player.CTWK
-        f1ed8fd7.CTWK -> PlayerControls.CTWK
-        3faec012.CTWK -> PlayerControls2.CTWK
-        85ca11e9.CTWK -> PlayerRes.CTWK
-        6907a32d.CTWK -> PlayerGun.CTWK
-        33b3323a.CTWK -> GunRes.CTWK
-        5ed56350.CTWK -> Ball.CTWK
-        94c76ecd.CTWK -> Targeting.CTWK
-        39ad28d3.CTWK -> CameraBob.CTWK
-        5f24eff8.CTWK -> SlideShow.CTWK
-        ed2e48a9.CTWK -> Gui.CTWK
-        c9954e56.CTWK -> GuiColors.CTWK
-        e66a4f86.CTWK -> AutoMapper.CTWK
-        1d180d7c.CTWK -> Particle.CTWK
-        */
+    }
+    // Registered after the other tweak patches so that the raw field edits take precedence
+    for (file_id, kind, fields) in ctwk_fields {
+        patcher.add_resource_patch(
+            (&[b"Tweaks.Pak"], file_id, FourCC::from_bytes(b"CTWK")),
+            move |res| patch_ctwk_fields(res, kind, fields),
+        );
     }
 
     patcher.add_resource_patch(resource_info!("FRME_CombatHud.FRME").into(), move |res| {
@@ -17172,6 +18741,13 @@ fn build_and_run_patches<'r>(
                             );
                         }
 
+                        if let Some(lights) = room.lights.as_ref() {
+                            patcher.add_scly_patch(
+                                (pak_name.as_bytes(), room_info.room_id.to_u32()),
+                                move |ps, area| patch_lights(ps, area, lights.clone()),
+                            );
+                        }
+
                         let (remove, submerge) = {
                             let remove = room.remove_water.unwrap_or(false);
                             let submerge = room.submerge.unwrap_or(false);
@@ -17226,8 +18802,10 @@ fn build_and_run_patches<'r>(
                             curr_increase: Some(0),
                             max_increase: Some(0),
                             position: None,
+                            snap_to_floor: None,
                             hudmemo_text: None,
                             scan_text: None,
+                            scan_images: None,
                             model: None,
                             respawn: None,
                             modal_hudmemo: None,
@@ -17293,6 +18871,7 @@ fn build_and_run_patches<'r>(
                             config.qol_pickup_scans,
                             extern_models,
                             config.shuffle_pickup_position,
+                            config.snap_shuffled_pickup_position,
                             config.seed + seed,
                             !config.starting_items.combat_visor
                                 && !config.starting_items.scan_visor
@@ -17367,6 +18946,7 @@ fn build_and_run_patches<'r>(
                             skip_hudmemos,
                             extern_models,
                             config.shuffle_pickup_pos_all_rooms,
+                            config.snap_shuffled_pickup_position,
                             config.seed,
                             !config.starting_items.combat_visor
                                 && !config.starting_items.scan_visor
@@ -17586,7 +19166,7 @@ fn build_and_run_patches<'r>(
                     );
 
                     if room_info.mapa_id != 0 {
-                        let map_object_type: u32 = if let Some(ref door_type) = door_type {
+                        let map_object_type = if let Some(ref door_type) = door_type {
                             door_type.map_object_type()
                         } else {
                             let counterpart =
@@ -17777,6 +19357,44 @@ fn build_and_run_patches<'r>(
                 idx += 1;
             }
 
+            // Map edits go last so that they take priority over the door color changes above
+            if room_info.mapa_id != 0 {
+                let room_config = level_data
+                    .get(world.to_json_key())
+                    .and_then(|level| level.rooms.get(room_info.name().trim()));
+                let map = room_config.and_then(|room| room.map.as_ref());
+                let bounding_box = room_config
+                    .filter(|room| {
+                        room.bounding_box_offset.is_some() || room.bounding_box_scale.is_some()
+                    })
+                    .map(|room| {
+                        (
+                            room.bounding_box_offset.unwrap_or([0.0, 0.0, 0.0]),
+                            room.bounding_box_scale.unwrap_or([1.0, 1.0, 1.0]),
+                        )
+                    });
+                let regenerate_outline = map.and_then(|map| map.regenerate_outline) == Some(true);
+
+                if map.is_some() || bounding_box.is_some() {
+                    patcher.add_resource_patch(
+                        (
+                            &[pak_name.as_bytes()],
+                            room_info.mapa_id.to_u32(),
+                            b"MAPA".into(),
+                        ),
+                        move |res| {
+                            patch_room_map(
+                                res,
+                                map,
+                                bounding_box,
+                                regenerate_outline,
+                                room_info.room_id.to_u32(),
+                            )
+                        },
+                    );
+                }
+            }
+
             if config.visible_bounding_box {
                 patcher.add_scly_patch(
                     (pak_name.as_bytes(), room_info.room_id.to_u32()),
@@ -17903,6 +19521,36 @@ fn build_and_run_patches<'r>(
         structs::FstEntryFile::ExternalFile(Box::new(rel_config)),
     )?;
 
+    for (disc_path, audio) in config.custom_audio.iter() {
+        let channels = dsp_encoder::encode_audio_file(
+            Path::new(&audio.file),
+            audio.loop_start,
+            audio.loop_end,
+        )?;
+        let disc_path = disc_path_on(gc_disc, disc_path);
+        for (suffix, dsp) in channels {
+            let path = dsp_encoder::channel_path(&disc_path, suffix);
+            let file = structs::FstEntryFile::ExternalFile(Box::new(dsp));
+            match gc_disc.find_file_mut(&path) {
+                Some(entry) => *entry.file_mut().unwrap() = file,
+                None => gc_disc.add_file(&path, file)?,
+            }
+        }
+    }
+
+    for (disc_path, video) in config.custom_videos.iter() {
+        let thp = thp_encoder::encode_thp_file(
+            Path::new(&video.frames),
+            video.audio.as_deref().map(Path::new),
+            video.quality,
+        )?;
+        let file = structs::FstEntryFile::ExternalFile(Box::new(thp));
+        match gc_disc.find_file_mut(disc_path) {
+            Some(entry) => *entry.file_mut().unwrap() = file,
+            None => gc_disc.add_file(disc_path, file)?,
+        }
+    }
+
     if !config.force_vanilla_layout {
         const ARTIFACT_TOTEM_SCAN_STRGS: &[ResourceInfo] = &[
             resource_info!("07_Over_Stonehenge Totem 5.STRG"), // Lifegiver
@@ -17963,10 +19611,28 @@ fn build_and_run_patches<'r>(
         patch_required_artifact_count(&mut patcher, config.required_artifact_count.unwrap());
     }
 
-    patcher.add_resource_patch(
-        resource_info!("TXTR_SaveBanner.TXTR").into(),
-        patch_save_banner_txtr,
-    );
+    let save_banner = config
+        .game_banner
+        .save_banner
+        .as_ref()
+        .map(|path| encode_save_banner_txtr(path, 96, 32))
+        .transpose()?;
+    patcher.add_resource_patch(resource_info!("TXTR_SaveBanner.TXTR").into(), move |res| {
+        patch_save_banner_txtr(res, save_banner.as_deref())
+    });
+    if let Some(path) = &config.game_banner.save_icon {
+        // Both frames of the icon animation get the same image
+        let save_icon = encode_save_banner_txtr(path, 32, 32)?;
+        for res_info in [
+            resource_info!("TXTR_SaveIcon0.TXTR"),
+            resource_info!("TXTR_SaveIcon1.TXTR"),
+        ] {
+            let save_icon = save_icon.clone();
+            patcher.add_resource_patch(res_info.into(), move |res| {
+                patch_save_banner_txtr(res, Some(&save_icon))
+            });
+        }
+    }
 
     if config.patch_power_conduits {
         patch_power_conduits(&mut patcher);
@@ -18545,7 +20211,20 @@ fn build_and_run_patches<'r>(
     );
 
     if config.qol_cosmetic {
-        patch_qol_cosmetic(&mut patcher, skip_ending_cinematic, config.quickpatch);
+        let custom_audio: Vec<String> = config
+            .custom_audio
+            .keys()
+            .flat_map(|path| {
+                let path = disc_path_on(gc_disc, path);
+                ["", "L", "R"].map(|suffix| dsp_encoder::channel_path(&path, suffix))
+            })
+            .collect();
+        patch_qol_cosmetic(
+            &mut patcher,
+            skip_ending_cinematic,
+            config.quickpatch,
+            &custom_audio,
+        );
 
         // Replace the FMVs that play when you select a file so each ISO always plays the only one.
         const SELECT_GAMES_FMVS: &[&[u8]] = &[
@@ -18747,6 +20426,130 @@ fn build_and_run_patches<'r>(
         }
     }
 
+    for (txtr, texture) in &config.textures {
+        let id = txtr
+            .parse::<u32>()
+            .map_err(|_| format!("{} is not a valid TXTR identifier", txtr))?;
+        let res_info = match resource_info_by_id(id) {
+            Some(res_info) if res_info.fourcc == b"TXTR".into() => res_info,
+            _ => Err(format!("{} is not a known TXTR", id))?,
+        };
+
+        let format_name = texture.format.as_deref().unwrap_or("CMPR");
+        let format = TxtrFormat::from_str(format_name)?;
+        let png = fs::read(&texture.png)
+            .map_err(|e| format!("Failed to read texture '{}': {}", texture.png, e))?;
+        let txtr = png_to_txtr(&png, format, texture.mipmap_count)
+            .map_err(|e| format!("Failed to convert texture '{}': {}", texture.png, e))?;
+        let mut bytes = vec![];
+        txtr.write_to(&mut bytes).unwrap();
+
+        patcher.add_resource_patch((*res_info).into(), move |res| {
+            res.kind = structs::ResourceKind::External(bytes.clone(), b"TXTR".into());
+            res.compressed = false;
+            Ok(())
+        });
+    }
+
+    for (font, font_config) in &config.fonts {
+        let id = font
+            .parse::<u32>()
+            .map_err(|_| format!("{} is not a valid FONT identifier", font))?;
+        let res_info = match resource_info_by_id(id) {
+            Some(res_info) if res_info.fourcc == b"FONT".into() => res_info,
+            _ => Err(format!("{} is not a known FONT", id))?,
+        };
+
+        let (font_bytes, txtr_info, txtr_bytes) =
+            build_font_edit(gc_disc, res_info, font_config)
+                .map_err(|e| format!("Failed to edit font {}: {}", id, e))?;
+
+        patcher.add_resource_patch((*res_info).into(), move |res| {
+            res.kind = structs::ResourceKind::External(font_bytes.clone(), b"FONT".into());
+            res.compressed = false;
+            Ok(())
+        });
+        patcher.add_resource_patch((*txtr_info).into(), move |res| {
+            res.kind = structs::ResourceKind::External(txtr_bytes.clone(), b"TXTR".into());
+            res.compressed = false;
+            Ok(())
+        });
+    }
+
+    for (frme, frame_config) in &config.gui_frames {
+        let id = frme
+            .parse::<u32>()
+            .map_err(|_| format!("{} is not a valid FRME identifier", frme))?;
+        let res_info = match resource_info_by_id(id) {
+            Some(res_info) if res_info.fourcc == b"FRME".into() => res_info,
+            _ => Err(format!("{} is not a known FRME", id))?,
+        };
+
+        patcher.add_resource_patch((*res_info).into(), move |res| {
+            patch_gui_frame(res, frame_config)
+                .map_err(|e| format!("Failed to edit FRME {}: {}", id, e))
+        });
+
+        // The fonts and textures of the widgets have to be loaded along with the frame
+        let deps = gui_frame_dependencies(gc_disc, frame_config)
+            .map_err(|e| format!("Failed to edit FRME {}: {}", id, e))?;
+        for pak_name in res_info.paks.iter() {
+            let rooms = pickup_meta::ROOM_INFO
+                .iter()
+                .find(|(name, _)| name.as_bytes() == *pak_name);
+            match rooms {
+                Some((_, rooms)) => {
+                    for room_info in rooms.iter() {
+                        let deps = deps.clone();
+                        patcher.add_scly_patch(
+                            (pak_name, room_info.room_id.to_u32()),
+                            move |_ps, area| {
+                                add_gui_frame_dependencies(area, game_resources, id, &deps);
+                                Ok(())
+                            },
+                        );
+                    }
+                }
+                None => {
+                    let deps = deps.clone();
+                    patcher.add_file_patch(pak_name, move |file| {
+                        add_gui_frame_resources(file, game_resources, &deps)
+                    });
+                }
+            }
+        }
+    }
+
+    for (world_name, world_map) in &config.world_maps {
+        let world = World::try_from_json_key(world_name)
+            .ok_or_else(|| format!("Unknown world '{}' in worldMaps", world_name))?;
+        patcher.add_resource_patch(
+            (
+                &[world.to_pak_str().as_bytes()],
+                world.mapw(),
+                FourCC::from_bytes(b"MAPW"),
+            ),
+            move |res| patch_world_map(res, world_map, world),
+        );
+    }
+
+    if let Some(universe_map) = config.universe_map.as_ref() {
+        patcher.add_resource_patch(resource_info!("MAPU_MapUniverse.MAPU").into(), move |res| {
+            patch_universe_map(res, universe_map)
+        });
+    }
+
+    if let Some(hints) = config.hints.as_ref() {
+        let (hint_edits, hint_strgs) = resolve_hints(hints, &config.level_data, config.version)?;
+        if !hint_strgs.is_empty() {
+            patcher.add_file_patch(b"NoARAM.pak", move |file| add_hint_strgs(file, &hint_strgs));
+        }
+        let remove_vanilla_hints = hints.remove_vanilla_hints.unwrap_or(false);
+        patcher.add_resource_patch(resource_info!("HINT_Hints_1.HINT").into(), move |res| {
+            patch_hints(res, &hint_edits, remove_vanilla_hints)
+        });
+    }
+
     // Change the missile refill text if it also refills ammo
     if config.missile_station_pb_refill {
         let id: u32 = 2871382149;
@@ -18899,7 +20702,10 @@ fn build_and_run_patches<'r>(
                             let mut reader = Reader::new(&data[..]);
                             reader.read(())
                         },
-                        _ => panic!("Unsupported resource kind for recoloring."),
+                        _ => Err(format!(
+                            "Unsupported resource kind for recoloring 0x{:08X}",
+                            res.file_id
+                        ))?,
                     };
                     let mut w = txtr.width as usize;
                     let mut h = txtr.height as usize;
@@ -18946,12 +20752,148 @@ fn build_and_run_patches<'r>(
                         w /= 2;
                         h /= 2;
                     }
-                    let mut bytes = vec![];
-                    txtr.write_to(&mut bytes).unwrap();
-                    res.kind = structs::ResourceKind::External(bytes, b"TXTR".into());
-                    res.compressed = false;
-                    Ok(())
-                })
+                    let mut bytes = vec![];
+                    txtr.write_to(&mut bytes).unwrap();
+                    res.kind = structs::ResourceKind::External(bytes, b"TXTR".into());
+                    res.compressed = false;
+                    Ok(())
+                })
+            }
+        }
+    }
+
+    if !config.texture_colors.is_empty() && !Path::new(&config.cache_dir).is_dir() {
+        if let Err(error) = fs::create_dir(&config.cache_dir) {
+            println!(
+                "Failed to create cache dir for texture recoloring: {}",
+                error
+            );
+        }
+    }
+    for texture_colors in config.texture_colors.iter() {
+        let adjustment = ColorAdjustment::new(
+            texture_colors.hue_deg,
+            texture_colors.saturation,
+            texture_colors.brightness,
+            texture_colors.tint,
+        );
+
+        // Results are cached per adjustment, next to the suit rotations
+        let cache_subdir = format!(
+            "{}/recolor_{:016x}",
+            config.cache_dir,
+            calculate_hash(&format!("{:?}", adjustment))
+        );
+        if !Path::new(&cache_subdir).is_dir() {
+            let _ = fs::create_dir(&cache_subdir);
+        }
+
+        let mut textures: Vec<ResourceInfo> = Vec::new();
+        for group in texture_colors.groups.iter().flatten() {
+            textures.extend(group.textures());
+        }
+        for id in texture_colors.textures.iter().flatten() {
+            match resource_info_by_id(*id) {
+                Some(res_info) if res_info.fourcc == b"TXTR".into() => textures.push(*res_info),
+                _ => Err(format!("{} is not a known TXTR", id))?,
+            }
+        }
+        textures.sort_by_key(|texture| texture.res_id);
+        textures.dedup_by_key(|texture| texture.res_id);
+
+        for texture in textures {
+            let cache_subdir = cache_subdir.clone();
+            patcher.add_resource_patch(texture.into(), move |res| {
+                patch_recolor_txtr(res, &adjustment, &cache_subdir)
+            });
+        }
+    }
+
+    for particle_colors in config.particle_colors.iter() {
+        let adjustment = ColorAdjustment::new(
+            particle_colors.hue_deg,
+            particle_colors.saturation,
+            particle_colors.brightness,
+            particle_colors.tint,
+        );
+
+        let mut textures = HashMap::new();
+        for (txtr, replacement) in particle_colors.textures.iter().flatten() {
+            let id = txtr
+                .parse::<u32>()
+                .map_err(|_| format!("{} is not a valid TXTR identifier", txtr))?;
+            for id in [id, *replacement] {
+                match resource_info_by_id(id) {
+                    Some(res_info) if res_info.fourcc == b"TXTR".into() => (),
+                    _ => Err(format!("{} is not a known TXTR", id))?,
+                }
+            }
+            textures.insert(id, *replacement);
+        }
+
+        let mut particles: Vec<ResourceInfo> = Vec::new();
+        for group in particle_colors.groups.iter().flatten() {
+            particles.extend(group.particles());
+        }
+        for id in particle_colors.particles.iter().flatten() {
+            match resource_info_by_id(*id) {
+                Some(res_info) if res_info.fourcc == b"PART".into() => particles.push(*res_info),
+                _ => Err(format!("{} is not a known PART", id))?,
+            }
+        }
+        particles.sort_by_key(|particle| particle.res_id);
+        particles.dedup_by_key(|particle| particle.res_id);
+
+        for particle in particles {
+            // Particles with elements which aren't modelled can't be rewritten
+            let part = read_vanilla_resource(gc_disc, &particle)
+                .and_then(|bytes| structs::Part::try_read(&mut Reader::new(&bytes)).map(|_| ()));
+            if let Err(e) = part {
+                pn.notify_warning(&format!(
+                    "skipping particle effect 0x{:08X} which couldn't be parsed: {}",
+                    particle.res_id, e
+                ));
+                continue;
+            }
+
+            let part_textures = textures.clone();
+            patcher.add_resource_patch(particle.into(), move |res| {
+                patch_recolor_part(res, &adjustment, &part_textures)
+            });
+            if textures.is_empty() {
+                continue;
+            }
+
+            // The replacement textures have to be loaded along with the particle
+            for pak_name in particle.paks.iter() {
+                let rooms = pickup_meta::ROOM_INFO
+                    .iter()
+                    .find(|(name, _)| name.as_bytes() == *pak_name);
+                match rooms {
+                    Some((_, rooms)) => {
+                        for room_info in rooms.iter() {
+                            let textures = textures.clone();
+                            patcher.add_scly_patch(
+                                (pak_name, room_info.room_id.to_u32()),
+                                move |_ps, area| {
+                                    add_recolored_part_dependencies(
+                                        area,
+                                        game_resources,
+                                        particle.res_id,
+                                        &textures,
+                                    );
+                                    Ok(())
+                                },
+                            );
+                        }
+                    }
+                    None => {
+                        let textures = textures.clone();
+                        patcher.add_file_patch(pak_name, move |file| {
+                            add_recolored_part_txtrs(file, game_resources, &textures)
+                        });
+                    }
+                }
             }
         }
     }
@@ -19209,7 +21151,541 @@ fn patch_maze_seeds(res: &mut structs::Resource, seeds: Vec<u32>) -> Result<(),
     Ok(())
 }
 
+// Spells `path` the way the disc does, since the game opens files case-insensitively and so
+// e.g. "audio/" should refer to the disc's "Audio/" rather than a new directory. The parts of the
+// path which aren't on the disc are kept as is.
+fn disc_path_on(gc_disc: &structs::GcDisc, path: &str) -> String {
+    let mut entry = Some(&gc_disc.file_system_root);
+    let mut segments = vec![];
+    for seg in path.split('/').filter(|seg| !seg.is_empty()) {
+        entry = match entry {
+            Some(structs::FstEntry::Dir(_, entries)) => entries
+                .iter()
+                .find(|e| e.name().to_bytes().eq_ignore_ascii_case(seg.as_bytes())),
+            _ => None,
+        };
+        match entry {
+            Some(entry) => {
+                segments.push(String::from_utf8_lossy(entry.name().to_bytes()).into_owned())
+            }
+            None => segments.push(seg.to_string()),
+        }
+    }
+    segments.join("/")
+}
+
+fn read_vanilla_resource(
+    gc_disc: &structs::GcDisc,
+    res_info: &ResourceInfo,
+) -> Result<Vec<u8>, String> {
+    let pak_name = std::str::from_utf8(res_info.paks[0]).unwrap();
+    let res = gc_disc
+        .find_resource(pak_name, |res| {
+            res.file_id == res_info.res_id && res.fourcc() == res_info.fourcc
+        })
+        .ok_or_else(|| format!("{:08X} is missing from {}", res_info.res_id, pak_name))?;
+    Ok(crate::ResourceData::new(&res).decompress().into_owned())
+}
+
+// Applies glyph and kerning edits to a vanilla font, returning the new FONT along with its
+// texture and the new TXTR
+fn build_font_edit(
+    gc_disc: &structs::GcDisc,
+    res_info: &ResourceInfo,
+    config: &FontConfig,
+) -> Result<(Vec<u8>, &'static ResourceInfo, Vec<u8>), String> {
+    let font_bytes = read_vanilla_resource(gc_disc, res_info)?;
+    let mut font = structs::Font::try_read(&mut Reader::new(&font_bytes[..]))
+        .map_err(|e| format!("Failed to read font {:08X}: {}", res_info.res_id, e))?;
+
+    let txtr_info = match resource_info_by_id(font.txtr.to_u32()) {
+        Some(txtr_info) if txtr_info.fourcc == b"TXTR".into() => txtr_info,
+        _ => Err(format!("{} is not a known TXTR", font.txtr.to_u32()))?,
+    };
+    let txtr_bytes = read_vanilla_resource(gc_disc, txtr_info)?;
+    let txtr = structs::Txtr::try_read(&mut Reader::new(&txtr_bytes[..])).map_err(|e| {
+        format!(
+            "Failed to read font texture {:08X}: {}",
+            txtr_info.res_id, e
+        )
+    })?;
+
+    let atlas = match &config.atlas {
+        Some(path) => {
+            let image = image::open(path)
+                .map_err(|e| format!("Failed to read glyph atlas '{}': {}", path, e))?;
+            Some(image.to_rgba8())
+        }
+        None => None,
+    };
+    let txtr = font_edit::edit_font(&mut font, &txtr, atlas.as_ref(), config)?;
+
+    let mut font_bytes = vec![];
+    font.write_to(&mut font_bytes).unwrap();
+    let mut txtr_bytes = vec![];
+    txtr.write_to(&mut txtr_bytes).unwrap();
+    Ok((font_bytes, txtr_info, txtr_bytes))
+}
+
+fn find_text_pane(frme: &structs::Frme, name: &str) -> Option<structs::TextPaneWidget> {
+    let name = format!("{}\0", name);
+    frme.widgets
+        .iter()
+        .find(|widget| widget.name.to_bytes_with_nul() == name.as_bytes())
+        .and_then(|widget| match &widget.kind {
+            structs::FrmeWidgetKind::TextPane(text_pane) => Some(text_pane.clone()),
+            _ => None,
+        })
+}
+
+// Loads every font of the game and the text panes that custom strings are displayed in. Anything
+// that can't be found is left out, which only makes the validation less thorough.
+fn build_text_validator(gc_disc: &structs::GcDisc) -> Result<TextValidator, String> {
+    let mut validator = TextValidator::new();
+
+    for res_info in resource_info_iter().filter(|r| r.fourcc == b"FONT".into()) {
+        if let Ok(bytes) = read_vanilla_resource(gc_disc, res_info) {
+            let font = structs::Font::try_read(&mut Reader::new(&bytes[..]))
+                .map_err(|e| format!("Failed to read {}: {}", res_info.long_name, e))?;
+            validator.add_font(res_info.res_id, &font);
+        }
+    }
+
+    let text_panes = [
+        (
+            TextContext::HudMemo,
+            resource_info!("FRME_BaseHud.FRME"),
+            "textpane_message",
+            false,
+        ),
+        (
+            TextContext::Scan,
+            resource_info!("FRME_ScanHud.FRME"),
+            "textpane_body",
+            true,
+        ),
+    ];
+    for (context, res_info, name, scrolls) in text_panes {
+        if let Ok(bytes) = read_vanilla_resource(gc_disc, &res_info) {
+            let frme = structs::Frme::try_read(&mut Reader::new(&bytes[..]))
+                .map_err(|e| format!("Failed to read {}: {}", res_info.long_name, e))?;
+            if let Some(text_pane) = find_text_pane(&frme, name) {
+                validator.add_text_pane(context, &text_pane, scrolls);
+            }
+        }
+    }
+    validator.add_text_pane(
+        TextContext::MainMenu,
+        &main_menu_message_text_pane(0),
+        false,
+    );
+
+    Ok(validator)
+}
+
+// The custom strings of the config along with their context and a label for warnings
+fn config_texts<'a>(
+    level_data: &'a mut HashMap<String, LevelConfig>,
+    artifact_hints: &'a mut Option<HashMap<String, String>>,
+    main_menu_message: &'a mut String,
+    credits_string: &'a mut Option<String>,
+    results_string: &'a mut Option<String>,
+    strg: &'a mut HashMap<String, Vec<String>>,
+    hints: &'a mut Option<HintsConfig>,
+) -> Vec<(TextContext, String, &'a mut String)> {
+    let mut texts: Vec<(TextContext, String, &mut String)> = vec![];
+    let mut level_data: Vec<_> = level_data.iter_mut().collect();
+    level_data.sort_by(|a, b| a.0.cmp(b.0));
+    for (world, level) in level_data {
+        let mut rooms: Vec<_> = level.rooms.iter_mut().collect();
+        rooms.sort_by(|a, b| a.0.cmp(b.0));
+        for (room_name, room) in rooms {
+            for (i, pickup) in room.pickups.iter_mut().flatten().enumerate() {
+                let label = format!("{} - {} pickup #{}", world, room_name, i);
+                if let Some(text) = pickup.hudmemo_text.as_mut() {
+                    texts.push((TextContext::HudMemo, format!("{} hudmemo", label), text));
+                }
+                if let Some(text) = pickup.scan_text.as_mut() {
+                    texts.push((TextContext::Scan, format!("{} scan", label), text));
+                }
+            }
+            for (i, scan) in room.extra_scans.iter_mut().flatten().enumerate() {
+                let label = format!("{} - {} extra scan #{}", world, room_name, i);
+                texts.push((TextContext::Scan, label, &mut scan.text));
+            }
+            for (i, hudmemo) in room.hudmemos.iter_mut().flatten().enumerate() {
+                if let Some(text) = hudmemo.text.as_mut() {
+                    let label = format!("{} - {} hudmemo #{}", world, room_name, i);
+                    texts.push((TextContext::HudMemo, label, text));
+                }
+            }
+        }
+    }
+    for (artifact, hint) in artifact_hints.iter_mut().flatten() {
+        let label = format!("{} hint", artifact);
+        texts.push((TextContext::Scan, label, hint));
+    }
+    texts.push((
+        TextContext::MainMenu,
+        "Main menu message".to_string(),
+        main_menu_message,
+    ));
+    if let Some(text) = credits_string.as_mut() {
+        texts.push((TextContext::Other, "Credits".to_string(), text));
+    }
+    if let Some(text) = results_string.as_mut() {
+        texts.push((TextContext::Other, "Results".to_string(), text));
+    }
+    for (strg, strings) in strg.iter_mut() {
+        for (i, text) in strings.iter_mut().enumerate() {
+            let label = format!("STRG {} string #{}", strg, i);
+            texts.push((TextContext::Other, label, text));
+        }
+    }
+    if let Some(hints) = hints.as_mut() {
+        let mut hints: Vec<_> = hints.hints.iter_mut().collect();
+        hints.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, hint) in hints {
+            if let Some(text) = hint.popup_text.as_mut() {
+                texts.push((TextContext::Other, format!("Hint '{}' popup", name), text));
+            }
+            if let Some(text) = hint.map_text.as_mut() {
+                texts.push((
+                    TextContext::Other,
+                    format!("Hint '{}' map text", name),
+                    text,
+                ));
+            }
+        }
+    }
+    texts
+}
+
+// Reports custom strings of the config which won't display properly in-game, after wrapping
+// the ones which are too wide if `autoWrapText` is enabled. Malformed markup is an error, since
+// it can crash the game's text renderer.
+fn validate_config_text<N>(
+    texts: Vec<(TextContext, String, &mut String)>,
+    gc_disc: &structs::GcDisc,
+    auto_wrap: bool,
+    pn: &mut N,
+) -> Result<(), String>
+where
+    N: structs::ProgressNotifier,
+{
+    let validator = build_text_validator(gc_disc)?;
+
+    let mut malformed = vec![];
+    for (context, label, text) in texts {
+        match structs::parse_markup(text) {
+            Ok(elements) => {
+                for element in elements {
+                    if let structs::MarkupElement::Tag(MarkupTag::Unknown(tag)) = element {
+                        pn.notify_warning(&format!("{}: unknown markup tag '&{};'", label, tag));
+                    }
+                }
+            }
+            Err(e) => {
+                malformed.push(format!("{}: {}", label, e));
+                continue;
+            }
+        }
+
+        if auto_wrap {
+            if let Some(wrapped) = validator.wrap(context, text) {
+                *text = wrapped;
+            }
+        }
+        for problem in validator.validate(context, text) {
+            pn.notify_warning(&format!("{}: {}", label, problem));
+        }
+    }
+
+    if !malformed.is_empty() {
+        return Err(format!(
+            "Malformed text markup in the config:\n{}",
+            malformed.join("\n")
+        ));
+    }
+
+    Ok(())
+}
+
+// Replaces the text of every STRG which has a translation in the languages it has a table for.
+// This runs after every other patch so that generated strings can be translated too.
+fn apply_translations<N>(
+    gc_disc: &mut structs::GcDisc,
+    config: &PatchConfig,
+    pn: &mut N,
+) -> Result<(), String>
+where
+    N: structs::ProgressNotifier,
+{
+    // language -> asset id -> (index, text)
+    let mut translations = vec![];
+    for (lang, path) in config.translations.iter() {
+        let lang_bytes: [u8; 4] = lang
+            .to_ascii_uppercase()
+            .as_bytes()
+            .try_into()
+            .map_err(|_| format!("Invalid translation language '{}'", lang))?;
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read translations from {}: {}", path, e))?;
+        let strings = strg_translation::parse_translations(
+            &contents,
+            TranslationFormat::from_path(path),
+            lang,
+        )
+        .map_err(|e| format!("Failed to parse translations from {}: {}", path, e))?;
+
+        let mut by_strg = HashMap::<u32, Vec<(usize, String)>>::new();
+        for ((asset_id, index), text) in strings {
+            by_strg.entry(asset_id).or_default().push((index, text));
+        }
+        translations.push((FourCC::from_bytes(&lang_bytes), by_strg, false));
+    }
+    if translations.is_empty() {
+        return Ok(());
+    }
+
+    let asset_ids: HashSet<u32> = translations
+        .iter()
+        .flat_map(|(_, by_strg, _)| by_strg.keys().copied())
+        .collect();
+    for (name, fst_entry) in gc_disc.file_system_root.dir_files_iter_mut() {
+        if !name.to_ascii_lowercase().ends_with(b".pak") {
+            continue;
+        }
+        fst_entry.guess_kind();
+        let pak = match fst_entry.file_mut().unwrap() {
+            structs::FstEntryFile::Pak(pak) => pak,
+            _ => continue,
+        };
+
+        let mut cursor = pak.resources.cursor();
+        while cursor.peek().is_some() {
+            let mut cursor = cursor.cursor_advancer();
+            let res = cursor.value().unwrap();
+            if res.fourcc() != b"STRG".into() || !asset_ids.contains(&res.file_id) {
+                continue;
+            }
+
+            let bytes = strg_resource_bytes(res);
+            let mut strg = structs::Strg::try_read(&mut Reader::new(&bytes))
+                .map_err(|e| format!("Failed to read STRG {:08X}: {}", res.file_id, e))?;
+            for table in strg.string_tables.as_mut_vec().iter_mut() {
+                for (lang, by_strg, applied) in translations.iter_mut() {
+                    if table.lang != *lang {
+                        continue;
+                    }
+                    *applied = true;
+                    let strings = table.strings.as_mut_vec();
+                    for (index, text) in by_strg.get(&res.file_id).into_iter().flatten() {
+                        if *index >= strings.len() {
+                            pn.notify_warning(&format!(
+                                "STRG {:08X} has no string #{} to translate",
+                                res.file_id, index
+                            ));
+                            continue;
+                        }
+                        let mut text = text.clone();
+                        if !text.ends_with('\0') {
+                            text += "\0";
+                        }
+                        strings[*index] = text.into();
+                    }
+                }
+            }
+
+            let mut bytes = vec![];
+            strg.write_to(&mut bytes).unwrap();
+            res.kind = structs::ResourceKind::External(bytes, b"STRG".into());
+            res.compressed = false;
+        }
+    }
+
+    for (lang, _, applied) in translations {
+        if !applied {
+            pn.notify_warning(&format!(
+                "no translated STRG has a {} table, so those translations were not applied",
+                lang
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 /* For mipmapcache */
+fn patch_recolor_txtr(
+    res: &mut structs::Resource,
+    adjustment: &ColorAdjustment,
+    cache_dir: &str,
+) -> Result<(), String> {
+    let res_data = match &res.kind {
+        structs::ResourceKind::Unknown(_, _) => crate::ResourceData::new(res),
+        structs::ResourceKind::External(_, _) => crate::ResourceData::new_external(res),
+        _ => Err(format!(
+            "Unsupported resource kind for recoloring 0x{:08X}",
+            res.file_id
+        ))?,
+    };
+    let data = res_data.decompress().into_owned();
+    let mut txtr: structs::Txtr = Reader::new(&data[..]).read(());
+
+    recolor_palette(&mut txtr.format, adjustment);
+
+    let format = txtr.format.clone();
+    let mut w = txtr.width as usize;
+    let mut h = txtr.height as usize;
+    for mipmap in txtr.pixel_data.as_mut_vec() {
+        let mipmap = mipmap.as_mut_vec();
+        let hash: u64 = calculate_hash(&(format!("{:?}", format), &mipmap[..]));
+        let filename = format!("{}/{}", cache_dir, hash);
+        match fs::read(&filename) {
+            Ok(bytes) if bytes.len() == mipmap.len() => *mipmap = bytes,
+            _ => {
+                if recolor_mipmap(&format, &mut mipmap[..], w, h, adjustment) {
+                    let _ = fs::write(&filename, &mipmap[..]);
+                }
+            }
+        }
+        w /= 2;
+        h /= 2;
+    }
+
+    let mut bytes = vec![];
+    txtr.write_to(&mut bytes).unwrap();
+    res.kind = structs::ResourceKind::External(bytes, b"TXTR".into());
+    res.compressed = false;
+    Ok(())
+}
+
+fn patch_recolor_part(
+    res: &mut structs::Resource,
+    adjustment: &ColorAdjustment,
+    textures: &HashMap<u32, u32>,
+) -> Result<(), String> {
+    let data = match &res.kind {
+        structs::ResourceKind::Unknown(_, _) => {
+            crate::ResourceData::new(res).decompress().into_owned()
+        }
+        structs::ResourceKind::External(_, _) => crate::ResourceData::new_external(res)
+            .decompress()
+            .into_owned(),
+        _ => Err(format!(
+            "Unsupported resource kind for recoloring 0x{:08X}",
+            res.file_id
+        ))?,
+    };
+    let mut part = structs::Part::try_read(&mut Reader::new(&data[..])).map_err(|e| {
+        format!(
+            "Failed to read particle effect 0x{:08X}: {}",
+            res.file_id, e
+        )
+    })?;
+
+    part.visit_elements_mut(|kind, element| match (kind, element) {
+        (PartElementKind::Color, PartElement::Function { class, args })
+            if *class == b"CNST".into() =>
+        {
+            if let [PartElement::Real(r), PartElement::Real(g), PartElement::Real(b), _] =
+                &mut args[..]
+            {
+                [*r, *g, *b] = adjustment.apply_rgb_f32([*r, *g, *b]);
+            }
+        }
+        (PartElementKind::Color, PartElement::Keyframes(keyframes)) => {
+            if let PartKeys::Color(keys) = &mut keyframes.keys {
+                for [r, g, b, _] in keys.iter_mut() {
+                    [*r, *g, *b] = adjustment.apply_rgb_f32([*r, *g, *b]);
+                }
+            }
+        }
+        (PartElementKind::Uv, PartElement::Function { args, .. }) => {
+            if let Some(PartElement::Asset(id)) = args.first_mut() {
+                if let Some(replacement) = textures.get(id) {
+                    *id = *replacement;
+                }
+            }
+        }
+        _ => (),
+    });
+
+    let mut bytes = vec![];
+    part.write_to(&mut bytes).unwrap();
+    res.kind = structs::ResourceKind::External(bytes, b"PART".into());
+    res.compressed = false;
+    Ok(())
+}
+
+// Adds the replacement of every texture which is loaded along with a recolored particle to the
+// same layers of the area
+fn add_recolored_part_dependencies<'r>(
+    area: &mut mlvl_wrapper::MlvlArea<'r, '_, '_, '_>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    part_id: u32,
+    textures: &HashMap<u32, u32>,
+) {
+    let is_dep = |dep: &structs::Dependency, id: u32, fourcc: &[u8; 4]| {
+        dep.asset_id == id && dep.asset_type == FourCC::from_bytes(fourcc)
+    };
+    let mut layer_deps = vec![];
+    for (layer, deps) in area.mlvl_area.dependencies.deps.iter().enumerate() {
+        if !deps.iter().any(|dep| is_dep(&dep, part_id, b"PART")) {
+            continue;
+        }
+        for (txtr, replacement) in textures.iter() {
+            if deps.iter().any(|dep| is_dep(&dep, *txtr, b"TXTR")) {
+                layer_deps.push((layer, *replacement));
+            }
+        }
+    }
+
+    for (layer, replacement) in layer_deps {
+        let dep = structs::Dependency {
+            asset_id: replacement,
+            asset_type: b"TXTR".into(),
+        };
+        area.add_dependencies(game_resources, layer, iter::once(dep));
+    }
+}
+
+// Appends the replacement of every texture in a PAK without areas, such as SamGunFx.pak
+fn add_recolored_part_txtrs<'r>(
+    file: &mut structs::FstEntryFile<'r>,
+    game_resources: &HashMap<(u32, FourCC), structs::Resource<'r>>,
+    textures: &HashMap<u32, u32>,
+) -> Result<(), String> {
+    let pak = match file {
+        structs::FstEntryFile::Pak(pak) => pak,
+        _ => Err("Expected a PAK when adding recolored particle textures")?,
+    };
+
+    let has_txtr = |id: u32| {
+        pak.resources
+            .iter()
+            .any(|res| res.file_id == id && res.fourcc() == b"TXTR".into())
+    };
+    let mut replacements: Vec<u32> = textures
+        .iter()
+        .filter(|(txtr, replacement)| has_txtr(**txtr) && !has_txtr(**replacement))
+        .map(|(_, replacement)| *replacement)
+        .collect();
+    replacements.sort();
+    replacements.dedup();
+
+    // append at the end of the pak
+    let mut cursor = pak.resources.cursor();
+    while cursor.cursor_advancer().peek().is_some() {}
+    for replacement in replacements {
+        cursor.insert_after(iter::once(
+            game_resources[&(replacement, b"TXTR".into())].clone(),
+        ));
+    }
+    Ok(())
+}
+
 fn calculate_hash<T: Hash>(t: &T) -> u64 {
     let mut s = DefaultHasher::new();
     t.hash(&mut s);
@@ -19515,3 +21991,255 @@ fn patch_elite_research_door_lock<'r>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use reader_writer::Readable;
+
+    use super::*;
+
+    fn frme_widget(fourcc: &[u8; 4], name: &str, kind: &[u8]) -> Vec<u8> {
+        let mut bytes = fourcc.to_vec();
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(b"kGSYS_HeadWidgetID\0");
+        bytes.extend_from_slice(&[0, 1, 1, 0]);
+        for value in [1.0f32; 4] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend_from_slice(&2u32.to_be_bytes());
+        bytes.extend_from_slice(kind);
+        bytes.push(0);
+        bytes.extend_from_slice(&[0; 66]);
+        bytes
+    }
+
+    fn frme_resource() -> structs::Resource<'static> {
+        let mut text_pane = vec![0; 74];
+        text_pane[0..4].copy_from_slice(&4.9f32.to_be_bytes());
+        let mut bytes = vec![];
+        for value in [0u32, 0, 0, 0, 2] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend(frme_widget(b"HWIG", "kGSYS_HeadWidgetID", &[]));
+        bytes.extend(frme_widget(b"TXPN", "textpane", &text_pane));
+        bytes.resize(bytes.len().div_ceil(32) * 32, 0);
+        crate::custom_assets::build_resource_raw(
+            0x12345678,
+            structs::ResourceKind::External(bytes, b"FRME".into()),
+        )
+    }
+
+    fn gui_frame_config(json: &str) -> GuiFrameConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn patch_gui_frame_widgets() {
+        let config = gui_frame_config(
+            r#"{
+                "widgets": {
+                    "textpane": {
+                        "name": "renamed",
+                        "textPane": { "justification": "Center", "verticalJustification": "Bottom" }
+                    }
+                },
+                "addWidgets": [
+                    { "name": "label", "textPane": { "size": [4.9, 0.98], "font": 1 } },
+                    {
+                        "name": "icon",
+                        "parent": "renamed",
+                        "image": { "texture": 2, "size": [2.0, 1.0] }
+                    }
+                ]
+            }"#,
+        );
+        let mut res = frme_resource();
+        patch_gui_frame(&mut res, &config).unwrap();
+
+        let bytes = crate::ResourceData::new_external(&res)
+            .decompress()
+            .into_owned();
+        let frme = structs::Frme::try_read(&mut Reader::new(&bytes)).unwrap();
+        let widgets: Vec<_> = frme.widgets.iter().collect();
+        assert_eq!(widgets.len(), 4);
+
+        assert_eq!(widgets[1].name.to_str().unwrap(), "renamed");
+        match &widgets[1].kind {
+            structs::FrmeWidgetKind::TextPane(text_pane) => {
+                assert_eq!(text_pane.x_dim, 4.9);
+                assert_eq!(text_pane.justification, 1);
+                assert_eq!(text_pane.vertical_justification, 2);
+            }
+            _ => panic!("Expected a text pane"),
+        }
+
+        assert_eq!(widgets[2].name.to_str().unwrap(), "label");
+        match &widgets[2].kind {
+            structs::FrmeWidgetKind::TextPane(text_pane) => {
+                assert_eq!(text_pane.font.to_u32(), 1);
+                assert!((text_pane.block_extent[0] - 100.0).abs() < 0.01);
+                assert!((text_pane.block_extent[1] - 20.0).abs() < 0.01);
+                assert_eq!(text_pane.jpn_font, None);
+            }
+            _ => panic!("Expected a text pane"),
+        }
+
+        assert_eq!(widgets[3].name.to_str().unwrap(), "icon");
+        assert_eq!(widgets[3].parent.to_str().unwrap(), "renamed");
+        match &widgets[3].kind {
+            structs::FrmeWidgetKind::Image(image) => {
+                assert_eq!(image.texture.to_u32(), 2);
+                let quad_coords: Vec<_> = image.quad_coords.iter().collect();
+                assert_eq!(quad_coords.len(), 4);
+                assert_eq!(quad_coords[0].as_slice(), &[-1.0, 0.0, 0.5]);
+                assert_eq!(image.uv_coords.len(), 4);
+            }
+            _ => panic!("Expected an image"),
+        }
+    }
+
+    #[test]
+    fn patch_gui_frame_errors() {
+        let cases = [
+            (
+                r#"{ "widgets": { "missing": { "visible": false } } }"#,
+                "no widget named",
+            ),
+            (
+                r#"{ "widgets": { "textpane": { "image": { "texture": 2 } } } }"#,
+                "not an image",
+            ),
+            (
+                r#"{ "addWidgets": [{ "name": "textpane", "image": { "texture": 2, "size": [1.0, 1.0] } }] }"#,
+                "already a widget",
+            ),
+            (
+                r#"{ "addWidgets": [{ "name": "icon", "image": { "size": [1.0, 1.0] } }] }"#,
+                "need a texture",
+            ),
+            (
+                r#"{ "addWidgets": [{ "name": "label", "textPane": { "font": 1 } }] }"#,
+                "need a size",
+            ),
+            (
+                r#"{ "addWidgets": [{ "name": "empty" }] }"#,
+                "either a text pane or an image",
+            ),
+        ];
+        for (json, expected) in cases {
+            let mut res = frme_resource();
+            let err = patch_gui_frame(&mut res, &gui_frame_config(json)).unwrap_err();
+            assert!(err.contains(expected), "{}: {}", json, err);
+        }
+
+        let mut res = crate::custom_assets::build_resource_raw(
+            0x12345678,
+            structs::ResourceKind::External(vec![0; 8], b"FRME".into()),
+        );
+        assert!(patch_gui_frame(&mut res, &gui_frame_config("{}")).is_err());
+    }
+
+    fn disc_header() -> structs::GcDiscHeader {
+        let mut bytes = vec![0; structs::GcDiscHeader::fixed_size().unwrap()];
+        bytes[0x1C..0x20].copy_from_slice(&0xC2339F3Du32.to_be_bytes());
+        Reader::new(&bytes).read(())
+    }
+
+    #[test]
+    fn patch_disc_header_identity() {
+        let mut header = disc_header();
+        let identity = DiscIdentity {
+            game_id: Some("GM8E02".to_string()),
+            disc_number: Some(1),
+            internal_name: Some("Metroid Prime Randomizer".to_string()),
+            save_file_name: None,
+        };
+        patch_disc_header(&mut header, &identity).unwrap();
+
+        assert_eq!(header.console_id, b'G');
+        assert_eq!(header.game_code.as_slice(), b"M8");
+        assert_eq!(header.country_code, b'E');
+        assert_eq!(header.maker_code.as_slice(), b"02");
+        assert_eq!(header.disc_id, 1);
+        assert_eq!(&header.game_name[..24], b"Metroid Prime Randomizer");
+        assert!(header.game_name[24..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn patch_disc_header_validation() {
+        for game_id in ["GM8E0", "GM8E011", "gm8e01", "GM8E-1", "GM8É1"] {
+            let mut header = disc_header();
+            let identity = DiscIdentity {
+                game_id: Some(game_id.to_string()),
+                ..Default::default()
+            };
+            assert!(
+                patch_disc_header(&mut header, &identity).is_err(),
+                "{}",
+                game_id
+            );
+            assert_eq!(header.console_id, 0);
+        }
+
+        // The name needs room for its nul terminator
+        let max_len = disc_header().game_name.len() - 1;
+        for (internal_name, valid) in [
+            ("A".repeat(max_len), true),
+            ("A".repeat(max_len + 1), false),
+            ("メトロイド".to_string(), false),
+        ] {
+            let mut header = disc_header();
+            let identity = DiscIdentity {
+                internal_name: Some(internal_name.clone()),
+                ..Default::default()
+            };
+            assert_eq!(
+                patch_disc_header(&mut header, &identity).is_ok(),
+                valid,
+                "{}",
+                internal_name
+            );
+        }
+    }
+
+    #[test]
+    fn patch_memorycard_game_name_languages() {
+        let table = |lang: &[u8; 4], string: &str| structs::StrgStringTable {
+            lang: lang.into(),
+            strings: vec![format!("{}\u{0}", string).into()].into(),
+        };
+        let strg = structs::Strg {
+            string_tables: vec![
+                table(b"ENGL", "Metroid Prime save data"),
+                table(b"JAPN", "メトロイドプライムのセーブ"),
+            ]
+            .into(),
+        };
+        let mut res =
+            crate::custom_assets::build_resource_raw(0, structs::ResourceKind::Strg(strg));
+        patch_memorycard_game_name(&mut res, "Prime Randomizer").unwrap();
+
+        let strings: Vec<String> = res
+            .kind
+            .as_strg()
+            .unwrap()
+            .string_tables
+            .iter()
+            .flat_map(|table| {
+                table
+                    .strings
+                    .iter()
+                    .map(|s| s.into_owned().into_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(
+            strings,
+            [
+                "Prime Randomizer save data\u{0}",
+                "Prime Randomizerのセーブ\u{0}"
+            ]
+        );
+    }
+}
@@ -191,26 +191,30 @@ impl PickupType {
 
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(string: &str) -> Self {
+        PickupType::try_from_str(string).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_from_str(string: &str) -> Result<Self, String> {
         let string = string.to_lowercase();
         let string = string.trim();
         for i in PickupType::iter() {
             if i.name().to_string().to_lowercase().trim() == string {
-                return i;
+                return Ok(i);
             }
         }
 
         // Alternate Names
         if ["combat"].contains(&string) {
-            return PickupType::CombatVisor;
+            return Ok(PickupType::CombatVisor);
         } else if ["scan"].contains(&string) {
-            return PickupType::ScanVisor;
+            return Ok(PickupType::ScanVisor);
         } else if ["thermal"].contains(&string) {
-            return PickupType::ThermalVisor;
+            return Ok(PickupType::ThermalVisor);
         } else if ["x-ray", "xray", "x-ray visor", "xray visor"].contains(&string) {
-            return PickupType::XRayVisor;
+            return Ok(PickupType::XRayVisor);
         }
 
-        panic!("Unknown Pickup Type - {}", string);
+        Err(format!("Unknown Pickup Type - {}", string))
     }
 
     // This is kind of a hack, but we need to index FJ and Nothing seperately
@@ -0,0 +1,962 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use dol_symbol_table::{mp1_symbol, Mp1Symbol};
+use reader_writer::{FourCC, Reader};
+use resource_info_table::resource_info;
+use serde::Deserialize;
+use structs::{Dol, GcDisc, Hint, Mlvl, Savw, Txtr, TxtrFormat};
+
+use crate::{
+    elevators::{SpawnRoomData, World},
+    patch_config::Version,
+    pickup_meta::{self, PickupType},
+    starting_items::StartingItems,
+    GcDiscLookupExtensions, ResourceData,
+};
+
+// Layout of the memory card file, see CMemoryCardSys::CardFileInfo and CMemoryCardDriver
+const CARD_BLOCK_SIZE: usize = 0x2000;
+const GCI_HEADER_SIZE: usize = 0x40;
+const SAVE_BUFFER_SIZE: usize = 3004;
+const GAME_STATE_SIZE: usize = 940;
+const SYSTEM_DATA_SIZE: usize = 174;
+
+// Seconds between the unix epoch and the GameCube epoch (2000-01-01)
+const GC_EPOCH_OFFSET: u64 = 946_684_800;
+
+// CPlayerState tracks one power up slot per item up to the last artifact
+const POWER_UP_COUNT: usize = PickupType::ArtifactOfNewborn as usize + 1;
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SaveRoomConfig {
+    pub visited: Option<bool>,
+    pub mapped: Option<bool>,
+    pub layers: Option<HashMap<u32, bool>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SaveWorldConfig {
+    #[serde(default)]
+    pub map_complete: bool,
+    #[serde(default)]
+    pub active_relays: Vec<u32>,
+    #[serde(default)]
+    pub rooms: HashMap<String, SaveRoomConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SaveFileConfig {
+    pub room: String,
+    #[serde(default, deserialize_with = "StartingItems::custom_deserialize")]
+    pub items: StartingItems,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    pub energy: Option<f32>,
+    #[serde(default)]
+    pub play_time: f64,
+    #[serde(default)]
+    pub hard_mode: bool,
+    #[serde(default)]
+    pub worlds: HashMap<String, SaveWorldConfig>,
+    #[serde(default)]
+    pub scans: Vec<u32>,
+    #[serde(default)]
+    pub all_scans: bool,
+    pub comment: Option<String>,
+}
+
+/// Everything the save file layout depends on which is read from the disc
+struct SaveWorldInfo {
+    world: World,
+    mlvl: u32,
+    area_mreas: Vec<u32>,
+    area_layers: Vec<(u32, u64)>,
+    relays: Vec<u32>,
+    doors: Vec<u32>,
+    scans: Vec<(u32, u32)>,
+}
+
+/// The banner or icon image of the save, in the native GX layout
+struct CardImage {
+    texels: Vec<u8>,
+    palette: Option<Vec<u8>>,
+}
+
+/// Writes values MSB first, like CBitStreamWriter
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_len: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        for i in (0..bits).rev() {
+            if self.bit_len % 8 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 != 0 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bit_len % 8);
+            }
+            self.bit_len += 1;
+        }
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.write(value as u32, 1);
+    }
+}
+
+/// CBitStreamWriter::GetBitCount
+fn bit_count(max_value: u32) -> u32 {
+    32 - max_value.leading_zeros()
+}
+
+/// The CRC the game stores in front of the card file (no final inversion)
+fn card_crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFF_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn symbol_addr(name: &str, symbol: Mp1Symbol, version: Version) -> Result<u32, String> {
+    match version {
+        Version::NtscU0_00 => symbol.addr_0_00,
+        Version::NtscU0_01 => symbol.addr_0_01,
+        Version::NtscU0_02 => symbol.addr_0_02,
+        Version::NtscK => symbol.addr_kor,
+        Version::NtscJ => symbol.addr_jpn,
+        Version::Pal => symbol.addr_pal,
+        _ => None,
+    }
+    .ok_or_else(|| format!("Symbol {} unknown for version {}", name, version))
+}
+
+fn read_dol_bytes(dol: &Dol, addr: u32, len: usize) -> Result<Vec<u8>, String> {
+    for segment in dol.text_segments.iter().chain(dol.data_segments.iter()) {
+        let start = segment.load_addr;
+        let end = start + segment.contents.len() as u32;
+        if addr < start || addr + len as u32 > end {
+            continue;
+        }
+        return Ok(segment
+            .contents
+            .iter()
+            .skip((addr - start) as usize)
+            .take(len)
+            .map(|b| *b)
+            .collect());
+    }
+    Err(format!("Address 0x{:08X} is not part of the DOL", addr))
+}
+
+fn read_dol_words(dol: &Dol, addr: u32, count: usize) -> Result<Vec<u32>, String> {
+    let bytes = read_dol_bytes(dol, addr, count * 4)?;
+    Ok(bytes
+        .chunks(4)
+        .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+        .collect())
+}
+
+fn read_dol_string(dol: &Dol, addr: u32) -> Result<String, String> {
+    let bytes = read_dol_bytes(dol, addr, 32)?;
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+}
+
+fn read_resource(
+    gc_disc: &GcDisc,
+    pak_name: &str,
+    file_id: u32,
+    fourcc: FourCC,
+) -> Result<Vec<u8>, String> {
+    let res = gc_disc
+        .find_resource(pak_name, |res| {
+            res.file_id == file_id && res.fourcc() == fourcc
+        })
+        .ok_or_else(|| {
+            format!(
+                "Could not find {} 0x{:08X} in {}",
+                fourcc, file_id, pak_name
+            )
+        })?;
+    let bytes = ResourceData::new(&res).decompress().into_owned();
+    Ok(bytes)
+}
+
+fn read_world_info(gc_disc: &GcDisc, world: World) -> Result<SaveWorldInfo, String> {
+    let pak_name = world.to_pak_str();
+    let mlvl_bytes = read_resource(gc_disc, pak_name, world.mlvl(), FourCC::from_bytes(b"MLVL"))?;
+    let mlvl = Mlvl::try_read(&mut Reader::new(&mlvl_bytes))
+        .map_err(|e| format!("Failed to read the {} MLVL: {}", world.to_json_key(), e))?;
+    let savw_bytes = read_resource(
+        gc_disc,
+        pak_name,
+        mlvl.world_savw.to_u32(),
+        FourCC::from_bytes(b"SAVW"),
+    )?;
+    let savw = Savw::try_read(&mut Reader::new(&savw_bytes))
+        .map_err(|e| format!("Failed to read the {} SAVW: {}", world.to_json_key(), e))?;
+
+    Ok(SaveWorldInfo {
+        world,
+        mlvl: world.mlvl(),
+        area_mreas: mlvl.areas.iter().map(|area| area.mrea.to_u32()).collect(),
+        area_layers: mlvl
+            .area_layer_flags
+            .iter()
+            .map(|flags| (flags.layer_count, flags.flags))
+            .collect(),
+        relays: savw.memory_relay_array.iter().collect(),
+        doors: savw.door_array.iter().collect(),
+        scans: savw
+            .scan_array
+            .iter()
+            .map(|scan| (scan.scan.to_u32(), scan.logbook_category))
+            .collect(),
+    })
+}
+
+fn read_card_image(
+    gc_disc: &GcDisc,
+    pak_name: &str,
+    file_id: u32,
+    width: u16,
+    height: u16,
+) -> Result<CardImage, String> {
+    let bytes = read_resource(gc_disc, pak_name, file_id, FourCC::from_bytes(b"TXTR"))?;
+    let txtr = Txtr::try_read(&mut Reader::new(&bytes)).map_err(|e| {
+        format!(
+            "Failed to read memory card texture 0x{:08X}: {}",
+            file_id, e
+        )
+    })?;
+    if txtr.width != width || txtr.height != height {
+        return Err(format!(
+            "Memory card texture 0x{:08X} must be {}x{}",
+            file_id, width, height
+        ));
+    }
+    let texels: Vec<u8> = txtr
+        .pixel_data
+        .iter()
+        .next()
+        .ok_or_else(|| format!("Memory card texture 0x{:08X} has no image data", file_id))?
+        .iter()
+        .map(|b| *b)
+        .collect();
+    let palette = match &txtr.format {
+        TxtrFormat::Rgb5A3 => None,
+        // The palette is copied as is, like the game does when it writes the card file
+        TxtrFormat::C8(_, palette) => Some(palette.to_vec()),
+        _ => {
+            return Err(format!(
+                "Memory card texture 0x{:08X} must be RGB5A3 or C8",
+                file_id
+            ))
+        }
+    };
+    Ok(CardImage { texels, palette })
+}
+
+/// Returns the index of the room's area in the MLVL
+fn find_room(world: &SaveWorldInfo, room_name: &str) -> Result<usize, String> {
+    let room_info = pickup_meta::ROOM_INFO
+        .iter()
+        .filter(|(pak_name, _)| World::from_pak(pak_name) == Some(world.world))
+        .flat_map(|(_, rooms)| rooms.iter())
+        .find(|room_info| {
+            room_info
+                .name()
+                .trim()
+                .eq_ignore_ascii_case(room_name.trim())
+        })
+        .ok_or_else(|| {
+            format!(
+                "Could not find room '{}' in {}",
+                room_name,
+                world.world.to_json_key()
+            )
+        })?;
+    world
+        .area_mreas
+        .iter()
+        .position(|mrea| *mrea == room_info.room_id.to_u32())
+        .ok_or_else(|| format!("Room '{}' is not part of the MLVL", room_name))
+}
+
+fn power_up_amounts(config: &SaveFileConfig) -> Result<[u32; POWER_UP_COUNT], String> {
+    let items = &config.items;
+    let mut amounts = [0; POWER_UP_COUNT];
+    let mut set = |pickup_type: PickupType, amount: u32| {
+        amounts[pickup_type.kind() as usize] = amount;
+    };
+    set(PickupType::PowerBeam, items.power_beam as u32);
+    set(PickupType::IceBeam, items.ice as u32);
+    set(PickupType::WaveBeam, items.wave as u32);
+    set(PickupType::PlasmaBeam, items.plasma as u32);
+    set(PickupType::Missile, items.missiles.max(0) as u32);
+    set(PickupType::ScanVisor, items.scan_visor as u32);
+    set(PickupType::MorphBallBomb, items.bombs as u32);
+    set(PickupType::PowerBomb, items.power_bombs.max(0) as u32);
+    set(PickupType::Flamethrower, items.flamethrower as u32);
+    set(PickupType::ThermalVisor, items.thermal_visor as u32);
+    set(PickupType::ChargeBeam, items.charge as u32);
+    set(PickupType::SuperMissile, items.super_missile as u32);
+    set(PickupType::GrappleBeam, items.grapple as u32);
+    set(PickupType::XRayVisor, items.xray as u32);
+    set(PickupType::IceSpreader, items.ice_spreader as u32);
+    set(PickupType::SpaceJumpBoots, items.space_jump as u32);
+    set(PickupType::MorphBall, items.morph_ball as u32);
+    set(PickupType::CombatVisor, items.combat_visor as u32);
+    set(PickupType::BoostBall, items.boost_ball as u32);
+    set(PickupType::SpiderBall, items.spider_ball as u32);
+    set(PickupType::PowerSuit, items.power_suit);
+    set(PickupType::GravitySuit, items.gravity_suit as u32);
+    set(PickupType::VariaSuit, items.varia_suit as u32);
+    set(PickupType::PhazonSuit, items.phazon_suit as u32);
+    set(PickupType::EnergyTank, items.energy_tanks.max(0) as u32);
+    set(PickupType::UnknownItem1, items.unknown_item_1);
+    set(PickupType::UnknownItem2, items.custom_items());
+    set(PickupType::Wavebuster, items.wavebuster as u32);
+
+    for artifact in &config.artifacts {
+        let pickup_type = PickupType::try_from_str(artifact)?;
+        if pickup_type.kind() < PickupType::ArtifactOfTruth.kind()
+            || pickup_type.kind() > PickupType::ArtifactOfNewborn.kind()
+        {
+            return Err(format!("'{}' is not an artifact", artifact));
+        }
+        set(pickup_type, 1);
+    }
+
+    Ok(amounts)
+}
+
+fn current_suit(items: &StartingItems) -> u32 {
+    // EPlayerSuit
+    if items.phazon_suit {
+        3
+    } else if items.gravity_suit {
+        1
+    } else if items.varia_suit {
+        2
+    } else {
+        0
+    }
+}
+
+/// Serializes a CGameState into the 940 byte buffer of a save slot
+#[allow(clippy::too_many_arguments)]
+fn write_game_state(
+    config: &SaveFileConfig,
+    worlds: &[SaveWorldInfo],
+    start: &SpawnRoomData,
+    power_up_max: &[u32],
+    etank_capacity: f32,
+    base_health: f32,
+    hint_count: usize,
+    timestamp: u32,
+) -> Result<Vec<u8>, String> {
+    let mut w = BitWriter::default();
+
+    for _ in 0..4 {
+        w.write(0, 32); // unused flags
+    }
+    w.write(timestamp, 32);
+    w.write_bool(config.hard_mode);
+    w.write_bool(false); // don't let the spawn point replace the inventory
+    w.write(start.mlvl, 32);
+    let play_time = config.play_time.to_bits();
+    w.write((play_time >> 32) as u32, 32);
+    w.write(play_time as u32, 32);
+
+    // CPlayerState
+    let amounts = power_up_amounts(config)?;
+    let energy = config
+        .energy
+        .unwrap_or(base_health + etank_capacity * amounts[PickupType::EnergyTank as usize] as f32);
+    w.write(0, 32); // enabled items
+    w.write(energy.to_bits(), 32);
+    w.write(0, bit_count(5)); // power beam
+    w.write(current_suit(&config.items), bit_count(4));
+    for (i, amount) in amounts.iter().enumerate() {
+        let max = power_up_max[i];
+        if *amount > max {
+            return Err(format!(
+                "{} exceeds the maximum of {} for {}",
+                amount,
+                max,
+                PickupType::iter()
+                    .find(|pickup_type| pickup_type.kind() as usize == i)
+                    .map(|pickup_type| pickup_type.name())
+                    .unwrap_or("Unknown Item")
+            ));
+        }
+        w.write(*amount, bit_count(max));
+        w.write(*amount, bit_count(max));
+    }
+
+    // CMemoryCardSys keeps the scans of every world sorted by id
+    let mut scans: Vec<(u32, u32)> = worlds
+        .iter()
+        .flat_map(|world| world.scans.clone())
+        .collect();
+    scans.sort_by_key(|(scan, _)| *scan);
+    let mut logbook_scanned = 0;
+    let mut logbook_total = 0;
+    for (scan, category) in scans.iter() {
+        let scanned = config.all_scans || config.scans.contains(scan);
+        w.write_bool(scanned);
+        if *category != 0 {
+            logbook_total += 1;
+            logbook_scanned += scanned as u32;
+        }
+    }
+    w.write(logbook_scanned.min(0x100), bit_count(0x100));
+    w.write(logbook_total.min(0x100), bit_count(0x100));
+
+    // CGameOptions, left at the game's defaults
+    w.write(0, 32);
+    w.write(0, 32);
+    w.write(1, 2); // stereo
+    w.write(4, 4); // brightness
+    w.write(0, 6); // screen x offset
+    w.write(0, 6); // screen y offset
+    w.write(0, 5); // screen stretch
+    w.write(0x7F, 7); // sfx volume
+    w.write(0x7F, 7); // music volume
+    w.write(0xFF, 8); // hud alpha
+    w.write(0xFF, 8); // helmet alpha
+    w.write_bool(true); // hud lag
+    w.write_bool(true); // hint system
+    w.write_bool(false); // invert y
+    w.write_bool(true); // rumble
+    w.write_bool(false); // swap beam controls
+
+    // CHintOptions
+    for _ in 0..hint_count {
+        w.write(0, 2);
+        w.write(0, 32);
+    }
+
+    // CWorldState, in the same order as the worlds
+    for world in worlds {
+        let world_config = config
+            .worlds
+            .iter()
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(world.world.to_json_key()))
+            .map(|(_, world_config)| world_config.clone())
+            .unwrap_or_default();
+
+        let area_count = world.area_mreas.len();
+        let mut visited = vec![world_config.map_complete; area_count];
+        let mut mapped = vec![world_config.map_complete; area_count];
+        let mut layers: Vec<u64> = world.area_layers.iter().map(|(_, flags)| *flags).collect();
+        for (room_name, room_config) in world_config.rooms.iter() {
+            let idx = find_room(world, room_name)?;
+            if let Some(value) = room_config.visited {
+                visited[idx] = value;
+            }
+            if let Some(value) = room_config.mapped {
+                mapped[idx] = value;
+            }
+            for (layer, enabled) in room_config.layers.iter().flatten() {
+                if *layer >= world.area_layers[idx].0 || *layer >= 64 {
+                    return Err(format!("Room '{}' has no layer {}", room_name, layer));
+                }
+                if *enabled {
+                    layers[idx] |= 1 << layer;
+                } else {
+                    layers[idx] &= !(1 << layer);
+                }
+            }
+        }
+
+        if world.mlvl == start.mlvl {
+            let idx = world
+                .area_mreas
+                .iter()
+                .position(|mrea| *mrea == start.mrea)
+                .ok_or("The starting room is not part of the MLVL")?;
+            visited[idx] = true;
+            mapped[idx] = true;
+            w.write(idx as u32, 32);
+            w.write(start.mrea, 32);
+        } else {
+            w.write(0, 32);
+            w.write(0xFFFFFFFF, 32);
+        }
+
+        // CScriptMailbox
+        let relay_bits = bit_count(world.relays.len() as u32);
+        let mut relays = vec![];
+        for relay in world_config.active_relays.iter() {
+            let idx = world
+                .relays
+                .iter()
+                .position(|id| id & 0x03FFFFFF == relay & 0x03FFFFFF)
+                .ok_or_else(|| {
+                    format!(
+                        "0x{:08X} is not a memory relay of {}",
+                        relay,
+                        world.world.to_json_key()
+                    )
+                })?;
+            relays.push(idx as u32);
+        }
+        w.write(relays.len() as u32, relay_bits);
+        for idx in relays {
+            w.write(idx, relay_bits);
+        }
+
+        // CMapWorldInfo
+        for value in visited {
+            w.write_bool(value);
+        }
+        for value in mapped {
+            w.write_bool(value);
+        }
+        for _ in world.doors.iter() {
+            w.write_bool(world_config.map_complete);
+        }
+        w.write_bool(world_config.map_complete);
+
+        // CWorldLayerState, layer 0 of each area is always active and not saved
+        let layer_count: u32 = world
+            .area_layers
+            .iter()
+            .map(|(count, _)| count.saturating_sub(1))
+            .sum();
+        w.write(layer_count, 10);
+        for ((count, _), flags) in world.area_layers.iter().zip(layers.iter()) {
+            for layer in 1..*count {
+                w.write_bool(flags & (1 << layer) != 0);
+            }
+        }
+    }
+
+    if w.bytes.len() > GAME_STATE_SIZE {
+        return Err(format!(
+            "The save data is {} bytes, but only {} fit in a save slot",
+            w.bytes.len(),
+            GAME_STATE_SIZE
+        ));
+    }
+    let mut bytes = w.bytes;
+    bytes.resize(GAME_STATE_SIZE, 0);
+    Ok(bytes)
+}
+
+fn padded_string(string: &str, len: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = string.bytes().take(len - 1).collect();
+    bytes.resize(len, 0);
+    bytes
+}
+
+fn disc_version(gc_disc: &GcDisc) -> Result<Version, String> {
+    if let Some(version) = Version::from_disc_header(&gc_disc.header) {
+        return Ok(version);
+    }
+
+    // Discs patched with a custom game ID are identified by the config the patcher stored
+    let config = match gc_disc
+        .find_file("randomprime.json")
+        .and_then(|entry| entry.file())
+    {
+        Some(structs::FstEntryFile::Unknown(reader)) => {
+            serde_json::from_slice::<serde_json::Value>(reader)
+                .map_err(|e| format!("Failed to parse randomprime.json: {}", e))?
+        }
+        _ => Err("The input ISO doesn't appear to be a GameCube version of Metroid Prime")?,
+    };
+    [
+        Version::NtscU0_00,
+        Version::NtscU0_01,
+        Version::NtscU0_02,
+        Version::NtscK,
+        Version::NtscJ,
+        Version::Pal,
+    ]
+    .into_iter()
+    .find(|version| serde_json::to_value(version).ok().as_ref() == config.get("version"))
+    .ok_or_else(|| "Unsupported version in randomprime.json".to_string())
+}
+
+/// Builds a memory card save file (GCI) with a single save slot for the game on the given disc.
+/// Returns the GCI along with its name in the `<maker>-<game>-<file>.gci` scheme used by Dolphin's
+/// GCI folders and the Nintendont/GC Loader memory card managers.
+pub fn build_gci(gc_disc: &GcDisc, config: &SaveFileConfig) -> Result<(String, Vec<u8>), String> {
+    let version = disc_version(gc_disc)?;
+
+    // Read the tables from the DOL so that patched item capacities are respected
+    let dol = match gc_disc
+        .find_file("default.dol")
+        .and_then(|entry| entry.file())
+    {
+        Some(structs::FstEntryFile::Unknown(reader)) => Dol::try_read(&mut reader.clone())
+            .map_err(|e| format!("Failed to read default.dol: {}", e))?,
+        _ => Err("Could not find default.dol")?,
+    };
+    let power_up_max_addr = symbol_addr(
+        "CPlayerState_PowerUpMaxValues",
+        mp1_symbol!("CPlayerState_PowerUpMaxValues"),
+        version,
+    )?;
+    let etank_capacity_addr =
+        symbol_addr("g_EtankCapacity", mp1_symbol!("g_EtankCapacity"), version)?;
+    let file_name_addr = if version == Version::Pal || version == Version::NtscJ {
+        symbol_addr("aMetroidprime", mp1_symbol!("aMetroidprime"), version)?
+    } else {
+        symbol_addr("aMetroidprimeA", mp1_symbol!("aMetroidprimeA"), version)?
+    };
+    let file_name = read_dol_string(&dol, file_name_addr)?;
+    let power_up_max = read_dol_words(&dol, power_up_max_addr, POWER_UP_COUNT)?;
+    let etank_capacity = read_dol_words(&dol, etank_capacity_addr, 2)?;
+    let base_health = f32::from_bits(etank_capacity[1]);
+    let etank_capacity = f32::from_bits(etank_capacity[0]);
+
+    // CMemoryCardSys orders the worlds by MLVL id
+    let mut worlds = vec![];
+    for world in World::iter() {
+        worlds.push(read_world_info(gc_disc, world)?);
+    }
+    worlds.sort_by_key(|world| world.mlvl);
+
+    let hint_bytes = read_resource(
+        gc_disc,
+        "NoARAM.pak",
+        resource_info!("HINT_Hints_1.HINT").res_id,
+        FourCC::from_bytes(b"HINT"),
+    )?;
+    let hint = Hint::try_read(&mut Reader::new(&hint_bytes))
+        .map_err(|e| format!("Failed to read the hints: {}", e))?;
+
+    let start = SpawnRoomData::try_from_str(&config.room)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(GC_EPOCH_OFFSET);
+    let timestamp = now.saturating_sub(GC_EPOCH_OFFSET) as u32;
+
+    let game_state = write_game_state(
+        config,
+        &worlds,
+        &start,
+        &power_up_max,
+        etank_capacity,
+        base_health,
+        hint.hints.len(),
+        timestamp,
+    )?;
+
+    // SSaveHeader, the slots and CPersistentOptions
+    let mut save_buffer = vec![];
+    save_buffer.extend_from_slice(&0_u32.to_be_bytes());
+    save_buffer.extend_from_slice(&[1, 0, 0]);
+    save_buffer.extend_from_slice(&game_state);
+    save_buffer.extend_from_slice(&[0; SYSTEM_DATA_SIZE]);
+    save_buffer.resize(SAVE_BUFFER_SIZE, 0);
+
+    let banner = read_card_image(
+        gc_disc,
+        "NoARAM.pak",
+        resource_info!("TXTR_SaveBanner.TXTR").res_id,
+        96,
+        32,
+    )?;
+    // The game animates the save icon between two frames
+    let icons = [
+        read_card_image(
+            gc_disc,
+            "NoARAM.pak",
+            resource_info!("TXTR_SaveIcon0.TXTR").res_id,
+            32,
+            32,
+        )?,
+        read_card_image(
+            gc_disc,
+            "NoARAM.pak",
+            resource_info!("TXTR_SaveIcon1.TXTR").res_id,
+            32,
+            32,
+        )?,
+    ];
+
+    let mut card_buffer = vec![0; 4];
+    card_buffer.extend_from_slice(&padded_string("Metroid Prime", 32));
+    card_buffer.extend_from_slice(&padded_string(
+        config.comment.as_deref().unwrap_or("Generated Save"),
+        32,
+    ));
+    card_buffer.extend_from_slice(&banner.texels);
+    card_buffer.extend(banner.palette.iter().flatten());
+    // C8 icon frames share the palette which follows the last frame
+    for icon in icons.iter() {
+        card_buffer.extend_from_slice(&icon.texels);
+    }
+    if let Some(palette) = icons.iter().rev().find_map(|icon| icon.palette.as_ref()) {
+        card_buffer.extend_from_slice(palette);
+    }
+    card_buffer.extend_from_slice(&save_buffer);
+    let block_count = card_buffer.len().div_ceil(CARD_BLOCK_SIZE);
+    card_buffer.resize(block_count * CARD_BLOCK_SIZE, 0);
+    let crc = card_crc32(&card_buffer[4..]);
+    card_buffer[0..4].copy_from_slice(&crc.to_be_bytes());
+
+    // Directory entry
+    let id = gc_disc.header.game_identifier();
+    let mut gci = Vec::with_capacity(GCI_HEADER_SIZE + card_buffer.len());
+    gci.extend_from_slice(&id);
+    gci.push(0xFF);
+    gci.push(if banner.palette.is_some() { 1 } else { 2 });
+    gci.extend_from_slice(&padded_string(&file_name, 32));
+    gci.extend_from_slice(&timestamp.to_be_bytes());
+    gci.extend_from_slice(&68_u32.to_be_bytes()); // image offset
+    let mut icon_format = 0_u16;
+    let mut icon_speed = 0_u16;
+    for (i, icon) in icons.iter().enumerate() {
+        icon_format |= (if icon.palette.is_some() { 1 } else { 2 }) << (i * 2);
+        icon_speed |= 2 << (i * 2);
+    }
+    gci.extend_from_slice(&icon_format.to_be_bytes());
+    gci.extend_from_slice(&icon_speed.to_be_bytes());
+    gci.push(0x04); // permissions
+    gci.push(0); // copy counter
+    gci.extend_from_slice(&0_u16.to_be_bytes()); // first block
+    gci.extend_from_slice(&(block_count as u16).to_be_bytes());
+    gci.extend_from_slice(&0xFFFF_u16.to_be_bytes());
+    gci.extend_from_slice(&4_u32.to_be_bytes()); // comment offset
+    gci.extend_from_slice(&card_buffer);
+
+    let gci_name = format!(
+        "{}-{}-{}.gci",
+        String::from_utf8_lossy(&id[4..6]),
+        String::from_utf8_lossy(&id[0..4]),
+        file_name
+    );
+
+    Ok((gci_name, gci))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_writer_round_trip() {
+        let values = [
+            (1, 1),
+            (0, 1),
+            (5, bit_count(5)),
+            (0x7F, 7),
+            (0xDEADBEEF, 32),
+            (0, 10),
+            (0x2A5, 10),
+            (1, 2),
+        ];
+        let mut w = BitWriter::default();
+        for (value, bits) in values {
+            w.write(value, bits);
+        }
+        let total_bits: u32 = values.iter().map(|(_, bits)| bits).sum();
+        assert_eq!(w.bit_len, total_bits as usize);
+        assert_eq!(w.bytes.len(), (total_bits as usize).div_ceil(8));
+
+        let mut pos = 0;
+        for (value, bits) in values {
+            let mut read = 0;
+            for _ in 0..bits {
+                let bit = (w.bytes[pos / 8] >> (7 - pos % 8)) & 1;
+                read = (read << 1) | bit as u32;
+                pos += 1;
+            }
+            assert_eq!(read, value);
+        }
+        // The bits after the last value are left clear
+        assert_eq!(w.bytes.last().unwrap() & (0xFF >> (pos % 8)), 0);
+    }
+
+    #[test]
+    fn game_state_layout() {
+        let rooms: Vec<_> = pickup_meta::ROOM_INFO
+            .iter()
+            .filter(|(pak_name, _)| World::from_pak(pak_name) == Some(World::TallonOverworld))
+            .flat_map(|(_, rooms)| rooms.iter())
+            .take(3)
+            .collect();
+        let world = SaveWorldInfo {
+            world: World::TallonOverworld,
+            mlvl: World::TallonOverworld.mlvl(),
+            area_mreas: rooms.iter().map(|room| room.room_id.to_u32()).collect(),
+            area_layers: vec![(1, 1), (3, 0b101), (2, 0b11)],
+            relays: vec![0x04000001, 0x04000002],
+            doors: vec![7],
+            scans: vec![(0x20, 0), (0x10, 3)],
+        };
+        let start = SpawnRoomData {
+            pak_name: World::TallonOverworld.to_pak_str(),
+            mlvl: world.mlvl,
+            mrea: world.area_mreas[1],
+            mrea_idx: 1,
+            room_id: 0,
+            name: "",
+        };
+        let config: SaveFileConfig = serde_json::from_value(serde_json::json!({
+            "room": "",
+            "artifacts": ["Artifact of Truth"],
+            "playTime": 1.5,
+            "hardMode": true,
+            "scans": [0x10],
+            "worlds": {
+                "Tallon Overworld": {
+                    "activeRelays": [2],
+                    "rooms": {
+                        rooms[0].name(): { "visited": true },
+                        rooms[2].name(): { "layers": { "1": false } },
+                    },
+                },
+            },
+        }))
+        .unwrap();
+        let mut power_up_max = vec![1; POWER_UP_COUNT];
+        power_up_max[PickupType::Missile as usize] = 250;
+        // Holds the launcher flags of the custom items
+        power_up_max[PickupType::UnknownItem2 as usize] = 0xFFFF;
+
+        let bytes = write_game_state(
+            &config,
+            &[world],
+            &start,
+            &power_up_max,
+            100.0,
+            99.0,
+            2,
+            1234,
+        )
+        .unwrap();
+        assert_eq!(bytes.len(), GAME_STATE_SIZE);
+
+        let mut pos = 0;
+        let mut read = |bits: u32| {
+            let mut value = 0_u64;
+            for _ in 0..bits {
+                let bit = (bytes[pos / 8] >> (7 - pos % 8)) & 1;
+                value = (value << 1) | bit as u64;
+                pos += 1;
+            }
+            value as u32
+        };
+
+        for _ in 0..4 {
+            assert_eq!(read(32), 0);
+        }
+        assert_eq!(read(32), 1234);
+        assert_eq!(read(1), 1);
+        assert_eq!(read(1), 0);
+        assert_eq!(read(32), World::TallonOverworld.mlvl());
+        assert_eq!(
+            ((read(32) as u64) << 32) | read(32) as u64,
+            1.5_f64.to_bits()
+        );
+
+        // CPlayerState
+        assert_eq!(read(32), 0);
+        assert_eq!(read(32), 99.0_f32.to_bits());
+        assert_eq!(read(3), 0);
+        assert_eq!(read(3), 0);
+        let owned = [
+            PickupType::PowerBeam,
+            PickupType::ScanVisor,
+            PickupType::CombatVisor,
+            PickupType::ArtifactOfTruth,
+        ];
+        for (i, max) in power_up_max.iter().enumerate() {
+            let amount = if i == PickupType::UnknownItem2 as usize {
+                config.items.custom_items()
+            } else {
+                owned.iter().any(|owned| *owned as usize == i) as u32
+            };
+            assert_eq!(read(bit_count(*max)), amount);
+            assert_eq!(read(bit_count(*max)), amount);
+        }
+        // The scans sorted by id, then the scanned and total logbook entries
+        assert_eq!(read(1), 1);
+        assert_eq!(read(1), 0);
+        assert_eq!(read(9), 1);
+        assert_eq!(read(9), 1);
+
+        // CGameOptions
+        for (bits, value) in [
+            (32, 0),
+            (32, 0),
+            (2, 1),
+            (4, 4),
+            (6, 0),
+            (6, 0),
+            (5, 0),
+            (7, 0x7F),
+            (7, 0x7F),
+            (8, 0xFF),
+            (8, 0xFF),
+            (1, 1),
+            (1, 1),
+            (1, 0),
+            (1, 1),
+            (1, 0),
+        ] {
+            assert_eq!(read(bits), value);
+        }
+
+        // CHintOptions
+        for _ in 0..2 {
+            assert_eq!(read(2), 0);
+            assert_eq!(read(32), 0);
+        }
+
+        // CWorldState
+        assert_eq!(read(32), 1);
+        assert_eq!(read(32), start.mrea);
+        assert_eq!(read(2), 1);
+        assert_eq!(read(2), 1);
+        for visited in [1, 1, 0] {
+            assert_eq!(read(1), visited);
+        }
+        for mapped in [0, 1, 0] {
+            assert_eq!(read(1), mapped);
+        }
+        assert_eq!(read(1), 0);
+        assert_eq!(read(1), 0);
+        assert_eq!(read(10), 3);
+        for active in [0, 1, 0] {
+            assert_eq!(read(1), active);
+        }
+
+        // The rest of the slot is left clear
+        assert_eq!(bytes[pos / 8] & (0xFF >> (pos % 8)), 0);
+        assert!(bytes[pos / 8 + 1..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn card_checksum() {
+        assert_eq!(bit_count(0x100), 9);
+        // The standard CRC-32 check value, without the final inversion
+        assert_eq!(card_crc32(b"123456789"), !0xCBF43926);
+        assert_eq!(card_crc32(&[]), 0xFFFFFFFF);
+    }
+}
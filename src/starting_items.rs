@@ -147,6 +147,11 @@ impl StartingItems {
         spawn_point.ice_spreader = self.ice_spreader as u32;
         spawn_point.flamethrower = self.flamethrower as u32;
         spawn_point.unknown_item_1 = self.unknown_item_1 as u32;
+        spawn_point.unknown_item_2 = self.custom_items();
+    }
+
+    /// The custom items packed into the bits of Unknown Item 2
+    pub fn custom_items(&self) -> u32 {
         let mut unknown_item_2 = 0;
         if self.unlimited_missiles {
             unknown_item_2 |= PickupType::UnlimitedMissiles.custom_item_value();
@@ -163,7 +168,7 @@ impl StartingItems {
         if self.spring_ball {
             unknown_item_2 |= PickupType::SpringBall.custom_item_value();
         }
-        spawn_point.unknown_item_2 = unknown_item_2 as u32;
+        unknown_item_2 as u32
     }
 
     /// Custom deserializataion function that accepts an int as well as the usual struct/object
@@ -0,0 +1,400 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// A file of strings keyed by the asset id and index of each string in its STRG.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TranslationFormat {
+    Po,
+    Csv,
+}
+
+impl TranslationFormat {
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".csv") {
+            TranslationFormat::Csv
+        } else {
+            TranslationFormat::Po
+        }
+    }
+}
+
+/// One string of a STRG, with its text in every language the STRG has.
+#[derive(Clone, Debug)]
+pub struct StrgString {
+    pub asset_id: u32,
+    pub index: usize,
+    pub pak_name: String,
+    pub texts: Vec<(String, String)>, // (language, text)
+}
+
+impl StrgString {
+    fn key(&self) -> String {
+        format!("{:08X}:{}", self.asset_id, self.index)
+    }
+
+    fn text(&self, lang: &str) -> Option<&str> {
+        self.texts
+            .iter()
+            .find(|(l, _)| l == lang)
+            .map(|(_, text)| &text[..])
+    }
+
+    // The text translators work from, English if there is any
+    fn source_text(&self) -> &str {
+        self.text("ENGL")
+            .or_else(|| self.texts.first().map(|(_, text)| &text[..]))
+            .unwrap_or("")
+    }
+}
+
+fn parse_key(key: &str) -> Result<(u32, usize), String> {
+    let (asset_id, index) = key
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid string key '{}', expected <asset id>:<index>", key))?;
+    let asset_id = u32::from_str_radix(asset_id.trim_start_matches("0x"), 16)
+        .map_err(|e| format!("Invalid asset id in string key '{}': {}", key, e))?;
+    let index = index
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid index in string key '{}': {}", key, e))?;
+    Ok((asset_id, index))
+}
+
+/*** gettext PO ***/
+
+fn po_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn po_unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('"') => unescaped.push('"'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(c) => return Err(format!("Unsupported escape sequence '\\{}'", c)),
+            None => return Err("Unterminated escape sequence".to_string()),
+        }
+    }
+    Ok(unescaped)
+}
+
+// Multi-line strings are split after each line break, as gettext tools do
+fn po_field(name: &str, text: &str) -> String {
+    if !text.trim_end_matches('\n').contains('\n') {
+        return format!("{} \"{}\"\n", name, po_escape(text));
+    }
+    let mut field = format!("{} \"\"\n", name);
+    for line in text.split_inclusive('\n') {
+        field += &format!("\"{}\"\n", po_escape(line));
+    }
+    field
+}
+
+/// Writes the strings as a PO template. The source text of each string is its English text, and
+/// `msgctxt` holds the key which the translation is applied to.
+pub fn write_po(strings: &[StrgString]) -> String {
+    let mut po = String::new();
+    po += "msgid \"\"\n";
+    po += "msgstr \"\"\n";
+    po += "\"Content-Type: text/plain; charset=UTF-8\\n\"\n";
+    for string in strings {
+        let source = string.source_text();
+        if source.is_empty() {
+            continue; // gettext reserves the empty msgid for the header
+        }
+        po += "\n";
+        po += &format!("#: {}\n", string.pak_name);
+        po += &po_field("msgctxt", &string.key());
+        po += &po_field("msgid", source);
+        po += "msgstr \"\"\n";
+    }
+    po
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum PoField {
+    Context,
+    Id,
+    Str,
+}
+
+#[derive(Default)]
+struct PoEntry {
+    context: Option<String>,
+    id: String,
+    string: String,
+    fuzzy: bool,
+}
+
+fn parse_po(contents: &str) -> Result<HashMap<(u32, usize), String>, String> {
+    let mut entries = vec![];
+    let mut entry = PoEntry::default();
+    let mut field = None;
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        let error = |e: String| format!("Line {}: {}", i + 1, e);
+
+        let (keyword, rest) = if let Some(rest) = line.strip_prefix("msgctxt ") {
+            (Some(PoField::Context), rest)
+        } else if let Some(rest) = line.strip_prefix("msgid ") {
+            (Some(PoField::Id), rest)
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            (Some(PoField::Str), rest)
+        } else if line.starts_with('"') {
+            (None, line)
+        } else {
+            // Comments come before the entry they belong to
+            if field == Some(PoField::Str) {
+                entries.push(std::mem::take(&mut entry));
+                field = None;
+            }
+            if line.starts_with("#,") && line.contains("fuzzy") {
+                entry.fuzzy = true;
+            }
+            continue;
+        };
+
+        let current = match keyword {
+            Some(keyword) => {
+                if field == Some(PoField::Str) && keyword != PoField::Str {
+                    entries.push(std::mem::take(&mut entry));
+                }
+                keyword
+            }
+            None => field.ok_or_else(|| error("String without a keyword".to_string()))?,
+        };
+        field = Some(current);
+
+        let quoted = rest
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .ok_or_else(|| error(format!("Expected a quoted string, found '{}'", rest)))?;
+        let text = po_unescape(quoted).map_err(error)?;
+        match current {
+            PoField::Context => entry
+                .context
+                .get_or_insert_with(String::new)
+                .push_str(&text),
+            PoField::Id => entry.id.push_str(&text),
+            PoField::Str => entry.string.push_str(&text),
+        }
+    }
+    entries.push(entry);
+
+    let mut translations = HashMap::new();
+    for entry in entries {
+        // Skip the header and entries that still need work
+        if entry.id.is_empty() || entry.string.is_empty() || entry.fuzzy {
+            continue;
+        }
+        let context = match entry.context {
+            Some(context) => context,
+            None => continue,
+        };
+        translations.insert(parse_key(&context)?, entry.string);
+    }
+    Ok(translations)
+}
+
+/*** CSV ***/
+
+fn csv_escape(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Writes the strings as CSV, with a column for every language found in the STRGs.
+pub fn write_csv(strings: &[StrgString]) -> String {
+    let langs: BTreeSet<&str> = strings
+        .iter()
+        .flat_map(|string| string.texts.iter().map(|(lang, _)| &lang[..]))
+        .collect();
+
+    let mut csv = String::from("id,index");
+    for lang in langs.iter() {
+        csv += ",";
+        csv += lang;
+    }
+    csv += "\n";
+    for string in strings {
+        csv += &format!("{:08X},{}", string.asset_id, string.index);
+        for lang in langs.iter() {
+            csv += ",";
+            csv += &csv_escape(string.text(lang).unwrap_or(""));
+        }
+        csv += "\n";
+    }
+    csv
+}
+
+// Returns each record along with the line it starts on, as quoted fields can span lines
+fn parse_csv_records(contents: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => (),
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    Ok(records)
+}
+
+fn parse_csv(contents: &str, lang: &str) -> Result<HashMap<(u32, usize), String>, String> {
+    let records = parse_csv_records(contents)?;
+    let (_, header) = records
+        .first()
+        .ok_or_else(|| "The file is empty".to_string())?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Missing column '{}'", name))
+    };
+    let id_column = column("id")?;
+    let index_column = column("index")?;
+    let lang_column = column(lang)?;
+
+    let mut translations = HashMap::new();
+    for (line, record) in records.iter().skip(1) {
+        if record.iter().all(|field| field.is_empty()) {
+            continue;
+        }
+        let field = |column: usize| record.get(column).map(|f| &f[..]).unwrap_or("");
+        let text = field(lang_column);
+        if text.is_empty() {
+            continue;
+        }
+        let key = format!("{}:{}", field(id_column), field(index_column));
+        let key = parse_key(&key).map_err(|e| format!("Line {}: {}", line, e))?;
+        translations.insert(key, text.to_string());
+    }
+    Ok(translations)
+}
+
+/// Reads the translated strings for `lang` from a PO or CSV file. Strings without a translation
+/// are left out.
+pub fn parse_translations(
+    contents: &str,
+    format: TranslationFormat,
+    lang: &str,
+) -> Result<HashMap<(u32, usize), String>, String> {
+    match format {
+        TranslationFormat::Po => parse_po(contents),
+        TranslationFormat::Csv => parse_csv(contents, lang),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRICKY: &str = "Say \"hi\", \\o/\n\tthen, leave\r\n";
+
+    fn string(index: usize, texts: &[(&str, &str)]) -> StrgString {
+        StrgString {
+            asset_id: 0x1234ABCD,
+            index,
+            pak_name: "Metroid2.pak".to_string(),
+            texts: texts
+                .iter()
+                .map(|(lang, text)| (lang.to_string(), text.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn po_round_trip() {
+        let strings = [string(3, &[("ENGL", TRICKY)])];
+        let po = write_po(&strings);
+        assert!(po.contains("\"Say \\\"hi\\\", \\\\o/\\n\"\n\"\\tthen, leave\\r\\n\"\n"));
+        // Nothing is translated yet
+        assert!(parse_po(&po).unwrap().is_empty());
+
+        let translated = po.strip_suffix("msgstr \"\"\n").unwrap().to_string()
+            + &po_field("msgstr", "Dis \"salut\"\n");
+        let translations = parse_translations(&translated, TranslationFormat::Po, "FREN").unwrap();
+        assert_eq!(translations.len(), 1);
+        assert_eq!(translations[&(0x1234ABCD, 3)], "Dis \"salut\"\n");
+
+        assert!(po_unescape("\\q").is_err());
+        assert!(parse_po("msgid \"unterminated").is_err());
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let strings = [
+            string(0, &[("ENGL", "Plain"), ("FREN", TRICKY)]),
+            string(1, &[("ENGL", "No translation"), ("FREN", "")]),
+        ];
+        let csv = write_csv(&strings);
+        assert!(csv.starts_with("id,index,ENGL,FREN\n1234ABCD,0,Plain,\"Say \"\"hi\"\", "));
+
+        let translations = parse_translations(&csv, TranslationFormat::Csv, "FREN").unwrap();
+        assert_eq!(translations.len(), 1);
+        assert_eq!(translations[&(0x1234ABCD, 0)], TRICKY);
+
+        // Errors point at the line of the record, past any multi-line fields
+        let bad = format!("{}nothex,0,,Oops\n", csv);
+        let e = parse_translations(&bad, TranslationFormat::Csv, "FREN").unwrap_err();
+        assert!(e.starts_with("Line 6:"), "{}", e);
+        assert!(parse_csv("id,index\n1,0\n", "FREN").is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet};
+
+use structs::{Font, MarkupTag, TextPaneWidget};
+
+/// Where a string from the config ends up being displayed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextContext {
+    HudMemo,
+    Scan,
+    MainMenu,
+    // Anything else, only checked for characters which no font can draw
+    Other,
+}
+
+struct FontMetrics {
+    line_height: u32,
+    line_margin: u32,
+    advances: HashMap<u16, u32>,
+    kernings: HashMap<(u16, u16), i32>,
+}
+
+impl FontMetrics {
+    fn new(font: &Font) -> Self {
+        FontMetrics {
+            line_height: font.line_height,
+            line_margin: font.line_margin,
+            advances: font
+                .glyphs
+                .iter()
+                .map(|g| {
+                    let advance = g.left_padding + g.print_head_advance + g.right_padding;
+                    (g.utf16_char, advance)
+                })
+                .collect(),
+            kernings: font
+                .kernings
+                .iter()
+                .map(|k| ((k.char1, k.char2), k.kerning_adjust))
+                .collect(),
+        }
+    }
+}
+
+struct TextPaneBounds {
+    font: u32,
+    width: f32,
+    height: f32,
+    word_wrap: bool,
+    // Text that doesn't fit vertically can be scrolled through
+    scrolls: bool,
+}
+
+// Font state carried along while measuring a string, since markup can switch fonts midway
+#[derive(Clone)]
+struct MeasureState {
+    fonts: Vec<u32>,
+    prev: Option<u16>,
+}
+
+/// Checks strings against the glyphs of the fonts they are drawn with and the size of the text
+/// panes they are displayed in, and wraps lines which are too long to fit.
+#[derive(Default)]
+pub struct TextValidator {
+    fonts: HashMap<u32, FontMetrics>,
+    panes: HashMap<TextContext, TextPaneBounds>,
+}
+
+impl TextValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_font(&mut self, font_id: u32, font: &Font) {
+        self.fonts.insert(font_id, FontMetrics::new(font));
+    }
+
+    pub fn add_text_pane(&mut self, context: TextContext, pane: &TextPaneWidget, scrolls: bool) {
+        self.panes.insert(
+            context,
+            TextPaneBounds {
+                font: pane.font.to_u32(),
+                width: pane.block_extent[0],
+                height: pane.block_extent[1],
+                word_wrap: pane.word_wrap != 0,
+                scrolls,
+            },
+        );
+    }
+
+    fn pane(&self, context: TextContext) -> Option<&TextPaneBounds> {
+        self.panes
+            .get(&context)
+            .filter(|pane| self.fonts.contains_key(&pane.font))
+    }
+
+    fn initial_state(&self, context: TextContext) -> MeasureState {
+        MeasureState {
+            fonts: self
+                .pane(context)
+                .map(|pane| pane.font)
+                .into_iter()
+                .collect(),
+            prev: None,
+        }
+    }
+
+    // Measures the width of `text`, which must not contain line breaks, collecting the characters
+    // which the current font can't draw. Markup is skipped, except for font changes.
+    fn measure(&self, state: &mut MeasureState, text: &str, missing: &mut Vec<char>) -> f32 {
+        let mut width = 0i64;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            // `&&` is an escaped `&`, which is drawn like any other character
+            if c == '&' && chars.next_if_eq(&'&').is_none() {
+                let tag: String = chars.by_ref().take_while(|c| *c != ';').collect();
+                match MarkupTag::parse(&tag) {
+                    Ok(MarkupTag::Font(font)) => {
+                        state.fonts.pop();
+                        // An unknown font makes the rest of the string fall back to any font
+                        state.fonts.push(font);
+                    }
+                    Ok(MarkupTag::Push) => state.fonts.extend(state.fonts.last().copied()),
+                    Ok(MarkupTag::Pop) if state.fonts.len() > 1 => {
+                        state.fonts.pop();
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+            if c == '\0' {
+                continue;
+            }
+
+            let utf16_char = match u16::try_from(c as u32) {
+                Ok(utf16_char) => utf16_char,
+                Err(_) => {
+                    missing.push(c);
+                    continue;
+                }
+            };
+            let font = match state.fonts.last().and_then(|font| self.fonts.get(font)) {
+                Some(font) => font,
+                None => {
+                    // Without knowing the font, at least make sure that some font has the glyph
+                    if !self.fonts.is_empty()
+                        && !self
+                            .fonts
+                            .values()
+                            .any(|font| font.advances.contains_key(&utf16_char))
+                    {
+                        missing.push(c);
+                    }
+                    continue;
+                }
+            };
+            match font.advances.get(&utf16_char) {
+                Some(advance) => width += *advance as i64,
+                None => missing.push(c),
+            }
+            if let Some(prev) = state.prev {
+                width += *font.kernings.get(&(prev, utf16_char)).unwrap_or(&0) as i64;
+            }
+            state.prev = Some(utf16_char);
+        }
+        width.max(0) as f32
+    }
+
+    fn line_spacing(&self, state: &MeasureState) -> Option<(u32, u32)> {
+        let font = self.fonts.get(state.fonts.last()?)?;
+        Some((font.line_height, font.line_margin))
+    }
+
+    // Splits `line` at spaces so that each piece fits in `width`. Words which are wider than
+    // `width` on their own are left as is.
+    fn wrap_line(&self, state: &mut MeasureState, line: &str, width: f32) -> Vec<String> {
+        let mut lines = vec![];
+        let mut current = String::new();
+        let mut current_width = 0.0;
+        for word in line.split(' ') {
+            let mut word_state = state.clone();
+            let word_width = if current.is_empty() {
+                self.measure(&mut word_state, word, &mut vec![])
+            } else {
+                self.measure(&mut word_state, &format!(" {}", word), &mut vec![])
+            };
+            if !current.is_empty() && current_width + word_width > width {
+                lines.push(current);
+                current = String::new();
+                state.prev = None;
+                word_state = state.clone();
+                current_width = self.measure(&mut word_state, word, &mut vec![]);
+            } else {
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current_width += word_width;
+            }
+            current.push_str(word);
+            *state = word_state;
+        }
+        lines.push(current);
+        lines
+    }
+
+    /// Returns a description of every problem found with `text` when displayed in `context`.
+    pub fn validate(&self, context: TextContext, text: &str) -> Vec<String> {
+        let mut problems = vec![];
+        let mut state = self.initial_state(context);
+        let pane = self.pane(context);
+
+        let mut missing = vec![];
+        let mut line_count = 0;
+        for (i, line) in text.split('\n').enumerate() {
+            state.prev = None;
+            let line_start = state.clone();
+            let width = self.measure(&mut state, line, &mut missing);
+
+            let pane = match pane {
+                Some(pane) => pane,
+                None => continue,
+            };
+            if pane.word_wrap {
+                let mut wrap_state = line_start;
+                line_count += self.wrap_line(&mut wrap_state, line, pane.width).len();
+            } else {
+                line_count += 1;
+                if width > pane.width {
+                    problems.push(format!(
+                        "line {} is {} pixels wide, but only {} fit in the text pane",
+                        i + 1,
+                        width,
+                        pane.width
+                    ));
+                }
+            }
+        }
+
+        if let Some(pane) = pane.filter(|pane| !pane.scrolls) {
+            if let Some((line_height, line_margin)) = self.line_spacing(&state) {
+                let height = line_count as u32 * line_height
+                    + line_count.saturating_sub(1) as u32 * line_margin;
+                if height as f32 > pane.height {
+                    problems.push(format!(
+                        "the text is {} lines tall, which doesn't fit in the text pane",
+                        line_count
+                    ));
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        missing.retain(|c| seen.insert(*c));
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing
+                .iter()
+                .map(|c| format!("'{}' (U+{:04X})", c, *c as u32))
+                .collect();
+            problems.push(format!(
+                "the font can't display the characters {}",
+                missing.join(", ")
+            ));
+        }
+
+        problems
+    }
+
+    /// Inserts line breaks into the lines of `text` which are too wide for the text pane of
+    /// `context`. Returns `None` if nothing needed to be wrapped.
+    pub fn wrap(&self, context: TextContext, text: &str) -> Option<String> {
+        let pane = self.pane(context).filter(|pane| !pane.word_wrap)?;
+        let mut state = self.initial_state(context);
+        let mut wrapped = false;
+        let mut lines = vec![];
+        for line in text.split('\n') {
+            state.prev = None;
+            let wrapped_line = self.wrap_line(&mut state, line, pane.width);
+            wrapped |= wrapped_line.len() > 1;
+            lines.extend(wrapped_line);
+        }
+        if wrapped {
+            Some(lines.join("\n"))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_escaped_ampersand() {
+        let mut validator = TextValidator::new();
+        validator.fonts.insert(
+            1,
+            FontMetrics {
+                line_height: 10,
+                line_margin: 0,
+                advances: [('a' as u16, 3), ('&' as u16, 5)].into_iter().collect(),
+                kernings: HashMap::new(),
+            },
+        );
+        let measure = |text: &str, missing: &mut Vec<char>| {
+            let mut state = MeasureState {
+                fonts: vec![1],
+                prev: None,
+            };
+            validator.measure(&mut state, text, missing)
+        };
+
+        let mut missing = vec![];
+        assert_eq!(measure("a&push;a&pop;", &mut missing), 6.0);
+        assert_eq!(measure("a&&a", &mut missing), 11.0);
+        assert_eq!(measure("&&&&", &mut missing), 10.0);
+        assert!(missing.is_empty());
+    }
+}
@@ -0,0 +1,754 @@
+// THP video encoder, used to replace the attract mode and menu videos.
+// Frames are stored as baseline 4:2:0 JPEGs. Unlike regular JPEG, THP entropy data is not byte
+// stuffed, so this carries its own small JPEG encoder rather than using an external one.
+
+// The DCT and block loops are easier to check against the spec with explicit indices
+#![allow(clippy::needless_range_loop)]
+
+use std::{
+    f32::consts::PI,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use image::RgbaImage;
+use reader_writer::{Readable, Reader, RoArray, Writable};
+use structs::{Thp, ThpAudioInfo, ThpComponent, ThpComponents, ThpFrameData, ThpVideoInfo};
+
+use crate::dsp_encoder::{correlate_coefs, encode_adpcm, read_audio_file, PcmAudio};
+
+/// THP files play at 29.97 frames per second, expressed as a fraction to split audio exactly.
+const FPS_NUMERATOR: u64 = 2997;
+const FPS_DENOMINATOR: u64 = 100;
+
+pub const DEFAULT_QUALITY: u8 = 85;
+
+const AUDIO_HEADER_SIZE: usize = 0x50;
+
+static VIDEO_COMPONENT_TYPES: [u8; 16] = [
+    0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+static VIDEO_AUDIO_COMPONENT_TYPES: [u8; 16] = [
+    0, 1, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+// Example tables from Annex K of the JPEG spec, in natural order
+const LUMINANCE_QUANT: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+const CHROMINANCE_QUANT: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+const DC_LUMINANCE_BITS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const DC_CHROMINANCE_BITS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+const AC_LUMINANCE_BITS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d];
+const AC_LUMINANCE_VALUES: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52, 0xd1, 0xf0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+    0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5,
+    0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2,
+    0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa,
+];
+
+const AC_CHROMINANCE_BITS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const AC_CHROMINANCE_VALUES: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33, 0x52, 0xf0,
+    0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26,
+    0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5,
+    0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3,
+    0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda,
+    0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8,
+    0xf9, 0xfa,
+];
+
+/// Code and length of each symbol of a Huffman table, indexed by symbol.
+struct HuffmanTable {
+    codes: [(u16, u8); 256],
+}
+
+impl HuffmanTable {
+    fn new(bits: &[u8; 16], values: &[u8]) -> Self {
+        let mut codes = [(0, 0); 256];
+        let mut code = 0u16;
+        let mut k = 0;
+        for len in 1..=16 {
+            for _ in 0..bits[len - 1] {
+                codes[values[k] as usize] = (code, len as u8);
+                code += 1;
+                k += 1;
+            }
+            code <<= 1;
+        }
+        HuffmanTable { codes }
+    }
+}
+
+struct BitWriter {
+    data: Vec<u8>,
+    acc: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new(data: Vec<u8>) -> Self {
+        BitWriter {
+            data,
+            acc: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, bits: u16, len: u8) {
+        self.acc = (self.acc << len) | (bits as u32 & ((1 << len) - 1));
+        self.count += len as u32;
+        while self.count >= 8 {
+            self.count -= 8;
+            // THP decoders don't expect 0xFF bytes to be followed by a stuffed 0x00
+            self.data.push((self.acc >> self.count) as u8);
+        }
+    }
+
+    fn write_symbol(&mut self, table: &HuffmanTable, symbol: u8) {
+        let (code, len) = table.codes[symbol as usize];
+        self.write(code, len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            let pad = 8 - self.count as u8;
+            self.write(0xFF, pad);
+        }
+        self.data
+    }
+}
+
+fn scale_quant_table(table: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = quality.clamp(1, 100) as u32;
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    let mut scaled = [0; 64];
+    for i in 0..64 {
+        scaled[i] = ((table[i] as u32 * scale + 50) / 100).clamp(1, 255) as u16;
+    }
+    scaled
+}
+
+fn forward_dct(block: &[f32; 64]) -> [f32; 64] {
+    let mut cos = [[0f32; 8]; 8];
+    for u in 0..8 {
+        let c = if u == 0 { 0.5 / 2f32.sqrt() } else { 0.5 };
+        for x in 0..8 {
+            cos[u][x] = c * ((2 * x + 1) as f32 * u as f32 * PI / 16.0).cos();
+        }
+    }
+
+    let mut rows = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            rows[y * 8 + u] = (0..8).map(|x| block[y * 8 + x] * cos[u][x]).sum();
+        }
+    }
+    let mut out = [0f32; 64];
+    for u in 0..8 {
+        for v in 0..8 {
+            out[v * 8 + u] = (0..8).map(|y| rows[y * 8 + u] * cos[v][y]).sum();
+        }
+    }
+    out
+}
+
+fn bit_size(value: i32) -> u8 {
+    (32 - value.unsigned_abs().leading_zeros()) as u8
+}
+
+fn write_value(writer: &mut BitWriter, value: i32, size: u8) {
+    let bits = if value < 0 { value - 1 } else { value };
+    writer.write(bits as u16, size);
+}
+
+fn encode_block(
+    writer: &mut BitWriter,
+    block: &[f32; 64],
+    quant: &[u16; 64],
+    dc_table: &HuffmanTable,
+    ac_table: &HuffmanTable,
+    prev_dc: &mut i32,
+) {
+    let coefs = forward_dct(block);
+    let mut quantized = [0i32; 64];
+    for k in 0..64 {
+        let i = ZIGZAG[k];
+        quantized[k] = (coefs[i] / quant[i] as f32).round() as i32;
+    }
+
+    let diff = quantized[0] - *prev_dc;
+    *prev_dc = quantized[0];
+    let size = bit_size(diff);
+    writer.write_symbol(dc_table, size);
+    write_value(writer, diff, size);
+
+    let mut run = 0;
+    for &value in &quantized[1..] {
+        if value == 0 {
+            run += 1;
+            continue;
+        }
+        while run > 15 {
+            writer.write_symbol(ac_table, 0xF0);
+            run -= 16;
+        }
+        let size = bit_size(value);
+        writer.write_symbol(ac_table, (run << 4) | size);
+        write_value(writer, value, size);
+        run = 0;
+    }
+    if run > 0 {
+        writer.write_symbol(ac_table, 0x00);
+    }
+}
+
+fn write_segment(data: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    data.extend_from_slice(&[0xFF, marker]);
+    data.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    data.extend_from_slice(payload);
+}
+
+fn write_huffman_table(data: &mut Vec<u8>, class_and_id: u8, bits: &[u8; 16], values: &[u8]) {
+    let mut payload = vec![class_and_id];
+    payload.extend_from_slice(bits);
+    payload.extend_from_slice(values);
+    write_segment(data, 0xC4, &payload);
+}
+
+/// Encodes an RGBA image as a baseline 4:2:0 JPEG in the form expected by THP. Both dimensions
+/// must be multiples of 16.
+pub fn encode_jpeg(rgba: &[u8], width: u32, height: u32, quality: u8) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let luma_quant = scale_quant_table(&LUMINANCE_QUANT, quality);
+    let chroma_quant = scale_quant_table(&CHROMINANCE_QUANT, quality);
+
+    let mut data = vec![0xFF, 0xD8];
+    for (id, quant) in [(0u8, &luma_quant), (1, &chroma_quant)] {
+        let mut payload = vec![id];
+        payload.extend(ZIGZAG.iter().map(|&i| quant[i] as u8));
+        write_segment(&mut data, 0xDB, &payload);
+    }
+    let mut frame_header = vec![8];
+    frame_header.extend_from_slice(&(height as u16).to_be_bytes());
+    frame_header.extend_from_slice(&(width as u16).to_be_bytes());
+    frame_header.extend_from_slice(&[3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    write_segment(&mut data, 0xC0, &frame_header);
+    write_huffman_table(&mut data, 0x00, &DC_LUMINANCE_BITS, &DC_VALUES);
+    write_huffman_table(&mut data, 0x10, &AC_LUMINANCE_BITS, &AC_LUMINANCE_VALUES);
+    write_huffman_table(&mut data, 0x01, &DC_CHROMINANCE_BITS, &DC_VALUES);
+    write_huffman_table(
+        &mut data,
+        0x11,
+        &AC_CHROMINANCE_BITS,
+        &AC_CHROMINANCE_VALUES,
+    );
+    write_segment(&mut data, 0xDA, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let dc_luma = HuffmanTable::new(&DC_LUMINANCE_BITS, &DC_VALUES);
+    let ac_luma = HuffmanTable::new(&AC_LUMINANCE_BITS, &AC_LUMINANCE_VALUES);
+    let dc_chroma = HuffmanTable::new(&DC_CHROMINANCE_BITS, &DC_VALUES);
+    let ac_chroma = HuffmanTable::new(&AC_CHROMINANCE_BITS, &AC_CHROMINANCE_VALUES);
+
+    let mut y_plane = vec![0f32; width * height];
+    let mut cb_plane = vec![0f32; width * height];
+    let mut cr_plane = vec![0f32; width * height];
+    for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+        let (r, g, b) = (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+        y_plane[i] = 0.299 * r + 0.587 * g + 0.114 * b - 128.0;
+        cb_plane[i] = -0.168736 * r - 0.331264 * g + 0.5 * b;
+        cr_plane[i] = 0.5 * r - 0.418688 * g - 0.081312 * b;
+    }
+
+    let mut writer = BitWriter::new(data);
+    let mut prev_dc = [0i32; 3];
+    for mcu_y in (0..height).step_by(16) {
+        for mcu_x in (0..width).step_by(16) {
+            for (block_x, block_y) in [(0, 0), (8, 0), (0, 8), (8, 8)] {
+                let mut block = [0f32; 64];
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let i = (mcu_y + block_y + y) * width + mcu_x + block_x + x;
+                        block[y * 8 + x] = y_plane[i];
+                    }
+                }
+                encode_block(
+                    &mut writer,
+                    &block,
+                    &luma_quant,
+                    &dc_luma,
+                    &ac_luma,
+                    &mut prev_dc[0],
+                );
+            }
+            for (plane, dc) in [(&cb_plane, 1), (&cr_plane, 2)] {
+                let mut block = [0f32; 64];
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let i = (mcu_y + y * 2) * width + mcu_x + x * 2;
+                        block[y * 8 + x] =
+                            (plane[i] + plane[i + 1] + plane[i + width] + plane[i + width + 1])
+                                / 4.0;
+                    }
+                }
+                encode_block(
+                    &mut writer,
+                    &block,
+                    &chroma_quant,
+                    &dc_chroma,
+                    &ac_chroma,
+                    &mut prev_dc[dc],
+                );
+            }
+        }
+    }
+
+    let mut data = writer.finish();
+    data.extend_from_slice(&[0xFF, 0xD9]);
+    data
+}
+
+/// Splits a Motion JPEG stream into its individual JPEG images.
+fn split_mjpeg(data: &[u8]) -> Vec<&[u8]> {
+    let mut images = vec![];
+    let mut pos = 0;
+    'images: while pos + 4 <= data.len() {
+        if data[pos..pos + 3] != [0xFF, 0xD8, 0xFF] {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        pos += 2;
+        // Skip over the marker segments, whose payloads may contain thumbnails, then scan the
+        // entropy coded data for the end of image marker
+        let mut in_scan = false;
+        while pos + 2 <= data.len() {
+            if data[pos] != 0xFF {
+                pos += 1;
+                continue;
+            }
+            let marker = data[pos + 1];
+            match marker {
+                0xD9 => {
+                    pos += 2;
+                    images.push(&data[start..pos]);
+                    continue 'images;
+                }
+                0x00 | 0xFF | 0xD0..=0xD7 => pos += if marker == 0xFF { 1 } else { 2 },
+                _ if in_scan && marker == 0xD8 => break,
+                _ => {
+                    if pos + 4 > data.len() {
+                        break;
+                    }
+                    let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+                    pos += 2 + len;
+                    in_scan = marker == 0xDA;
+                }
+            }
+        }
+        // Truncated image, look for the next one
+        pos = start + 2;
+    }
+    images
+}
+
+fn is_frame_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ["png", "jpg", "jpeg"].contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Calls `f` with each frame of a video, read from either a directory of PNG or JPEG images
+/// sorted by name, or a Motion JPEG file.
+pub fn for_each_frame<F>(path: &Path, mut f: F) -> Result<(), String>
+where
+    F: FnMut(RgbaImage) -> Result<(), String>,
+{
+    if path.is_dir() {
+        let mut paths: Vec<PathBuf> = fs::read_dir(path)
+            .map_err(|e| format!("Failed to read directory '{}': {}", path.display(), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_frame_image(path))
+            .collect();
+        if paths.is_empty() {
+            return Err(format!(
+                "No PNG or JPEG frames found in '{}'",
+                path.display()
+            ));
+        }
+        paths.sort();
+        for path in paths {
+            let image = image::open(&path)
+                .map_err(|e| format!("Failed to read frame '{}': {}", path.display(), e))?;
+            f(image.to_rgba8())?;
+        }
+    } else {
+        let data = fs::read(path)
+            .map_err(|e| format!("Failed to read video '{}': {}", path.display(), e))?;
+        let images = split_mjpeg(&data);
+        if images.is_empty() {
+            return Err(format!("No JPEG frames found in '{}'", path.display()));
+        }
+        for (i, jpeg) in images.into_iter().enumerate() {
+            let image = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)
+                .map_err(|e| {
+                    format!(
+                        "Failed to decode frame {} of '{}': {}",
+                        i,
+                        path.display(),
+                        e
+                    )
+                })?;
+            f(image.to_rgba8())?;
+        }
+    }
+    Ok(())
+}
+
+/// The audio of one video frame, as a THP audio block.
+#[derive(Clone, Debug)]
+pub struct ThpAudioFrame {
+    pub sample_count: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ThpAudio {
+    pub channels: u32,
+    pub frequency: u32,
+    pub frames: Vec<ThpAudioFrame>,
+}
+
+fn frame_sample_offset(frame: usize, frequency: u32) -> usize {
+    (frame as u64 * frequency as u64 * FPS_DENOMINATOR / FPS_NUMERATOR) as usize
+}
+
+/// Splits audio into one ADPCM block per video frame. Audio past the end of the video is
+/// dropped, and the last frames are padded with silence if the audio is shorter.
+pub fn encode_thp_audio(pcm: &PcmAudio, frame_count: usize) -> ThpAudio {
+    let coefs: Vec<_> = pcm.channels.iter().map(|c| correlate_coefs(c)).collect();
+    let mut history = vec![[0i16; 2]; pcm.channels.len()];
+    let mut frames = Vec::with_capacity(frame_count);
+    for i in 0..frame_count {
+        let start = frame_sample_offset(i, pcm.sample_rate);
+        let end = frame_sample_offset(i + 1, pcm.sample_rate);
+
+        let mut channel_data = vec![];
+        let mut channel_history = [[0i16; 2]; 2];
+        for (ch, samples) in pcm.channels.iter().enumerate() {
+            let mut frame_samples = samples
+                .get(start.min(samples.len())..end.min(samples.len()))
+                .unwrap_or(&[])
+                .to_vec();
+            frame_samples.resize(end - start, 0);
+            channel_history[ch] = history[ch];
+            let (data, _) = encode_adpcm(&frame_samples, &coefs[ch], &mut history[ch]);
+            channel_data.push(data);
+        }
+        let channel_size = channel_data[0].len().div_ceil(32) * 32;
+
+        let mut data = Vec::with_capacity(AUDIO_HEADER_SIZE + channel_size * channel_data.len());
+        data.extend_from_slice(&(channel_size as u32).to_be_bytes());
+        data.extend_from_slice(&((end - start) as u32).to_be_bytes());
+        for ch in 0..2 {
+            for coef in coefs.get(ch).unwrap_or(&[[0; 2]; 8]).iter().flatten() {
+                data.extend_from_slice(&coef.to_be_bytes());
+            }
+        }
+        for [older, last] in channel_history {
+            data.extend_from_slice(&last.to_be_bytes());
+            data.extend_from_slice(&older.to_be_bytes());
+        }
+        for mut channel in channel_data {
+            channel.resize(channel_size, 0);
+            data.extend_from_slice(&channel);
+        }
+        frames.push(ThpAudioFrame {
+            sample_count: (end - start) as u32,
+            data,
+        });
+    }
+    ThpAudio {
+        channels: pcm.channels.len() as u32,
+        frequency: pcm.sample_rate,
+        frames,
+    }
+}
+
+/// An encoded video, owning the frame data that a `Thp` borrows.
+#[derive(Clone, Debug)]
+pub struct ThpVideo {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<Vec<u8>>,
+    pub audio: Option<ThpAudio>,
+}
+
+fn read_bytes(bytes: &[u8]) -> RoArray<'_, u8> {
+    Reader::new(bytes).read((bytes.len(), ()))
+}
+
+impl ThpVideo {
+    pub fn build(&self) -> Thp<'_> {
+        let mut components = vec![ThpComponent {
+            video_info: Some(ThpVideoInfo {
+                width: self.width,
+                height: self.height,
+            }),
+            audio_info: None,
+        }];
+        if let Some(audio) = &self.audio {
+            components.push(ThpComponent {
+                video_info: None,
+                audio_info: Some(ThpAudioInfo {
+                    channels_count: audio.channels,
+                    frequency: audio.frequency,
+                    samples_count: audio.frames.iter().map(|f| f.sample_count).sum(),
+                }),
+            });
+        }
+
+        let frames: Vec<ThpFrameData> = self
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(i, jpeg)| {
+                let audio = self.audio.as_ref().map(|audio| &audio.frames[i]);
+                ThpFrameData {
+                    frame_size_next: 0,
+                    frame_size_prev: 0,
+                    video_size: jpeg.len() as u32,
+                    audio_size: audio.map(|a| a.data.len() as u32),
+                    video_data: read_bytes(jpeg),
+                    audio_data: audio.map(|a| read_bytes(&a.data)),
+                }
+            })
+            .collect();
+        let max_buffer_size = frames.iter().map(|f| f.size()).max().unwrap_or(0) as u32;
+        let max_audio_samples = self
+            .audio
+            .as_ref()
+            .and_then(|audio| audio.frames.iter().map(|f| f.sample_count).max())
+            .unwrap_or(0);
+
+        let mut thp = Thp {
+            max_buffer_size,
+            max_audio_samples,
+            components: ThpComponents {
+                component_count: components.len() as u32,
+                component_types: if self.audio.is_some() {
+                    read_bytes(&VIDEO_AUDIO_COMPONENT_TYPES)
+                } else {
+                    read_bytes(&VIDEO_COMPONENT_TYPES)
+                },
+                components: components.into(),
+            },
+            frames: frames.into(),
+        };
+        thp.update_sibling_frame_sizes();
+        thp
+    }
+}
+
+/// Encodes a video from a directory of frames or a Motion JPEG file, with optional WAV or FLAC
+/// audio, and returns the bytes of the THP file.
+pub fn encode_thp_file(
+    frames_path: &Path,
+    audio_path: Option<&Path>,
+    quality: Option<u8>,
+) -> Result<Vec<u8>, String> {
+    let quality = quality.unwrap_or(DEFAULT_QUALITY);
+    if !(1..=100).contains(&quality) {
+        return Err(format!(
+            "Video quality must be between 1 and 100, got {}",
+            quality
+        ));
+    }
+
+    let mut size = None;
+    let mut frames = vec![];
+    for_each_frame(frames_path, |image| {
+        let (width, height) = image.dimensions();
+        match size {
+            None => {
+                if width % 16 != 0 || height % 16 != 0 || width == 0 || height == 0 {
+                    return Err(format!(
+                        "Video frames must be a multiple of 16 pixels wide and tall, got {}x{}",
+                        width, height
+                    ));
+                }
+                size = Some((width, height));
+            }
+            Some(size) if size != (width, height) => {
+                return Err(format!(
+                    "Frame {} is {}x{}, but the first frame is {}x{}",
+                    frames.len(),
+                    width,
+                    height,
+                    size.0,
+                    size.1
+                ));
+            }
+            Some(_) => (),
+        }
+        // Keep the audio that follows each frame's video aligned
+        let mut jpeg = encode_jpeg(image.as_raw(), width, height, quality);
+        jpeg.resize(jpeg.len().div_ceil(32) * 32, 0);
+        frames.push(jpeg);
+        Ok(())
+    })?;
+    let (width, height) =
+        size.ok_or_else(|| format!("No frames found in '{}'", frames_path.display()))?;
+
+    let audio = match audio_path {
+        Some(path) => Some(encode_thp_audio(&read_audio_file(path)?, frames.len())),
+        None => None,
+    };
+
+    let video = ThpVideo {
+        width,
+        height,
+        frames,
+        audio,
+    };
+    let mut bytes = vec![];
+    video
+        .build()
+        .write_to(&mut bytes)
+        .map_err(|e| format!("Failed to write THP: {}", e))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use image::RgbaImage;
+    use reader_writer::Reader;
+    use structs::Thp;
+
+    use super::{encode_jpeg, encode_thp_file};
+
+    // Gradients with a sharp edge in the middle
+    fn test_image(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let edge = if x < width / 2 { 0 } else { 96 };
+            image::Rgba([(x * 4) as u8, (y * 8) as u8, edge, 255])
+        })
+    }
+
+    // Standard decoders expect 0xFF bytes in the entropy coded data to be followed by a stuffed
+    // 0x00, which THP leaves out
+    fn stuff_entropy_data(jpeg: &[u8]) -> Vec<u8> {
+        let sos = jpeg.windows(2).position(|w| w == [0xFF, 0xDA]).unwrap();
+        let scan_start = sos + 2 + u16::from_be_bytes([jpeg[sos + 2], jpeg[sos + 3]]) as usize;
+        // Frames are padded after the end of image marker
+        let scan_end = jpeg.windows(2).rposition(|w| w == [0xFF, 0xD9]).unwrap();
+        let mut stuffed = jpeg[..scan_start].to_vec();
+        for byte in jpeg[scan_start..scan_end].iter() {
+            stuffed.push(*byte);
+            if *byte == 0xFF {
+                stuffed.push(0);
+            }
+        }
+        stuffed.extend_from_slice(&[0xFF, 0xD9]);
+        stuffed
+    }
+
+    fn max_error(a: &RgbaImage, b: &RgbaImage) -> i32 {
+        a.as_raw()
+            .iter()
+            .zip(b.as_raw().iter())
+            .map(|(a, b)| (*a as i32 - *b as i32).abs())
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn jpeg_decodes() {
+        let image = test_image(32, 32);
+        let jpeg = encode_jpeg(image.as_raw(), 32, 32, 95);
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+        assert_eq!(&jpeg[jpeg.len() - 2..], &[0xFF, 0xD9]);
+
+        let decoded = image::load_from_memory_with_format(
+            &stuff_entropy_data(&jpeg),
+            image::ImageFormat::Jpeg,
+        )
+        .unwrap()
+        .to_rgba8();
+        assert_eq!(decoded.dimensions(), (32, 32));
+        assert!(max_error(&image, &decoded) <= 40);
+    }
+
+    #[test]
+    fn thp_from_frames() {
+        let dir = std::env::temp_dir().join(format!("randomprime-thp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = test_image(32, 16);
+        for i in 0..3 {
+            image.save(dir.join(format!("frame{}.png", i))).unwrap();
+        }
+        let thp = encode_thp_file(&dir, None, Some(90));
+        test_image(16, 16).save(dir.join("frame3.png")).unwrap();
+        let mismatched = encode_thp_file(&dir, None, Some(90));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(mismatched.is_err());
+
+        let thp = thp.unwrap();
+        let thp: Thp = Reader::new(&thp[..]).read(());
+        let video_info = thp
+            .components
+            .components
+            .iter()
+            .next()
+            .unwrap()
+            .video_info
+            .clone()
+            .unwrap();
+        assert_eq!((video_info.width, video_info.height), (32, 16));
+        assert_eq!(thp.frames.len(), 3);
+        for frame in thp.frames.iter() {
+            assert!(frame.audio_data.is_none());
+            let jpeg: Vec<u8> = frame.video_data.iter().collect();
+            let decoded = image::load_from_memory_with_format(
+                &stuff_entropy_data(&jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap()
+            .to_rgba8();
+            assert!(max_error(&image, &decoded) <= 40);
+        }
+    }
+}
@@ -1,7 +1,11 @@
 use std::convert::TryInto;
 
+use image::{ColorType, GenericImageView, ImageFormat};
 use resource_info_table::{resource_info, ResourceInfo};
-use texpresso::Format;
+use structs::{
+    box_filter_pixels, compress_dxt1gcn_block, decompress_dxt1gcn_block, Txtr, TxtrFormat,
+    TxtrPaletteFormat,
+};
 
 // 0 - Power
 // 1 - Gravity
@@ -151,6 +155,42 @@ pub const FUSION_PHAZON_SUIT_TEXTURES: &[ResourceInfo] = &[
     // Left arm SamusGun/FusionP.CMDL
 ];
 
+pub const VISOR_TEXTURES: &[ResourceInfo] = &[
+    // GUI_ART/scan_visor
+    resource_info!("panelAcopy.TXTR"),
+    resource_info!("eggscopy.TXTR"),
+    resource_info!("metroidcopy.TXTR"),
+    resource_info!("arm2copy.TXTR"),
+    // Thermal and X-Ray color ramps
+    resource_info!("TXTR_ThermoPalette.TXTR"),
+    resource_info!("TXTR_XRayPalette.TXTR"),
+];
+
+pub const DOOR_TEXTURES: &[ResourceInfo] = &[
+    // Shield colors (blue, purple, white, red)
+    resource_info!("blue.TXTR"),
+    resource_info!("F68DF7F1.TXTR"),
+    resource_info!("BE4CD99D.TXTR"),
+    resource_info!("FC095F6C.TXTR"),
+    // Holorims (blue, purple, white, red)
+    resource_info!("blueholorim_I.TXTR"),
+    resource_info!("AB031EA9.TXTR"),
+    resource_info!("F6870C9F.TXTR"),
+    resource_info!("61A6945B.TXTR"),
+    // Damageable trigger pattern
+    resource_info!("testb.TXTR"),
+];
+
+pub const HUD_TEXTURES: &[ResourceInfo] = &[
+    // GUI_ART/HUD16
+    resource_info!("NUMBER.TXTR"),
+    // GUI_ART/scan_hud
+    resource_info!("pzon.TXTR"),
+    resource_info!("bigtext.TXTR"),
+    resource_info!("pzonlevels.TXTR"),
+    resource_info!("pzonspread.TXTR"),
+];
+
 struct CmprPixelIter {
     cnt: usize,
     width: usize,
@@ -266,38 +306,273 @@ pub fn huerotate_in_place(image: &mut [u8], width: usize, height: usize, matrix:
     }
 }
 
-pub fn compress_dxt1gcn_block(rgba: [[u8; 4]; 16], block: &mut [u8]) {
-    Format::Bc1.compress_block_masked(
-        rgba,
-        0xFFFF,
-        texpresso::Params {
-            algorithm: texpresso::Algorithm::IterativeClusterFit,
-            ..Default::default()
-        },
-        block,
-    );
-    block.swap(0, 1);
-    block.swap(2, 3);
-    for byte in block[4..8].iter_mut() {
-        *byte = reverse_byte(*byte);
+/// A color adjustment which can be applied to textures of any format. Grayscale textures only
+/// have their brightness adjusted.
+#[derive(Clone, Copy, Debug)]
+pub struct ColorAdjustment {
+    pub hue_matrix: Option<[f32; 9]>,
+    pub saturation: f32,
+    pub brightness: f32,
+    pub tint: [f32; 3],
+}
+
+impl ColorAdjustment {
+    /// Builds an adjustment from the optional settings of the config, where missing settings
+    /// leave colors unchanged.
+    pub fn new(
+        hue_deg: Option<i16>,
+        saturation: Option<f32>,
+        brightness: Option<f32>,
+        tint: Option<[f32; 3]>,
+    ) -> Self {
+        ColorAdjustment {
+            hue_matrix: hue_deg
+                .map(|deg| deg % 360)
+                .filter(|deg| *deg != 0)
+                .map(|deg| huerotate_matrix(deg as f32)),
+            saturation: saturation.unwrap_or(1.0),
+            brightness: brightness.unwrap_or(1.0),
+            tint: tint.unwrap_or([1.0, 1.0, 1.0]),
+        }
+    }
+
+    pub fn apply_rgb(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let [r, g, b] = match self.hue_matrix {
+            Some(matrix) => huerotate_color(matrix, rgb[0], rgb[1], rgb[2]),
+            None => rgb,
+        };
+        let rgb = [r as f32, g as f32, b as f32];
+        let luma = 0.213 * rgb[0] + 0.715 * rgb[1] + 0.072 * rgb[2];
+
+        let mut out = [0u8; 3];
+        for i in 0..3 {
+            let c = luma + (rgb[i] - luma) * self.saturation;
+            out[i] = (c * self.brightness * self.tint[i]).clamp(0.0, 255.0) as u8;
+        }
+        out
+    }
+
+    /// Adjusts a color with 1.0 as full intensity, as used by particle effects. Channels are only
+    /// clamped at 0.0, since particle colors are allowed to go past 1.0.
+    pub fn apply_rgb_f32(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let rgb = match self.hue_matrix {
+            Some(m) => [
+                m[0] * rgb[0] + m[1] * rgb[1] + m[2] * rgb[2],
+                m[3] * rgb[0] + m[4] * rgb[1] + m[5] * rgb[2],
+                m[6] * rgb[0] + m[7] * rgb[1] + m[8] * rgb[2],
+            ],
+            None => rgb,
+        };
+        let luma = 0.213 * rgb[0] + 0.715 * rgb[1] + 0.072 * rgb[2];
+
+        let mut out = [0.0; 3];
+        for i in 0..3 {
+            let c = luma + (rgb[i] - luma) * self.saturation;
+            out[i] = (c * self.brightness * self.tint[i]).max(0.0);
+        }
+        out
+    }
+
+    pub fn apply_intensity(&self, i: u8) -> u8 {
+        (i as f32 * self.brightness).clamp(0.0, 255.0) as u8
+    }
+
+    /// Adjusts RGBA pixels. `color_type` is the native color type of the texture they were
+    /// decoded from.
+    pub fn apply_pixels(&self, pixels: &mut [u8], color_type: ColorType) {
+        let grayscale = matches!(color_type, ColorType::L8 | ColorType::La8);
+        for p in pixels.chunks_mut(4) {
+            let rgb = if grayscale {
+                let i = self.apply_intensity(p[0]);
+                [i, i, i]
+            } else {
+                self.apply_rgb([p[0], p[1], p[2]])
+            };
+            p[..3].copy_from_slice(&rgb[..]);
+        }
+    }
+}
+
+/// Adjusts the colors of a single mipmap in place. Returns false if the mipmap is too small to be
+/// decoded, in which case it is left untouched. Paletted formats are left untouched as well,
+/// see `recolor_palette`.
+pub fn recolor_mipmap(
+    format: &TxtrFormat,
+    mipmap_data: &mut [u8],
+    w: usize,
+    h: usize,
+    adjustment: &ColorAdjustment,
+) -> bool {
+    let (block_w, block_h) = format.block_dimensions();
+    if format.is_paletted() || w < block_w || h < block_h {
+        return false;
+    }
+
+    let mut pixels = format.decode_mipmap(mipmap_data, w, h);
+    adjustment.apply_pixels(&mut pixels[..], format.color_type());
+    mipmap_data.copy_from_slice(&format.encode_mipmap(&pixels[..], w, h)[..]);
+    true
+}
+
+/// Adjusts the colors of the palette of a C4 or C8 texture. Does nothing for other formats.
+pub fn recolor_palette(format: &mut TxtrFormat, adjustment: &ColorAdjustment) {
+    let (fmt, palette) = match format {
+        TxtrFormat::C4(fmt, palette) => (*fmt, &mut palette[..]),
+        TxtrFormat::C8(fmt, palette) => (*fmt, &mut palette[..]),
+        _ => return,
+    };
+    for entry in palette.chunks_mut(2) {
+        let mut color = fmt.decode((&*entry).try_into().unwrap());
+        adjustment.apply_pixels(&mut color[..], fmt.color_type());
+        entry.copy_from_slice(&fmt.encode(color)[..]);
     }
 }
 
-pub fn decompress_dxt1gcn_block(block: &[u8]) -> [[u8; 4]; 16] {
-    let mut compressed = [0u8; 8];
-    compressed[0] = block[1];
-    compressed[1] = block[0];
-    compressed[2] = block[3];
-    compressed[3] = block[2];
-    for (byte, val) in compressed[4..8].iter_mut().zip(block[4..8].iter()) {
-        *byte = reverse_byte(*val);
+/// Decodes a single mipmap of a TXTR into a row-major pixel buffer. The channel layout of the
+/// returned pixels is given by `txtr.format.color_type()`.
+pub fn decode_txtr_mipmap(txtr: &Txtr, mipmap: usize) -> Result<(Vec<u8>, usize, usize), String> {
+    let (rgba, w, h) = txtr.to_rgba(mipmap)?;
+    let pixels = match txtr.format.color_type() {
+        ColorType::L8 => rgba.chunks(4).map(|p| p[0]).collect(),
+        ColorType::La8 => rgba.chunks(4).flat_map(|p| [p[0], p[3]]).collect(),
+        ColorType::Rgb8 => rgba.chunks(4).flat_map(|p| [p[0], p[1], p[2]]).collect(),
+        _ => rgba,
+    };
+    Ok((pixels, w, h))
+}
+
+/// Encodes row-major RGBA pixels into a TXTR, generating `mipmap_count` mipmaps or as many as
+/// the image and format allow.
+pub fn encode_txtr(
+    format: TxtrFormat,
+    pixels: Vec<u8>,
+    w: usize,
+    h: usize,
+    mipmap_count: Option<u8>,
+) -> Result<Txtr<'static>, String> {
+    let (block_w, block_h) = format.block_dimensions();
+    if w % block_w != 0 || h % block_h != 0 {
+        Err(format!(
+            "The images width and height ({}, {}) must be a multiple of the chosen format's \
+             block dimensions ({}, {})",
+            w, h, block_w, block_h
+        ))?
+    }
+
+    let max_mipmaps_for_fomat = if format.is_paletted() {
+        1
+    } else {
+        let mut i = 1;
+        while (w >> i) % block_w == 0 && (h >> i) % block_h == 0 && (w >> i) > 0 && (h >> i) > 0 {
+            i += 1
+        }
+        i
+    };
+    let max_mipmaps = mipmap_count.unwrap_or(max_mipmaps_for_fomat);
+    if max_mipmaps > max_mipmaps_for_fomat {
+        Err(format!(
+            "Specified format supports a max of {} mipmaps for this image",
+            max_mipmaps_for_fomat
+        ))?
     }
-    Format::Bc1.decompress_block(&compressed)
+
+    let mut mipmaps = vec![(pixels, w, h)];
+    for i in 1..max_mipmaps as usize {
+        let (prev, prev_w, prev_h) = &mipmaps[i - 1];
+        let pixels = box_filter_pixels(
+            &prev[..],
+            *prev_w,
+            *prev_h,
+            4,
+            matches!(format, TxtrFormat::Cmpr),
+        );
+        mipmaps.push((pixels, w >> i, h >> i));
+    }
+
+    Txtr::from_rgba(format, mipmaps)
 }
 
-fn reverse_byte(byte: u8) -> u8 {
-    (byte & 0b00000011) << 6
-        | (byte & 0b00001100) << 2
-        | (byte & 0b00110000) >> 2
-        | (byte & 0b11000000) >> 6
+/// Decodes a PNG and encodes it as a TXTR.
+pub fn png_to_txtr(
+    png: &[u8],
+    format: TxtrFormat,
+    mipmap_count: Option<u8>,
+) -> Result<Txtr<'static>, String> {
+    let image = image::load_from_memory_with_format(png, ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+    let (w, h) = image.dimensions();
+    encode_txtr(
+        format,
+        image.to_rgba8().into_raw(),
+        w as usize,
+        h as usize,
+        mipmap_count,
+    )
+}
+
+pub trait TxtrFormatExt {
+    fn color_type(&self) -> ColorType;
+    fn from_str(s: &str) -> Result<TxtrFormat, String>;
+}
+
+impl TxtrFormatExt for TxtrFormat {
+    fn color_type(&self) -> ColorType {
+        match self {
+            TxtrFormat::I4 => ColorType::L8,
+            TxtrFormat::I8 => ColorType::L8,
+            TxtrFormat::Ia4 => ColorType::La8,
+            TxtrFormat::Ia8 => ColorType::La8,
+            TxtrFormat::C4(fmt, _) | TxtrFormat::C8(fmt, _) => fmt.color_type(),
+            TxtrFormat::Rgb565 => ColorType::Rgb8,
+            TxtrFormat::Rgb5A3 => ColorType::Rgba8,
+            TxtrFormat::Rgba8 => ColorType::Rgba8,
+            TxtrFormat::Cmpr => ColorType::Rgba8,
+        }
+    }
+
+    fn from_str(s: &str) -> Result<TxtrFormat, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "i4" => Ok(TxtrFormat::I4),
+            "i8" => Ok(TxtrFormat::I8),
+            "ia4" => Ok(TxtrFormat::Ia4),
+            "ia8" => Ok(TxtrFormat::Ia8),
+            "c4(ia8)" => Ok(TxtrFormat::C4(TxtrPaletteFormat::Ia8, Default::default())),
+            "c4(rgb565)" => Ok(TxtrFormat::C4(
+                TxtrPaletteFormat::Rgb565,
+                Default::default(),
+            )),
+            "c4(rgb5a3)" => Ok(TxtrFormat::C4(
+                TxtrPaletteFormat::Rgb5A3,
+                Default::default(),
+            )),
+            "c8(ia8)" => Ok(TxtrFormat::C8(TxtrPaletteFormat::Ia8, Default::default())),
+            "c8(rgb565)" => Ok(TxtrFormat::C8(
+                TxtrPaletteFormat::Rgb565,
+                Default::default(),
+            )),
+            "c8(rgb5a3)" => Ok(TxtrFormat::C8(
+                TxtrPaletteFormat::Rgb5A3,
+                Default::default(),
+            )),
+            "rgb565" => Ok(TxtrFormat::Rgb565),
+            "rgb5a3" => Ok(TxtrFormat::Rgb5A3),
+            "rgba8" => Ok(TxtrFormat::Rgba8),
+            "cmpr" => Ok(TxtrFormat::Cmpr),
+            _ => Err(format!("Unknown texture format \"{}\"", s)),
+        }
+    }
+}
+
+pub trait TxtrPaletteFormatExt {
+    fn color_type(&self) -> ColorType;
+}
+
+impl TxtrPaletteFormatExt for TxtrPaletteFormat {
+    fn color_type(&self) -> ColorType {
+        match self {
+            TxtrPaletteFormat::Ia8 => ColorType::La8,
+            TxtrPaletteFormat::Rgb565 => ColorType::Rgb8,
+            TxtrPaletteFormat::Rgb5A3 => ColorType::Rgba8,
+        }
+    }
 }
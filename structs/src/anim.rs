@@ -1,7 +1,9 @@
 use auto_struct_macros::auto_struct;
+use std::io;
+
 use reader_writer::{
     generic_array::{typenum::*, GenericArray},
-    Readable, Reader, RoArray,
+    Readable, Reader, RoArray, Writable,
 };
 
 use crate::{res_id::*, ResId};
@@ -18,7 +20,7 @@ impl<'r> Readable<'r> for Anim<'r> {
         let kind: u32 = reader.read(());
         let res = match kind {
             0 => Anim::Uncompressed(reader.read(())),
-            2 => Anim::Compressed(reader.read(())),
+            2 => Anim::Compressed(reader.read(reader.len())),
             i => panic!("Invalid ANIM kind {}", i),
         };
         res
@@ -33,20 +35,16 @@ impl<'r> Readable<'r> for Anim<'r> {
     }
 }
 
-/*
-impl<'r> Writable for Anim<'r>
-{
-    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<u64>
-    {
+impl<'r> Writable for Anim<'r> {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<u64> {
         Ok(match self {
             Anim::Uncompressed(anim) => 0u32.write_to(writer)? + anim.write_to(writer)?,
             Anim::Compressed(anim) => 2u32.write_to(writer)? + anim.write_to(writer)?,
         })
     }
 }
-*/
 
-#[auto_struct(Readable)]
+#[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct AnimUncompressed<'r> {
     duration: CharAnimTime,
@@ -77,9 +75,12 @@ pub struct AnimUncompressed<'r> {
     evnt: ResId<EVNT>,
 }
 
-#[auto_struct(Readable)]
+#[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct AnimCompressed<'r> {
+    #[auto_struct(args)]
+    size: usize,
+
     scratch_size: u32,
     evnt: ResId<EVNT>,
 
@@ -103,6 +104,17 @@ pub struct AnimCompressed<'r> {
     key_bitmap_array: RoArray<u32, 'r>,
 
     bone_channel_count_2: u32,
+
+    // The bone channel descriptors and the compressed keys aren't parsed yet, they are kept as is.
+    // Truncated data, which is shorter than the header, is read without them.
+    #[auto_struct(init = (
+        size
+            .checked_sub(52)
+            .and_then(|len| len.checked_sub(key_bitmap_array.len().checked_mul(4)?))
+            .unwrap_or(0),
+        (),
+    ))]
+    bone_channel_data: RoArray<'r, u8>,
     // #[auto_struct(derive = bone_channel_descriptor_array.len() as u32)]
     // bone_channel_descriptor_count: u32,
     // #[auto_struct(init = (bone_channel_descriptor_count as usize, ()))]
@@ -131,7 +143,7 @@ pub struct AnimCompressed<'r> {
 //     translation_bits_z: u8,
 // }
 
-#[auto_struct(Readable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize)]
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct CharAnimTime {
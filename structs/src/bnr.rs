@@ -4,6 +4,11 @@ use reader_writer::{
     FixedArray, FourCC, RoArray,
 };
 
+use crate::TxtrFormat;
+
+pub const BNR_IMAGE_WIDTH: usize = 96;
+pub const BNR_IMAGE_HEIGHT: usize = 32;
+
 #[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct Bnr<'r> {
@@ -20,6 +25,31 @@ pub struct Bnr<'r> {
     pub other_lang_fields: Option<FixedArray<BnrMetadata, U5>>,
 }
 
+impl<'r> Bnr<'r> {
+    /// Encodes a 96x32 RGBA image as the banner's RGB5A3 pixels. Unlike textures, banners are
+    /// stored top row first.
+    pub fn set_image(&mut self, pixels: &[u8]) {
+        let pixels = flip_rows(pixels, BNR_IMAGE_WIDTH);
+        self.pixels
+            .clone_from_slice(&TxtrFormat::Rgb5A3.encode_mipmap(
+                &pixels,
+                BNR_IMAGE_WIDTH,
+                BNR_IMAGE_HEIGHT,
+            ));
+    }
+
+    /// Decodes the banner's pixels into a 96x32 RGBA image.
+    pub fn image(&self) -> Vec<u8> {
+        let pixels =
+            TxtrFormat::Rgb5A3.decode_mipmap(&self.pixels[..], BNR_IMAGE_WIDTH, BNR_IMAGE_HEIGHT);
+        flip_rows(&pixels, BNR_IMAGE_WIDTH)
+    }
+}
+
+fn flip_rows(pixels: &[u8], w: usize) -> Vec<u8> {
+    pixels.chunks(w * 4).rev().flatten().copied().collect()
+}
+
 #[auto_struct(Readable, Writable, FixedSize)]
 #[derive(Debug, Clone)]
 pub struct BnrMetadata {
@@ -30,3 +60,28 @@ pub struct BnrMetadata {
     pub developer_full: FixedArray<u8, U64>, // 0x40
     pub description: FixedArray<u8, U128>,   // 0x80
 }
+
+#[test]
+fn test_bnr_image_round_trip() {
+    use reader_writer::Reader;
+
+    let mut bytes = b"BNR1".to_vec();
+    bytes.resize(4 + 0x1c + 0x1800 + 0x140, 0);
+    let mut bnr: Bnr = Reader::new(&bytes[..]).read(());
+
+    // Red on top, blue on the bottom
+    let pixels: Vec<u8> = (0..BNR_IMAGE_WIDTH * BNR_IMAGE_HEIGHT)
+        .flat_map(|i| {
+            if i / BNR_IMAGE_WIDTH < BNR_IMAGE_HEIGHT / 2 {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 255, 255]
+            }
+        })
+        .collect();
+    bnr.set_image(&pixels);
+
+    // The first 4x4 tile is the top left corner, as opaque RGB555 red
+    assert_eq!(&bnr.pixels[..2], &[0xFC, 0x00]);
+    assert_eq!(bnr.image(), pixels);
+}
@@ -1,12 +1,13 @@
 use auto_struct_macros::auto_struct;
 use reader_writer::{
-    generic_array::GenericArray, typenum::*, IteratorArray, LazyArray, RoArray, RoArrayIter,
+    generic_array::GenericArray, pad_bytes_count, typenum::*, IteratorArray, LCow, LazyArray,
+    Readable, Reader, RoArray, RoArrayIter, Writable,
 };
 
 use crate::{res_id::*, ResId};
 
-// We don't need to modify CMDLs, so most of the details are left out.
-// We only actually care about reading out the TXTR file ids.
+// Most users only need to swap out the TXTR ids, so the materials and geometry are kept as raw
+// bytes and only parsed on demand (see `CmdlMaterialSet::materials` and `Cmdl::geometry`).
 #[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct Cmdl<'r> {
@@ -20,14 +21,19 @@ pub struct Cmdl<'r> {
 
     pub maab: GenericArray<f32, U6>,
 
-    pub data_section_count: u32,
-    pub material_set_count: u32,
+    #[auto_struct(derive = (material_sets.len() + data_sections.len()) as u32)]
+    data_section_count: u32,
+    #[auto_struct(derive = material_sets.len() as u32)]
+    material_set_count: u32,
 
-    // TODO: Iter derive
+    #[auto_struct(derive_from_iter = material_sets.iter()
+            .map(&|i: LCow<CmdlMaterialSet>| i.size() as u32))]
     #[auto_struct(init = (material_set_count as usize, ()))]
-    pub material_set_sizes: RoArray<'r, u32>,
+    material_set_sizes: RoArray<'r, u32>,
+    #[auto_struct(derive_from_iter = data_sections.iter()
+            .map(&|i: LCow<CmdlDataSection>| i.size() as u32))]
     #[auto_struct(init = ((data_section_count - material_set_count) as usize, ()))]
-    pub data_section_sizes: RoArray<'r, u32>,
+    data_section_sizes: RoArray<'r, u32>,
 
     #[auto_struct(pad_align = 32)]
     _pad: (),
@@ -38,6 +44,9 @@ pub struct Cmdl<'r> {
     pub data_sections: IteratorArray<'r, CmdlDataSection<'r>, RoArrayIter<'r, u32>>,
 }
 
+pub const CMDL_FLAG_SHORT_NORMALS: u32 = 0x2;
+pub const CMDL_FLAG_SHORT_UVS: u32 = 0x4;
+
 #[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct CmdlMaterialSet<'r> {
@@ -50,7 +59,168 @@ pub struct CmdlMaterialSet<'r> {
     pub texture_ids: LazyArray<'r, ResId<TXTR>>,
 
     #[auto_struct(init = (size as usize - 4 - texture_ids.size(), ()))]
-    pub remainder: RoArray<'r, u8>,
+    pub remainder: LazyArray<'r, u8>,
+}
+
+impl<'r> CmdlMaterialSet<'r> {
+    /// Parses the materials which follow the texture list.
+    pub fn materials<'s>(&'s self) -> Result<Vec<CmdlMaterial<'s>>, String> {
+        read_materials(lazy_array_reader(&self.remainder))
+    }
+
+    /// Replaces the materials of the set. The section is padded to 32 bytes.
+    pub fn set_materials(&mut self, materials: Vec<CmdlMaterial>) {
+        let materials = CmdlMaterials {
+            materials: materials.into(),
+        };
+        let mut bytes = vec![];
+        materials.write_to(&mut bytes).unwrap();
+        let len = 4 + self.texture_ids.size() + bytes.len();
+        bytes.extend(reader_writer::pad_bytes(32, len).iter());
+        self.remainder = bytes.into();
+    }
+}
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+struct CmdlMaterials<'r> {
+    #[auto_struct(derive = materials.len() as u32)]
+    material_count: u32,
+    #[auto_struct(derive_from_iter = materials.iter()
+        .scan(0u32, &|end: &mut u32, material: LCow<CmdlMaterial>| {
+            *end += material.size() as u32;
+            Some(*end)
+        }))]
+    #[auto_struct(init = (material_count as usize, ()))]
+    material_end_offsets: RoArray<'r, u32>,
+    #[auto_struct(init = (material_count as usize, ()))]
+    materials: LazyArray<'r, CmdlMaterial<'r>>,
+}
+
+fn read_materials(mut reader: Reader) -> Result<Vec<CmdlMaterial>, String> {
+    let materials: CmdlMaterials = reader.read(());
+    let materials: Vec<CmdlMaterial> = materials.materials.iter().map(|m| m.into_owned()).collect();
+    for material in materials.iter() {
+        for animation in material.uv_animations.iter() {
+            uv_animation_param_count(animation.mode)?;
+        }
+    }
+    Ok(materials)
+}
+
+fn bool_to_opt(b: bool) -> Option<()> {
+    if b {
+        Some(())
+    } else {
+        None
+    }
+}
+
+pub const MATERIAL_FLAG_KONST: u32 = 0x8;
+pub const MATERIAL_FLAG_TRANSPARENT: u32 = 0x10;
+pub const MATERIAL_FLAG_PUNCHTHROUGH: u32 = 0x20;
+pub const MATERIAL_FLAG_INDIRECT_TEXTURE: u32 = 0x400;
+pub const MATERIAL_FLAG_SHORT_UVS: u32 = 0x2000;
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct CmdlMaterial<'r> {
+    pub flags: u32,
+
+    #[auto_struct(derive = texture_indices.len() as u32)]
+    texture_count: u32,
+    // Indices into the material set's texture list
+    #[auto_struct(init = (texture_count as usize, ()))]
+    pub texture_indices: LazyArray<'r, u32>,
+
+    pub vertex_attribute_flags: u32,
+    pub group_index: u32,
+
+    // Present if `flags` contains MATERIAL_FLAG_KONST
+    #[auto_struct(init = bool_to_opt(flags & MATERIAL_FLAG_KONST != 0))]
+    pub konst_colors: Option<CmdlKonstColors<'r>>,
+
+    pub blend_dst_factor: u16,
+    pub blend_src_factor: u16,
+
+    // Present if `flags` contains MATERIAL_FLAG_INDIRECT_TEXTURE
+    #[auto_struct(init = bool_to_opt(flags & MATERIAL_FLAG_INDIRECT_TEXTURE != 0))]
+    pub indirect_texture_index: Option<u32>,
+
+    #[auto_struct(derive = color_channel_flags.len() as u32)]
+    color_channel_count: u32,
+    #[auto_struct(init = (color_channel_count as usize, ()))]
+    pub color_channel_flags: LazyArray<'r, u32>,
+
+    #[auto_struct(derive = tev_stages.len() as u32)]
+    tev_stage_count: u32,
+    #[auto_struct(init = (tev_stage_count as usize, ()))]
+    pub tev_stages: LazyArray<'r, CmdlTevStage>,
+    // One per TEV stage
+    #[auto_struct(init = (tev_stage_count as usize, ()))]
+    pub tev_stage_texture_inputs: LazyArray<'r, CmdlTevStageTextureInput>,
+
+    #[auto_struct(derive = texgen_flags.len() as u32)]
+    texgen_count: u32,
+    #[auto_struct(init = (texgen_count as usize, ()))]
+    pub texgen_flags: LazyArray<'r, u32>,
+
+    #[auto_struct(derive = (4 + uv_animations.size()) as u32)]
+    uv_animations_size: u32,
+    #[auto_struct(derive = uv_animations.len() as u32)]
+    uv_animation_count: u32,
+    #[auto_struct(init = (uv_animation_count as usize, ()))]
+    pub uv_animations: LazyArray<'r, CmdlUvAnimation<'r>>,
+}
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct CmdlKonstColors<'r> {
+    #[auto_struct(derive = colors.len() as u32)]
+    count: u32,
+    #[auto_struct(init = (count as usize, ()))]
+    pub colors: LazyArray<'r, GenericArray<u8, U4>>,
+}
+
+#[auto_struct(Readable, Writable, FixedSize)]
+#[derive(Debug, Clone)]
+pub struct CmdlTevStage {
+    pub color_input_flags: u32,
+    pub alpha_input_flags: u32,
+    pub color_combine_flags: u32,
+    pub alpha_combine_flags: u32,
+    pub padding: u8,
+    pub konst_alpha_input: u8,
+    pub konst_color_input: u8,
+    pub rasterized_color_input: u8,
+}
+
+#[auto_struct(Readable, Writable, FixedSize)]
+#[derive(Debug, Clone)]
+pub struct CmdlTevStageTextureInput {
+    pub padding: u16,
+    // 0xFF if the stage doesn't sample a texture
+    pub texture_index: u8,
+    pub texcoord_index: u8,
+}
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct CmdlUvAnimation<'r> {
+    pub mode: u32,
+    // Unknown modes are read without parameters and rejected by `CmdlMaterialSet::materials`
+    #[auto_struct(init = (uv_animation_param_count(mode).unwrap_or(0), ()))]
+    pub params: LazyArray<'r, f32>,
+}
+
+pub fn uv_animation_param_count(mode: u32) -> Result<usize, String> {
+    match mode {
+        0 | 1 | 6 => Ok(0), // Inverse modelview, model matrix
+        3 | 7 => Ok(2),     // Rotation, cylinder environment
+        2 | 4 | 5 => Ok(4), // Scroll, horizontal/vertical filmstrip
+        8 => Ok(9),         // Prime 2 only
+        _ => Err(format!("Unknown UV animation mode {}", mode)),
+    }
 }
 
 #[auto_struct(Readable, Writable)]
@@ -60,5 +230,618 @@ pub struct CmdlDataSection<'r> {
     size: u32,
 
     #[auto_struct(init = (size as usize, ()))]
-    pub remainder: RoArray<'r, u8>,
+    pub remainder: LazyArray<'r, u8>,
+}
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct CmdlSurface<'r> {
+    pub centroid: GenericArray<f32, U3>,
+    pub material_index: u32,
+    pub mantissa: u16,
+    #[auto_struct(derive = display_list.len() as u16)]
+    display_list_size: u16,
+    // Filled in by the game at runtime
+    pub parent_model_pointer: u32,
+    pub next_surface_pointer: u32,
+    #[auto_struct(derive = extra_data.len() as u32)]
+    extra_data_size: u32,
+    pub reflection_normal: GenericArray<f32, U3>,
+    #[auto_struct(init = (extra_data_size as usize, ()))]
+    pub extra_data: LazyArray<'r, u8>,
+
+    #[auto_struct(pad_align = 32)]
+    _pad: (),
+
+    // Zero padded to a multiple of 32 bytes
+    #[auto_struct(init = (display_list_size as usize, ()))]
+    pub display_list: LazyArray<'r, u8>,
+}
+
+/// The vertex arrays and surfaces of a CMDL, decoded from its data sections.
+#[derive(Debug, Clone)]
+pub struct CmdlGeometry<'r> {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub colors: Vec<[u8; 4]>,
+    pub uvs: Vec<[f32; 2]>,
+    // Only used by materials with MATERIAL_FLAG_SHORT_UVS
+    pub short_uvs: Vec<[f32; 2]>,
+    pub surfaces: Vec<CmdlSurface<'r>>,
+}
+
+impl<'r> Cmdl<'r> {
+    /// Reads a CMDL, failing on an unknown version or truncated data instead of panicking like
+    /// `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        let mut header = reader.clone();
+        if header.len() < 44 {
+            Err("CMDL header is truncated")?
+        }
+        let magic: u32 = header.read(());
+        let version: u32 = header.read(());
+        if magic != 0xDEADBABE || version != 2 {
+            Err("Not a version 2 CMDL")?
+        }
+        header.advance(28);
+        let section_count: u32 = header.read(());
+        let material_set_count: u32 = header.read(());
+        if material_set_count > section_count {
+            Err("CMDL has more material sets than sections")?
+        }
+        if header.len() < section_count as usize * 4 {
+            Err("CMDL section sizes are truncated")?
+        }
+        let sizes: Vec<u32> = (0..section_count).map(|_| header.read(())).collect();
+        let pad = pad_bytes_count(32, reader.len() - header.len());
+        if header.len() < pad {
+            Err("CMDL header is truncated")?
+        }
+        header.advance(pad);
+
+        for (i, size) in sizes.iter().enumerate() {
+            let size = *size as usize;
+            if header.len() < size {
+                Err(format!("CMDL section {} is truncated", i))?
+            }
+            if i < material_set_count as usize {
+                let mut material_set = header.truncated(size);
+                if material_set.len() < 4 {
+                    Err(format!("CMDL material set {} is truncated", i))?
+                }
+                let texture_count: u32 = material_set.read(());
+                if material_set.len() < texture_count as usize * 4 {
+                    Err(format!("CMDL material set {} is truncated", i))?
+                }
+            }
+            header.advance(size);
+        }
+
+        Ok(reader.read(()))
+    }
+
+    /// Parses the materials of one of the CMDL's material sets.
+    pub fn materials<'s>(&'s self, material_set: usize) -> Result<Vec<CmdlMaterial<'s>>, String> {
+        let reader = match self.material_sets.iter().nth(material_set) {
+            Some(LCow::Borrowed(material_set)) => lazy_array_reader(&material_set.remainder),
+            Some(LCow::Owned(material_set)) => owned_section_reader(material_set.remainder)?,
+            None => Err(format!("CMDL doesn't have a material set {}", material_set))?,
+        };
+        read_materials(reader)
+    }
+
+    /// Decodes the vertex arrays and surfaces. As the arrays are padded to 32 bytes, they may end
+    /// with a few unreferenced entries.
+    pub fn geometry<'s>(&'s self) -> Result<CmdlGeometry<'s>, String> {
+        let mut sections = self.data_sections.iter().map(|section| match section {
+            LCow::Borrowed(section) => Ok(lazy_array_reader(&section.remainder)),
+            LCow::Owned(section) => owned_section_reader(section.remainder),
+        });
+        let mut next_section = |name: &str| {
+            sections
+                .next()
+                .ok_or_else(|| format!("CMDL is missing its {} section", name))?
+        };
+
+        let positions = next_section("position")?;
+        let positions = positions
+            .chunks(12)
+            .filter(|c| c.len() == 12)
+            .map(|c| [be_f32(&c[0..]), be_f32(&c[4..]), be_f32(&c[8..])])
+            .collect();
+
+        let normals = next_section("normal")?;
+        let normals = if self.flags & CMDL_FLAG_SHORT_NORMALS != 0 {
+            normals
+                .chunks(6)
+                .filter(|c| c.len() == 6)
+                .map(|c| {
+                    [
+                        be_i16(&c[0..]) as f32 / 16384.0,
+                        be_i16(&c[2..]) as f32 / 16384.0,
+                        be_i16(&c[4..]) as f32 / 16384.0,
+                    ]
+                })
+                .collect()
+        } else {
+            normals
+                .chunks(12)
+                .filter(|c| c.len() == 12)
+                .map(|c| [be_f32(&c[0..]), be_f32(&c[4..]), be_f32(&c[8..])])
+                .collect()
+        };
+
+        let colors = next_section("color")?;
+        let colors = colors
+            .chunks(4)
+            .filter(|c| c.len() == 4)
+            .map(|c| [c[0], c[1], c[2], c[3]])
+            .collect();
+
+        let uvs = next_section("UV")?;
+        let uvs = uvs
+            .chunks(8)
+            .filter(|c| c.len() == 8)
+            .map(|c| [be_f32(&c[0..]), be_f32(&c[4..])])
+            .collect();
+
+        let short_uvs = if self.flags & CMDL_FLAG_SHORT_UVS != 0 {
+            let short_uvs = next_section("short UV")?;
+            short_uvs
+                .chunks(4)
+                .filter(|c| c.len() == 4)
+                .map(|c| {
+                    [
+                        be_i16(&c[0..]) as f32 / 32768.0,
+                        be_i16(&c[2..]) as f32 / 32768.0,
+                    ]
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let surface_offsets = next_section("surface offsets")?;
+        if surface_offsets.len() < 4 {
+            Err("CMDL surface offsets section is truncated")?
+        }
+        let surface_count = be_u32(&surface_offsets[..]) as usize;
+        let surfaces = (0..surface_count)
+            .map(|_| next_section("surface").map(|mut reader| reader.read(())))
+            .collect::<Result<Vec<CmdlSurface>, String>>()?;
+
+        Ok(CmdlGeometry {
+            positions,
+            normals,
+            colors,
+            uvs,
+            short_uvs,
+            surfaces,
+        })
+    }
+}
+
+impl Cmdl<'static> {
+    /// Builds a CMDL with a single material set from decoded geometry, the inverse of
+    /// `Cmdl::geometry`. Normals are stored as floats and the bounding box is computed from the
+    /// positions.
+    pub fn from_geometry(
+        texture_ids: Vec<ResId<TXTR>>,
+        materials: Vec<CmdlMaterial>,
+        geometry: &CmdlGeometry,
+    ) -> Self {
+        let mut material_set = CmdlMaterialSet {
+            texture_ids: texture_ids.into(),
+            remainder: vec![].into(),
+        };
+        material_set.set_materials(materials);
+
+        let floats =
+            |values: &[f32]| -> Vec<u8> { values.iter().flat_map(|f| f.to_be_bytes()).collect() };
+        let mut sections: Vec<Vec<u8>> = vec![
+            floats(geometry.positions.as_flattened()),
+            floats(geometry.normals.as_flattened()),
+            geometry.colors.as_flattened().to_vec(),
+            floats(geometry.uvs.as_flattened()),
+            geometry
+                .short_uvs
+                .as_flattened()
+                .iter()
+                .flat_map(|f| ((f * 32768.0) as i16).to_be_bytes())
+                .collect(),
+        ];
+
+        let surfaces: Vec<Vec<u8>> = geometry
+            .surfaces
+            .iter()
+            .map(|surface| {
+                let mut bytes = vec![];
+                surface.write_to(&mut bytes).unwrap();
+                bytes
+            })
+            .collect();
+        let mut surface_offsets = vec![];
+        surface_offsets.extend_from_slice(&(surfaces.len() as u32).to_be_bytes());
+        let mut end = 0;
+        for surface in surfaces.iter() {
+            end += surface.len() as u32;
+            surface_offsets.extend_from_slice(&end.to_be_bytes());
+        }
+        sections.push(surface_offsets);
+        sections.extend(surfaces);
+
+        let data_sections = sections
+            .into_iter()
+            .map(|mut bytes| {
+                let len = bytes.len();
+                bytes.extend(reader_writer::pad_bytes(32, len).iter());
+                CmdlDataSection {
+                    remainder: bytes.into(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut maab = [0.0; 6];
+        if let Some(first) = geometry.positions.first() {
+            maab[..3].copy_from_slice(first);
+            maab[3..].copy_from_slice(first);
+        }
+        for position in geometry.positions.iter() {
+            for i in 0..3 {
+                maab[i] = maab[i].min(position[i]);
+                maab[i + 3] = maab[i + 3].max(position[i]);
+            }
+        }
+
+        Cmdl {
+            // The short UV section is always present, even if empty
+            flags: CMDL_FLAG_SHORT_UVS,
+            maab: maab.into(),
+            material_sets: vec![material_set].into(),
+            data_sections: data_sections.into(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CmdlPrimitiveKind {
+    Quads,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    Lines,
+    LineStrip,
+    Points,
+}
+
+impl CmdlPrimitiveKind {
+    fn from_opcode(opcode: u8) -> Option<Self> {
+        match opcode & 0xF8 {
+            0x80 => Some(CmdlPrimitiveKind::Quads),
+            0x90 => Some(CmdlPrimitiveKind::Triangles),
+            0x98 => Some(CmdlPrimitiveKind::TriangleStrip),
+            0xA0 => Some(CmdlPrimitiveKind::TriangleFan),
+            0xA8 => Some(CmdlPrimitiveKind::Lines),
+            0xB0 => Some(CmdlPrimitiveKind::LineStrip),
+            0xB8 => Some(CmdlPrimitiveKind::Points),
+            _ => None,
+        }
+    }
+
+    fn opcode(&self) -> u8 {
+        match self {
+            CmdlPrimitiveKind::Quads => 0x80,
+            CmdlPrimitiveKind::Triangles => 0x90,
+            CmdlPrimitiveKind::TriangleStrip => 0x98,
+            CmdlPrimitiveKind::TriangleFan => 0xA0,
+            CmdlPrimitiveKind::Lines => 0xA8,
+            CmdlPrimitiveKind::LineStrip => 0xB0,
+            CmdlPrimitiveKind::Points => 0xB8,
+        }
+    }
+}
+
+/// A vertex of a display list. Each attribute is an index into the corresponding array of
+/// `CmdlGeometry`, and is only present if enabled by the material's vertex attribute flags.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CmdlVertex {
+    pub pos_matrix_index: Option<u8>,
+    pub tex_matrix_indices: [Option<u8>; 7],
+    pub position: Option<u16>,
+    pub normal: Option<u16>,
+    pub colors: [Option<u16>; 2],
+    pub uvs: [Option<u16>; 8],
+}
+
+#[derive(Clone, Debug)]
+pub struct CmdlPrimitive {
+    pub kind: CmdlPrimitiveKind,
+    pub vertex_format: u8,
+    pub vertices: Vec<CmdlVertex>,
+}
+
+impl CmdlPrimitive {
+    /// Splits the primitive into triangles, as indices into `vertices`. Front facing triangles
+    /// are counter-clockwise in model space. Lines and points produce no triangles.
+    pub fn triangles(&self) -> Vec<[usize; 3]> {
+        let n = self.vertices.len();
+        match self.kind {
+            CmdlPrimitiveKind::Triangles => {
+                (0..n / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect()
+            }
+            CmdlPrimitiveKind::TriangleStrip => (0..n.saturating_sub(2))
+                .map(|i| {
+                    if i % 2 == 0 {
+                        [i, i + 1, i + 2]
+                    } else {
+                        [i + 1, i, i + 2]
+                    }
+                })
+                .collect(),
+            CmdlPrimitiveKind::TriangleFan => {
+                (1..n.saturating_sub(1)).map(|i| [0, i, i + 1]).collect()
+            }
+            CmdlPrimitiveKind::Quads => (0..n / 4)
+                .flat_map(|i| {
+                    let q = i * 4;
+                    [[q, q + 1, q + 2], [q, q + 2, q + 3]]
+                })
+                .collect(),
+            CmdlPrimitiveKind::Lines | CmdlPrimitiveKind::LineStrip | CmdlPrimitiveKind::Points => {
+                vec![]
+            }
+        }
+    }
+}
+
+// Each indexed attribute uses two bits of the vertex attribute flags, in display list order
+const ATTRIBUTE_COUNT: usize = 12;
+
+fn attribute_index_size(vertex_attribute_flags: u32, attribute: usize) -> Result<usize, String> {
+    match (vertex_attribute_flags >> (attribute * 2)) & 0x3 {
+        0 => Ok(0),
+        1 => Err("Direct vertex attributes are not supported".to_string()),
+        2 => Ok(1),
+        _ => Ok(2),
+    }
+}
+
+impl<'r> CmdlSurface<'r> {
+    /// Decodes the surface's display list. `vertex_attribute_flags` comes from the surface's
+    /// material.
+    pub fn primitives(&self, vertex_attribute_flags: u32) -> Result<Vec<CmdlPrimitive>, String> {
+        let display_list: Vec<u8> = self.display_list.iter().map(|b| *b).collect();
+        let mut index_sizes = [0; ATTRIBUTE_COUNT];
+        for (i, size) in index_sizes.iter_mut().enumerate() {
+            *size = attribute_index_size(vertex_attribute_flags, i)?;
+        }
+
+        let mut primitives = vec![];
+        let mut pos = 0;
+        let read = |pos: &mut usize, size: usize| -> Result<u16, String> {
+            let bytes = display_list
+                .get(*pos..*pos + size)
+                .ok_or_else(|| "Display list ended in the middle of a vertex".to_string())?;
+            *pos += size;
+            Ok(bytes.iter().fold(0u16, |v, b| (v << 8) | *b as u16))
+        };
+        while pos < display_list.len() {
+            let opcode = display_list[pos];
+            pos += 1;
+            // The remainder of the list is zero padding
+            if opcode == 0 {
+                break;
+            }
+            let kind = CmdlPrimitiveKind::from_opcode(opcode)
+                .ok_or_else(|| format!("Unknown display list opcode {:#04x}", opcode))?;
+            let vertex_count = read(&mut pos, 2)?;
+
+            let mut vertices = Vec::with_capacity(vertex_count as usize);
+            for _ in 0..vertex_count {
+                let mut vertex = CmdlVertex::default();
+                if vertex_attribute_flags & 0x01000000 != 0 {
+                    vertex.pos_matrix_index = Some(read(&mut pos, 1)? as u8);
+                }
+                for (i, index) in vertex.tex_matrix_indices.iter_mut().enumerate() {
+                    if vertex_attribute_flags & (0x02000000 << i) != 0 {
+                        *index = Some(read(&mut pos, 1)? as u8);
+                    }
+                }
+                let mut attributes = [None; ATTRIBUTE_COUNT];
+                for (attribute, size) in attributes.iter_mut().zip(index_sizes.iter()) {
+                    if *size != 0 {
+                        *attribute = Some(read(&mut pos, *size)?);
+                    }
+                }
+                vertex.position = attributes[0];
+                vertex.normal = attributes[1];
+                vertex.colors.copy_from_slice(&attributes[2..4]);
+                vertex.uvs.copy_from_slice(&attributes[4..12]);
+                vertices.push(vertex);
+            }
+
+            primitives.push(CmdlPrimitive {
+                kind,
+                vertex_format: opcode & 0x7,
+                vertices,
+            });
+        }
+
+        Ok(primitives)
+    }
+
+    /// Replaces the surface's display list. Every attribute enabled by `vertex_attribute_flags`
+    /// must be set on every vertex.
+    pub fn set_primitives(
+        &mut self,
+        primitives: &[CmdlPrimitive],
+        vertex_attribute_flags: u32,
+    ) -> Result<(), String> {
+        let mut index_sizes = [0; ATTRIBUTE_COUNT];
+        for (i, size) in index_sizes.iter_mut().enumerate() {
+            *size = attribute_index_size(vertex_attribute_flags, i)?;
+        }
+
+        let mut display_list = vec![];
+        for primitive in primitives {
+            display_list.push(primitive.kind.opcode() | (primitive.vertex_format & 0x7));
+            if primitive.vertices.len() > u16::MAX as usize {
+                Err(format!(
+                    "A primitive can have at most {} vertices",
+                    u16::MAX
+                ))?
+            }
+            display_list.extend_from_slice(&(primitive.vertices.len() as u16).to_be_bytes());
+            for vertex in primitive.vertices.iter() {
+                if vertex_attribute_flags & 0x01000000 != 0 {
+                    display_list.push(vertex.pos_matrix_index.unwrap_or(0));
+                }
+                for (i, index) in vertex.tex_matrix_indices.iter().enumerate() {
+                    if vertex_attribute_flags & (0x02000000 << i) != 0 {
+                        display_list.push(index.unwrap_or(0));
+                    }
+                }
+                let attributes = [vertex.position, vertex.normal]
+                    .into_iter()
+                    .chain(vertex.colors)
+                    .chain(vertex.uvs);
+                for (i, (attribute, size)) in attributes.zip(index_sizes.iter()).enumerate() {
+                    let index = match (attribute, size) {
+                        (_, 0) => continue,
+                        (Some(index), _) => index,
+                        (None, _) => Err(format!("Vertex is missing attribute {}", i))?,
+                    };
+                    if *size == 1 {
+                        display_list.push(index as u8);
+                    } else {
+                        display_list.extend_from_slice(&index.to_be_bytes());
+                    }
+                }
+            }
+        }
+        let len = display_list.len();
+        display_list.extend(reader_writer::pad_bytes(32, len).iter());
+
+        self.display_list = display_list.into();
+        Ok(())
+    }
+}
+
+// Sections are only read on the fly while the array is still borrowed, so their contents are
+// borrowed from the CMDL's data as well. An owned copy would be dropped along with the section.
+fn owned_section_reader<'r>(remainder: LazyArray<'r, u8>) -> Result<Reader<'r>, String> {
+    match remainder {
+        LazyArray::Borrowed(array) => Ok(array.data_start()),
+        LazyArray::Owned(_) => Err("CMDL section data isn't backed by the CMDL".to_string()),
+    }
+}
+
+fn lazy_array_reader<'s, 'r: 's>(array: &'s LazyArray<'r, u8>) -> Reader<'s> {
+    match array {
+        LazyArray::Borrowed(array) => array.data_start(),
+        LazyArray::Owned(vec) => Reader::new(&vec[..]),
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn be_i16(bytes: &[u8]) -> i16 {
+    i16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn be_f32(bytes: &[u8]) -> f32 {
+    f32::from_bits(be_u32(bytes))
+}
+
+#[test]
+fn test_cmdl_round_trip() {
+    // Position and normal with 16-bit indices
+    let vertex_attribute_flags = 0xF;
+    let material = CmdlMaterial {
+        flags: 0x11083,
+        texture_indices: vec![0].into(),
+        vertex_attribute_flags,
+        group_index: 0,
+        konst_colors: None,
+        blend_dst_factor: 0,
+        blend_src_factor: 1,
+        indirect_texture_index: None,
+        color_channel_flags: vec![0x3000].into(),
+        tev_stages: vec![].into(),
+        tev_stage_texture_inputs: vec![].into(),
+        texgen_flags: vec![].into(),
+        uv_animations: vec![CmdlUvAnimation {
+            mode: 2,
+            params: vec![0.5, 0.25, 1.0, 0.0].into(),
+        }]
+        .into(),
+    };
+    let vertex = |i| CmdlVertex {
+        position: Some(i),
+        normal: Some(0),
+        ..Default::default()
+    };
+    let primitive = CmdlPrimitive {
+        kind: CmdlPrimitiveKind::Triangles,
+        vertex_format: 0,
+        vertices: vec![vertex(0), vertex(1), vertex(2)],
+    };
+    let mut surface = CmdlSurface {
+        centroid: [0.0, 0.0, 0.0].into(),
+        material_index: 0,
+        mantissa: 0x8000,
+        parent_model_pointer: 0,
+        next_surface_pointer: 0,
+        reflection_normal: [0.0, 0.0, 1.0].into(),
+        extra_data: vec![].into(),
+        display_list: vec![].into(),
+    };
+    surface
+        .set_primitives(std::slice::from_ref(&primitive), vertex_attribute_flags)
+        .unwrap();
+    let geometry = CmdlGeometry {
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        normals: vec![[0.0, 0.0, 1.0]],
+        colors: vec![],
+        uvs: vec![],
+        short_uvs: vec![],
+        surfaces: vec![surface],
+    };
+    let cmdl = Cmdl::from_geometry(vec![ResId::new(0x12345678)], vec![material], &geometry);
+
+    let mut bytes = vec![];
+    cmdl.write_to(&mut bytes).unwrap();
+    for len in [20, 50, bytes.len() - 1] {
+        assert!(Cmdl::try_read(&mut Reader::new(&bytes[..len])).is_err());
+    }
+    let cmdl = Cmdl::try_read(&mut Reader::new(&bytes[..])).unwrap();
+
+    let materials = cmdl.materials(0).unwrap();
+    assert_eq!(materials.len(), 1);
+    assert_eq!(materials[0].flags, 0x11083);
+    let uv_animations: Vec<_> = materials[0].uv_animations.iter().collect();
+    assert_eq!(uv_animations.len(), 1);
+    assert_eq!(uv_animations[0].mode, 2);
+    assert_eq!(
+        uv_animations[0]
+            .params
+            .iter()
+            .map(|f| *f)
+            .collect::<Vec<_>>(),
+        [0.5, 0.25, 1.0, 0.0]
+    );
+    assert!(cmdl.materials(1).is_err());
+
+    let decoded = cmdl.geometry().unwrap();
+    assert_eq!(&decoded.positions[..3], &geometry.positions[..]);
+    assert_eq!(decoded.normals[0], [0.0, 0.0, 1.0]);
+    assert_eq!(decoded.surfaces.len(), 1);
+    let primitives = decoded.surfaces[0]
+        .primitives(vertex_attribute_flags)
+        .unwrap();
+    assert_eq!(primitives.len(), 1);
+    assert_eq!(primitives[0].kind, CmdlPrimitiveKind::Triangles);
+    assert_eq!(primitives[0].vertices, primitive.vertices);
 }
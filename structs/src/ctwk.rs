@@ -1,16 +1,169 @@
-use std::io;
+use std::{collections::HashMap, ffi::CString, io};
 
 use auto_struct_macros::auto_struct;
-use reader_writer::{generic_array::GenericArray, typenum::*, CStr, Readable, Reader, Writable};
+use reader_writer::{
+    generic_array::{ArrayLength, GenericArray},
+    pad_bytes_count,
+    typenum::*,
+    CStr, Readable, Reader, Writable,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::ResourceKind;
+
+/// The tweak files of `Tweaks.Pak`, named as in the pak's named resources
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CtwkKind {
+    Game,
+    Player,
+    PlayerControls,
+    PlayerControls2,
+    PlayerRes,
+    PlayerGun,
+    GunRes,
+    Ball,
+    Targeting,
+    CameraBob,
+    SlideShow,
+    Gui,
+    GuiColors,
+    AutoMapper,
+    Particle,
+}
+
+impl CtwkKind {
+    pub const ALL: [CtwkKind; 15] = [
+        CtwkKind::Game,
+        CtwkKind::Player,
+        CtwkKind::PlayerControls,
+        CtwkKind::PlayerControls2,
+        CtwkKind::PlayerRes,
+        CtwkKind::PlayerGun,
+        CtwkKind::GunRes,
+        CtwkKind::Ball,
+        CtwkKind::Targeting,
+        CtwkKind::CameraBob,
+        CtwkKind::SlideShow,
+        CtwkKind::Gui,
+        CtwkKind::GuiColors,
+        CtwkKind::AutoMapper,
+        CtwkKind::Particle,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CtwkKind::Game => "Game",
+            CtwkKind::Player => "Player",
+            CtwkKind::PlayerControls => "PlayerControls",
+            CtwkKind::PlayerControls2 => "PlayerControls2",
+            CtwkKind::PlayerRes => "PlayerRes",
+            CtwkKind::PlayerGun => "PlayerGun",
+            CtwkKind::GunRes => "GunRes",
+            CtwkKind::Ball => "Ball",
+            CtwkKind::Targeting => "Targeting",
+            CtwkKind::CameraBob => "CameraBob",
+            CtwkKind::SlideShow => "SlideShow",
+            CtwkKind::Gui => "Gui",
+            CtwkKind::GuiColors => "GuiColors",
+            CtwkKind::AutoMapper => "AutoMapper",
+            CtwkKind::Particle => "Particle",
+        }
+    }
+
+    /// Accepts named resource names ("Game", with or without the nul terminator) as well as file
+    /// names ("Game.CTWK")
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        let name = name.strip_suffix(b"\0").unwrap_or(name);
+        let name = name.strip_suffix(b".CTWK").unwrap_or(name);
+        CtwkKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name().as_bytes() == name)
+    }
+}
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug)]
 pub enum Ctwk<'r> {
     Game(CtwkGame<'r>),
     Player(CtwkPlayer<'r>),
+    PlayerControls(CtwkPlayerControls<'r>),
+    PlayerControls2(CtwkPlayerControls<'r>),
+    PlayerRes(CtwkPlayerRes<'r>),
     PlayerGun(CtwkPlayerGun<'r>),
+    GunRes(CtwkGunRes<'r>),
     Ball(CtwkBall<'r>),
+    Targeting(CtwkTargeting<'r>),
+    SlideShow(CtwkSlideShow<'r>),
     GuiColors(CtwkGuiColors<'r>),
+    AutoMapper(CtwkAutoMapper<'r>),
+    Particle(CtwkParticle<'r>),
+    // Tweaks which aren't modeled (CameraBob, Gui) or don't have the expected layout
+    Unknown(Reader<'r>),
+}
+
+impl<'r> Ctwk<'r> {
+    /// Reads `reader` as the tweak `kind`. Falls back to `Ctwk::Unknown` if the data doesn't fit
+    /// that tweak's layout.
+    pub fn read_kind(reader: Reader<'r>, kind: CtwkKind) -> Self {
+        match kind {
+            CtwkKind::Game if CtwkGame::fits(&reader) => Ctwk::Game(reader.clone().read(())),
+            CtwkKind::Player if CtwkPlayer::fits(&reader) => Ctwk::Player(reader.clone().read(())),
+            CtwkKind::PlayerControls if CtwkPlayerControls::fits(&reader) => {
+                Ctwk::PlayerControls(reader.clone().read(()))
+            }
+            CtwkKind::PlayerControls2 if CtwkPlayerControls::fits(&reader) => {
+                Ctwk::PlayerControls2(reader.clone().read(()))
+            }
+            CtwkKind::PlayerRes if CtwkPlayerRes::fits(&reader) => {
+                Ctwk::PlayerRes(reader.clone().read(()))
+            }
+            CtwkKind::PlayerGun if CtwkPlayerGun::fits(&reader) => {
+                Ctwk::PlayerGun(reader.clone().read(()))
+            }
+            CtwkKind::GunRes if CtwkGunRes::fits(&reader) => Ctwk::GunRes(reader.clone().read(())),
+            CtwkKind::Ball if CtwkBall::fits(&reader) => Ctwk::Ball(reader.clone().read(())),
+            CtwkKind::Targeting if CtwkTargeting::fits(&reader) => {
+                Ctwk::Targeting(reader.clone().read(()))
+            }
+            CtwkKind::SlideShow if CtwkSlideShow::fits(&reader) => {
+                Ctwk::SlideShow(reader.clone().read(()))
+            }
+            CtwkKind::GuiColors if CtwkGuiColors::fits(&reader) => {
+                Ctwk::GuiColors(reader.clone().read(()))
+            }
+            CtwkKind::AutoMapper if CtwkAutoMapper::fits(&reader) => {
+                Ctwk::AutoMapper(reader.clone().read(()))
+            }
+            CtwkKind::Particle if CtwkParticle::fits(&reader) => {
+                Ctwk::Particle(reader.clone().read(()))
+            }
+            _ => Ctwk::Unknown(reader),
+        }
+    }
+
+    /// Sets the field called `name` of this tweak, e.g. `"fov"` for Game. Fields of nested
+    /// structures are set with a map of their own fields and arrays with a list of values.
+    pub fn set_field(&mut self, name: &str, value: &CtwkValue) -> Result<(), String> {
+        match self {
+            Ctwk::Game(ctwk) => ctwk.set_field(name, value),
+            Ctwk::Player(ctwk) => ctwk.set_field(name, value),
+            Ctwk::PlayerControls(ctwk) => ctwk.set_field(name, value),
+            Ctwk::PlayerControls2(ctwk) => ctwk.set_field(name, value),
+            Ctwk::PlayerRes(ctwk) => ctwk.set_field(name, value),
+            Ctwk::PlayerGun(ctwk) => ctwk.set_field(name, value),
+            Ctwk::GunRes(ctwk) => ctwk.set_field(name, value),
+            Ctwk::Ball(ctwk) => ctwk.set_field(name, value),
+            Ctwk::Targeting(ctwk) => ctwk.set_field(name, value),
+            Ctwk::SlideShow(ctwk) => ctwk.set_field(name, value),
+            Ctwk::GuiColors(ctwk) => ctwk.set_field(name, value),
+            Ctwk::AutoMapper(ctwk) => ctwk.set_field(name, value),
+            Ctwk::Particle(ctwk) => ctwk.set_field(name, value),
+            Ctwk::Unknown(_) => {
+                Err("This tweak's layout isn't known, so it can't be edited".to_string())
+            }
+        }
+    }
 }
 
 impl<'r> Writable for Ctwk<'r> {
@@ -18,9 +171,21 @@ impl<'r> Writable for Ctwk<'r> {
         match self {
             Ctwk::Game(ctwk) => ctwk.write_to(writer),
             Ctwk::Player(ctwk) => ctwk.write_to(writer),
+            Ctwk::PlayerControls(ctwk) => ctwk.write_to(writer),
+            Ctwk::PlayerControls2(ctwk) => ctwk.write_to(writer),
+            Ctwk::PlayerRes(ctwk) => ctwk.write_to(writer),
             Ctwk::PlayerGun(ctwk) => ctwk.write_to(writer),
+            Ctwk::GunRes(ctwk) => ctwk.write_to(writer),
             Ctwk::Ball(ctwk) => ctwk.write_to(writer),
+            Ctwk::Targeting(ctwk) => ctwk.write_to(writer),
+            Ctwk::SlideShow(ctwk) => ctwk.write_to(writer),
             Ctwk::GuiColors(ctwk) => ctwk.write_to(writer),
+            Ctwk::AutoMapper(ctwk) => ctwk.write_to(writer),
+            Ctwk::Particle(ctwk) => ctwk.write_to(writer),
+            Ctwk::Unknown(data) => {
+                writer.write_all(data)?;
+                Ok(data.len() as u64)
+            }
         }
     }
 }
@@ -28,333 +193,746 @@ impl<'r> Writable for Ctwk<'r> {
 impl<'r> Readable<'r> for Ctwk<'r> {
     type Args = ();
     fn read_from(reader: &mut Reader<'r>, (): ()) -> Self {
-        // TODO: This will not work for every CTWK, need a way to differentiate:
-        //  - PlayerControls from PlayerControls2 (size == 288)
-        //  - Ball from GunRes (size == 480)
-        match reader.len() {
-            96 => Ctwk::Game(reader.read(())),
-            800 => Ctwk::Player(reader.read(())),
-            512 => Ctwk::PlayerGun(reader.read(())),
-            480 => Ctwk::Ball(reader.read(())),
-            2368 => Ctwk::GuiColors(reader.read(())),
-            _ => panic!("Unhandled CTWK size - {}", reader.len()),
-        }
+        // Which tweak this is can't be told from the data, it comes from the name of the resource.
+        // Use `ResourceKind::as_ctwk_kind_mut` to read a known tweak.
+        let data = reader.truncated(reader.len());
+        reader.advance(reader.len());
+        Ctwk::Unknown(data)
     }
 
     fn size(&self) -> usize {
         match self {
             Ctwk::Game(ctwk) => ctwk.size(),
             Ctwk::Player(ctwk) => ctwk.size(),
+            Ctwk::PlayerControls(ctwk) => ctwk.size(),
+            Ctwk::PlayerControls2(ctwk) => ctwk.size(),
+            Ctwk::PlayerRes(ctwk) => ctwk.size(),
             Ctwk::PlayerGun(ctwk) => ctwk.size(),
+            Ctwk::GunRes(ctwk) => ctwk.size(),
             Ctwk::Ball(ctwk) => ctwk.size(),
+            Ctwk::Targeting(ctwk) => ctwk.size(),
+            Ctwk::SlideShow(ctwk) => ctwk.size(),
             Ctwk::GuiColors(ctwk) => ctwk.size(),
+            Ctwk::AutoMapper(ctwk) => ctwk.size(),
+            Ctwk::Particle(ctwk) => ctwk.size(),
+            Ctwk::Unknown(data) => data.len(),
         }
     }
 }
 
-#[auto_struct(Readable, Writable)]
-#[derive(Clone, Debug)]
-pub struct CtwkGame<'r> {
-    pub start: Reader<'r>,
-    pub world_prefix: CStr<'r>,
-    pub default_room: CStr<'r>,
-    pub fov: f32,
-    pub unknown1: u8,
-    pub unknown2: u8,
-    pub unknown3: u8,
-    pub splash_screens_disabled: u8,
-    pub unknown5: f32,
-    pub press_start_delay: f32,
-    pub wavecap_intensity_normal: f32,
-    pub wavecap_intensity_poison: f32,
-    pub wavecap_intensity_lava: f32,
-    pub ripple_intensity_normal: f32,
-    pub ripple_intensity_poison: f32,
-    pub ripple_intensity_lava: f32,
-    pub fluid_env_bump_scale: f32,
-    pub water_fog_distance_base: f32,
-    pub water_fog_distance_range: f32,
-    pub gravity_water_fog_distance_base: f32,
-    pub gravity_water_fog_distance_range: f32,
-    pub hardmode_damage_mult: f32,
-    pub hardmode_weapon_mult: f32,
-
-    #[auto_struct(pad_align = 32)]
-    _pad: (),
-}
-
-#[auto_struct(Readable, Writable)]
-#[derive(Clone, Debug)]
-pub struct CtwkPlayer<'r> {
-    // Copied from URDE. Note that the URDE header files are arbitrarily ordered. You need to view the .cpp to see the actual order
-    pub start: Reader<'r>,
-    pub max_translational_acceleration: GenericArray<f32, U8>,
-    pub max_rotational_acceleration: GenericArray<f32, U8>,
-    pub translation_friction: GenericArray<f32, U8>,
-    pub rotation_friction: GenericArray<f32, U8>,
-    pub rotation_max_speed: GenericArray<f32, U8>,
-    pub translation_max_speed: GenericArray<f32, U8>,
-    pub normal_grav_accel: f32,
-    pub fluid_grav_accel: f32,
-    pub vertical_jump_accel: f32,
-    pub horizontal_jump_accel: f32,
-    pub vertical_double_jump_accel: f32,
-    pub horizontal_double_jump_accel: f32,
-    pub water_jump_factor: f32,
-    pub water_ball_jump_factor: f32,
-    pub lava_jump_factor: f32,
-    pub lava_ball_jump_factor: f32,
-    pub phazon_jump_factor: f32,
-    pub phazon_ball_jump_factor: f32,
-    pub allowed_jump_time: f32,
-    pub allowed_double_jump_time: f32,
-    pub min_double_jump_window: f32,
-    pub max_double_jump_window: f32,
-    pub unknown0: f32,
-    pub min_jump_time: f32,
-    pub min_double_jump_time: f32,
-    pub allowed_ledge_time: f32,
-    pub double_jump_impulse: f32,
-    pub backwards_force_multiplier: f32,
-    pub bomb_jump_radius: f32,
-    pub bomb_jump_height: f32,
-    pub eye_offset: f32,
-    pub turn_speed_multiplier: f32,
-    pub free_look_turn_speed_multiplier: f32,
-    pub horizontal_free_look_angle_vel: f32,
-    pub vertical_free_look_angle_vel: f32,
-    pub free_look_speed: f32,
-    pub free_look_snap_speed: f32,
-    pub unknown1: f32,
-    pub free_look_centered_threshold_angle: f32,
-    pub free_look_centered_time: f32,
-    pub free_look_dampen_factor: f32,
-    pub left_div: f32,
-    pub right_div: f32,
-    pub freelook_turns_player: u8,
-    pub unknownbool_25: u8,
-    pub unknownbool_26: u8,
-    pub move_during_free_look: u8,
-    pub hold_buttons_for_free_look: u8,
-    pub two_buttons_for_free_look: u8,
-    pub unknownbool_30: u8,
-    pub unknownbool_31: u8,
-    pub unknownbool_24: u8,
-    pub aim_when_orbiting_point: u8,
-    pub stay_in_free_look_while_firing: u8,
-    pub unknownbool_27: u8,
-    pub unknownbool_28: u8,
-    pub orbit_fixed_offset: u8,
-    pub gun_button_toggles_holster: u8,
-    pub gun_not_firing_holsters_gun: u8,
-    pub falling_double_jump: u8,
-    pub impulse_double_jump: u8,
-    pub firing_cancels_camera_pitch: u8,
-    pub assisted_aiming_ignore_horizontal: u8,
-    pub assisted_aiming_ignore_vertical: u8,
-    pub unknown10: f32,
-    pub unknown11: f32,
-    pub aim_max_distance: f32,
-    pub unknown12: f32,
-    pub unknown13: f32,
-    pub unknown15: f32,
-    pub unknown16: f32,
-    pub unknown17: f32,
-    pub aim_threshold_distance: f32,
-    pub unknown18: f32,
-    pub unknown19: f32,
-    pub aim_box_width: f32,
-    pub aim_box_height: f32,
-    pub aim_target_timer: f32,
-    pub aim_assist_horizontal_angle: f32,
-    pub aim_assist_vertical_angle: f32,
-    pub orbit_min_distance: GenericArray<f32, U3>,
-    pub orbit_normal_distance: GenericArray<f32, U3>,
-    pub orbit_max_distance: GenericArray<f32, U3>,
-    pub unknown2: f32,
-    pub orbit_mode_timer: f32,
-    pub orbit_camera_speed: f32,
-    pub orbit_upper_angle: f32,
-    pub orbit_lower_angle: f32,
-    pub orbit_horiz_angle: f32,
-    pub unknown3: f32,
-    pub unknown4: f32,
-    pub orbit_max_target_distance: f32,
-    pub orbit_max_lock_distance: f32,
-    pub orbit_distance_threshold: f32,
-    pub orbit_screen_box_half_extent_x: GenericArray<u32, U2>,
-    pub orbit_screen_box_half_extent_y: GenericArray<u32, U2>,
-    pub orbit_screen_box_center_x: GenericArray<u32, U2>,
-    pub orbit_screen_box_center_y: GenericArray<u32, U2>,
-    pub orbit_zone_ideal_x: GenericArray<u32, U2>,
-    pub orbit_zone_ideal_y: GenericArray<u32, U2>,
-    pub orbit_near_x: f32,
-    pub orbit_near_z: f32,
-    pub unknown5: f32,
-    pub unknown6: f32,
-    pub orbit_fixed_offset_z_diff: f32,
-    pub orbit_z_range: f32,
-    pub unknown7: f32,
-    pub unknown8: f32,
-    pub unknown9: f32,
-    pub orbit_prevention_time: f32,
-    pub dash_enabled: u8,
-    pub dash_on_button_release: u8,
-    pub dash_button_hold_cancel_time: f32,
-    pub dash_strafe_input_threshold: f32,
-    pub sideways_double_jump_impulse: f32,
-    pub sideways_vertical_double_jump_accel: f32,
-    pub sideways_horizontal_double_jump_accel: f32,
-    pub scanning_range: f32,
-    pub scan_retention: u8,
-    pub scan_freezes_game: u8,
-    pub orbit_while_scanning: u8,
-    pub scan_max_target_distance: f32,
-    pub scan_max_lock_distance: f32,
-    pub orbit_distance_max: f32,
-    pub grapple_swing_length: f32,
-    pub grapple_swing_period: f32,
-    pub grapple_pull_speed_min: f32,
-    pub grapple_camera_speed: f32,
-    pub max_grapple_locked_turn_align_distance: f32,
-    pub grapple_pull_speed_proportion: f32,
-    pub grapple_pull_speed_max: f32,
-    pub grapple_look_center_speed: f32,
-    pub max_grapple_turn_speed: f32,
-    pub grapple_jump_force: f32,
-    pub grapple_release_time: f32,
-    pub grapple_jump_mode: u32,
-    pub orbit_release_breaks_grapple: u8,
-    pub invert_grapple_turn: u8,
-    pub grapple_beam_speed: f32,
-    pub grapple_beam_x_wave_amplitude: f32,
-    pub grapple_beam_z_wave_amplitude: f32,
-    pub grapple_beam_angle_phase_delta: f32,
-    pub player_height: f32,
-    pub player_xy_half_extent: f32,
-    pub step_up_height: f32,
-    pub step_down_height: f32,
-    pub player_ball_half_extent: f32,
-    pub first_person_camera_speed: f32,
-    pub unknown20: f32,
-    pub jump_camera_pitch_down_start: f32,
-    pub jump_camera_pitch_down_full: f32,
-    pub jump_camera_pitch_down_angle: f32,
-    pub fall_camera_pitch_down_start: f32,
-    pub fall_camera_pitch_down_full: f32,
-    pub fall_camera_pitch_down_angle: f32,
-    pub unknown21: f32,
-    pub unknown22: f32,
-    pub unknown23: f32,
-    pub unknown24: u8,
-    pub frozen_timeout: f32,
-    pub ice_break_jump_count: u32,
-    pub varia_damage_reduction: f32,
-    pub gravity_damage_reduction: f32,
-    pub phazon_damage_reduction: f32,
-
-    #[auto_struct(pad_align = 32)]
-    _pad: (),
-}
-
-#[auto_struct(Readable, Writable)]
-#[derive(Clone, Debug)]
-pub struct SShotParam {
-    pub weapon_type: i32,
-    //    pub charged : u8,
-    //    pub combo : u8,
-    //    pub insta_kill : u8,
-    pub damage: f32,
-    pub radius_damage: f32,
-    pub radius: f32,
-    pub knockback: f32,
-    //    pub no_immunity: u8,
-}
-
-#[auto_struct(Readable, Writable)]
-#[derive(Clone, Debug)]
-pub struct SWeaponInfo {
-    pub cool_down: f32,
-    pub normal: SShotParam,
-    pub charged: SShotParam,
+impl<'r> ResourceKind<'r> {
+    /// Like `as_ctwk_mut`, but parses the resource as the tweak `kind`, which `as_ctwk_mut` can't
+    /// tell
+    pub fn as_ctwk_kind_mut(&mut self, kind: CtwkKind) -> Option<&mut Ctwk<'r>> {
+        let reader = match self {
+            ResourceKind::Unknown(reader, fourcc) if *fourcc == b"CTWK".into() => reader.clone(),
+            ResourceKind::Ctwk(Ctwk::Unknown(reader)) => reader.clone(),
+            _ => return self.as_ctwk_mut(),
+        };
+        *self = ResourceKind::Ctwk(Ctwk::read_kind(reader, kind));
+        self.as_ctwk_mut()
+    }
 }
 
-#[auto_struct(Readable, Writable)]
-#[derive(Clone, Debug)]
-pub struct CtwkPlayerGun<'r> {
-    pub start: Reader<'r>,
-    pub up_look_angle: f32,
-    pub down_look_angle: f32,
-    pub vertical_spread: f32,
-    pub horizontal_spread: f32,
-    pub high_vertical_spread: f32,
-    pub high_horizontal_spread: f32,
-    pub low_vertical_spread: f32,
-    pub low_horizontal_spread: f32,
-    pub aim_vertical_speed: f32,
-    pub aim_horizontal_speed: f32,
-    pub bomb_fuse_time: f32,
-    pub bomb_drop_delay_time: f32,
-    pub holo_hold_time: f32,
-    pub gun_transform_time: f32,
-    pub gun_holster_time: f32,
-    pub gun_not_firing_time: f32,
-    pub fixed_vertical_aim: f32,
-    pub gun_extend_distance: f32,
-    pub gun_position: GenericArray<f32, U3>,
-    pub unknown0: GenericArray<f32, U3>,
-    pub grappling_arm_position: GenericArray<f32, U3>,
-    pub bomb: SShotParam,
-    pub power_bomb: SShotParam,
-    pub missile: SShotParam,
-    pub beams: GenericArray<SWeaponInfo, U5>,
-    pub combos: GenericArray<SShotParam, U5>,
-    pub ricochet_data: GenericArray<f32, U6>,
-
-    #[auto_struct(pad_align = 32)]
-    _pad: (),
-}
-
-#[auto_struct(Readable, Writable)]
-#[derive(Clone, Debug)]
-pub struct CtwkBall<'r> {
-    pub start: Reader<'r>,
-    pub max_translation_accel: GenericArray<f32, U8>,
-    pub translation_friction: GenericArray<f32, U8>,
-    pub translation_max_speed: GenericArray<f32, U8>,
-    pub unknown0: GenericArray<f32, U4>,
-    pub ball_forward_braking_accel: GenericArray<f32, U8>,
-    pub ball_gravity: f32,
-    pub ball_water_gravity: f32,
-    pub unknown1: GenericArray<f32, U3>,
-    pub dont_care0: GenericArray<f32, U27>,
-    pub unknown2: GenericArray<f32, U6>,
-    pub conservative_door_cam_distance: f32,
-    pub unknown3: f32,
-    pub dont_care1: GenericArray<f32, U27>,
-    pub boost_drain_time: f32,
-    pub boost_min_charge_time: f32,
-    pub boost_min_rel_speed_for_damage: f32,
-    pub boost_charge_time0: f32,
-    pub boost_charge_time1: f32,
-    pub boost_charge_time2: f32,
-    pub boost_incremental_speed0: f32,
-    pub boost_incremental_speed1: f32,
-    pub boost_incremental_speed2: f32,
-    pub filler: GenericArray<u8, U32>,
-
-    #[auto_struct(pad_align = 32)]
-    _pad: (),
-}
-
-#[auto_struct(Readable, Writable)]
+/// A value to store in a tweak field, see `Ctwk::set_field`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum CtwkValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<CtwkValue>),
+    Fields(HashMap<String, CtwkValue>),
+}
+
+pub trait CtwkField {
+    /// Advances `reader` past one of this field, returning false if there isn't enough data
+    fn skip(reader: &mut Reader) -> bool
+    where
+        Self: Sized;
+    fn set(&mut self, value: &CtwkValue) -> Result<(), String>;
+}
+
+fn skip_bytes(reader: &mut Reader, len: usize) -> bool {
+    if reader.len() < len {
+        return false;
+    }
+    reader.advance(len);
+    true
+}
+
+fn integer_value(value: &CtwkValue, min: f64, max: f64) -> Result<f64, String> {
+    match value {
+        CtwkValue::Bool(b) => Ok(*b as u8 as f64),
+        CtwkValue::Number(n) if n.fract() == 0.0 && *n >= min && *n <= max => Ok(*n),
+        _ => Err(format!(
+            "Expected an integer from {} to {}, found {:?}",
+            min, max, value
+        )),
+    }
+}
+
+impl CtwkField for f32 {
+    fn skip(reader: &mut Reader) -> bool {
+        skip_bytes(reader, 4)
+    }
+
+    fn set(&mut self, value: &CtwkValue) -> Result<(), String> {
+        match value {
+            CtwkValue::Number(n) => *self = *n as f32,
+            _ => Err(format!("Expected a number, found {:?}", value))?,
+        }
+        Ok(())
+    }
+}
+
+impl CtwkField for u32 {
+    fn skip(reader: &mut Reader) -> bool {
+        skip_bytes(reader, 4)
+    }
+
+    fn set(&mut self, value: &CtwkValue) -> Result<(), String> {
+        *self = integer_value(value, u32::MIN as f64, u32::MAX as f64)? as u32;
+        Ok(())
+    }
+}
+
+impl CtwkField for i32 {
+    fn skip(reader: &mut Reader) -> bool {
+        skip_bytes(reader, 4)
+    }
+
+    fn set(&mut self, value: &CtwkValue) -> Result<(), String> {
+        *self = integer_value(value, i32::MIN as f64, i32::MAX as f64)? as i32;
+        Ok(())
+    }
+}
+
+impl CtwkField for u8 {
+    fn skip(reader: &mut Reader) -> bool {
+        skip_bytes(reader, 1)
+    }
+
+    fn set(&mut self, value: &CtwkValue) -> Result<(), String> {
+        *self = integer_value(value, u8::MIN as f64, u8::MAX as f64)? as u8;
+        Ok(())
+    }
+}
+
+impl<'r> CtwkField for CStr<'r> {
+    fn skip(reader: &mut Reader) -> bool {
+        match reader.iter().position(|b| *b == 0) {
+            Some(nul) => skip_bytes(reader, nul + 1),
+            None => false,
+        }
+    }
+
+    fn set(&mut self, value: &CtwkValue) -> Result<(), String> {
+        match value {
+            CtwkValue::String(s) => {
+                let s = CString::new(s.as_str())
+                    .map_err(|_| format!("\"{}\" contains a nul character", s))?;
+                *self = CStr::Owned(s);
+            }
+            _ => Err(format!("Expected a string, found {:?}", value))?,
+        }
+        Ok(())
+    }
+}
+
+impl<T, N> CtwkField for GenericArray<T, N>
+where
+    T: CtwkField,
+    N: ArrayLength<T>,
+{
+    fn skip(reader: &mut Reader) -> bool {
+        (0..N::to_usize()).all(|_| T::skip(reader))
+    }
+
+    fn set(&mut self, value: &CtwkValue) -> Result<(), String> {
+        let values = match value {
+            CtwkValue::List(values) if values.len() == N::to_usize() => values,
+            _ => Err(format!(
+                "Expected a list of {} values, found {:?}",
+                N::to_usize(),
+                value
+            ))?,
+        };
+        for (i, (elem, value)) in self.iter_mut().zip(values).enumerate() {
+            elem.set(value).map_err(|e| format!("[{}]: {}", i, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whatever follows the known fields of a tweak, written back as it was read
 #[derive(Clone, Debug)]
-pub struct CtwkGuiColors<'r> {
-    pub start: Reader<'r>,
-    pub colors: GenericArray<GenericArray<f32, U4>, U112>, // Set of 112 RGBA values
-    pub visor_count: u32,
-    pub visor_colors: GenericArray<GenericArray<GenericArray<f32, U4>, U7>, U5>, // Set of 7 RGBA values repeated for 5 visors
-
-    #[auto_struct(pad_align = 32)]
-    _pad: (),
+pub struct CtwkRest<'r>(pub Reader<'r>);
+
+impl<'r> Readable<'r> for CtwkRest<'r> {
+    type Args = ();
+    fn read_from(reader: &mut Reader<'r>, (): ()) -> Self {
+        let rest = reader.truncated(reader.len());
+        reader.advance(reader.len());
+        CtwkRest(rest)
+    }
+
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'r> Writable for CtwkRest<'r> {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<u64> {
+        writer.write_all(&self.0)?;
+        Ok(self.0.len() as u64)
+    }
+}
+
+macro_rules! ctwk_struct {
+    // A whole tweak file
+    (pub struct $name:ident<$lt:lifetime> { $(pub $field:ident: $ty:ty,)* }) => {
+        #[auto_struct(Readable, Writable)]
+        #[derive(Clone, Debug)]
+        pub struct $name<$lt> {
+            pub start: Reader<$lt>,
+            $(pub $field: $ty,)*
+
+            #[auto_struct(pad_align = 32)]
+            _pad: (),
+        }
+
+        impl<$lt> $name<$lt> {
+            fn fits(reader: &Reader) -> bool {
+                let mut fields = reader.clone();
+                $name::skip_fields(&mut fields)
+                    && fields.len() == pad_bytes_count(32, reader.len() - fields.len())
+            }
+        }
+
+        ctwk_struct!(@fields $name<$lt> { $($field: $ty,)* });
+    };
+    // A tweak file of which only the leading fields are known
+    (pub struct $name:ident<$lt:lifetime> { $(pub $field:ident: $ty:ty,)* ..rest }) => {
+        #[auto_struct(Readable, Writable)]
+        #[derive(Clone, Debug)]
+        pub struct $name<$lt> {
+            pub start: Reader<$lt>,
+            $(pub $field: $ty,)*
+            pub rest: CtwkRest<$lt>,
+        }
+
+        impl<$lt> $name<$lt> {
+            fn fits(reader: &Reader) -> bool {
+                $name::skip_fields(&mut reader.clone())
+            }
+        }
+
+        ctwk_struct!(@fields $name<$lt> { $($field: $ty,)* });
+    };
+    // A structure within a tweak file
+    (pub struct $name:ident { $(pub $field:ident: $ty:ty,)* }) => {
+        #[auto_struct(Readable, Writable)]
+        #[derive(Clone, Debug)]
+        pub struct $name {
+            $(pub $field: $ty,)*
+        }
+
+        impl CtwkField for $name {
+            fn skip(reader: &mut Reader) -> bool {
+                $name::skip_fields(reader)
+            }
+
+            fn set(&mut self, value: &CtwkValue) -> Result<(), String> {
+                match value {
+                    CtwkValue::Fields(fields) => {
+                        for (name, value) in fields {
+                            self.set_field(name, value)?;
+                        }
+                    }
+                    _ => Err(format!("Expected a map of fields, found {:?}", value))?,
+                }
+                Ok(())
+            }
+        }
+
+        ctwk_struct!(@fields $name<> { $($field: $ty,)* });
+    };
+    (@fields $name:ident<$($lt:lifetime)?> { $($field:ident: $ty:ty,)* }) => {
+        impl<$($lt)?> $name<$($lt)?> {
+            pub fn set_field(&mut self, name: &str, value: &CtwkValue) -> Result<(), String> {
+                let field: &mut dyn CtwkField = match name {
+                    $(stringify!($field) => &mut self.$field,)*
+                    _ => Err(format!("{} has no field \"{}\"", stringify!($name), name))?,
+                };
+                field.set(value).map_err(|e| format!("{}: {}", name, e))
+            }
+
+            fn skip_fields(reader: &mut Reader) -> bool {
+                true $(&& <$ty as CtwkField>::skip(reader))*
+            }
+        }
+    };
+}
+
+ctwk_struct! {
+    pub struct CtwkGame<'r> {
+        pub world_prefix: CStr<'r>,
+        pub default_room: CStr<'r>,
+        pub fov: f32,
+        pub unknown1: u8,
+        pub unknown2: u8,
+        pub unknown3: u8,
+        pub splash_screens_disabled: u8,
+        pub unknown5: f32,
+        pub press_start_delay: f32,
+        pub wavecap_intensity_normal: f32,
+        pub wavecap_intensity_poison: f32,
+        pub wavecap_intensity_lava: f32,
+        pub ripple_intensity_normal: f32,
+        pub ripple_intensity_poison: f32,
+        pub ripple_intensity_lava: f32,
+        pub fluid_env_bump_scale: f32,
+        pub water_fog_distance_base: f32,
+        pub water_fog_distance_range: f32,
+        pub gravity_water_fog_distance_base: f32,
+        pub gravity_water_fog_distance_range: f32,
+        pub hardmode_damage_mult: f32,
+        pub hardmode_weapon_mult: f32,
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkPlayer<'r> {
+        // Copied from URDE. Note that the URDE header files are arbitrarily ordered. You need to view the .cpp to see the actual order
+        pub max_translational_acceleration: GenericArray<f32, U8>,
+        pub max_rotational_acceleration: GenericArray<f32, U8>,
+        pub translation_friction: GenericArray<f32, U8>,
+        pub rotation_friction: GenericArray<f32, U8>,
+        pub rotation_max_speed: GenericArray<f32, U8>,
+        pub translation_max_speed: GenericArray<f32, U8>,
+        pub normal_grav_accel: f32,
+        pub fluid_grav_accel: f32,
+        pub vertical_jump_accel: f32,
+        pub horizontal_jump_accel: f32,
+        pub vertical_double_jump_accel: f32,
+        pub horizontal_double_jump_accel: f32,
+        pub water_jump_factor: f32,
+        pub water_ball_jump_factor: f32,
+        pub lava_jump_factor: f32,
+        pub lava_ball_jump_factor: f32,
+        pub phazon_jump_factor: f32,
+        pub phazon_ball_jump_factor: f32,
+        pub allowed_jump_time: f32,
+        pub allowed_double_jump_time: f32,
+        pub min_double_jump_window: f32,
+        pub max_double_jump_window: f32,
+        pub unknown0: f32,
+        pub min_jump_time: f32,
+        pub min_double_jump_time: f32,
+        pub allowed_ledge_time: f32,
+        pub double_jump_impulse: f32,
+        pub backwards_force_multiplier: f32,
+        pub bomb_jump_radius: f32,
+        pub bomb_jump_height: f32,
+        pub eye_offset: f32,
+        pub turn_speed_multiplier: f32,
+        pub free_look_turn_speed_multiplier: f32,
+        pub horizontal_free_look_angle_vel: f32,
+        pub vertical_free_look_angle_vel: f32,
+        pub free_look_speed: f32,
+        pub free_look_snap_speed: f32,
+        pub unknown1: f32,
+        pub free_look_centered_threshold_angle: f32,
+        pub free_look_centered_time: f32,
+        pub free_look_dampen_factor: f32,
+        pub left_div: f32,
+        pub right_div: f32,
+        pub freelook_turns_player: u8,
+        pub unknownbool_25: u8,
+        pub unknownbool_26: u8,
+        pub move_during_free_look: u8,
+        pub hold_buttons_for_free_look: u8,
+        pub two_buttons_for_free_look: u8,
+        pub unknownbool_30: u8,
+        pub unknownbool_31: u8,
+        pub unknownbool_24: u8,
+        pub aim_when_orbiting_point: u8,
+        pub stay_in_free_look_while_firing: u8,
+        pub unknownbool_27: u8,
+        pub unknownbool_28: u8,
+        pub orbit_fixed_offset: u8,
+        pub gun_button_toggles_holster: u8,
+        pub gun_not_firing_holsters_gun: u8,
+        pub falling_double_jump: u8,
+        pub impulse_double_jump: u8,
+        pub firing_cancels_camera_pitch: u8,
+        pub assisted_aiming_ignore_horizontal: u8,
+        pub assisted_aiming_ignore_vertical: u8,
+        pub unknown10: f32,
+        pub unknown11: f32,
+        pub aim_max_distance: f32,
+        pub unknown12: f32,
+        pub unknown13: f32,
+        pub unknown15: f32,
+        pub unknown16: f32,
+        pub unknown17: f32,
+        pub aim_threshold_distance: f32,
+        pub unknown18: f32,
+        pub unknown19: f32,
+        pub aim_box_width: f32,
+        pub aim_box_height: f32,
+        pub aim_target_timer: f32,
+        pub aim_assist_horizontal_angle: f32,
+        pub aim_assist_vertical_angle: f32,
+        pub orbit_min_distance: GenericArray<f32, U3>,
+        pub orbit_normal_distance: GenericArray<f32, U3>,
+        pub orbit_max_distance: GenericArray<f32, U3>,
+        pub unknown2: f32,
+        pub orbit_mode_timer: f32,
+        pub orbit_camera_speed: f32,
+        pub orbit_upper_angle: f32,
+        pub orbit_lower_angle: f32,
+        pub orbit_horiz_angle: f32,
+        pub unknown3: f32,
+        pub unknown4: f32,
+        pub orbit_max_target_distance: f32,
+        pub orbit_max_lock_distance: f32,
+        pub orbit_distance_threshold: f32,
+        pub orbit_screen_box_half_extent_x: GenericArray<u32, U2>,
+        pub orbit_screen_box_half_extent_y: GenericArray<u32, U2>,
+        pub orbit_screen_box_center_x: GenericArray<u32, U2>,
+        pub orbit_screen_box_center_y: GenericArray<u32, U2>,
+        pub orbit_zone_ideal_x: GenericArray<u32, U2>,
+        pub orbit_zone_ideal_y: GenericArray<u32, U2>,
+        pub orbit_near_x: f32,
+        pub orbit_near_z: f32,
+        pub unknown5: f32,
+        pub unknown6: f32,
+        pub orbit_fixed_offset_z_diff: f32,
+        pub orbit_z_range: f32,
+        pub unknown7: f32,
+        pub unknown8: f32,
+        pub unknown9: f32,
+        pub orbit_prevention_time: f32,
+        pub dash_enabled: u8,
+        pub dash_on_button_release: u8,
+        pub dash_button_hold_cancel_time: f32,
+        pub dash_strafe_input_threshold: f32,
+        pub sideways_double_jump_impulse: f32,
+        pub sideways_vertical_double_jump_accel: f32,
+        pub sideways_horizontal_double_jump_accel: f32,
+        pub scanning_range: f32,
+        pub scan_retention: u8,
+        pub scan_freezes_game: u8,
+        pub orbit_while_scanning: u8,
+        pub scan_max_target_distance: f32,
+        pub scan_max_lock_distance: f32,
+        pub orbit_distance_max: f32,
+        pub grapple_swing_length: f32,
+        pub grapple_swing_period: f32,
+        pub grapple_pull_speed_min: f32,
+        pub grapple_camera_speed: f32,
+        pub max_grapple_locked_turn_align_distance: f32,
+        pub grapple_pull_speed_proportion: f32,
+        pub grapple_pull_speed_max: f32,
+        pub grapple_look_center_speed: f32,
+        pub max_grapple_turn_speed: f32,
+        pub grapple_jump_force: f32,
+        pub grapple_release_time: f32,
+        pub grapple_jump_mode: u32,
+        pub orbit_release_breaks_grapple: u8,
+        pub invert_grapple_turn: u8,
+        pub grapple_beam_speed: f32,
+        pub grapple_beam_x_wave_amplitude: f32,
+        pub grapple_beam_z_wave_amplitude: f32,
+        pub grapple_beam_angle_phase_delta: f32,
+        pub player_height: f32,
+        pub player_xy_half_extent: f32,
+        pub step_up_height: f32,
+        pub step_down_height: f32,
+        pub player_ball_half_extent: f32,
+        pub first_person_camera_speed: f32,
+        pub unknown20: f32,
+        pub jump_camera_pitch_down_start: f32,
+        pub jump_camera_pitch_down_full: f32,
+        pub jump_camera_pitch_down_angle: f32,
+        pub fall_camera_pitch_down_start: f32,
+        pub fall_camera_pitch_down_full: f32,
+        pub fall_camera_pitch_down_angle: f32,
+        pub unknown21: f32,
+        pub unknown22: f32,
+        pub unknown23: f32,
+        pub unknown24: u8,
+        pub frozen_timeout: f32,
+        pub ice_break_jump_count: u32,
+        pub varia_damage_reduction: f32,
+        pub gravity_damage_reduction: f32,
+        pub phazon_damage_reduction: f32,
+    }
+}
+
+ctwk_struct! {
+    pub struct SShotParam {
+        pub weapon_type: i32,
+        //    pub charged : u8,
+        //    pub combo : u8,
+        //    pub insta_kill : u8,
+        pub damage: f32,
+        pub radius_damage: f32,
+        pub radius: f32,
+        pub knockback: f32,
+        //    pub no_immunity: u8,
+    }
+}
+
+ctwk_struct! {
+    pub struct SWeaponInfo {
+        pub cool_down: f32,
+        pub normal: SShotParam,
+        pub charged: SShotParam,
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkPlayerGun<'r> {
+        pub up_look_angle: f32,
+        pub down_look_angle: f32,
+        pub vertical_spread: f32,
+        pub horizontal_spread: f32,
+        pub high_vertical_spread: f32,
+        pub high_horizontal_spread: f32,
+        pub low_vertical_spread: f32,
+        pub low_horizontal_spread: f32,
+        pub aim_vertical_speed: f32,
+        pub aim_horizontal_speed: f32,
+        pub bomb_fuse_time: f32,
+        pub bomb_drop_delay_time: f32,
+        pub holo_hold_time: f32,
+        pub gun_transform_time: f32,
+        pub gun_holster_time: f32,
+        pub gun_not_firing_time: f32,
+        pub fixed_vertical_aim: f32,
+        pub gun_extend_distance: f32,
+        pub gun_position: GenericArray<f32, U3>,
+        pub unknown0: GenericArray<f32, U3>,
+        pub grappling_arm_position: GenericArray<f32, U3>,
+        pub bomb: SShotParam,
+        pub power_bomb: SShotParam,
+        pub missile: SShotParam,
+        pub beams: GenericArray<SWeaponInfo, U5>,
+        pub combos: GenericArray<SShotParam, U5>,
+        pub ricochet_data: GenericArray<f32, U6>,
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkBall<'r> {
+        pub max_translation_accel: GenericArray<f32, U8>,
+        pub translation_friction: GenericArray<f32, U8>,
+        pub translation_max_speed: GenericArray<f32, U8>,
+        pub unknown0: GenericArray<f32, U4>,
+        pub ball_forward_braking_accel: GenericArray<f32, U8>,
+        pub ball_gravity: f32,
+        pub ball_water_gravity: f32,
+        pub unknown1: GenericArray<f32, U3>,
+        pub dont_care0: GenericArray<f32, U27>,
+        pub unknown2: GenericArray<f32, U6>,
+        pub conservative_door_cam_distance: f32,
+        pub unknown3: f32,
+        pub dont_care1: GenericArray<f32, U27>,
+        pub boost_drain_time: f32,
+        pub boost_min_charge_time: f32,
+        pub boost_min_rel_speed_for_damage: f32,
+        pub boost_charge_time0: f32,
+        pub boost_charge_time1: f32,
+        pub boost_charge_time2: f32,
+        pub boost_incremental_speed0: f32,
+        pub boost_incremental_speed1: f32,
+        pub boost_incremental_speed2: f32,
+        pub filler: GenericArray<u8, U32>,
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkGuiColors<'r> {
+        pub colors: GenericArray<GenericArray<f32, U4>, U112>, // Set of 112 RGBA values
+        pub visor_count: u32,
+        pub visor_colors: GenericArray<GenericArray<GenericArray<f32, U4>, U7>, U5>, // Set of 7 RGBA values repeated for 5 visors
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkPlayerControls<'r> {
+        // One input per control, in the order of CFinalInput's commands
+        pub mappings: GenericArray<u32, U67>,
+        ..rest
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkPlayerRes<'r> {
+        pub save_station_icon: CStr<'r>,
+        pub missile_station_icon: CStr<'r>,
+        pub elevator_icon: CStr<'r>,
+        pub mines_break_first_top_icon: CStr<'r>,
+        pub mines_break_first_bottom_icon: CStr<'r>,
+        pub mines_break_second_top_icon: CStr<'r>,
+        pub mines_break_second_bottom_icon: CStr<'r>,
+        ..rest
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkGunRes<'r> {
+        pub gun_motion: CStr<'r>,
+        pub grapple_arm: CStr<'r>,
+        pub right_hand: CStr<'r>,
+        pub power_beam: CStr<'r>,
+        pub ice_beam: CStr<'r>,
+        pub wave_beam: CStr<'r>,
+        pub plasma_beam: CStr<'r>,
+        pub phazon_beam: CStr<'r>,
+        pub holo_transition: CStr<'r>,
+        pub bomb_set: CStr<'r>,
+        pub bomb_explode: CStr<'r>,
+        pub power_bomb_explode: CStr<'r>,
+        // Power, Ice, Wave, Plasma, Phazon
+        pub weapons: GenericArray<CStr<'r>, U5>,
+        pub muzzle_particles: GenericArray<CStr<'r>, U5>,
+        pub charge_particles: GenericArray<CStr<'r>, U5>,
+        pub aux_muzzle_particles: GenericArray<CStr<'r>, U5>,
+        pub grapple_segment: CStr<'r>,
+        pub grapple_claw: CStr<'r>,
+        pub grapple_hit: CStr<'r>,
+        pub grapple_muzzle: CStr<'r>,
+        pub grapple_swoosh: CStr<'r>,
+        ..rest
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkTargeting<'r> {
+        pub target_radius_mode: u32,
+        pub curr_lock_on_exit_duration: f32,
+        pub curr_lock_on_enter_duration: f32,
+        pub curr_lock_on_switch_duration: f32,
+        pub lock_confirm_scale: f32,
+        pub next_lock_on_exit_duration: f32,
+        pub next_lock_on_enter_duration: f32,
+        pub next_lock_on_switch_duration: f32,
+        pub seeker_scale: f32,
+        pub seeker_angle_speed: f32,
+        pub xray_ret_angle_speed: f32,
+        ..rest
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkSlideShow<'r> {
+        pub pak_name: CStr<'r>,
+        pub font: CStr<'r>,
+        pub font_color: GenericArray<f32, U4>,
+        pub outline_color: GenericArray<f32, U4>,
+        pub scan_percent_interval: f32,
+        ..rest
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkAutoMapper<'r> {
+        pub show_one_mini_map_area: u8,
+        pub unknown0: u8,
+        pub scale_move_speed_with_cam_dist: u8,
+        pub cam_dist: f32,
+        pub min_cam_dist: f32,
+        pub max_cam_dist: f32,
+        pub min_cam_rotate_x: f32,
+        pub max_cam_rotate_x: f32,
+        pub cam_angle: f32,
+        pub unknown1: f32,
+        pub automapper_widget_color: GenericArray<f32, U4>,
+        pub mini_cam_dist: f32,
+        pub mini_cam_x_angle: f32,
+        pub mini_cam_angle: f32,
+        pub unknown2: f32,
+        pub automapper_widget_mini_color: GenericArray<f32, U4>,
+        pub surf_color_visited: GenericArray<f32, U4>,
+        pub outline_color_visited: GenericArray<f32, U4>,
+        pub surf_color_unvisited: GenericArray<f32, U4>,
+        pub outline_color_unvisited: GenericArray<f32, U4>,
+        pub surface_select_color_visited: GenericArray<f32, U4>,
+        pub outline_select_color_visited: GenericArray<f32, U4>,
+        pub map_surface_norm_color_linear: f32,
+        pub map_surface_norm_color_constant: f32,
+        ..rest
+    }
+}
+
+ctwk_struct! {
+    pub struct CtwkParticle<'r> {
+        pub particle: CStr<'r>,
+        pub power_beam: CStr<'r>,
+        pub gen_thrust: CStr<'r>,
+        ..rest
+    }
+}
+
+#[test]
+fn test_ctwk_set_field() {
+    let mut bytes = b"Metroid1\0Room\0".to_vec();
+    bytes.extend_from_slice(&55.0f32.to_be_bytes());
+    bytes.extend_from_slice(&[0, 0, 0, 0]);
+    for i in 0..15 {
+        bytes.extend_from_slice(&(i as f32).to_be_bytes());
+    }
+    bytes.resize(96, 0);
+
+    let mut ctwk = Ctwk::read_kind(Reader::new(&bytes[..]), CtwkKind::Game);
+    ctwk.set_field("fov", &CtwkValue::Number(80.0)).unwrap();
+    ctwk.set_field("splash_screens_disabled", &CtwkValue::Bool(true))
+        .unwrap();
+    ctwk.set_field(
+        "default_room",
+        &CtwkValue::String("A room with a much longer name".to_string()),
+    )
+    .unwrap();
+    assert!(ctwk.set_field("fov", &CtwkValue::Bool(true)).is_err());
+    assert!(ctwk
+        .set_field("unknown1", &CtwkValue::Number(256.0))
+        .is_err());
+    assert!(ctwk
+        .set_field("no_such_field", &CtwkValue::Number(0.0))
+        .is_err());
+
+    let mut written = vec![];
+    ctwk.write_to(&mut written).unwrap();
+    assert_eq!(written.len() % 32, 0);
+    assert_eq!(written.len(), ctwk.size());
+
+    let ctwk_game = match Ctwk::read_kind(Reader::new(&written[..]), CtwkKind::Game) {
+        Ctwk::Game(ctwk_game) => ctwk_game,
+        _ => panic!("The edited tweak no longer fits the Game layout"),
+    };
+    assert_eq!(ctwk_game.world_prefix.to_bytes(), b"Metroid1");
+    assert_eq!(
+        ctwk_game.default_room.to_bytes(),
+        b"A room with a much longer name"
+    );
+    assert_eq!(ctwk_game.fov, 80.0);
+    assert_eq!(ctwk_game.splash_screens_disabled, 1);
+    assert_eq!(ctwk_game.hardmode_weapon_mult, 14.0);
+
+    // Data which doesn't fit the layout is kept as is and can't be edited
+    let mut ctwk = Ctwk::read_kind(Reader::new(&bytes[..20]), CtwkKind::Game);
+    assert!(matches!(ctwk, Ctwk::Unknown(_)));
+    assert!(ctwk.set_field("fov", &CtwkValue::Number(80.0)).is_err());
+
+    // The tweak isn't guessed from the size of the data, but read as the kind asked for
+    let mut kind = ResourceKind::Unknown(Reader::new(&bytes[..]), b"CTWK".into());
+    assert!(matches!(kind.as_ctwk_mut(), Some(Ctwk::Unknown(_))));
+    assert!(matches!(
+        kind.as_ctwk_kind_mut(CtwkKind::Game),
+        Some(Ctwk::Game(_))
+    ));
 }
@@ -4,7 +4,7 @@ use auto_struct_macros::auto_struct;
 use reader_writer::{
     generic_array::{GenericArray, GenericArrayIter},
     typenum::*,
-    IteratorArray, LCow, LazyArray,
+    IteratorArray, LCow, LazyArray, Reader,
 };
 
 pub type DolSegementsIter<S> = ZipIter<GenericArrayIter<u32, S>, GenericArrayIter<u32, S>>;
@@ -51,6 +51,32 @@ pub struct Dol<'r> {
     pub data_segments: IteratorArray<'r, DolSegment<'r>, DolSegementsIter<U11>>,
 }
 
+impl<'r> Dol<'r> {
+    /// Reads a DOL, failing on truncated data instead of panicking like `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        if reader.len() < 0x100 {
+            Err("DOL header is truncated")?
+        }
+        if reader[0xE4..0x100].iter().any(|b| *b != 0) {
+            Err("DOL header padding isn't empty")?
+        }
+        // The segments follow the header back to back, in the order of the size table
+        let segments_size: usize = reader[0x90..0xD8]
+            .chunks(4)
+            .map(|size| u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize)
+            .sum();
+        if reader.len() - 0x100 < segments_size {
+            Err(format!(
+                "Expected {} bytes of DOL segments, found {}",
+                segments_size,
+                reader.len() - 0x100
+            ))?
+        }
+
+        Ok(reader.read(()))
+    }
+}
+
 #[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct DolSegment<'r> {
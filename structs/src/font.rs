@@ -1,5 +1,5 @@
 use auto_struct_macros::auto_struct;
-use reader_writer::{CStr, FourCC, RoArray};
+use reader_writer::{CStr, FourCC, LazyArray, Readable, Reader};
 
 use crate::{res_id::*, ResId};
 
@@ -28,35 +28,126 @@ pub struct Font<'r> {
     #[auto_struct(derive = glyphs.len() as u32)]
     glyph_count: u32,
     #[auto_struct(init = (glyph_count as usize, ()))]
-    glyphs: RoArray<'r, FontGlyph>,
+    pub glyphs: LazyArray<'r, FontGlyph>,
 
     #[auto_struct(derive = kernings.len() as u32)]
     kerning_count: u32,
     #[auto_struct(init = (kerning_count as usize, ()))]
-    kernings: RoArray<'r, FontKerning>,
+    pub kernings: LazyArray<'r, FontKerning>,
 }
 
+impl<'r> Font<'r> {
+    /// Reads a FONT, failing on an unknown version or truncated data instead of panicking like
+    /// `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        let mut header = reader.clone();
+        if header.len() < 38 {
+            Err("FONT header is truncated")?
+        }
+        let magic: FourCC = header.read(());
+        let version: u32 = header.read(());
+        if magic != FourCC::from_bytes(b"FONT") || version != 2 {
+            Err("Not a version 2 FONT")?
+        }
+        header.advance(30);
+        let name_len = header
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or("FONT name is unterminated")?;
+        header.advance(name_len + 1);
+        if header.len() < 12 {
+            Err("FONT header is truncated")?
+        }
+        header.advance(8);
+
+        for (what, size) in [
+            ("glyphs", FontGlyph::fixed_size().unwrap()),
+            ("kernings", FontKerning::fixed_size().unwrap()),
+        ] {
+            if header.len() < 4 {
+                Err(format!("FONT {} are truncated", what))?
+            }
+            let count: u32 = header.read(());
+            let len = (count as usize)
+                .checked_mul(size)
+                .filter(|len| *len <= header.len())
+                .ok_or_else(|| format!("FONT {} are truncated", what))?;
+            header.advance(len);
+        }
+
+        Ok(reader.read(()))
+    }
+}
+
+pub const FONT_NO_KERNING: u32 = 0xFFFFFFFF;
+
 #[auto_struct(Readable, Writable, FixedSize)]
 #[derive(Debug, Clone)]
 pub struct FontGlyph {
-    utf16_char: u16,
-    left_uv_coordinate: f32,
-    top_uv_coordinate: f32,
-    right_uv_coordinate: f32,
-    bottom_uv_coordinate: f32,
-    left_padding: u32,
-    print_head_advance: u32,
-    right_padding: u32,
-    width: u32,
-    height: u32,
-    vertical_offset: u32,
-    kerning_start_index: u32,
+    pub utf16_char: u16,
+    pub left_uv_coordinate: f32,
+    pub top_uv_coordinate: f32,
+    pub right_uv_coordinate: f32,
+    pub bottom_uv_coordinate: f32,
+    pub left_padding: u32,
+    pub print_head_advance: u32,
+    pub right_padding: u32,
+    pub width: u32,
+    pub height: u32,
+    pub vertical_offset: u32,
+    pub kerning_start_index: u32, // FONT_NO_KERNING if the glyph has no kerning pairs
 }
 
 #[auto_struct(Readable, Writable, FixedSize)]
 #[derive(Debug, Clone)]
 pub struct FontKerning {
-    char1: u16,
-    char2: u16,
-    kerning_adjust: i32,
+    pub char1: u16,
+    pub char2: u16,
+    pub kerning_adjust: i32,
+}
+
+#[test]
+fn test_font_try_read() {
+    use reader_writer::Writable;
+
+    let font = Font {
+        unknown0: 0,
+        line_height: 20,
+        vertical_offset: 0,
+        line_margin: 0,
+        unknown1: 0,
+        unknown2: 0,
+        unknown3: 0,
+        font_size: 16,
+        name: CStr::Owned(std::ffi::CString::new("Test").unwrap()),
+        txtr: ResId::new(0x12345678),
+        txtr_fmt: 0,
+        glyphs: vec![FontGlyph {
+            utf16_char: 'A' as u16,
+            left_uv_coordinate: 0.0,
+            top_uv_coordinate: 0.0,
+            right_uv_coordinate: 0.5,
+            bottom_uv_coordinate: 0.5,
+            left_padding: 0,
+            print_head_advance: 10,
+            right_padding: 0,
+            width: 10,
+            height: 16,
+            vertical_offset: 0,
+            kerning_start_index: FONT_NO_KERNING,
+        }]
+        .into(),
+        kernings: vec![].into(),
+    };
+    let mut bytes = vec![];
+    font.write_to(&mut bytes).unwrap();
+
+    let read = Font::try_read(&mut Reader::new(&bytes[..])).unwrap();
+    assert_eq!(read.name.to_bytes(), b"Test");
+    assert_eq!(read.glyphs.len(), 1);
+    for len in [0, 20, 40, bytes.len() - 1] {
+        assert!(Font::try_read(&mut Reader::new(&bytes[..len])).is_err());
+    }
+    bytes[7] = 3;
+    assert!(Font::try_read(&mut Reader::new(&bytes[..])).is_err());
 }
@@ -3,7 +3,7 @@ use std::io;
 use auto_struct_macros::auto_struct;
 use reader_writer::{
     generic_array::{typenum::*, GenericArray},
-    CStr, FourCC, LazyArray, Readable, Reader, RoArray, Writable,
+    pad_bytes_count, CStr, FourCC, LazyArray, Readable, Reader, RoArray, Writable,
 };
 
 use crate::{res_id::*, ResId};
@@ -31,6 +31,103 @@ pub struct Frme<'r> {
     _pad: (),
 }
 
+impl<'r> Frme<'r> {
+    /// Reads a FRME, failing on an unknown widget type or truncated data instead of panicking
+    /// like `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        fn skip(reader: &mut Reader, size: usize, what: &str) -> Result<(), String> {
+            if reader.len() < size {
+                Err(format!("FRME {} is truncated", what))?
+            }
+            reader.advance(size);
+            Ok(())
+        }
+
+        fn read_u32(reader: &mut Reader, what: &str) -> Result<u32, String> {
+            if reader.len() < 4 {
+                Err(format!("FRME {} is truncated", what))?
+            }
+            Ok(reader.read(()))
+        }
+
+        fn skip_cstr(reader: &mut Reader, what: &str) -> Result<(), String> {
+            let len = reader
+                .iter()
+                .position(|b| *b == 0)
+                .ok_or_else(|| format!("FRME {} is unterminated", what))?;
+            reader.advance(len + 1);
+            Ok(())
+        }
+
+        let mut header = reader.clone();
+        let version = read_u32(&mut header, "header")?;
+        for _ in 0..3 {
+            read_u32(&mut header, "header")?;
+        }
+        let widget_count = read_u32(&mut header, "header")?;
+
+        for i in 0..widget_count {
+            let what = format!("widget {}", i);
+            let fourcc = FourCC::from_bytes(&read_u32(&mut header, &what)?.to_be_bytes());
+            skip_cstr(&mut header, &format!("{} name", what))?;
+            skip_cstr(&mut header, &format!("{} parent", what))?;
+            // The flags, color and model draw flags
+            skip(&mut header, 24, &what)?;
+
+            if fourcc == b"CAMR".into() {
+                match read_u32(&mut header, &what)? {
+                    0 => skip(&mut header, 16, &what)?,
+                    1 => skip(&mut header, 24, &what)?,
+                    projection => Err(format!(
+                        "FRME {} has an unknown projection type {}",
+                        what, projection
+                    ))?,
+                }
+            } else if fourcc == b"LITE".into() {
+                let light_type = read_u32(&mut header, &what)?;
+                skip(&mut header, if light_type == 0 { 32 } else { 28 }, &what)?;
+            } else if fourcc == b"IMGP".into() {
+                skip(&mut header, 12, &what)?;
+                for elem_size in [12, 8] {
+                    let count = read_u32(&mut header, &what)? as usize;
+                    let size = count
+                        .checked_mul(elem_size)
+                        .ok_or_else(|| format!("FRME {} is truncated", what))?;
+                    skip(&mut header, size, &what)?;
+                }
+            } else {
+                let size = match fourcc.as_bytes() {
+                    b"HWIG" | b"BWIG" => 0,
+                    b"MODL" => 12,
+                    b"TXPN" if version == 1 => 86,
+                    b"TXPN" => 74,
+                    b"METR" => 10,
+                    b"ENRG" => 4,
+                    b"GRUP" => 3,
+                    b"TBGP" => 35,
+                    b"PANE" => 20,
+                    b"SLGP" => 16,
+                    _ => Err(format!("FRME {} has an unknown type {:?}", what, fourcc))?,
+                };
+                skip(&mut header, size, &what)?;
+            }
+
+            let is_worker = header.first().copied();
+            skip(&mut header, 1, &what)?;
+            if is_worker == Some(1) {
+                skip(&mut header, 2, &what)?;
+            }
+            // The origin, basis, rotation center and the trailing unknowns
+            skip(&mut header, 66, &what)?;
+        }
+        if header.len() < pad_bytes_count(32, reader.len() - header.len()) {
+            Err("FRME padding is truncated")?
+        }
+
+        Ok(reader.read(()))
+    }
+}
+
 #[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct FrmeWidget<'r> {
@@ -319,3 +416,49 @@ pub struct ModelWidget {
     pub blend_mode: u32,
     pub light_mask: u32,
 }
+
+#[test]
+fn test_frme_try_read() {
+    fn widget(fourcc: &[u8; 4], name: &str, kind: &[u8]) -> Vec<u8> {
+        let mut bytes = fourcc.to_vec();
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(b"kGSYS_HeadWidgetID\0");
+        bytes.extend_from_slice(&[1, 1, 1, 0]);
+        for value in [1.0f32; 4] {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.extend_from_slice(kind);
+        bytes.push(0);
+        bytes.extend_from_slice(&[0; 66]);
+        bytes
+    }
+
+    let mut text_pane = vec![0; 74];
+    text_pane[0..4].copy_from_slice(&100.0f32.to_be_bytes());
+    let mut bytes = vec![];
+    for value in [0u32, 0, 0, 0, 2] {
+        bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    bytes.extend(widget(b"HWIG", "kGSYS_HeadWidgetID", &[]));
+    bytes.extend(widget(b"TXPN", "textpane", &text_pane));
+    bytes.resize(bytes.len().div_ceil(32) * 32, 0);
+
+    let frme = Frme::try_read(&mut Reader::new(&bytes)).unwrap();
+    assert_eq!(frme.widgets.len(), 2);
+    let widget = frme.widgets.iter().nth(1).unwrap();
+    assert_eq!(widget.name.to_str().unwrap(), "textpane");
+    match &widget.kind {
+        FrmeWidgetKind::TextPane(text_pane) => assert_eq!(text_pane.x_dim, 100.0),
+        _ => panic!("Expected a text pane"),
+    }
+    let mut written = vec![];
+    frme.write_to(&mut written).unwrap();
+    assert_eq!(written, bytes);
+
+    assert!(Frme::try_read(&mut Reader::new(&bytes[..bytes.len() - 40])).is_err());
+    let mut unknown = bytes.clone();
+    unknown[20..24].copy_from_slice(b"XXXX");
+    assert!(Frme::try_read(&mut Reader::new(&unknown)).is_err());
+}
@@ -55,6 +55,9 @@ pub trait ProgressNotifier {
     fn notify_writing_file(&mut self, file_name: &CStr, file_bytes: usize);
     fn notify_writing_header(&mut self);
     fn notify_flushing_to_disk(&mut self);
+    fn notify_warning(&mut self, msg: &str) {
+        eprintln!("Warning, {}", msg);
+    }
 }
 
 pub trait WriteExt {
@@ -1,5 +1,5 @@
 use auto_struct_macros::auto_struct;
-use reader_writer::{CStr, LazyArray};
+use reader_writer::{CStr, LazyArray, Readable, Reader};
 
 use crate::{res_id::*, ResId};
 
@@ -17,6 +17,43 @@ pub struct Hint<'r> {
     pub hints: LazyArray<'r, HintDetails<'r>>,
 }
 
+impl<'r> Hint<'r> {
+    /// Reads a HINT, failing on an unknown version or truncated data instead of panicking like
+    /// `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        let mut header = reader.clone();
+        if header.len() < 12 {
+            Err("HINT header is truncated")?
+        }
+        let magic: u32 = header.read(());
+        let version: u32 = header.read(());
+        if magic != 0x00BADBAD || version != 1 {
+            Err("Not a version 1 HINT")?
+        }
+        let hint_count: u32 = header.read(());
+        for i in 0..hint_count {
+            let name_len = header
+                .iter()
+                .position(|b| *b == 0)
+                .ok_or_else(|| format!("HINT {} name is unterminated", i))?;
+            header.advance(name_len + 1);
+            // The times and popup text, then the location count
+            if header.len() < 20 {
+                Err(format!("HINT {} is truncated", i))?
+            }
+            header.advance(16);
+            let location_count: u32 = header.read(());
+            let size = (location_count as usize)
+                .checked_mul(HintLocation::fixed_size().unwrap())
+                .filter(|size| *size <= header.len())
+                .ok_or_else(|| format!("HINT {} locations are truncated", i))?;
+            header.advance(size);
+        }
+
+        Ok(reader.read(()))
+    }
+}
+
 #[auto_struct(Readable, Writable)]
 #[derive(Debug, Clone)]
 pub struct HintDetails<'r> {
@@ -14,6 +14,7 @@ mod frme;
 mod gc_disc;
 mod hint;
 mod mapa;
+mod mapu;
 mod mapw;
 pub mod mlvl;
 mod mrea;
@@ -167,6 +168,7 @@ pub use frme::*;
 pub use gc_disc::*;
 pub use hint::*;
 pub use mapa::*;
+pub use mapu::*;
 pub use mapw::*;
 pub use mlvl::*;
 pub use mrea::*;
@@ -1,7 +1,7 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use auto_struct_macros::auto_struct;
-use reader_writer::{generic_array::GenericArray, typenum::*, LazyArray, RoArray};
+use reader_writer::{generic_array::GenericArray, typenum::*, LazyArray, Readable};
 use serde::{Deserialize, Serialize};
 
 #[auto_struct(Readable, Writable)]
@@ -38,7 +38,7 @@ pub struct Mapa<'r> {
     _pad: (),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
 pub enum MapaObjectType {
     DoorNormal = 0,
     DoorShield = 1,
@@ -121,7 +121,7 @@ pub struct MapaObject {
 
 impl MapaObject {
     pub fn is_door(&self) -> bool {
-        self.type_ < 16 && self.type_ > 0
+        self.type_ < 16
     }
 
     pub fn is_vertical(&self) -> bool {
@@ -144,12 +144,12 @@ pub struct MapaSurface<'r> {
     #[auto_struct(derive = primitives.len() as u32)]
     pub primitive_count: u32,
     #[auto_struct(init = (primitive_count as usize, ()))]
-    pub primitives: RoArray<'r, MapaPrimitive<'r>>,
+    pub primitives: LazyArray<'r, MapaPrimitive<'r>>,
 
     #[auto_struct(derive = borders.len() as u32)]
     pub border_count: u32,
     #[auto_struct(init = (border_count as usize, ()))]
-    pub borders: RoArray<'r, MapaBorder<'r>>,
+    pub borders: LazyArray<'r, MapaBorder<'r>>,
 }
 
 #[auto_struct(Readable, Writable)]
@@ -159,7 +159,7 @@ pub struct MapaPrimitive<'r> {
     #[auto_struct(derive = indices.len() as u32)]
     pub index_count: u32,
     #[auto_struct(init = (index_count as usize, ()))]
-    pub indices: RoArray<'r, u8>,
+    pub indices: LazyArray<'r, u8>,
 
     #[auto_struct(pad_align = 4)]
     pub _pad: (),
@@ -171,42 +171,188 @@ pub struct MapaBorder<'r> {
     #[auto_struct(derive = indices.len() as u32)]
     pub index_count: u32,
     #[auto_struct(init = (index_count as usize, ()))]
-    pub indices: RoArray<'r, u8>,
+    pub indices: LazyArray<'r, u8>,
 
     #[auto_struct(pad_align = 4)]
     pub _pad: (),
 }
 
+const GX_TRIANGLES: u32 = 0x90;
+const GX_TRIANGLE_STRIP: u32 = 0x98;
+const GX_TRIANGLE_FAN: u32 = 0xA0;
+
+// ceiling, floor and second floor variants of the ice, wave and plasma doors
+const VERTICAL_DOOR_TYPES: [[MapaObjectType; 3]; 3] = [
+    [
+        MapaObjectType::DoorIceCeiling,
+        MapaObjectType::DoorIceFloor,
+        MapaObjectType::DoorIceFloor2,
+    ],
+    [
+        MapaObjectType::DoorWaveCeiling,
+        MapaObjectType::DoorWaveFloor,
+        MapaObjectType::DoorWaveFloor2,
+    ],
+    [
+        MapaObjectType::DoorPlasmaCeiling,
+        MapaObjectType::DoorPlasmaFloor,
+        MapaObjectType::DoorPlasmaFloor2,
+    ],
+];
+
+impl MapaObjectType {
+    pub fn is_door(&self) -> bool {
+        (*self as u32) < 16
+    }
+}
+
+impl<'r> MapaPrimitive<'r> {
+    /// Splits the primitive into individual triangles of vertex indices
+    pub fn triangles(&self) -> Vec<[u8; 3]> {
+        let indices: Vec<u8> = self.indices.iter().map(|i| *i).collect();
+        let mut triangles = Vec::new();
+        match self.type_ {
+            GX_TRIANGLES => {
+                for tri in indices.chunks_exact(3) {
+                    triangles.push([tri[0], tri[1], tri[2]]);
+                }
+            }
+            GX_TRIANGLE_STRIP => {
+                for i in 2..indices.len() {
+                    triangles.push([indices[i - 2], indices[i - 1], indices[i]]);
+                }
+            }
+            GX_TRIANGLE_FAN => {
+                for i in 2..indices.len() {
+                    triangles.push([indices[0], indices[i - 1], indices[i]]);
+                }
+            }
+            _ => (),
+        }
+        triangles
+    }
+}
+
+impl<'r> MapaSurface<'r> {
+    /// The edges of every triangle of the surface, skipping degenerate triangles
+    fn triangle_edges(&self) -> Vec<(u8, u8)> {
+        let mut edges = Vec::new();
+        for primitive in self.primitives.iter() {
+            for tri in primitive.triangles() {
+                if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] {
+                    continue;
+                }
+                for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                    edges.push((a.min(b), a.max(b)));
+                }
+            }
+        }
+        edges
+    }
+
+    /// Rebuilds the outline of the surface from the edges which belong to only one of its
+    /// triangles. Edges shared with other surfaces are outlined too, see
+    /// `Mapa::regenerate_outline` for the outline of a whole map.
+    fn regenerate_borders(&mut self) {
+        let mut edge_counts = HashMap::<(u8, u8), u32>::new();
+        for edge in self.triangle_edges() {
+            *edge_counts.entry(edge).or_insert(0) += 1;
+        }
+        let edges = edge_counts
+            .into_iter()
+            .filter(|(_, count)| *count == 1)
+            .map(|(edge, _)| edge)
+            .collect();
+        self.set_borders(edges);
+    }
+
+    fn set_borders(&mut self, mut edges: Vec<(u8, u8)>) {
+        edges.sort();
+
+        // Chain the edges into line strips
+        let mut borders = Vec::new();
+        while let Some((start, next)) = edges.pop() {
+            let mut indices = vec![start, next];
+            // Extend the strip from both of its ends
+            for _ in 0..2 {
+                let mut current = *indices.last().unwrap();
+                while let Some(pos) = edges
+                    .iter()
+                    .position(|(a, b)| *a == current || *b == current)
+                {
+                    let (a, b) = edges.remove(pos);
+                    current = if a == current { b } else { a };
+                    indices.push(current);
+                }
+                indices.reverse();
+            }
+            borders.push(MapaBorder {
+                indices: indices.into(),
+            });
+        }
+
+        self.borders = borders.into();
+    }
+}
+
 impl<'r> Mapa<'r> {
-    fn update_offsets(&mut self) {
-        // update the table start offsets
-        for i in 0..self.surfaces.len() {
-            let surface_header = &mut self.surface_headers.as_mut_vec()[i];
-            surface_header.primitive_table_start += 80;
-            surface_header.border_table_start += 80;
+    fn surface_table_start(&self) -> u32 {
+        (self.objects.len() * 0x50 + self.vertices.len() * 0xC + self.surface_headers.len() * 0x20)
+            as u32
+    }
+
+    /// The surface table offsets are relative to a fixed point in the file. Record where that
+    /// is before changing anything which shifts the surface data.
+    fn surface_offset_origin(&self) -> i64 {
+        match self.surface_headers.iter().next() {
+            Some(header) => header.primitive_table_start as i64 - self.surface_table_start() as i64,
+            None => 0,
         }
     }
 
-    pub fn add_pickup(&mut self, editor_id: u32, pickup_pos: [f32; 3]) {
-        let mappable_objects = &mut self.objects;
+    fn update_offsets(&mut self, origin: i64) {
+        let mut offset = self.surface_table_start() as i64 + origin;
+        let headers = self.surface_headers.as_mut_vec();
+        for (header, surface) in headers.iter_mut().zip(self.surfaces.iter()) {
+            header.primitive_table_start = offset as u32;
+            offset += 4 + surface.primitives.size() as i64;
+            header.border_table_start = offset as u32;
+            offset += 4 + surface.borders.size() as i64;
+        }
+    }
+
+    pub fn add_object(
+        &mut self,
+        type_: MapaObjectType,
+        visibility_mode: MapaObjectVisibilityMode,
+        editor_id: u32,
+        position: [f32; 3],
+        rotation: [f32; 3],
+    ) {
+        let origin = self.surface_offset_origin();
+
+        let [x, y, z] = rotation.map(|r| r.to_radians());
+        let (sx, cx) = x.sin_cos();
+        let (sy, cy) = y.sin_cos();
+        let (sz, cz) = z.sin_cos();
         let transform_matrix = [
-            1.0f32,
-            0.0f32,
-            0.0f32,
-            pickup_pos[0],
-            0.0f32,
-            1.0f32,
-            0.0f32,
-            pickup_pos[1],
-            0.0f32,
-            0.0f32,
-            1.0f32,
-            pickup_pos[2],
+            cz * cy,
+            cz * sy * sx - sz * cx,
+            cz * sy * cx + sz * sx,
+            position[0],
+            sz * cy,
+            sz * sy * sx + cz * cx,
+            sz * sy * cx - cz * sx,
+            position[1],
+            -sy,
+            cy * sx,
+            cy * cx,
+            position[2],
         ]
         .into();
-        mappable_objects.as_mut_vec().push(MapaObject {
-            type_: MapaObjectType::Pickup as u32,
-            visibility_mode: MapaObjectVisibilityMode::Always as u32,
+        self.objects.as_mut_vec().push(MapaObject {
+            type_: type_ as u32,
+            visibility_mode: visibility_mode as u32,
             editor_id,
             seed1: 0xFFFFFFFF,
             transform_matrix,
@@ -214,6 +360,296 @@ impl<'r> Mapa<'r> {
         });
 
         // fix offsets else it crashes
-        self.update_offsets()
+        self.update_offsets(origin)
+    }
+
+    pub fn add_pickup(&mut self, editor_id: u32, pickup_pos: [f32; 3]) {
+        self.add_object(
+            MapaObjectType::Pickup,
+            MapaObjectVisibilityMode::Always,
+            editor_id,
+            pickup_pos,
+            [0.0, 0.0, 0.0],
+        )
+    }
+
+    /// Returns the number of objects removed
+    pub fn remove_objects(&mut self, editor_id: u32) -> usize {
+        let origin = self.surface_offset_origin();
+        let objects = self.objects.as_mut_vec();
+        let len = objects.len();
+        objects.retain(|obj| obj.editor_id != editor_id);
+        let removed = len - objects.len();
+        self.update_offsets(origin);
+        removed
     }
+
+    /// Recolors a door icon, keeping the ceiling/floor variant of vertical doors. `door_type` is
+    /// one of the horizontal door types. Returns false if the door isn't on this map.
+    pub fn set_door_type(&mut self, editor_id: u32, door_type: MapaObjectType) -> bool {
+        let door = match self
+            .objects
+            .iter_mut()
+            .find(|obj| obj.editor_id == editor_id && obj.is_door())
+        {
+            Some(door) => door,
+            None => return false,
+        };
+
+        let color = match door_type {
+            MapaObjectType::DoorIce => Some(0),
+            MapaObjectType::DoorWave => Some(1),
+            MapaObjectType::DoorPlasma => Some(2),
+            _ => None,
+        };
+        let variant = VERTICAL_DOOR_TYPES
+            .iter()
+            .find_map(|types| types.iter().position(|t| *t as u32 == door.type_));
+        door.type_ = match (color, variant) {
+            (Some(color), Some(variant)) => VERTICAL_DOOR_TYPES[color][variant] as u32,
+            _ => door_type as u32,
+        };
+
+        true
+    }
+
+    /// Adds a flat polygon to the map geometry. Vertices are given in winding order.
+    pub fn add_surface(&mut self, polygon: &[[f32; 3]]) -> Result<(), String> {
+        if polygon.len() < 3 {
+            return Err(format!(
+                "Map surfaces need at least 3 vertices, got {}",
+                polygon.len()
+            ));
+        }
+        let first_index = self.vertices.len();
+        if first_index + polygon.len() > 256 {
+            return Err("Map has too many vertices to add this surface (max 256)".to_string());
+        }
+
+        let origin = self.surface_offset_origin();
+
+        let mut centroid = [0.0f32; 3];
+        for vertex in polygon {
+            for i in 0..3 {
+                centroid[i] += vertex[i] / polygon.len() as f32;
+            }
+        }
+        let (a, b, c) = (polygon[0], polygon[1], polygon[2]);
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let normal = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2])
+            .sqrt()
+            .max(f32::EPSILON);
+
+        for vertex in polygon {
+            for (i, coord) in vertex.iter().enumerate() {
+                self.aabb[i] = self.aabb[i].min(*coord);
+                self.aabb[i + 3] = self.aabb[i + 3].max(*coord);
+            }
+        }
+        self.vertices
+            .as_mut_vec()
+            .extend(polygon.iter().map(|v| GenericArray::from(*v)));
+
+        let indices: Vec<u8> = (first_index..first_index + polygon.len())
+            .map(|i| i as u8)
+            .collect();
+        let mut surface = MapaSurface {
+            primitives: vec![MapaPrimitive {
+                type_: GX_TRIANGLE_FAN,
+                indices: indices.into(),
+            }]
+            .into(),
+            borders: vec![].into(),
+        };
+        surface.regenerate_borders();
+
+        // the first vector of the header is the surface normal
+        self.surface_headers.as_mut_vec().push(MapaSurfaceHeader {
+            center: normal.map(|n| n / length).into(),
+            center_of_mass: centroid.into(),
+            primitive_table_start: 0,
+            border_table_start: 0,
+        });
+        self.surfaces.as_mut_vec().push(surface);
+
+        self.update_offsets(origin);
+
+        Ok(())
+    }
+
+    pub fn remove_surface(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.surfaces.len() {
+            return Err(format!(
+                "Map surface {} doesn't exist, there are only {}",
+                index,
+                self.surfaces.len()
+            ));
+        }
+
+        let origin = self.surface_offset_origin();
+        self.surface_headers.as_mut_vec().remove(index);
+        self.surfaces.as_mut_vec().remove(index);
+        self.update_offsets(origin);
+
+        Ok(())
+    }
+
+    /// Rebuilds every surface outline from the map geometry. Only the edges which belong to
+    /// exactly one triangle of the map are outlined, so the edges where two surfaces meet aren't.
+    /// Edges are compared by vertex position, as adjacent surfaces don't always share vertices.
+    pub fn regenerate_outline(&mut self) {
+        let origin = self.surface_offset_origin();
+
+        let positions: Vec<[u32; 3]> = self
+            .vertices
+            .iter()
+            .map(|v| [v[0].to_bits(), v[1].to_bits(), v[2].to_bits()])
+            .collect();
+        let edge_key = |(a, b): (u8, u8)| {
+            let (a, b) = (positions[a as usize], positions[b as usize]);
+            (a.min(b), a.max(b))
+        };
+
+        let mut edge_counts = HashMap::<([u32; 3], [u32; 3]), u32>::new();
+        for surface in self.surfaces.iter() {
+            for edge in surface.triangle_edges() {
+                *edge_counts.entry(edge_key(edge)).or_insert(0) += 1;
+            }
+        }
+
+        for surface in self.surfaces.as_mut_vec() {
+            let mut edges = surface.triangle_edges();
+            edges.retain(|edge| edge_counts[&edge_key(*edge)] == 1);
+            surface.set_borders(edges);
+        }
+
+        self.update_offsets(origin);
+    }
+}
+
+#[test]
+fn test_mapa_add_surface() {
+    use reader_writer::{Reader, Writable};
+
+    let mut mapa = Mapa {
+        type_: 0,
+        visibility_mode: MapaObjectVisibilityMode::MapStationOrVisit as u32,
+        aabb: [0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+        objects: vec![].into(),
+        vertices: vec![].into(),
+        surface_headers: vec![].into(),
+        surfaces: vec![].into(),
+    };
+    mapa.add_surface(&[
+        [0.0, 0.0, 0.0],
+        [10.0, 0.0, 0.0],
+        [10.0, 10.0, 0.0],
+        [0.0, 10.0, 0.0],
+    ])
+    .unwrap();
+    mapa.add_pickup(1, [5.0, 5.0, 0.0]);
+
+    let mut bytes = vec![];
+    mapa.write_to(&mut bytes).unwrap();
+    let mapa: Mapa = Reader::new(&bytes).read(());
+
+    let header = mapa.surface_headers.iter().next().unwrap();
+    assert_eq!(header.primitive_table_start, 0x50 + 4 * 0xC + 0x20);
+    assert_eq!(
+        header.border_table_start,
+        header.primitive_table_start + 4 + 12
+    );
+    assert_eq!(mapa.aabb[3], 10.0);
+
+    let surface = mapa.surfaces.iter().next().unwrap();
+    let borders: Vec<Vec<u8>> = surface
+        .borders
+        .iter()
+        .map(|border| border.indices.iter().map(|i| *i).collect())
+        .collect();
+    assert_eq!(borders.len(), 1);
+    assert_eq!(borders[0].len(), 5);
+    assert_eq!(borders[0].first(), borders[0].last());
+}
+
+#[test]
+fn test_mapa_regenerate_outline() {
+    let mut mapa = Mapa {
+        type_: 0,
+        visibility_mode: 0,
+        aabb: [0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+        objects: vec![].into(),
+        vertices: vec![].into(),
+        surface_headers: vec![].into(),
+        surfaces: vec![].into(),
+    };
+    // Two squares sharing the edge at x = 10, each with its own vertices
+    mapa.add_surface(&[
+        [0.0, 0.0, 0.0],
+        [10.0, 0.0, 0.0],
+        [10.0, 10.0, 0.0],
+        [0.0, 10.0, 0.0],
+    ])
+    .unwrap();
+    mapa.add_surface(&[
+        [10.0, 0.0, 0.0],
+        [20.0, 0.0, 0.0],
+        [20.0, 10.0, 0.0],
+        [10.0, 10.0, 0.0],
+    ])
+    .unwrap();
+    mapa.regenerate_outline();
+
+    let vertices: Vec<[f32; 3]> = mapa.vertices.iter().map(|v| [v[0], v[1], v[2]]).collect();
+    for surface in mapa.surfaces.iter() {
+        let borders: Vec<Vec<u8>> = surface
+            .borders
+            .iter()
+            .map(|border| border.indices.iter().map(|i| *i).collect())
+            .collect();
+        assert_eq!(borders.len(), 1);
+        assert_eq!(borders[0].len(), 4);
+        for pair in borders[0].windows(2) {
+            let (a, b) = (vertices[pair[0] as usize], vertices[pair[1] as usize]);
+            assert!(a[0] != 10.0 || b[0] != 10.0, "shared edge outlined");
+        }
+    }
+}
+
+#[test]
+fn test_mapa_vertical_door_type() {
+    let mut mapa = Mapa {
+        type_: 0,
+        visibility_mode: 0,
+        aabb: [0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+        objects: vec![].into(),
+        vertices: vec![].into(),
+        surface_headers: vec![].into(),
+        surfaces: vec![].into(),
+    };
+    mapa.add_object(
+        MapaObjectType::DoorIceFloor,
+        MapaObjectVisibilityMode::Always,
+        7,
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 0.0],
+    );
+
+    assert!(mapa.set_door_type(7, MapaObjectType::DoorPlasma));
+    assert_eq!(
+        mapa.objects.iter().next().unwrap().type_,
+        MapaObjectType::DoorPlasmaFloor as u32
+    );
+    assert!(mapa.set_door_type(7, MapaObjectType::DoorNormal));
+    assert_eq!(
+        mapa.objects.iter().next().unwrap().type_,
+        MapaObjectType::DoorNormal as u32
+    );
+    assert!(!mapa.set_door_type(8, MapaObjectType::DoorNormal));
 }
@@ -0,0 +1,78 @@
+use auto_struct_macros::auto_struct;
+use reader_writer::{generic_array::GenericArray, typenum::*, CStr, LazyArray};
+
+use crate::{res_id::*, ResId};
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct Mapu<'r> {
+    #[auto_struct(expect = 0xABCDEF01)]
+    pub magic: u32,
+    #[auto_struct(expect = 1)]
+    pub version: u32,
+
+    pub hexagon_mapa: ResId<MAPA>,
+
+    #[auto_struct(derive = worlds.len() as u32)]
+    pub world_count: u32,
+    #[auto_struct(init = (world_count as usize, ()))]
+    pub worlds: LazyArray<'r, MapuWorld<'r>>,
+
+    #[auto_struct(pad_align = 32)]
+    _pad: (),
+}
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct MapuWorld<'r> {
+    pub label: CStr<'r>,
+    pub mlvl: ResId<MLVL>,
+    pub transform: GenericArray<f32, U12>,
+
+    #[auto_struct(derive = hexagon_transforms.len() as u32)]
+    pub hexagon_count: u32,
+    #[auto_struct(init = (hexagon_count as usize, ()))]
+    pub hexagon_transforms: LazyArray<'r, GenericArray<f32, U12>>,
+
+    // RGBA of the surface when this world is selected
+    pub color: GenericArray<f32, U4>,
+}
+
+impl<'r> Mapu<'r> {
+    pub fn world_index(&self, mlvl: u32) -> Option<usize> {
+        self.worlds
+            .iter()
+            .position(|world| world.mlvl.to_u32() == mlvl)
+    }
+}
+
+#[test]
+fn test_mapu_round_trip() {
+    use std::{borrow::Cow, ffi::CString};
+
+    use reader_writer::{Reader, Writable};
+
+    let mapu = Mapu {
+        hexagon_mapa: ResId::new(0x12345678),
+        worlds: vec![MapuWorld {
+            label: Cow::Owned(CString::new("IntroLevel").unwrap()),
+            mlvl: ResId::new(0x158efe17),
+            transform: [1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 1.0, 3.0].into(),
+            hexagon_transforms: vec![[0.0; 12].into(), [1.0; 12].into()].into(),
+            color: [1.0, 0.5, 0.25, 1.0].into(),
+        }]
+        .into(),
+    };
+
+    let mut bytes = vec![];
+    mapu.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes.len() % 32, 0);
+
+    let mapu: Mapu = Reader::new(&bytes).read(());
+    assert_eq!(mapu.hexagon_mapa.to_u32(), 0x12345678);
+    assert_eq!(mapu.world_index(0x158efe17), Some(0));
+    let world = mapu.worlds.iter().next().unwrap();
+    assert_eq!(world.label.to_str().unwrap(), "IntroLevel");
+    assert_eq!(world.hexagon_transforms.len(), 2);
+    assert_eq!(world.color[1], 0.5);
+}
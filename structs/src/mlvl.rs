@@ -2,8 +2,8 @@ use std::{io, iter::Peekable};
 
 use auto_struct_macros::auto_struct;
 use reader_writer::{
-    generic_array::GenericArray, typenum::*, CStr, FourCC, IteratorArray, LazyArray, Readable,
-    Reader, RoArray, RoArrayIter, Writable,
+    generic_array::GenericArray, pad_bytes_count, typenum::*, CStr, FourCC, IteratorArray,
+    LazyArray, Readable, Reader, RoArray, RoArrayIter, Writable,
 };
 
 use crate::{res_id::*, ResId};
@@ -62,6 +62,116 @@ pub struct Mlvl<'r> {
     _pad: (),
 }
 
+impl<'r> Mlvl<'r> {
+    /// Reads a MLVL, failing on an unknown version, truncated data or inconsistent dependency
+    /// and layer name tables instead of panicking like `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        fn read_u32(reader: &mut Reader, what: &str) -> Result<u32, String> {
+            if reader.len() < 4 {
+                Err(format!("MLVL {} is truncated", what))?
+            }
+            Ok(reader.read(()))
+        }
+
+        fn skip_array(reader: &mut Reader, what: &str, elem_size: usize) -> Result<u32, String> {
+            let count = read_u32(reader, what)?;
+            let size = (count as usize)
+                .checked_mul(elem_size)
+                .filter(|size| *size <= reader.len())
+                .ok_or_else(|| format!("MLVL {} are truncated", what))?;
+            reader.advance(size);
+            Ok(count)
+        }
+
+        // Offsets into a table of `len` entries, which must start at 0 and be ordered
+        fn check_offsets(reader: &mut Reader, what: &str, len: u32) -> Result<u32, String> {
+            let count = read_u32(reader, what)?;
+            let mut last = 0;
+            for i in 0..count {
+                let offset = read_u32(reader, what)?;
+                if (i == 0 && offset != 0) || offset < last || offset > len {
+                    Err(format!("MLVL {} are out of order", what))?
+                }
+                last = offset;
+            }
+            Ok(count)
+        }
+
+        let mut header = reader.clone();
+        let magic = read_u32(&mut header, "header")?;
+        let version = read_u32(&mut header, "header")?;
+        if magic != 0xDEAFBABE || version != 0x11 {
+            Err("Not a version 0x11 MLVL")?
+        }
+        // The world name, SAVW and skybox
+        for _ in 0..3 {
+            read_u32(&mut header, "header")?;
+        }
+        skip_array(&mut header, "memory relay connections", 11)?;
+
+        let area_count = read_u32(&mut header, "area count")?;
+        if read_u32(&mut header, "area count")? != 1 {
+            Err("Unknown MLVL area header")?
+        }
+        for i in 0..area_count {
+            let what = format!("area {}", i);
+            // The name, transform, bounding box, MREA and internal id
+            if header.len() < 84 {
+                Err(format!("MLVL {} is truncated", what))?
+            }
+            header.advance(84);
+            skip_array(&mut header, &format!("{} attached areas", what), 2)?;
+            if read_u32(&mut header, &what)? != 0 {
+                Err(format!("Unknown MLVL {} header", what))?
+            }
+            let dependency_count = skip_array(&mut header, &format!("{} dependencies", what), 8)?;
+            check_offsets(
+                &mut header,
+                &format!("{} dependency offsets", what),
+                dependency_count,
+            )?;
+            let dock_count = read_u32(&mut header, &format!("{} docks", what))?;
+            for _ in 0..dock_count {
+                skip_array(&mut header, &format!("{} dock connections", what), 8)?;
+                skip_array(&mut header, &format!("{} dock coordinates", what), 12)?;
+            }
+        }
+
+        // The world map, then an empty script instance array
+        read_u32(&mut header, "world map")?;
+        if header.len() < 5 || header[..5].iter().any(|b| *b != 0) {
+            Err("Unknown MLVL world map section")?
+        }
+        header.advance(5);
+        skip_array(&mut header, "audio groups", 8)?;
+        if header.first() != Some(&0) {
+            Err("Unknown MLVL audio group section")?
+        }
+        header.advance(1);
+        if skip_array(&mut header, "area layer flags", 12)? != area_count {
+            Err("MLVL doesn't have layer flags for every area")?
+        }
+
+        let name_count = read_u32(&mut header, "layer names")?;
+        for _ in 0..name_count {
+            let len = header
+                .iter()
+                .position(|b| *b == 0)
+                .ok_or("MLVL layer name is unterminated")?;
+            header.advance(len + 1);
+        }
+        let offset_count = check_offsets(&mut header, "layer name offsets", name_count)?;
+        if offset_count != area_count || offset_count == 0 {
+            Err("MLVL doesn't have layer names for every area")?
+        }
+        if header.len() < pad_bytes_count(32, reader.len() - header.len()) {
+            Err("MLVL padding is truncated")?
+        }
+
+        Ok(reader.read(()))
+    }
+}
+
 #[auto_struct(Readable, Writable, FixedSize)]
 #[derive(Clone, Debug)]
 pub struct MemoryRelayConn {
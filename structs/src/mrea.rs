@@ -2,8 +2,8 @@ use std::io;
 
 use auto_struct_macros::auto_struct;
 use reader_writer::{
-    generic_array::GenericArray, typenum::*, IteratorArray, LCow, LazyArray, Readable, Reader,
-    RoArray, RoArrayIter, Writable,
+    generic_array::GenericArray, pad_bytes_count, typenum::*, IteratorArray, LCow, LazyArray,
+    Readable, Reader, RoArray, RoArrayIter, Writable,
 };
 
 use crate::scly::Scly;
@@ -89,6 +89,31 @@ impl<'r> Mrea<'r> {
     pub fn lights_section_mut(&mut self) -> &mut Lights<'r> {
         self.sections.as_mut_vec()[self.lights_section_idx as usize].convert_to_lights()
     }
+
+    pub fn collision_section<'s>(&'s self) -> Result<LCow<'s, AreaCollision<'r>>, String> {
+        let section = self
+            .sections
+            .iter()
+            .nth(self.collision_section_idx as usize)
+            .ok_or("MREA doesn't have a collision section")?;
+        match section {
+            LCow::Owned(MreaSection::Unknown(ref reader))
+            | LCow::Borrowed(MreaSection::Unknown(ref reader)) => {
+                Ok(LCow::Owned(AreaCollision::try_read(&mut reader.clone())?))
+            }
+            LCow::Owned(MreaSection::Collision(collision)) => Ok(LCow::Owned(*collision)),
+            LCow::Borrowed(MreaSection::Collision(collision)) => Ok(LCow::Borrowed(&**collision)),
+            _ => Err("The collision section was read as another kind of section".to_string()),
+        }
+    }
+
+    pub fn collision_section_mut(&mut self) -> Result<&mut AreaCollision<'r>, String> {
+        self.sections
+            .as_mut_vec()
+            .get_mut(self.collision_section_idx as usize)
+            .ok_or("MREA doesn't have a collision section")?
+            .convert_to_collision()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -96,6 +121,7 @@ pub enum MreaSection<'r> {
     Unknown(Reader<'r>),
     Scly(Scly<'r>),
     Lights(Lights<'r>),
+    Collision(Box<AreaCollision<'r>>),
 }
 
 impl<'r> MreaSection<'r> {
@@ -122,6 +148,20 @@ impl<'r> MreaSection<'r> {
             _ => panic!(),
         }
     }
+
+    pub fn convert_to_collision(&mut self) -> Result<&mut AreaCollision<'r>, String> {
+        *self = match *self {
+            MreaSection::Unknown(ref reader) => {
+                MreaSection::Collision(Box::new(AreaCollision::try_read(&mut reader.clone())?))
+            }
+            MreaSection::Collision(ref mut collision) => return Ok(collision),
+            _ => Err("The collision section was read as another kind of section")?,
+        };
+        match *self {
+            MreaSection::Collision(ref mut collision) => Ok(collision),
+            _ => unreachable!(),
+        }
+    }
 }
 
 impl<'r> Readable<'r> for MreaSection<'r> {
@@ -137,6 +177,7 @@ impl<'r> Readable<'r> for MreaSection<'r> {
             MreaSection::Unknown(ref reader) => reader.len(),
             MreaSection::Scly(ref scly) => scly.size(),
             MreaSection::Lights(ref lights) => lights.size(),
+            MreaSection::Collision(ref collision) => collision.size(),
         }
     }
 }
@@ -150,6 +191,7 @@ impl<'r> Writable for MreaSection<'r> {
             }
             MreaSection::Scly(ref scly) => scly.write_to(writer),
             MreaSection::Lights(ref lights) => lights.write_to(writer),
+            MreaSection::Collision(ref collision) => collision.write_to(writer),
         }
     }
 }
@@ -181,8 +223,468 @@ pub struct LightLayer {
     pub brightness: f32,
     pub spot_cutoff: f32,
     pub unknown0: f32,
-    pub unknown1: u8,
+    pub cast_shadows: u8,
     pub unknown2: f32,
     pub falloff_type: u32,
     pub unknown3: f32,
 }
+
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct AreaCollision<'r> {
+    #[auto_struct(expect = 0x01000000)]
+    unknown: u32,
+    #[auto_struct(derive = collision.size() as u32)]
+    collision_size: u32,
+
+    pub collision: CollisionIndexData<'r>,
+
+    #[auto_struct(pad_align = 32)]
+    _pad: (),
+}
+
+impl<'r> AreaCollision<'r> {
+    /// Reads the collision section, failing on an unknown version or truncated data instead of
+    /// panicking like `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        fn read_u32(reader: &mut Reader, what: &str) -> Result<u32, String> {
+            if reader.len() < 4 {
+                Err(format!("Collision {} is truncated", what))?
+            }
+            Ok(reader.read(()))
+        }
+
+        let mut header = reader.clone();
+        if read_u32(&mut header, "header")? != 0x01000000 {
+            Err("Unknown collision section header")?
+        }
+        read_u32(&mut header, "header")?;
+        if read_u32(&mut header, "header")? != 0xDEAFBABE {
+            Err("Unknown collision index magic")?
+        }
+        let version = read_u32(&mut header, "header")?;
+        if version != 3 {
+            Err(format!("Unsupported collision index version {}", version))?
+        }
+        // The bounding box and octree root type
+        for _ in 0..7 {
+            read_u32(&mut header, "header")?;
+        }
+
+        // Each array follows its count, which is given in elements of this size
+        for (what, elem_size) in [
+            ("octree", 1),
+            ("materials", 4),
+            ("vertex materials", 1),
+            ("edge materials", 1),
+            ("triangle materials", 1),
+            ("edges", 4),
+            ("triangles", 2),
+            ("vertices", 12),
+        ] {
+            let count = read_u32(&mut header, what)? as usize;
+            if what == "triangles" && count % 3 != 0 {
+                Err("Collision triangle edge count isn't a multiple of 3")?
+            }
+            let size = count
+                .checked_mul(elem_size)
+                .filter(|size| *size <= header.len())
+                .ok_or_else(|| format!("Collision {} are truncated", what))?;
+            header.advance(size);
+        }
+        if header.len() < pad_bytes_count(32, reader.len() - header.len()) {
+            Err("Collision section padding is truncated")?
+        }
+
+        Ok(reader.read(()))
+    }
+}
+
+// Collision material flags
+pub const COLLISION_MATERIAL_SOLID: u32 = 1 << 19;
+pub const COLLISION_MATERIAL_FLIPPED: u32 = 1 << 25;
+pub const COLLISION_MATERIAL_CEILING: u32 = 1 << 29;
+pub const COLLISION_MATERIAL_WALL: u32 = 1 << 30;
+pub const COLLISION_MATERIAL_FLOOR: u32 = 1 << 31;
+
+// Octree node types
+pub const COLLISION_NODE_NONE: u32 = 0;
+pub const COLLISION_NODE_BRANCH: u32 = 1;
+pub const COLLISION_NODE_LEAF: u32 = 2;
+
+/// The collision triangles of an area, along with an octree used by the game to speed up
+/// collision tests. Coordinates are in world space.
+#[auto_struct(Readable, Writable)]
+#[derive(Debug, Clone)]
+pub struct CollisionIndexData<'r> {
+    #[auto_struct(expect = 0xDEAFBABE)]
+    magic: u32,
+    #[auto_struct(expect = 3)]
+    version: u32,
+
+    pub aabb: GenericArray<f32, U6>,
+    pub octree_root_type: u32,
+    #[auto_struct(derive = octree.len() as u32)]
+    octree_size: u32,
+    // See `CollisionIndexData::octree_root`
+    #[auto_struct(init = (octree_size as usize, ()))]
+    pub octree: LazyArray<'r, u8>,
+
+    #[auto_struct(derive = materials.len() as u32)]
+    material_count: u32,
+    #[auto_struct(init = (material_count as usize, ()))]
+    pub materials: LazyArray<'r, u32>,
+
+    // Indices into `materials`
+    #[auto_struct(derive = vertex_materials.len() as u32)]
+    vertex_material_count: u32,
+    #[auto_struct(init = (vertex_material_count as usize, ()))]
+    pub vertex_materials: LazyArray<'r, u8>,
+    #[auto_struct(derive = edge_materials.len() as u32)]
+    edge_material_count: u32,
+    #[auto_struct(init = (edge_material_count as usize, ()))]
+    pub edge_materials: LazyArray<'r, u8>,
+    #[auto_struct(derive = triangle_materials.len() as u32)]
+    triangle_material_count: u32,
+    #[auto_struct(init = (triangle_material_count as usize, ()))]
+    pub triangle_materials: LazyArray<'r, u8>,
+
+    #[auto_struct(derive = edges.len() as u32)]
+    edge_count: u32,
+    #[auto_struct(init = (edge_count as usize, ()))]
+    pub edges: LazyArray<'r, CollisionEdge>,
+    // The count is of edge indices, three per triangle
+    #[auto_struct(derive = (triangles.len() * 3) as u32)]
+    triangle_edge_count: u32,
+    #[auto_struct(init = (triangle_edge_count as usize / 3, ()))]
+    pub triangles: LazyArray<'r, CollisionTriangle>,
+
+    #[auto_struct(derive = vertices.len() as u32)]
+    vertex_count: u32,
+    #[auto_struct(init = (vertex_count as usize, ()))]
+    pub vertices: LazyArray<'r, GenericArray<f32, U3>>,
+}
+
+#[auto_struct(Readable, Writable, FixedSize)]
+#[derive(Debug, Clone)]
+pub struct CollisionEdge {
+    pub vertices: GenericArray<u16, U2>,
+}
+
+#[auto_struct(Readable, Writable, FixedSize)]
+#[derive(Debug, Clone)]
+pub struct CollisionTriangle {
+    pub edges: GenericArray<u16, U3>,
+}
+
+/// A node of the collision octree. The children of a branch split its bounding box in half
+/// along each axis; bit 0 of the child index selects the upper half of X, bit 1 of Y and bit 2
+/// of Z.
+#[derive(Debug, Clone)]
+pub enum CollisionOctreeNode {
+    Branch {
+        aabb: [f32; 6],
+        children: Box<[Option<CollisionOctreeNode>; 8]>,
+    },
+    Leaf {
+        aabb: [f32; 6],
+        triangles: Vec<u16>,
+    },
+}
+
+/// A triangle of the area collision with its vertices in front facing (counter-clockwise) order.
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionSurface {
+    pub vertices: [[f32; 3]; 3],
+    pub material: u32,
+}
+
+impl CollisionSurface {
+    pub fn normal(&self) -> [f32; 3] {
+        let [a, b, c] = self.vertices;
+        let n = cross(sub(b, a), sub(c, a));
+        let len = dot(n, n).sqrt();
+        if len > 0.0 {
+            [n[0] / len, n[1] / len, n[2] / len]
+        } else {
+            [0.0; 3]
+        }
+    }
+
+    /// Returns the distance along `direction` at which a ray starting at `origin` crosses the
+    /// triangle, from either side.
+    pub fn intersect_ray(&self, origin: [f32; 3], direction: [f32; 3]) -> Option<f32> {
+        // Möller–Trumbore
+        let [a, b, c] = self.vertices;
+        let e1 = sub(b, a);
+        let e2 = sub(c, a);
+        let p = cross(direction, e2);
+        let det = dot(e1, p);
+        if det.abs() < 1e-8 {
+            return None;
+        }
+        let t_vec = sub(origin, a);
+        let u = dot(t_vec, p) / det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = cross(t_vec, e1);
+        let v = dot(direction, q) / det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = dot(e2, q) / det;
+        if t >= 0.0 {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionRayHit {
+    pub distance: f32,
+    pub position: [f32; 3],
+    pub surface: CollisionSurface,
+}
+
+impl<'r> CollisionIndexData<'r> {
+    /// Looks up the vertices and material of a triangle.
+    pub fn surface(&self, triangle: usize) -> Option<CollisionSurface> {
+        let edge = |i: u16| {
+            self.edges
+                .get(i as usize)
+                .map(|e| [e.vertices[0], e.vertices[1]])
+        };
+        let vertex = |i: u16| self.vertices.get(i as usize).map(|v| [v[0], v[1], v[2]]);
+
+        let edges = self.triangles.get(triangle)?.edges;
+        let e0 = edge(edges[0])?;
+        let e1 = edge(edges[1])?;
+        // The third vertex is the one of the second edge that isn't part of the first
+        let third = if e1[0] != e0[0] && e1[0] != e0[1] {
+            e1[0]
+        } else {
+            e1[1]
+        };
+        let material = self
+            .triangle_materials
+            .get(triangle)
+            .and_then(|m| self.materials.get(*m as usize))
+            .map(|m| *m)
+            .unwrap_or(0);
+
+        let (first, second) = if material & COLLISION_MATERIAL_FLIPPED != 0 {
+            (e0[1], e0[0])
+        } else {
+            (e0[0], e0[1])
+        };
+        Some(CollisionSurface {
+            vertices: [vertex(first)?, vertex(second)?, vertex(third)?],
+            material,
+        })
+    }
+
+    pub fn surfaces(&self) -> Vec<CollisionSurface> {
+        (0..self.triangles.len())
+            .filter_map(|i| self.surface(i))
+            .collect()
+    }
+
+    /// Finds the closest triangle hit by a ray within `max_distance`. Areas only have a few
+    /// thousand triangles, so every triangle is tested instead of walking the octree.
+    pub fn raycast(
+        &self,
+        origin: [f32; 3],
+        direction: [f32; 3],
+        max_distance: f32,
+    ) -> Option<CollisionRayHit> {
+        let len = dot(direction, direction).sqrt();
+        if len == 0.0 {
+            return None;
+        }
+        let direction = [direction[0] / len, direction[1] / len, direction[2] / len];
+
+        self.surfaces()
+            .into_iter()
+            .filter_map(|surface| {
+                let distance = surface.intersect_ray(origin, direction)?;
+                if distance > max_distance {
+                    return None;
+                }
+                Some(CollisionRayHit {
+                    distance,
+                    position: [
+                        origin[0] + direction[0] * distance,
+                        origin[1] + direction[1] * distance,
+                        origin[2] + direction[2] * distance,
+                    ],
+                    surface,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Finds the surface directly below `point`, at most `max_drop` units down. Returns `None`
+    /// if there is nothing below or if the first surface hit isn't walkable, meaning it is too
+    /// steep or `point` is inside the level geometry.
+    pub fn find_floor(&self, point: [f32; 3], max_drop: f32) -> Option<[f32; 3]> {
+        let hit = self.raycast(point, [0.0, 0.0, -1.0], max_drop)?;
+        // Floors up to 45 degrees steep can be walked on
+        if hit.surface.normal()[2] < std::f32::consts::FRAC_1_SQRT_2 {
+            return None;
+        }
+        Some(hit.position)
+    }
+
+    /// Parses the octree, returning `None` if the area has no collision.
+    pub fn octree_root(&self) -> Result<Option<CollisionOctreeNode>, String> {
+        let octree: Vec<u8> = self.octree.iter().map(|b| *b).collect();
+        let aabb = [
+            self.aabb[0],
+            self.aabb[1],
+            self.aabb[2],
+            self.aabb[3],
+            self.aabb[4],
+            self.aabb[5],
+        ];
+        read_octree_node(&octree, 0, self.octree_root_type, aabb)
+    }
+}
+
+fn read_octree_node(
+    octree: &[u8],
+    offset: usize,
+    node_type: u32,
+    aabb: [f32; 6],
+) -> Result<Option<CollisionOctreeNode>, String> {
+    let bytes = |start: usize, len: usize| {
+        octree
+            .get(start..start + len)
+            .ok_or_else(|| format!("Collision octree node at {:#x} is out of bounds", start))
+    };
+    let u16_at = |start: usize| bytes(start, 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let u32_at =
+        |start: usize| bytes(start, 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+
+    match node_type {
+        COLLISION_NODE_NONE => Ok(None),
+        COLLISION_NODE_BRANCH => {
+            // 2 bits of child type per child, padding, then the offsets of the children
+            // relative to the end of this header
+            let child_types = u16_at(offset)?;
+            let mut children: [Option<CollisionOctreeNode>; 8] = Default::default();
+            for (i, child) in children.iter_mut().enumerate() {
+                let child_type = (child_types >> (i * 2)) as u32 & 0x3;
+                let child_offset = offset + 36 + u32_at(offset + 4 + i * 4)? as usize;
+                let mut child_aabb = aabb;
+                for axis in 0..3 {
+                    let mid = (aabb[axis] + aabb[axis + 3]) / 2.0;
+                    if i & (1 << axis) != 0 {
+                        child_aabb[axis] = mid;
+                    } else {
+                        child_aabb[axis + 3] = mid;
+                    }
+                }
+                *child = read_octree_node(octree, child_offset, child_type, child_aabb)?;
+            }
+            Ok(Some(CollisionOctreeNode::Branch {
+                aabb,
+                children: Box::new(children),
+            }))
+        }
+        COLLISION_NODE_LEAF => {
+            let mut aabb = [0.0; 6];
+            for (i, f) in aabb.iter_mut().enumerate() {
+                *f = f32::from_bits(u32_at(offset + i * 4)?);
+            }
+            let count = u16_at(offset + 24)? as usize;
+            let triangles = (0..count)
+                .map(|i| u16_at(offset + 26 + i * 2))
+                .collect::<Result<_, _>>()?;
+            Ok(Some(CollisionOctreeNode::Leaf { aabb, triangles }))
+        }
+        _ => Err(format!("Unknown collision octree node type {}", node_type)),
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[test]
+fn test_collision_floor() {
+    // A 10x10 floor at z = 0, split into two triangles, with the second one flipped
+    let collision = AreaCollision {
+        collision: CollisionIndexData {
+            aabb: [0.0, 0.0, 0.0, 10.0, 10.0, 0.0].into(),
+            octree_root_type: COLLISION_NODE_NONE,
+            octree: vec![].into(),
+            materials: vec![
+                COLLISION_MATERIAL_SOLID | COLLISION_MATERIAL_FLOOR,
+                COLLISION_MATERIAL_SOLID | COLLISION_MATERIAL_FLOOR | COLLISION_MATERIAL_FLIPPED,
+            ]
+            .into(),
+            vertex_materials: vec![0; 4].into(),
+            edge_materials: vec![0; 5].into(),
+            triangle_materials: vec![0, 1].into(),
+            edges: [[0, 1], [1, 2], [2, 0], [3, 2], [0, 3]]
+                .iter()
+                .map(|e| CollisionEdge {
+                    vertices: (*e).into(),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            triangles: vec![
+                CollisionTriangle {
+                    edges: [0, 1, 2].into(),
+                },
+                CollisionTriangle {
+                    edges: [2, 3, 4].into(),
+                },
+            ]
+            .into(),
+            vertices: [
+                [0.0, 0.0, 0.0],
+                [10.0, 0.0, 0.0],
+                [10.0, 10.0, 0.0],
+                [0.0, 10.0, 0.0],
+            ]
+            .iter()
+            .map(|v| (*v).into())
+            .collect::<Vec<_>>()
+            .into(),
+        },
+    };
+    let mut bytes = vec![];
+    collision.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes.len() % 32, 0);
+
+    for len in [4, 40, 100, bytes.len() - 32] {
+        assert!(AreaCollision::try_read(&mut Reader::new(&bytes[..len])).is_err());
+    }
+    let collision = AreaCollision::try_read(&mut Reader::new(&bytes[..])).unwrap();
+    let collision = &collision.collision;
+    for surface in collision.surfaces() {
+        assert_eq!(surface.normal(), [0.0, 0.0, 1.0]);
+    }
+    assert_eq!(
+        collision.find_floor([2.0, 7.0, 5.0], 10.0),
+        Some([2.0, 7.0, 0.0])
+    );
+    assert_eq!(collision.find_floor([2.0, 7.0, 5.0], 1.0), None);
+    assert_eq!(collision.find_floor([12.0, 7.0, 5.0], 10.0), None);
+}
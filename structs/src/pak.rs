@@ -6,8 +6,8 @@ use reader_writer::{
 };
 
 use crate::{
-    ctwk::*, dumb::Dumb, evnt::Evnt, frme::Frme, hint::Hint, mapa::Mapa, mapw::Mapw, mlvl::Mlvl,
-    mrea::Mrea, savw::Savw, scan::Scan, strg::Strg,
+    anim::Anim, ctwk::*, dumb::Dumb, evnt::Evnt, font::Font, frme::Frme, hint::Hint, mapa::Mapa,
+    mapu::Mapu, mapw::Mapw, mlvl::Mlvl, mrea::Mrea, savw::Savw, scan::Scan, strg::Strg,
 };
 
 #[auto_struct(Readable, Writable)]
@@ -598,6 +598,10 @@ macro_rules! build_resource_data {
 }
 
 build_resource_data!(
+    Anim,
+    b"ANIM",
+    as_anim,
+    as_anim_mut,
     Dumb,
     b"DUMB",
     as_dumb,
@@ -606,6 +610,10 @@ build_resource_data!(
     b"EVNT",
     as_evnt,
     as_evnt_mut,
+    Font,
+    b"FONT",
+    as_font,
+    as_font_mut,
     Frme,
     b"FRME",
     as_frme,
@@ -618,6 +626,10 @@ build_resource_data!(
     b"MAPA",
     as_mapa,
     as_mapa_mut,
+    Mapu,
+    b"MAPU",
+    as_mapu,
+    as_mapu_mut,
     Mapw,
     b"MAPW",
     as_mapw,
@@ -1,10 +1,519 @@
+use std::{io, marker::PhantomData};
+
 use auto_struct_macros::auto_struct;
-// This is intentionally _very_ incomplete. The particle format is huge, so only some of
-// the substructures are implemented.
-use reader_writer::RoArray;
+use reader_writer::{FourCC, Readable, Reader, RoArray, Writable};
 
 use crate::{res_id::*, ResId};
 
+/// What an element of a particle description evaluates to. The same class id can mean different
+/// things depending on the kind of element which is expected, e.g. `CNST` is followed by a raw
+/// integer for an int element, but by three real elements for a vector element.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PartElementKind {
+    Int,
+    Real,
+    Vector,
+    Color,
+    ModVector,
+    Emitter,
+    Uv,
+    Bool,
+    Asset,
+    SpawnSystem,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PartKeys {
+    Int(Vec<i32>),
+    Real(Vec<f32>),
+    Vector(Vec<[f32; 3]>),
+    Color(Vec<[f32; 4]>),
+}
+
+/// A `KEYE` or `KEYP` element, which interpolates between keyframes over the lifetime of the
+/// emitter or of each particle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartKeyframes {
+    pub class: FourCC,
+    pub percent: u32,
+    pub unknown1: u32,
+    pub looping: bool,
+    pub unknown2: bool,
+    pub loop_end: u32,
+    pub loop_start: u32,
+    pub keys: PartKeys,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartSpawnItem {
+    pub id: u32,
+    pub fourcc: FourCC,
+    pub unknown0: u32,
+    pub unknown1: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartSpawnFrame {
+    pub frame: u32,
+    pub items: Vec<PartSpawnItem>,
+}
+
+/// Particle systems spawned on specific frames, see `Kssm`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartSpawnSystem {
+    pub unknown0: u32,
+    pub unknown1: u32,
+    pub end_frame: u32,
+    pub unknown2: u32,
+    pub frames: Vec<PartSpawnFrame>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PartElement {
+    // `NONE`
+    None,
+    // An element computed from other elements, like `ADD_` or `CHAN`. Vector and color
+    // constants are functions of real elements too.
+    Function {
+        class: FourCC,
+        args: Vec<PartElement>,
+    },
+    // `CNST` of an int or real element
+    Int(i32),
+    Real(f32),
+    Bool(bool),
+    // `CNST` followed by an asset id
+    Asset(u32),
+    Keyframes(PartKeyframes),
+    SpawnSystem(PartSpawnSystem),
+    // `SETR`, which names its two vector elements
+    SimpleEmitter {
+        location: Box<PartElement>,
+        velocity: Box<PartElement>,
+    },
+}
+
+/// A particle generator description (`GPSM`), stored as a list of properties in file order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Part<'r> {
+    pub properties: Vec<(FourCC, PartElement)>,
+    _lifetime: PhantomData<&'r ()>,
+}
+
+// The kind of element stored in each property
+fn property_kind(property: FourCC) -> Option<PartElementKind> {
+    use PartElementKind::*;
+    let kind = match property.as_bytes() {
+        b"MAXP" | b"LTME" | b"PSLT" | b"SEED" | b"CSSD" | b"NDSY" | b"NCSY" | b"PISY" | b"SISY"
+        | b"SESD" | b"SSSD" | b"PSWT" | b"MBSP" | b"LTYP" | b"LFOT" => Int,
+        b"GRTE" | b"LENG" | b"WIDT" | b"ROTA" | b"SIZE" | b"PSTS" | b"LINT" | b"LSLA" | b"LFOR"
+        | b"ADV1" | b"ADV2" | b"ADV3" | b"ADV4" | b"ADV5" | b"ADV6" | b"ADV7" | b"ADV8" => Real,
+        b"ILOC" | b"IVEC" | b"POFS" | b"PSIV" | b"PSOV" | b"SEPO" | b"SSPO" | b"PMSC" | b"PMOP"
+        | b"PMRT" | b"LOFF" | b"LDIR" => Vector,
+        b"COLR" | b"LCLR" | b"PMCL" => Color,
+        b"PSVM" | b"VEL1" | b"VEL2" | b"VEL3" | b"VEL4" => ModVector,
+        b"EMTR" => Emitter,
+        b"TEXR" | b"TIND" => Uv,
+        b"AAPH" | b"ZBUF" | b"SORT" | b"LINE" | b"LIT_" | b"MBLR" | b"PMAB" | b"PMUS" | b"PMOO"
+        | b"VMD1" | b"VMD2" | b"VMD3" | b"VMD4" | b"OPTS" | b"CIND" | b"ORNT" | b"RSOP"
+        | b"FXLL" => Bool,
+        b"ICTS" | b"IDTS" | b"IITS" | b"PMDL" | b"SSWH" | b"SELC" => Asset,
+        b"KSSM" => SpawnSystem,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// The kinds of the elements which follow the class id of a function element. Constants,
+/// keyframes and `NONE` are handled separately.
+pub fn part_function_args(
+    kind: PartElementKind,
+    class: FourCC,
+) -> Option<&'static [PartElementKind]> {
+    use PartElementKind::*;
+    let args: &'static [PartElementKind] = match (kind, class.as_bytes()) {
+        (Int, b"DETH" | b"ADD_" | b"IRND" | b"MULT" | b"RAND" | b"MODU" | b"SUB_") => &[Int, Int],
+        (Int, b"CLMP" | b"CHAN" | b"SPAH") => &[Int, Int, Int],
+        (Int, b"PULS") => &[Int, Int, Int, Int],
+        (Int, b"IMPL" | b"ILPT") => &[Int],
+        (Int, b"TSCL") => &[Real],
+        (Int, b"RTOI") => &[Real, Real],
+        (Int, b"GTCP" | b"GAPC" | b"GEMT") => &[],
+
+        (Real, b"LFTW" | b"ADD_" | b"IRND" | b"RAND" | b"MULT" | b"ISWT" | b"SUB_") => {
+            &[Real, Real]
+        }
+        (Real, b"CHAN") => &[Real, Real, Int],
+        (Real, b"CLMP" | b"SINE") => &[Real, Real, Real],
+        (Real, b"DOTP") => &[Vector, Vector],
+        (Real, b"PULS") => &[Int, Int, Real, Real],
+        (Real, b"SPAH") => &[Real, Int, Int],
+        (Real, b"RLPT") => &[Real],
+        (Real, b"CLTN" | b"CEQL") => &[Real, Real, Real, Real],
+        (Real, b"CRNG") => &[Real, Real, Real, Real, Real],
+        (Real, b"VMAG" | b"VXTR" | b"VYTR" | b"VZTR") => &[Vector],
+        (Real, b"CEXT") => &[Int],
+        (Real, b"ITRL") => &[Int, Real],
+        (Real, b"GTCR" | b"GTCG" | b"GTCB" | b"GTCA") => &[Color],
+        (
+            Real,
+            b"PAP1" | b"PAP2" | b"PAP3" | b"PAP4" | b"PAP5" | b"PAP6" | b"PAP7" | b"PAP8" | b"PSLL"
+            | b"PRLW",
+        ) => &[],
+
+        (Vector, b"CNST") => &[Real, Real, Real],
+        (Vector, b"CONE") => &[Vector, Real],
+        (Vector, b"CHAN") => &[Vector, Vector, Int],
+        (Vector, b"ANGC") => &[Real, Real, Real, Real, Real],
+        (Vector, b"ADD_" | b"MULT" | b"SUB_") => &[Vector, Vector],
+        (Vector, b"CCLU") => &[Vector, Vector, Int, Real],
+        (Vector, b"CIRC") => &[Vector, Vector, Real, Real, Real],
+        (Vector, b"RTOV") => &[Real],
+        (Vector, b"PULS") => &[Int, Int, Vector, Vector],
+        (Vector, b"CTVC") => &[Color],
+        (Vector, b"PVEL" | b"PLCO" | b"PLOC" | b"PSOF" | b"PSOU" | b"PSOR" | b"PSTR") => &[],
+
+        (Color, b"CNST") => &[Real, Real, Real, Real],
+        (Color, b"CHAN") => &[Color, Color, Int],
+        (Color, b"CFDE") => &[Color, Color, Real, Real],
+        (Color, b"FADE") => &[Color, Color, Real],
+        (Color, b"PULS") => &[Int, Int, Color, Color],
+        (Color, b"PCOL") => &[],
+
+        (ModVector, b"IMPL" | b"EMPL" | b"LMPL") => &[Vector, Real, Real, Real, Bool],
+        (ModVector, b"CHAN") => &[ModVector, ModVector, Int],
+        (ModVector, b"BNCE") => &[Vector, Vector, Real, Real, Bool],
+        (ModVector, b"CNST") => &[Real, Real, Real],
+        (ModVector, b"GRAV" | b"SPOS") => &[Vector],
+        (ModVector, b"EXPL") => &[Real, Real],
+        (ModVector, b"PULS") => &[Int, Int, ModVector, ModVector],
+        (ModVector, b"WIND") => &[Vector, Real],
+        (ModVector, b"SWRL") => &[Vector, Vector, Real, Real],
+
+        (Emitter, b"SEMR") => &[Vector, Vector],
+        (Emitter, b"SPHE") => &[Vector, Real, Real],
+        (Emitter, b"ASPH") => &[Vector, Real, Real, Real, Real, Real, Real],
+
+        (Uv, b"CNST") => &[Asset],
+        (Uv, b"ATEX") => &[Asset, Int, Int, Int, Int, Int, Bool],
+
+        _ => return None,
+    };
+    Some(args)
+}
+
+// Bounds checked reads, so that malformed or unsupported data is an error rather than a panic
+struct PartReader<'a, 'r>(&'a mut Reader<'r>);
+
+impl<'a, 'r> PartReader<'a, 'r> {
+    fn read<T: Readable<'r, Args = ()>>(&mut self) -> Result<T, String> {
+        let size = T::fixed_size().unwrap();
+        if self.0.len() < size {
+            return Err("unexpected end of data".to_string());
+        }
+        Ok(self.0.read(()))
+    }
+
+    fn count(&mut self) -> Result<usize, String> {
+        let count: u32 = self.read()?;
+        // Every entry takes at least 4 bytes, this stops garbage counts from allocating
+        if count as usize > self.0.len() / 4 {
+            return Err(format!("invalid count {}", count));
+        }
+        Ok(count as usize)
+    }
+
+    fn keyframes(&mut self, class: FourCC, kind: PartElementKind) -> Result<PartKeyframes, String> {
+        let percent = self.read()?;
+        let unknown1 = self.read()?;
+        let looping = self.read::<u8>()? != 0;
+        let unknown2 = self.read::<u8>()? != 0;
+        let loop_end = self.read()?;
+        let loop_start = self.read()?;
+        let count = self.count()?;
+        let keys = match kind {
+            PartElementKind::Int => {
+                PartKeys::Int((0..count).map(|_| self.read()).collect::<Result<_, _>>()?)
+            }
+            PartElementKind::Real => {
+                PartKeys::Real((0..count).map(|_| self.read()).collect::<Result<_, _>>()?)
+            }
+            PartElementKind::Vector => PartKeys::Vector(
+                (0..count)
+                    .map(|_| Ok([self.read()?, self.read()?, self.read()?]))
+                    .collect::<Result<_, String>>()?,
+            ),
+            PartElementKind::Color => PartKeys::Color(
+                (0..count)
+                    .map(|_| Ok([self.read()?, self.read()?, self.read()?, self.read()?]))
+                    .collect::<Result<_, String>>()?,
+            ),
+            _ => return Err(format!("{:?} elements can't have keyframes", kind)),
+        };
+        Ok(PartKeyframes {
+            class,
+            percent,
+            unknown1,
+            looping,
+            unknown2,
+            loop_end,
+            loop_start,
+            keys,
+        })
+    }
+
+    fn spawn_system(&mut self) -> Result<PartSpawnSystem, String> {
+        let unknown0 = self.read()?;
+        let unknown1 = self.read()?;
+        let end_frame = self.read()?;
+        let unknown2 = self.read()?;
+        let mut frames = vec![];
+        for _ in 0..self.count()? {
+            let frame = self.read()?;
+            let mut items = vec![];
+            for _ in 0..self.count()? {
+                items.push(PartSpawnItem {
+                    id: self.read()?,
+                    fourcc: self.read()?,
+                    unknown0: self.read()?,
+                    unknown1: self.read()?,
+                });
+            }
+            frames.push(PartSpawnFrame { frame, items });
+        }
+        Ok(PartSpawnSystem {
+            unknown0,
+            unknown1,
+            end_frame,
+            unknown2,
+            frames,
+        })
+    }
+
+    fn element(&mut self, kind: PartElementKind) -> Result<PartElement, String> {
+        use PartElementKind::*;
+        let class: FourCC = self.read()?;
+        let class_bytes = *class.as_bytes();
+        if &class_bytes == b"NONE" {
+            return Ok(PartElement::None);
+        }
+        let element = match (kind, &class_bytes) {
+            (Int, b"CNST") => PartElement::Int(self.read()?),
+            (Real, b"CNST") => PartElement::Real(self.read()?),
+            (Bool, b"CNST") => PartElement::Bool(self.read::<u8>()? != 0),
+            (Asset, b"CNST") => PartElement::Asset(self.read()?),
+            (SpawnSystem, b"CNST") => PartElement::SpawnSystem(self.spawn_system()?),
+            (Int | Real | Vector | Color, b"KEYE" | b"KEYP") => {
+                PartElement::Keyframes(self.keyframes(class, kind)?)
+            }
+            (Emitter, b"SETR") => {
+                let mut named_vector = |name: &[u8; 4]| {
+                    let prop: FourCC = self.read()?;
+                    if prop != FourCC::from_bytes(name) {
+                        return Err(format!(
+                            "expected {} in SETR, found {}",
+                            FourCC::from_bytes(name),
+                            prop
+                        ));
+                    }
+                    self.element(Vector)
+                };
+                let location = Box::new(named_vector(b"ILOC")?);
+                let velocity = Box::new(named_vector(b"IVEC")?);
+                PartElement::SimpleEmitter { location, velocity }
+            }
+            _ => {
+                let arg_kinds = part_function_args(kind, class)
+                    .ok_or_else(|| format!("unknown {:?} element {}", kind, class))?;
+                let mut args = vec![];
+                for arg_kind in arg_kinds {
+                    let arg = self.element(*arg_kind)?;
+                    // An animated texture without a texture has nothing else
+                    let stop = kind == Uv && arg == PartElement::None;
+                    args.push(arg);
+                    if stop {
+                        break;
+                    }
+                }
+                PartElement::Function { class, args }
+            }
+        };
+        Ok(element)
+    }
+}
+
+impl PartElement {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<u64> {
+        let cnst = FourCC::from_bytes(b"CNST");
+        Ok(match self {
+            PartElement::None => FourCC::from_bytes(b"NONE").write_to(writer)?,
+            PartElement::Function { class, args } => {
+                let mut len = class.write_to(writer)?;
+                for arg in args {
+                    len += arg.write_to(writer)?;
+                }
+                len
+            }
+            PartElement::Int(value) => cnst.write_to(writer)? + value.write_to(writer)?,
+            PartElement::Real(value) => cnst.write_to(writer)? + value.write_to(writer)?,
+            PartElement::Bool(value) => cnst.write_to(writer)? + (*value as u8).write_to(writer)?,
+            PartElement::Asset(id) => cnst.write_to(writer)? + id.write_to(writer)?,
+            PartElement::Keyframes(keyframes) => {
+                let mut len = keyframes.class.write_to(writer)?;
+                len += keyframes.percent.write_to(writer)?;
+                len += keyframes.unknown1.write_to(writer)?;
+                len += (keyframes.looping as u8).write_to(writer)?;
+                len += (keyframes.unknown2 as u8).write_to(writer)?;
+                len += keyframes.loop_end.write_to(writer)?;
+                len += keyframes.loop_start.write_to(writer)?;
+                match &keyframes.keys {
+                    PartKeys::Int(keys) => {
+                        len += (keys.len() as u32).write_to(writer)?;
+                        for key in keys {
+                            len += key.write_to(writer)?;
+                        }
+                    }
+                    PartKeys::Real(keys) => {
+                        len += (keys.len() as u32).write_to(writer)?;
+                        for key in keys {
+                            len += key.write_to(writer)?;
+                        }
+                    }
+                    PartKeys::Vector(keys) => {
+                        len += (keys.len() as u32).write_to(writer)?;
+                        for key in keys.iter().flatten() {
+                            len += key.write_to(writer)?;
+                        }
+                    }
+                    PartKeys::Color(keys) => {
+                        len += (keys.len() as u32).write_to(writer)?;
+                        for key in keys.iter().flatten() {
+                            len += key.write_to(writer)?;
+                        }
+                    }
+                }
+                len
+            }
+            PartElement::SpawnSystem(spawn) => {
+                let mut len = cnst.write_to(writer)?;
+                len += spawn.unknown0.write_to(writer)?;
+                len += spawn.unknown1.write_to(writer)?;
+                len += spawn.end_frame.write_to(writer)?;
+                len += spawn.unknown2.write_to(writer)?;
+                len += (spawn.frames.len() as u32).write_to(writer)?;
+                for frame in spawn.frames.iter() {
+                    len += frame.frame.write_to(writer)?;
+                    len += (frame.items.len() as u32).write_to(writer)?;
+                    for item in frame.items.iter() {
+                        len += item.id.write_to(writer)?;
+                        len += item.fourcc.write_to(writer)?;
+                        len += item.unknown0.write_to(writer)?;
+                        len += item.unknown1.write_to(writer)?;
+                    }
+                }
+                len
+            }
+            PartElement::SimpleEmitter { location, velocity } => {
+                FourCC::from_bytes(b"SETR").write_to(writer)?
+                    + FourCC::from_bytes(b"ILOC").write_to(writer)?
+                    + location.write_to(writer)?
+                    + FourCC::from_bytes(b"IVEC").write_to(writer)?
+                    + velocity.write_to(writer)?
+            }
+        })
+    }
+
+    fn visit_mut<F>(&mut self, kind: PartElementKind, f: &mut F)
+    where
+        F: FnMut(PartElementKind, &mut PartElement),
+    {
+        f(kind, self);
+        match self {
+            PartElement::Function { class, args } => {
+                let arg_kinds = part_function_args(kind, *class).unwrap_or(&[]);
+                for (arg, arg_kind) in args.iter_mut().zip(arg_kinds) {
+                    arg.visit_mut(*arg_kind, f);
+                }
+            }
+            PartElement::SimpleEmitter { location, velocity } => {
+                location.visit_mut(PartElementKind::Vector, f);
+                velocity.visit_mut(PartElementKind::Vector, f);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl<'r> Part<'r> {
+    pub fn new(properties: Vec<(FourCC, PartElement)>) -> Self {
+        Part {
+            properties,
+            _lifetime: PhantomData,
+        }
+    }
+
+    /// Reads a particle description, failing on anything that isn't understood. Many particles
+    /// use elements which aren't modelled, so unlike most formats, PART isn't `Readable` and this
+    /// is the only way to read one.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        let mut reader = PartReader(reader);
+        let magic: FourCC = reader.read()?;
+        if magic != FourCC::from_bytes(b"GPSM") {
+            return Err(format!("expected GPSM, found {}", magic));
+        }
+
+        let mut properties = vec![];
+        loop {
+            let property: FourCC = reader.read()?;
+            if property == FourCC::from_bytes(b"_END") {
+                break;
+            }
+            let kind =
+                property_kind(property).ok_or_else(|| format!("unknown property {}", property))?;
+            let element = reader
+                .element(kind)
+                .map_err(|e| format!("property {}: {}", property, e))?;
+            properties.push((property, element));
+        }
+        Ok(Part::new(properties))
+    }
+
+    pub fn property(&self, property: &[u8; 4]) -> Option<&PartElement> {
+        let property = FourCC::from_bytes(property);
+        self.properties
+            .iter()
+            .find(|(p, _)| *p == property)
+            .map(|(_, element)| element)
+    }
+
+    /// Calls `f` on every element of every property, along with the kind of the element. Parents
+    /// are visited before their arguments.
+    pub fn visit_elements_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(PartElementKind, &mut PartElement),
+    {
+        for (property, element) in self.properties.iter_mut() {
+            if let Some(kind) = property_kind(*property) {
+                element.visit_mut(kind, &mut f);
+            }
+        }
+    }
+}
+
+impl<'r> Writable for Part<'r> {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<u64> {
+        let mut len = FourCC::from_bytes(b"GPSM").write_to(writer)?;
+        for (property, element) in self.properties.iter() {
+            len += property.write_to(writer)?;
+            len += element.write_to(writer)?;
+        }
+        len += FourCC::from_bytes(b"_END").write_to(writer)?;
+        Ok(len)
+    }
+}
+
+// The spawn system property on its own, as found after `KSSMCNST` when scanning raw data.
+
 #[auto_struct(Readable)]
 #[derive(Debug, Clone)]
 pub struct Kssm<'r> {
@@ -34,3 +543,57 @@ pub struct KssmFrameInfoItem {
     pub unknown1: u32,
     pub unknown2: u32,
 }
+
+#[test]
+fn test_part_round_trip() {
+    let real = |value| PartElement::Real(value);
+    let part = Part::new(vec![
+        (b"MAXP".into(), PartElement::Int(20)),
+        (
+            b"COLR".into(),
+            PartElement::Function {
+                class: b"CNST".into(),
+                args: vec![real(1.0), real(0.5), real(0.25), real(1.0)],
+            },
+        ),
+        (
+            b"SIZE".into(),
+            PartElement::Keyframes(PartKeyframes {
+                class: b"KEYP".into(),
+                percent: 0,
+                unknown1: 0,
+                looping: true,
+                unknown2: false,
+                loop_end: 10,
+                loop_start: 0,
+                keys: PartKeys::Real(vec![0.0, 0.5, 1.0]),
+            }),
+        ),
+        (
+            b"EMTR".into(),
+            PartElement::SimpleEmitter {
+                location: Box::new(PartElement::None),
+                velocity: Box::new(PartElement::Function {
+                    class: b"CNST".into(),
+                    args: vec![real(0.0), real(0.0), real(1.0)],
+                }),
+            },
+        ),
+        (
+            b"TEXR".into(),
+            PartElement::Function {
+                class: b"CNST".into(),
+                args: vec![PartElement::Asset(0x12345678)],
+            },
+        ),
+        (b"ZBUF".into(), PartElement::Bool(true)),
+    ]);
+
+    let mut bytes = vec![];
+    part.write_to(&mut bytes).unwrap();
+    assert_eq!(Part::try_read(&mut Reader::new(&bytes[..])).unwrap(), part);
+
+    // Truncated data is an error rather than a panic
+    let truncated = &bytes[..bytes.len() - 6];
+    assert!(Part::try_read(&mut Reader::new(truncated)).is_err());
+}
@@ -114,6 +114,12 @@ impl<K> Writable for ResId<K> {
     }
 }
 
+impl<K> serde::Serialize for ResId<K> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
 impl<K: ResIdKind> fmt::Debug for ResId<K> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -1,5 +1,5 @@
 use auto_struct_macros::auto_struct;
-use reader_writer::{LazyArray, RoArray};
+use reader_writer::{pad_bytes_count, LazyArray, Reader, RoArray};
 
 use crate::{res_id::*, ResId};
 
@@ -43,6 +43,47 @@ pub struct Savw<'r> {
     _pad: (),
 }
 
+impl<'r> Savw<'r> {
+    /// Reads a SAVW, failing on an unknown version or truncated data instead of panicking like
+    /// `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        let mut header = reader.clone();
+        if header.len() < 12 {
+            Err("SAVW header is truncated")?
+        }
+        let magic: u32 = header.read(());
+        let version: u32 = header.read(());
+        if magic != 0xC001D00D || version != 3 {
+            Err("Not a version 3 SAVW")?
+        }
+        header.advance(4);
+
+        // Each array follows its count, which is given in elements of this size
+        for (what, elem_size) in [
+            ("cinematic skips", 4),
+            ("memory relays", 4),
+            ("layer toggles", 8),
+            ("doors", 4),
+            ("scans", 8),
+        ] {
+            if header.len() < 4 {
+                Err(format!("SAVW {} are truncated", what))?
+            }
+            let count: u32 = header.read(());
+            let size = (count as usize)
+                .checked_mul(elem_size)
+                .filter(|size| *size <= header.len())
+                .ok_or_else(|| format!("SAVW {} are truncated", what))?;
+            header.advance(size);
+        }
+        if header.len() < pad_bytes_count(32, reader.len() - header.len()) {
+            Err("SAVW padding is truncated")?
+        }
+
+        Ok(reader.read(()))
+    }
+}
+
 #[auto_struct(Readable, Writable, FixedSize)]
 #[derive(Clone, Debug)]
 pub struct LayerToggle {
@@ -228,6 +228,15 @@ macro_rules! build_scly_property {
                 }
             }
 
+            /// The name of the struct holding the properties, `None` if they haven't been read
+            pub fn struct_name(&self) -> Option<&'static str>
+            {
+                match *self {
+                    SclyProperty::Unknown { .. } => None,
+                    $(SclyProperty::$name(_) => Some(stringify!($name)),)*
+                }
+            }
+
             /* Position */
 
             pub fn supports_position(&self) -> bool {
@@ -568,6 +577,19 @@ macro_rules! build_scly_property {
             )*
         }
 
+        impl<'r> serde::Serialize for SclyProperty<'r>
+        {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            {
+                match self {
+                    SclyProperty::Unknown { object_type, .. } => Err(serde::ser::Error::custom(
+                        format!("Object type 0x{:02X} has no struct definition", object_type),
+                    )),
+                    $(SclyProperty::$name(inst) => inst.serialize(serializer),)*
+                }
+            }
+        }
+
         impl<'r> Readable<'r> for SclyProperty<'r>
         {
             type Args = (u8, usize);
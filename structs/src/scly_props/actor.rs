@@ -7,7 +7,7 @@ use crate::{
     SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct Actor<'r> {
     #[auto_struct(expect = 24)]
@@ -8,7 +8,7 @@ use crate::{
     SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ActorContraption<'r> {
     #[auto_struct(expect = 15)]
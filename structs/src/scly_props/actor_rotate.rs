@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr};
 
 use crate::{impl_rotation, SclyPropertyData};
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ActorRotate<'r> {
     #[auto_struct(expect = 6)]
@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr};
 
 use crate::{impl_position, impl_rotation, impl_scale, scly_props::structs::*, SclyPropertyData};
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct AmbientAI<'r> {
     #[auto_struct(expect = 16)]
@@ -6,7 +6,7 @@ use crate::{
     SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct AtomicAlpha<'r> {
     #[auto_struct(expect = 14)]
@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::U3, CStr};
 
 use crate::SclyPropertyData;
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct BallTrigger<'r> {
     #[auto_struct(expect = 9)]
@@ -6,7 +6,7 @@ use crate::{
     SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ChozoGhost<'r> {
     #[auto_struct(expect = 31)]
@@ -35,7 +35,7 @@ pub struct ChozoGhost<'r> {
     pub dont_cares: GenericArray<u32, U14>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct BehaveChance {
     #[auto_struct(expect = 7)]
@@ -3,7 +3,7 @@ use reader_writer::CStr;
 
 use crate::SclyPropertyData;
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct Counter<'r> {
     #[auto_struct(expect = 5)]
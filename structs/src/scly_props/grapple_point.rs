@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr};
 
 use crate::SclyPropertyData;
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct GrappleParams {
     #[auto_struct(expect = 12)]
@@ -24,7 +24,7 @@ pub struct GrappleParams {
     pub disable_turning: u8,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct GrapplePoint<'r> {
     #[auto_struct(expect = 5)]
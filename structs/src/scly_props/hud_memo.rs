@@ -3,7 +3,7 @@ use reader_writer::CStr;
 
 use crate::{res_id::*, ResId, SclyPropertyData};
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct HudMemo<'r> {
     #[auto_struct(expect = 6)]
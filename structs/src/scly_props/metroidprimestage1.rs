@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr};
 
 use crate::{scly_props::structs::*, SclyPropertyData};
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct MetroidPrimeStage1<'r> {
     #[auto_struct(expect = 22)]
@@ -31,7 +31,7 @@ pub struct MetroidPrimeStage1<'r> {
     pub exo_struct_b: ExoStructB,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ExoStructA {
     #[auto_struct(expect = 14)]
@@ -39,7 +39,7 @@ pub struct ExoStructA {
     pub dont_care: GenericArray<f32, U14>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ExoStructB {
     #[auto_struct(expect = 29)]
@@ -63,7 +63,7 @@ pub struct ExoStructB {
     pub exo_struct_bc: GenericArray<ExoStructBC, U4>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct CameraShakeData {
     // PrimeStruct2
@@ -73,7 +73,7 @@ pub struct CameraShakeData {
     pub components: GenericArray<CameraShakerComponent, U3>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct CameraShakerComponent {
     pub use_modulation: u8,
@@ -81,13 +81,13 @@ pub struct CameraShakerComponent {
     pub fm: CameraShakePoint,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct CameraShakePoint {
     pub dont_care: GenericArray<f32, U4>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ExoStructBA {
     #[auto_struct(expect = 9)]
@@ -102,7 +102,7 @@ pub struct ExoStructBA {
     pub part4: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ExoStructBB {
     pub beam_info: BeamInfo,
@@ -113,7 +113,7 @@ pub struct ExoStructBB {
     pub damage_info2: DamageInfo,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ExoStructBBA {
     #[auto_struct(expect = 8)]
@@ -121,7 +121,7 @@ pub struct ExoStructBBA {
     pub dont_care: GenericArray<u32, U8>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ExoStructBC {
     #[auto_struct(expect = 4)]
@@ -131,7 +131,7 @@ pub struct ExoStructBC {
     pub dont_care: GenericArray<u32, U2>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ExoProjectileInfo {
     #[auto_struct(expect = 10)]
@@ -9,7 +9,7 @@ use crate::{
     ResId, SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct MetroidPrimeStage2<'r> {
     #[auto_struct(expect = 11)]
@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr};
 
 use crate::SclyPropertyData;
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct NewCameraShaker<'r> {
     #[auto_struct(expect = 8)]
@@ -23,7 +23,7 @@ pub struct NewCameraShaker<'r> {
     pub shakers: GenericArray<NewCameraShakerComponent, U3>,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct NewCameraShakerComponent {
     pub unknown1: u32,
@@ -32,7 +32,7 @@ pub struct NewCameraShakerComponent {
     pub fm: NewCameraShakePoint,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct NewCameraShakePoint {
     pub unknown1: u32,
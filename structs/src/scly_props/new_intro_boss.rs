@@ -9,7 +9,7 @@ use crate::{
     SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct NewIntroBoss<'r> {
     #[auto_struct(expect = 13)]
@@ -6,7 +6,7 @@ use crate::{
     scly_props::structs::*, SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct Parasite<'r> {
     #[auto_struct(expect = 25)]
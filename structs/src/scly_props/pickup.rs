@@ -7,7 +7,7 @@ use crate::{
     ResId, SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct Pickup<'r> {
     #[auto_struct(expect = 18)]
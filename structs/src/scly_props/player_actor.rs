@@ -7,7 +7,7 @@ use crate::{
     ResId, SclyPropertyData,
 };
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct PlayerActor<'r> {
     #[auto_struct(expect = 19)]
@@ -39,7 +39,7 @@ pub struct PlayerActor<'r> {
     pub unknown8: u32,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct PlayerActorParams {
     #[auto_struct(derive = 5 + unknown5.is_some() as u32)]
@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr};
 
 use crate::SclyPropertyData;
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct PlayerHintStruct {
     #[auto_struct(expect = 15)]
@@ -26,7 +26,7 @@ pub struct PlayerHintStruct {
     pub face_object_on_unmorph: u8,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct PlayerHint<'r> {
     #[auto_struct(expect = 6)]
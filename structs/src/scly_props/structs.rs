@@ -3,7 +3,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*};
 
 use crate::{res_id::*, ResId};
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct GrappleParameters {
     #[auto_struct(expect = 12)]
@@ -13,7 +13,7 @@ pub struct GrappleParameters {
     pub disable_turning: u8,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ActorParameters {
     #[auto_struct(expect = 14)]
@@ -39,7 +39,7 @@ pub struct ActorParameters {
     pub unknown5: f32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct AnimationParameters {
     pub animation_character_set: u32,
@@ -47,7 +47,7 @@ pub struct AnimationParameters {
     pub default_animation: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct AncsProp {
     pub file_id: ResId<ANCS>,
@@ -55,7 +55,7 @@ pub struct AncsProp {
     pub default_animation: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct LightParameters {
     #[auto_struct(expect = 14)]
@@ -77,7 +77,7 @@ pub struct LightParameters {
     pub light_layer_id: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ScannableParameters {
     #[auto_struct(expect = 1)]
@@ -85,7 +85,7 @@ pub struct ScannableParameters {
     pub scan: ResId<SCAN>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct VisorParameters {
     #[auto_struct(expect = 3)]
@@ -95,7 +95,7 @@ pub struct VisorParameters {
     pub visor_mask: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Copy, Clone)]
 pub struct DamageInfo {
     #[auto_struct(expect = 4)]
@@ -106,7 +106,7 @@ pub struct DamageInfo {
     pub knockback_power: f32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct DamageVulnerability {
     #[auto_struct(expect = 18)]
@@ -143,7 +143,7 @@ pub enum TypeVulnerability {
     DirectNormal = 0x5,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct ChargedBeams {
     #[auto_struct(expect = 5)]
@@ -156,7 +156,7 @@ pub struct ChargedBeams {
     pub phazon: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct BeamCombos {
     #[auto_struct(expect = 5)]
@@ -169,7 +169,7 @@ pub struct BeamCombos {
     pub phazon: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct HealthInfo {
     #[auto_struct(expect = 2)]
@@ -179,7 +179,7 @@ pub struct HealthInfo {
     pub knockback_resistance: f32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct PatternedInfo {
     #[auto_struct(expect = 38)]
@@ -225,7 +225,7 @@ pub struct PatternedInfo {
     pub ice_shatter_sfx: u32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct BeamInfo {
     #[auto_struct(expect = 16)]
@@ -249,7 +249,7 @@ pub struct BeamInfo {
     pub outter_color: GenericArray<f32, U4>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct RidleyStruct1 {
     pub unknown0: u32,
@@ -269,7 +269,7 @@ pub struct RidleyStruct1 {
     pub color1: GenericArray<f32, U4>,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct RidleyStruct2 {
     pub unknown0: u32,
@@ -283,7 +283,7 @@ pub struct RidleyStruct2 {
     pub unknown8: u8,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct CameraShakerComponent {
     pub unknown1: u32,
@@ -292,7 +292,7 @@ pub struct CameraShakerComponent {
     pub fm: CameraShakePoint,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct CameraShakePoint {
     pub unknown1: u32,
@@ -303,7 +303,7 @@ pub struct CameraShakePoint {
     pub magnitude: f32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct CameraHintParameters {
     #[auto_struct(expect = 22)]
@@ -332,14 +332,14 @@ pub struct CameraHintParameters {
     pub unknown22: u8,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct BoolFloat {
     pub override_flags: u8,
     pub value: f32,
 }
 
-#[auto_struct(Readable, Writable, FixedSize)]
+#[auto_struct(Readable, Writable, FixedSize, Serialize)]
 #[derive(Debug, Clone)]
 pub struct BoolVec3 {
     pub override_flags: u8,
@@ -4,7 +4,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr};
 use crate::{scly_props::structs::DamageInfo, SclyPropertyData};
 
 // https://github.com/AxioDL/metaforce/blob/1655d229cfdfbd5f792a7c3e84adc862653f70a7/DataSpec/DNAMP1/ScriptObjects/Water.hpp
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct Water<'r> {
     #[auto_struct(expect = 63)]
@@ -73,7 +73,7 @@ pub struct Water<'r> {
     pub crash_the_game: u8,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct FluidUVMotion {
     pub fluid_layer_motion1: FluidLayerMotion,
@@ -83,7 +83,7 @@ pub struct FluidUVMotion {
     pub unknown2: f32,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct FluidLayerMotion {
     pub fluid_uv_motion: u32,
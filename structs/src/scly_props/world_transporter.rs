@@ -5,7 +5,7 @@ use reader_writer::{generic_array::GenericArray, typenum::*, CStr, CStrConversio
 
 use crate::{res_id::*, scly_props::structs::AncsProp, ResId, SclyPropertyData};
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct WorldTransporter<'r> {
     #[auto_struct(derive = 21 + 5 * pal_additions.is_some() as u32)]
@@ -38,7 +38,7 @@ pub struct WorldTransporter<'r> {
     pub pal_additions: Option<WorldTransporterPalAdditions<'r>>,
 }
 
-#[auto_struct(Readable, Writable)]
+#[auto_struct(Readable, Writable, Serialize)]
 #[derive(Debug, Clone)]
 pub struct WorldTransporterPalAdditions<'r> {
     pub audio_stream: CStr<'r>,
@@ -1,7 +1,11 @@
+use std::fmt;
+
 use auto_struct_macros::auto_struct;
 use reader_writer::{
-    FourCC, IteratorArray, LCow, LazyArray, LazyUtf16beStr, Readable, RoArray, RoArrayIter,
+    pad_bytes_count, FourCC, IteratorArray, LCow, LazyArray, LazyUtf16beStr, Readable, Reader,
+    RoArray, RoArrayIter,
 };
+use serde::{Deserialize, Serialize};
 
 static SUPPORTED_LANGUAGES: &[&[u8; 4]] = &[
     b"ENGL", b"DUTC", b"FREN", b"GERM", b"ITAL", b"JAPN", b"SPAN",
@@ -42,6 +46,50 @@ pub struct Strg<'r> {
 }
 
 impl<'r> Strg<'r> {
+    /// Reads a STRG, failing on an unknown version or truncated data instead of panicking like
+    /// `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        let mut header = reader.clone();
+        if header.len() < 16 {
+            Err("STRG header is truncated")?
+        }
+        let magic: u32 = header.read(());
+        let version: u32 = header.read(());
+        if magic != 0x87654321 || version != 0 {
+            Err("Not a version 0 STRG")?
+        }
+        let lang_count: u32 = header.read(());
+        let string_count: u32 = header.read(());
+        let langs_size = lang_count as usize * 8;
+        if header.len() < langs_size {
+            Err("STRG language table is truncated")?
+        }
+        header.advance(langs_size);
+
+        // The tables follow each other, and their strings are read up to the terminator
+        for table in 0..lang_count {
+            let offsets_size = 4 + string_count as usize * 4;
+            if header.len() < offsets_size {
+                Err(format!("STRG table {} is truncated", table))?
+            }
+            header.advance(offsets_size);
+            for string in 0..string_count {
+                let len = header
+                    .chunks_exact(2)
+                    .position(|c| c == [0, 0])
+                    .ok_or_else(|| {
+                        format!("STRG table {} string {} is unterminated", table, string)
+                    })?;
+                header.advance(len * 2 + 2);
+            }
+        }
+        if header.len() < pad_bytes_count(32, reader.len() - header.len()) {
+            Err("STRG padding is truncated")?
+        }
+
+        Ok(reader.read(()))
+    }
+
     fn is_jpn_version(languages: &[&[u8; 4]]) -> bool {
         languages.len() == 2
             && languages.iter().any(|lang| *lang == b"ENGL")
@@ -58,10 +106,8 @@ impl<'r> Strg<'r> {
             if languages.contains(&table.lang.as_bytes()) {
                 for string in strings.iter() {
                     if is_jpn {
-                        table
-                            .strings
-                            .as_mut_vec()
-                            .push(format!("&line-extra-space=4;&font=C29C51F1;{}", string).into());
+                        let string = MarkupBuilder::new().jpn_font().text(string).build();
+                        table.strings.as_mut_vec().push(string.into());
                     } else {
                         table.strings.as_mut_vec().push(string.to_string().into());
                     }
@@ -103,7 +149,7 @@ impl<'r> Strg<'r> {
     pub fn from_strings_jpn(strings: Vec<String>) -> Strg<'r> {
         let strings: LazyArray<LazyUtf16beStr> = strings
             .into_iter()
-            .map(|i| format!("&line-extra-space=4;&font=C29C51F1;{}", i).into())
+            .map(|i| MarkupBuilder::new().jpn_font().text(&i).build().into())
             .collect::<Vec<_>>()
             .into();
         Strg {
@@ -206,3 +252,459 @@ pub struct StrgStringTable<'r> {
     #[auto_struct(init = (string_count, ()))]
     pub strings: LazyArray<'r, LazyUtf16beStr<'r>>,
 }
+
+/*** Text markup ***/
+
+/// Horizontal alignment set by the `just` markup tag.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Justification {
+    Left,
+    Center,
+    Right,
+    Full,
+    NLeft,
+    NCenter,
+    NRight,
+}
+
+impl Justification {
+    const NAMES: &'static [(Justification, &'static str)] = &[
+        (Justification::Left, "left"),
+        (Justification::Center, "center"),
+        (Justification::Right, "right"),
+        (Justification::Full, "full"),
+        (Justification::NLeft, "nleft"),
+        (Justification::NCenter, "ncenter"),
+        (Justification::NRight, "nright"),
+    ];
+}
+
+/// Vertical alignment set by the `vjust` markup tag.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerticalJustification {
+    Top,
+    Center,
+    Bottom,
+    Full,
+    NTop,
+    NCenter,
+    NBottom,
+}
+
+impl VerticalJustification {
+    const NAMES: &'static [(VerticalJustification, &'static str)] = &[
+        (VerticalJustification::Top, "top"),
+        (VerticalJustification::Center, "center"),
+        (VerticalJustification::Bottom, "bottom"),
+        (VerticalJustification::Full, "full"),
+        (VerticalJustification::NTop, "ntop"),
+        (VerticalJustification::NCenter, "ncenter"),
+        (VerticalJustification::NBottom, "nbottom"),
+    ];
+}
+
+/// An image drawn inline with the text by the `image` markup tag.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkupImage {
+    Texture(u32),
+    // Cycles through the textures, showing each for `interval` seconds
+    Animated {
+        interval: f32,
+        textures: Vec<u32>,
+    },
+    Scaled {
+        scale: [f32; 2],
+        texture: u32,
+    },
+    ScaledAnimated {
+        interval: f32,
+        scale: [f32; 2],
+        textures: Vec<u32>,
+    },
+}
+
+/// A tag of the text markup understood by the game's text renderer, written as `&<tag>;`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkupTag {
+    // Saves the current font, colors, justification and spacing
+    Push,
+    // Restores the state saved by the matching push
+    Pop,
+    Font(u32),
+    MainColor([u8; 4]),
+    OutlineColor([u8; 4]),
+    GeomColor([u8; 4]),
+    Just(Justification),
+    VJust(VerticalJustification),
+    LineSpacing(f32),
+    LineExtraSpace(i32),
+    Image(MarkupImage),
+    // A well-formed tag this parser doesn't know about
+    Unknown(String),
+}
+
+fn parse_asset_id(text: &str) -> Result<u32, String> {
+    if text.is_empty() || text.len() > 8 {
+        return Err(format!("'{}' is not an asset id", text));
+    }
+    u32::from_str_radix(text, 16).map_err(|_| format!("'{}' is not an asset id", text))
+}
+
+fn parse_color(text: &str) -> Result<[u8; 4], String> {
+    let error = || format!("'{}' is not a color, expected #RRGGBB or #RRGGBBAA", text);
+    let hex = text.strip_prefix('#').ok_or_else(error)?;
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error());
+    }
+    let mut color = [0xFF; 4];
+    for (i, c) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *c = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    Ok(color)
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", text))
+}
+
+fn parse_image(text: &str) -> Result<MarkupImage, String> {
+    let args: Vec<&str> = text.split(',').map(|arg| arg.trim()).collect();
+    let expect_args = |count: usize| {
+        if args.len() < count {
+            Err(format!("'{}' is missing arguments", text))
+        } else {
+            Ok(())
+        }
+    };
+    let textures = |args: &[&str]| -> Result<Vec<u32>, String> {
+        args.iter().map(|arg| parse_asset_id(arg)).collect()
+    };
+    match args[0] {
+        "A" => {
+            expect_args(3)?;
+            Ok(MarkupImage::Animated {
+                interval: parse_number(args[1])?,
+                textures: textures(&args[2..])?,
+            })
+        }
+        "SI" => {
+            expect_args(4)?;
+            if args.len() > 4 {
+                return Err(format!("'{}' has too many arguments", text));
+            }
+            Ok(MarkupImage::Scaled {
+                scale: [parse_number(args[1])?, parse_number(args[2])?],
+                texture: parse_asset_id(args[3])?,
+            })
+        }
+        "SA" => {
+            expect_args(5)?;
+            Ok(MarkupImage::ScaledAnimated {
+                interval: parse_number(args[1])?,
+                scale: [parse_number(args[2])?, parse_number(args[3])?],
+                textures: textures(&args[4..])?,
+            })
+        }
+        _ if args.len() == 1 => Ok(MarkupImage::Texture(parse_asset_id(args[0])?)),
+        kind => Err(format!(
+            "'{}' is not an image kind, expected A, SI or SA",
+            kind
+        )),
+    }
+}
+
+impl MarkupTag {
+    /// Parses the text between `&` and `;`.
+    pub fn parse(tag: &str) -> Result<Self, String> {
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (tag, None),
+        };
+        let require_value = || value.ok_or_else(|| format!("'{}' needs a value", name));
+        let tag = match name {
+            "push" if value.is_none() => MarkupTag::Push,
+            "pop" if value.is_none() => MarkupTag::Pop,
+            "font" => MarkupTag::Font(parse_asset_id(require_value()?)?),
+            "main-color" => MarkupTag::MainColor(parse_color(require_value()?)?),
+            "outline-color" => MarkupTag::OutlineColor(parse_color(require_value()?)?),
+            "geom-color" => MarkupTag::GeomColor(parse_color(require_value()?)?),
+            "just" => {
+                let value = require_value()?;
+                let just = Justification::NAMES
+                    .iter()
+                    .find(|(_, n)| n.eq_ignore_ascii_case(value))
+                    .ok_or_else(|| format!("'{}' is not a justification", value))?;
+                MarkupTag::Just(just.0)
+            }
+            "vjust" => {
+                let value = require_value()?;
+                let just = VerticalJustification::NAMES
+                    .iter()
+                    .find(|(_, n)| n.eq_ignore_ascii_case(value))
+                    .ok_or_else(|| format!("'{}' is not a vertical justification", value))?;
+                MarkupTag::VJust(just.0)
+            }
+            "line-spacing" => MarkupTag::LineSpacing(parse_number(require_value()?)?),
+            "line-extra-space" => MarkupTag::LineExtraSpace(parse_number(require_value()?)?),
+            "image" => MarkupTag::Image(parse_image(require_value()?)?),
+            _ if name.is_empty() => return Err("empty tag".to_string()),
+            _ => MarkupTag::Unknown(tag.to_string()),
+        };
+        Ok(tag)
+    }
+}
+
+fn fmt_color(f: &mut fmt::Formatter, color: &[u8; 4]) -> fmt::Result {
+    write!(f, "#{:02X}{:02X}{:02X}", color[0], color[1], color[2])?;
+    if color[3] != 0xFF {
+        write!(f, "{:02X}", color[3])?;
+    }
+    Ok(())
+}
+
+fn fmt_textures(f: &mut fmt::Formatter, textures: &[u32]) -> fmt::Result {
+    for texture in textures {
+        write!(f, ",{:08X}", texture)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for MarkupTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "&")?;
+        match self {
+            MarkupTag::Push => write!(f, "push")?,
+            MarkupTag::Pop => write!(f, "pop")?,
+            MarkupTag::Font(font) => write!(f, "font={:08X}", font)?,
+            MarkupTag::MainColor(color) => {
+                write!(f, "main-color=")?;
+                fmt_color(f, color)?;
+            }
+            MarkupTag::OutlineColor(color) => {
+                write!(f, "outline-color=")?;
+                fmt_color(f, color)?;
+            }
+            MarkupTag::GeomColor(color) => {
+                write!(f, "geom-color=")?;
+                fmt_color(f, color)?;
+            }
+            MarkupTag::Just(just) => {
+                let name = Justification::NAMES
+                    .iter()
+                    .find(|(j, _)| j == just)
+                    .unwrap()
+                    .1;
+                write!(f, "just={}", name)?;
+            }
+            MarkupTag::VJust(just) => {
+                let name = VerticalJustification::NAMES
+                    .iter()
+                    .find(|(j, _)| j == just)
+                    .unwrap()
+                    .1;
+                write!(f, "vjust={}", name)?;
+            }
+            MarkupTag::LineSpacing(spacing) => write!(f, "line-spacing={}", spacing)?,
+            MarkupTag::LineExtraSpace(space) => write!(f, "line-extra-space={}", space)?,
+            MarkupTag::Image(MarkupImage::Texture(texture)) => write!(f, "image={:08X}", texture)?,
+            MarkupTag::Image(MarkupImage::Animated { interval, textures }) => {
+                write!(f, "image=A,{}", interval)?;
+                fmt_textures(f, textures)?;
+            }
+            MarkupTag::Image(MarkupImage::Scaled { scale, texture }) => {
+                write!(f, "image=SI,{},{},{:08X}", scale[0], scale[1], texture)?
+            }
+            MarkupTag::Image(MarkupImage::ScaledAnimated {
+                interval,
+                scale,
+                textures,
+            }) => {
+                write!(f, "image=SA,{},{},{}", interval, scale[0], scale[1])?;
+                fmt_textures(f, textures)?;
+            }
+            MarkupTag::Unknown(tag) => write!(f, "{}", tag)?,
+        }
+        write!(f, ";")
+    }
+}
+
+/// A piece of a marked up string.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkupElement<'a> {
+    Text(&'a str),
+    Tag(MarkupTag),
+}
+
+/// Splits `text` into plain text and markup tags. Malformed tags and pops without a matching
+/// push are errors, since the game's text renderer doesn't cope with them. `&&` is an escaped
+/// `&` and is kept as text.
+pub fn parse_markup(text: &str) -> Result<Vec<MarkupElement<'_>>, String> {
+    let mut elements = vec![];
+    let mut depth = 0usize;
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        if rest[start + 1..].starts_with('&') {
+            elements.push(MarkupElement::Text(&rest[..start + 2]));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        if start > 0 {
+            elements.push(MarkupElement::Text(&rest[..start]));
+        }
+        let len = rest[start..].find(';').ok_or_else(|| {
+            format!(
+                "unterminated tag '{}', tags must end with ';'",
+                &rest[start..]
+            )
+        })?;
+        let tag_text = &rest[start + 1..start + len];
+        let tag = MarkupTag::parse(tag_text)
+            .map_err(|e| format!("invalid tag '&{};': {}", tag_text, e))?;
+        match tag {
+            MarkupTag::Push => depth += 1,
+            MarkupTag::Pop if depth == 0 => {
+                return Err("'&pop;' without a matching '&push;'".to_string())
+            }
+            MarkupTag::Pop => depth -= 1,
+            _ => (),
+        }
+        elements.push(MarkupElement::Tag(tag));
+        rest = &rest[start + len + 1..];
+    }
+    if !rest.is_empty() {
+        elements.push(MarkupElement::Text(rest));
+    }
+    Ok(elements)
+}
+
+/// Builds a string with text markup, without having to spell out the tags by hand.
+#[derive(Clone, Debug, Default)]
+pub struct MarkupBuilder {
+    text: String,
+}
+
+impl MarkupBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text.push_str(text);
+        self
+    }
+
+    /// Like `text`, but escapes `&` so that `text` is drawn as-is rather than read as markup.
+    pub fn literal(mut self, text: &str) -> Self {
+        self.text += &text.replace('&', "&&");
+        self
+    }
+
+    pub fn tag(mut self, tag: MarkupTag) -> Self {
+        self.text += &tag.to_string();
+        self
+    }
+
+    pub fn push(self) -> Self {
+        self.tag(MarkupTag::Push)
+    }
+
+    pub fn pop(self) -> Self {
+        self.tag(MarkupTag::Pop)
+    }
+
+    pub fn font(self, font: u32) -> Self {
+        self.tag(MarkupTag::Font(font))
+    }
+
+    /// `color` is RGBA.
+    pub fn main_color(self, color: [u8; 4]) -> Self {
+        self.tag(MarkupTag::MainColor(color))
+    }
+
+    pub fn just(self, just: Justification) -> Self {
+        self.tag(MarkupTag::Just(just))
+    }
+
+    /// `spacing` scales the font's line height, 1.0 being the default.
+    pub fn line_spacing(self, spacing: f32) -> Self {
+        self.tag(MarkupTag::LineSpacing(spacing))
+    }
+
+    pub fn line_extra_space(self, space: i32) -> Self {
+        self.tag(MarkupTag::LineExtraSpace(space))
+    }
+
+    pub fn image(self, image: MarkupImage) -> Self {
+        self.tag(MarkupTag::Image(image))
+    }
+
+    /// Draws `text` in `color`, then goes back to the previous color.
+    pub fn colored(self, color: [u8; 4], text: &str) -> Self {
+        self.push().main_color(color).text(text).pop()
+    }
+
+    /// The font and line spacing which the NTSC-J version needs for text to be legible.
+    pub fn jpn_font(self) -> Self {
+        self.line_extra_space(4).font(0xC29C51F1)
+    }
+
+    pub fn build(self) -> String {
+        self.text
+    }
+}
+
+#[test]
+fn test_markup_round_trip() {
+    let text = "&just=center;Hold &image=SI,0.7,0.68,46434ED3; or &push;&main-color=#D91818;X&pop;";
+    let rebuilt: String = parse_markup(text)
+        .unwrap()
+        .iter()
+        .map(|element| match element {
+            MarkupElement::Text(text) => text.to_string(),
+            MarkupElement::Tag(tag) => tag.to_string(),
+        })
+        .collect();
+    assert_eq!(rebuilt, text);
+    let escaped = MarkupBuilder::new()
+        .just(Justification::Center)
+        .literal("Rock & Roll;")
+        .build();
+    assert_eq!(escaped, "&just=center;Rock && Roll;");
+    assert_eq!(
+        parse_markup(&escaped).unwrap(),
+        vec![
+            MarkupElement::Tag(MarkupTag::Just(Justification::Center)),
+            MarkupElement::Text("Rock &&"),
+            MarkupElement::Text(" Roll;"),
+        ]
+    );
+    assert_eq!(
+        MarkupBuilder::new().line_spacing(0.8).text("A").build(),
+        "&line-spacing=0.8;A"
+    );
+    assert!(parse_markup("Rock & Roll").is_err());
+    assert!(parse_markup("&push;&pop;&pop;").is_err());
+    assert!(parse_markup("&main-color=red;").is_err());
+}
+
+#[test]
+fn test_strg_try_read() {
+    let strg = Strg::from_strings(vec!["First\0".to_string(), "Second\0".to_string()]);
+    let mut bytes = vec![];
+    reader_writer::Writable::write_to(&strg, &mut bytes).unwrap();
+
+    let read = Strg::try_read(&mut Reader::new(&bytes)).unwrap();
+    let table = read.string_tables.iter().next().unwrap();
+    let strings: Vec<String> = table
+        .strings
+        .iter()
+        .map(|s| s.into_owned().into_string())
+        .collect();
+    assert_eq!(strings, ["First\0", "Second\0"]);
+
+    // Cut into the second string
+    assert!(Strg::try_read(&mut Reader::new(&bytes[..48])).is_err());
+    assert!(Strg::try_read(&mut Reader::new(&bytes[..12])).is_err());
+}
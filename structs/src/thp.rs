@@ -53,7 +53,7 @@ impl<'r> Thp<'r> {
             let curr = start.last_mut().unwrap();
             let next = rest.first_mut().unwrap();
             curr.frame_size_next = next.size() as u32;
-            next.frame_size_next = curr.size() as u32;
+            next.frame_size_prev = curr.size() as u32;
         }
     }
 }
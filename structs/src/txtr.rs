@@ -1,3 +1,5 @@
+use std::{collections::HashMap, convert::TryInto};
+
 use auto_struct_macros::auto_struct;
 use reader_writer::{
     generic_array::{
@@ -6,21 +8,22 @@ use reader_writer::{
     },
     IteratorArray, LazyArray, Reader, RoArray,
 };
+use texpresso::Format;
 
 #[derive(Debug, Clone)]
 pub struct MipmapSizeIter {
     width: usize,
     height: usize,
-    format: u32,
+    bits_per_pixel: usize,
     count: u32,
 }
 
 impl MipmapSizeIter {
-    fn new(width: u16, height: u16, format: u32, count: u32) -> Self {
+    fn new(width: u16, height: u16, format: &TxtrFormat, count: u32) -> Self {
         MipmapSizeIter {
             width: width as usize,
             height: height as usize,
-            format,
+            bits_per_pixel: format.bits_per_pixel(),
             count,
         }
     }
@@ -32,7 +35,7 @@ impl Iterator for MipmapSizeIter {
         if self.count == 0 {
             None
         } else {
-            let ret = format_pixel_bytes(self.format, self.height * self.width);
+            let ret = self.height * self.width * self.bits_per_pixel / 8;
             self.count -= 1;
             self.width /= 2;
             self.height /= 2;
@@ -63,10 +66,12 @@ pub struct Txtr<'r> {
     #[auto_struct(derive = format.palette())]
     palette: Option<TxtrPalette>,
 
-    #[auto_struct(literal = TxtrFormat::new(hdr_format, &palette))]
+    #[auto_struct(
+        literal = TxtrFormat::new(hdr_format, &palette).unwrap_or_else(|e| panic!("{}", e))
+    )]
     pub format: TxtrFormat,
 
-    #[auto_struct(init = MipmapSizeIter::new(width, height, format.txtr_format(), mipmap_count))]
+    #[auto_struct(init = MipmapSizeIter::new(width, height, &format, mipmap_count))]
     pub pixel_data: IteratorArray<'r, LazyArray<'r, u8>, MipmapSizeIter>,
     // #[auto_struct(pad_align = 32)]
     // _pad: (),
@@ -87,32 +92,40 @@ pub enum TxtrFormat {
 }
 
 impl TxtrFormat {
-    fn new(fmt: u32, palette: &Option<TxtrPalette>) -> Self {
-        match fmt {
+    fn new(fmt: u32, palette: &Option<TxtrPalette>) -> Result<Self, String> {
+        let palette_data = |expected_len: usize| {
+            let palette = palette.as_ref().ok_or("Paletted TXTR without a palette")?;
+            if palette.color_data.len() != expected_len {
+                Err(format!(
+                    "Expected a palette of {} bytes, found {}",
+                    expected_len,
+                    palette.color_data.len()
+                ))?
+            }
+            Ok::<_, String>((
+                TxtrPaletteFormat::from_u32(palette.format)?,
+                palette.color_data.iter(),
+            ))
+        };
+        Ok(match fmt {
             0x0 => TxtrFormat::I4,
             0x1 => TxtrFormat::I8,
             0x2 => TxtrFormat::Ia4,
             0x3 => TxtrFormat::Ia8,
             0x4 => {
-                let palette = palette.as_ref().unwrap();
-                TxtrFormat::C4(
-                    TxtrPaletteFormat::from_u32(palette.format),
-                    Box::new(palette.color_data.iter().collect()),
-                )
+                let (format, data) = palette_data(32)?;
+                TxtrFormat::C4(format, Box::new(data.collect()))
             }
             0x5 => {
-                let palette = palette.as_ref().unwrap();
-                TxtrFormat::C8(
-                    TxtrPaletteFormat::from_u32(palette.format),
-                    Box::new(palette.color_data.iter().collect()),
-                )
+                let (format, data) = palette_data(512)?;
+                TxtrFormat::C8(format, Box::new(data.collect()))
             }
             0x7 => TxtrFormat::Rgb565,
             0x8 => TxtrFormat::Rgb5A3,
             0x9 => TxtrFormat::Rgba8,
             0xa => TxtrFormat::Cmpr,
-            fmt => panic!("Unknown or unsupported TXTR format: {:#x}", fmt),
-        }
+            fmt => Err(format!("Unknown or unsupported TXTR format: {:#x}", fmt))?,
+        })
     }
 
     fn txtr_format(&self) -> u32 {
@@ -130,6 +143,326 @@ impl TxtrFormat {
         }
     }
 
+    fn bits_per_pixel(&self) -> usize {
+        match self {
+            TxtrFormat::I4 | TxtrFormat::C4(_, _) | TxtrFormat::Cmpr => 4,
+            TxtrFormat::I8 | TxtrFormat::Ia4 | TxtrFormat::C8(_, _) => 8,
+            TxtrFormat::Ia8 | TxtrFormat::Rgb565 | TxtrFormat::Rgb5A3 => 16,
+            TxtrFormat::Rgba8 => 32,
+        }
+    }
+
+    pub fn bytes_per_block(&self) -> usize {
+        match self {
+            TxtrFormat::Rgba8 => 64,
+            _ => 32,
+        }
+    }
+
+    pub fn block_dimensions(&self) -> (usize, usize) {
+        match self {
+            TxtrFormat::I4 => (8, 8),
+            TxtrFormat::I8 => (8, 4),
+            TxtrFormat::Ia4 => (8, 4),
+            TxtrFormat::Ia8 => (4, 4),
+            TxtrFormat::C4(_, _) => (8, 8),
+            TxtrFormat::C8(_, _) => (8, 4),
+            TxtrFormat::Rgb565 => (4, 4),
+            TxtrFormat::Rgb5A3 => (4, 4),
+            TxtrFormat::Rgba8 => (4, 4),
+            TxtrFormat::Cmpr => (8, 8),
+        }
+    }
+
+    pub fn is_paletted(&self) -> bool {
+        matches!(self, TxtrFormat::C4(_, _) | TxtrFormat::C8(_, _))
+    }
+
+    // Non-paletted textures are stored bottom row first
+    fn flipped(&self) -> bool {
+        !self.is_paletted()
+    }
+
+    /// Builds the palette of a paletted format from the colors used by `pixels` (RGBA). Does
+    /// nothing for other formats.
+    pub fn compute_palette(&mut self, pixels: &[u8]) -> Result<(), String> {
+        let (fmt, buf) = match self {
+            TxtrFormat::C4(fmt, buf) => (*fmt, &mut buf[..]),
+            TxtrFormat::C8(fmt, buf) => (*fmt, &mut buf[..]),
+            _ => return Ok(()),
+        };
+        for byte in buf.iter_mut() {
+            *byte = 0;
+        }
+        let max_colors = buf.len() / 2;
+        let mut palette_values = HashMap::with_capacity(max_colors);
+        for pixel in pixels.chunks(4) {
+            let encoded = fmt.encode(pixel.try_into().unwrap());
+            let pv_len = palette_values.len();
+            let idx = *palette_values.entry(encoded).or_insert(pv_len);
+            if idx >= max_colors {
+                Err(format!(
+                    "Image contains more than {} colors, which is the maximum for the chosen \
+                     paletted format",
+                    max_colors
+                ))?;
+            }
+            buf[idx * 2..idx * 2 + 2].copy_from_slice(&encoded[..]);
+        }
+        Ok(())
+    }
+
+    /// Decodes the raw data of a single `w` x `h` mipmap into row-major RGBA pixels. The
+    /// dimensions must be a multiple of the format's block dimensions.
+    pub fn decode_mipmap(&self, mipmap_data: &[u8], w: usize, h: usize) -> Vec<u8> {
+        let mut pixels = vec![0u8; w * h * 4];
+
+        let (block_w, block_h) = self.block_dimensions();
+        let mut decoded_block = vec![0u8; block_w * block_h * 4];
+        for (i, block) in mipmap_data.chunks(self.bytes_per_block()).enumerate() {
+            self.decode_block(block, &mut decoded_block[..]);
+            let outer_x = (i % (w / block_w)) * block_w;
+            let outer_y = (i / (w / block_w)) * block_h;
+            for inner_y in 0..block_h {
+                let y = if self.flipped() {
+                    h - 1 - (outer_y + inner_y)
+                } else {
+                    outer_y + inner_y
+                };
+                let block_start = inner_y * block_w * 4;
+                let pixels_start = (y * w + outer_x) * 4;
+                pixels[pixels_start..pixels_start + block_w * 4]
+                    .copy_from_slice(&decoded_block[block_start..block_start + block_w * 4]);
+            }
+        }
+
+        pixels
+    }
+
+    /// Encodes `w` x `h` row-major RGBA pixels into the raw data of a single mipmap. The palette
+    /// of paletted formats must already contain every color of the image.
+    pub fn encode_mipmap(&self, pixels: &[u8], w: usize, h: usize) -> Vec<u8> {
+        let (block_w, block_h) = self.block_dimensions();
+        let mut blocks = vec![0u8; w * h * self.bytes_per_block() / (block_w * block_h)];
+
+        let palette_indices = match self {
+            TxtrFormat::C4(_, palette) => palette_indices(&palette[..]),
+            TxtrFormat::C8(_, palette) => palette_indices(&palette[..]),
+            _ => HashMap::new(),
+        };
+
+        let mut block_pixels = vec![0u8; block_w * block_h * 4];
+        for (i, block) in blocks.chunks_mut(self.bytes_per_block()).enumerate() {
+            let outer_x = (i % (w / block_w)) * block_w;
+            let outer_y = (i / (w / block_w)) * block_h;
+            for inner_y in 0..block_h {
+                let y = if self.flipped() {
+                    h - 1 - (outer_y + inner_y)
+                } else {
+                    outer_y + inner_y
+                };
+                let block_start = inner_y * block_w * 4;
+                let pixels_start = (y * w + outer_x) * 4;
+                block_pixels[block_start..block_start + block_w * 4]
+                    .copy_from_slice(&pixels[pixels_start..pixels_start + block_w * 4]);
+            }
+            self.encode_block(block, &block_pixels[..], &palette_indices);
+        }
+
+        blocks
+    }
+
+    /// Decodes the raw data of a single mipmap of a paletted format into row-major palette
+    /// indices, without looking the colors up. Returns `None` for other formats.
+    pub fn decode_mipmap_indices(&self, mipmap_data: &[u8], w: usize, h: usize) -> Option<Vec<u8>> {
+        let pixels = self
+            .with_identity_palette()?
+            .decode_mipmap(mipmap_data, w, h);
+        Some(pixels.chunks(4).map(|pixel| pixel[0]).collect())
+    }
+
+    /// Encodes row-major palette indices into the raw data of a single mipmap of a paletted
+    /// format. Returns `None` for other formats.
+    pub fn encode_mipmap_indices(&self, indices: &[u8], w: usize, h: usize) -> Option<Vec<u8>> {
+        let pixels: Vec<u8> = indices
+            .iter()
+            .flat_map(|index| intensity_to_rgba(*index, 0xff))
+            .collect();
+        Some(
+            self.with_identity_palette()?
+                .encode_mipmap(&pixels[..], w, h),
+        )
+    }
+
+    // A copy of this format whose palette maps each index to an IA8 entry of the same intensity
+    fn with_identity_palette(&self) -> Option<TxtrFormat> {
+        let identity = |palette: &mut [u8]| {
+            for (i, entry) in palette.chunks_mut(2).enumerate() {
+                entry.copy_from_slice(&[0xff, i as u8]);
+            }
+        };
+        let mut format = match self {
+            TxtrFormat::C4(_, palette) => TxtrFormat::C4(TxtrPaletteFormat::Ia8, palette.clone()),
+            TxtrFormat::C8(_, palette) => TxtrFormat::C8(TxtrPaletteFormat::Ia8, palette.clone()),
+            _ => return None,
+        };
+        match &mut format {
+            TxtrFormat::C4(_, palette) => identity(&mut palette[..]),
+            TxtrFormat::C8(_, palette) => identity(&mut palette[..]),
+            _ => unreachable!(),
+        }
+        Some(format)
+    }
+
+    fn decode_block(&self, block: &[u8], pixels: &mut [u8]) {
+        match self {
+            TxtrFormat::I4 => {
+                for (texel, pixel) in block.iter().zip(pixels.chunks_mut(8)) {
+                    pixel[..4].copy_from_slice(&intensity_to_rgba(convert4to8(texel >> 4), 0xff));
+                    pixel[4..].copy_from_slice(&intensity_to_rgba(convert4to8(texel & 0xf), 0xff));
+                }
+            }
+            TxtrFormat::I8 => {
+                for (texel, pixel) in block.iter().zip(pixels.chunks_mut(4)) {
+                    pixel.copy_from_slice(&intensity_to_rgba(*texel, 0xff));
+                }
+            }
+            TxtrFormat::Ia4 => {
+                for (texel, pixel) in block.iter().zip(pixels.chunks_mut(4)) {
+                    let i = convert4to8(texel & 0xf);
+                    let a = convert4to8(texel >> 4);
+                    pixel.copy_from_slice(&intensity_to_rgba(i, a));
+                }
+            }
+            TxtrFormat::Ia8 => {
+                for (texel, pixel) in block.chunks(2).zip(pixels.chunks_mut(4)) {
+                    pixel.copy_from_slice(&decode_ia8(texel.try_into().unwrap()));
+                }
+            }
+            TxtrFormat::C4(fmt, palette) => {
+                let indices = block
+                    .iter()
+                    .flat_map(|texel| [texel >> 4, texel & 0xf])
+                    .map(|index| index as usize * 2);
+                for (index, pixel) in indices.zip(pixels.chunks_mut(4)) {
+                    let entry = palette[index..index + 2].try_into().unwrap();
+                    pixel.copy_from_slice(&fmt.decode(entry));
+                }
+            }
+            TxtrFormat::C8(fmt, palette) => {
+                for (index, pixel) in block.iter().zip(pixels.chunks_mut(4)) {
+                    let index = *index as usize * 2;
+                    let entry = palette[index..index + 2].try_into().unwrap();
+                    pixel.copy_from_slice(&fmt.decode(entry));
+                }
+            }
+            TxtrFormat::Rgb565 => {
+                for (texel, pixel) in block.chunks(2).zip(pixels.chunks_mut(4)) {
+                    let rgb = decode_rgb565(texel.try_into().unwrap());
+                    pixel.copy_from_slice(&[rgb[0], rgb[1], rgb[2], 0xff]);
+                }
+            }
+            TxtrFormat::Rgb5A3 => {
+                for (texel, pixel) in block.chunks(2).zip(pixels.chunks_mut(4)) {
+                    pixel.copy_from_slice(&decode_rgb5a3(texel.try_into().unwrap()));
+                }
+            }
+            TxtrFormat::Rgba8 => {
+                // The alpha and red channels are stored in the first half of the block, the green
+                // and blue channels in the second half
+                let (ar, gb) = block.split_at(32);
+                for (k, pixel) in pixels.chunks_mut(4).enumerate() {
+                    pixel.copy_from_slice(&[ar[k * 2 + 1], gb[k * 2], gb[k * 2 + 1], ar[k * 2]]);
+                }
+            }
+            TxtrFormat::Cmpr => {
+                for (i, sub_block) in block.chunks(8).enumerate() {
+                    let decoded = decompress_dxt1gcn_block(sub_block);
+                    let outer_x = i % 2 * 4;
+                    let outer_y = i / 2 * 4;
+                    for (k, decoded_pixel) in decoded.iter().enumerate() {
+                        let start = (outer_y + k / 4) * 32 + (outer_x + k % 4) * 4;
+                        pixels[start..start + 4].copy_from_slice(&decoded_pixel[..]);
+                    }
+                }
+            }
+        }
+    }
+
+    fn encode_block(
+        &self,
+        block: &mut [u8],
+        pixels: &[u8],
+        palette_indices: &HashMap<[u8; 2], u8>,
+    ) {
+        match self {
+            TxtrFormat::I4 => {
+                for (texel, pixel) in block.iter_mut().zip(pixels.chunks(8)) {
+                    *texel = (rgba_to_intensity(&pixel[..4]) & 0xf0)
+                        | (rgba_to_intensity(&pixel[4..]) >> 4);
+                }
+            }
+            TxtrFormat::I8 => {
+                for (texel, pixel) in block.iter_mut().zip(pixels.chunks(4)) {
+                    *texel = rgba_to_intensity(pixel);
+                }
+            }
+            TxtrFormat::Ia4 => {
+                for (texel, pixel) in block.iter_mut().zip(pixels.chunks(4)) {
+                    *texel = (pixel[3] & 0xf0) | (rgba_to_intensity(pixel) >> 4);
+                }
+            }
+            TxtrFormat::Ia8 => {
+                for (texel, pixel) in block.chunks_mut(2).zip(pixels.chunks(4)) {
+                    texel.copy_from_slice(&encode_ia8(pixel.try_into().unwrap()));
+                }
+            }
+            TxtrFormat::C4(fmt, _) => {
+                for (texel, pixels) in block.iter_mut().zip(pixels.chunks(8)) {
+                    let hi = palette_indices[&fmt.encode(pixels[..4].try_into().unwrap())];
+                    let lo = palette_indices[&fmt.encode(pixels[4..].try_into().unwrap())];
+                    *texel = (hi << 4) | lo;
+                }
+            }
+            TxtrFormat::C8(fmt, _) => {
+                for (texel, pixel) in block.iter_mut().zip(pixels.chunks(4)) {
+                    *texel = palette_indices[&fmt.encode(pixel.try_into().unwrap())];
+                }
+            }
+            TxtrFormat::Rgb565 => {
+                for (texel, pixel) in block.chunks_mut(2).zip(pixels.chunks(4)) {
+                    texel.copy_from_slice(&encode_rgb565(pixel[..3].try_into().unwrap()));
+                }
+            }
+            TxtrFormat::Rgb5A3 => {
+                for (texel, pixel) in block.chunks_mut(2).zip(pixels.chunks(4)) {
+                    texel.copy_from_slice(&encode_rgb5a3(pixel.try_into().unwrap()));
+                }
+            }
+            TxtrFormat::Rgba8 => {
+                let (ar, gb) = block.split_at_mut(32);
+                for (k, pixel) in pixels.chunks(4).enumerate() {
+                    ar[k * 2] = pixel[3];
+                    ar[k * 2 + 1] = pixel[0];
+                    gb[k * 2] = pixel[1];
+                    gb[k * 2 + 1] = pixel[2];
+                }
+            }
+            TxtrFormat::Cmpr => {
+                let mut sub_block_pixels = [[0u8; 4]; 16];
+                for (i, sub_block) in block.chunks_mut(8).enumerate() {
+                    let outer_x = i % 2 * 4;
+                    let outer_y = i / 2 * 4;
+                    for (k, sub_block_pixel) in sub_block_pixels.iter_mut().enumerate() {
+                        let start = (outer_y + k / 4) * 32 + (outer_x + k % 4) * 4;
+                        sub_block_pixel.copy_from_slice(&pixels[start..start + 4]);
+                    }
+                    compress_dxt1gcn_block(sub_block_pixels, sub_block);
+                }
+            }
+        }
+    }
+
     fn palette(&self) -> Option<TxtrPalette> {
         let (format, bytes, width, height) = match self {
             TxtrFormat::C4(fmt, bytes) => (fmt, &bytes[..], 1, 16),
@@ -157,12 +490,33 @@ pub enum TxtrPaletteFormat {
 }
 
 impl TxtrPaletteFormat {
-    fn from_u32(x: u32) -> Self {
+    fn from_u32(x: u32) -> Result<Self, String> {
         match x {
-            0x0 => TxtrPaletteFormat::Ia8,
-            0x1 => TxtrPaletteFormat::Rgb565,
-            0x2 => TxtrPaletteFormat::Rgb5A3,
-            _ => panic!("Invalid TXTR palette format {:#x}", x),
+            0x0 => Ok(TxtrPaletteFormat::Ia8),
+            0x1 => Ok(TxtrPaletteFormat::Rgb565),
+            0x2 => Ok(TxtrPaletteFormat::Rgb5A3),
+            _ => Err(format!("Invalid TXTR palette format {:#x}", x)),
+        }
+    }
+
+    /// Decodes a palette entry into an RGBA color.
+    pub fn decode(&self, entry: [u8; 2]) -> [u8; 4] {
+        match self {
+            TxtrPaletteFormat::Ia8 => decode_ia8(entry),
+            TxtrPaletteFormat::Rgb565 => {
+                let rgb = decode_rgb565(entry);
+                [rgb[0], rgb[1], rgb[2], 0xff]
+            }
+            TxtrPaletteFormat::Rgb5A3 => decode_rgb5a3(entry),
+        }
+    }
+
+    /// Encodes an RGBA color into a palette entry.
+    pub fn encode(&self, pixel: [u8; 4]) -> [u8; 2] {
+        match self {
+            TxtrPaletteFormat::Ia8 => encode_ia8(pixel),
+            TxtrPaletteFormat::Rgb565 => encode_rgb565([pixel[0], pixel[1], pixel[2]]),
+            TxtrPaletteFormat::Rgb5A3 => encode_rgb5a3(pixel),
         }
     }
 }
@@ -182,19 +536,384 @@ fn has_palette(format: u32) -> bool {
     matches!(format, 0x4..=0x6)
 }
 
-fn format_pixel_bytes(format: u32, pixels: usize) -> usize {
-    match format {
-        0x0 => pixels / 2,
-        0x1 => pixels,
-        0x2 => pixels,
-        0x3 => pixels * 2,
-        0x4 => pixels / 2,
-        0x5 => pixels,
-        0x6 => pixels * 2,
-        0x7 => pixels * 2,
-        0x8 => pixels * 2,
-        0x9 => pixels * 4,
-        0xA => pixels / 2,
-        _ => panic!(),
+impl<'r> Txtr<'r> {
+    /// Reads a TXTR, failing on an unsupported format or truncated data instead of panicking
+    /// like `Readable::read_from`.
+    pub fn try_read(reader: &mut Reader<'r>) -> Result<Self, String> {
+        let mut header = reader.clone();
+        if header.len() < 12 {
+            Err("TXTR header is truncated")?
+        }
+        let hdr_format: u32 = header.read(());
+        let width: u16 = header.read(());
+        let height: u16 = header.read(());
+        let mipmap_count: u32 = header.read(());
+
+        let palette: Option<TxtrPalette> = if has_palette(hdr_format) {
+            if header.len() < 8 {
+                Err("TXTR palette header is truncated")?
+            }
+            let mut palette_header = header.clone();
+            palette_header.advance(4);
+            let palette_width: u16 = palette_header.read(());
+            let palette_height: u16 = palette_header.read(());
+            if header.len() < 8 + palette_width as usize * palette_height as usize * 2 {
+                Err("TXTR palette is truncated")?
+            }
+            Some(header.read(()))
+        } else {
+            None
+        };
+        let format = TxtrFormat::new(hdr_format, &palette)?;
+
+        let pixel_data_size: usize = MipmapSizeIter::new(width, height, &format, mipmap_count)
+            .map(|(size, ())| size)
+            .sum();
+        if header.len() < pixel_data_size {
+            Err(format!(
+                "Expected {} bytes of pixel data, found {}",
+                pixel_data_size,
+                header.len()
+            ))?
+        }
+
+        Ok(reader.read(()))
     }
+
+    /// Decodes a mipmap into row-major RGBA pixels. Returns the pixels along with the width and
+    /// height of the mipmap.
+    pub fn to_rgba(&self, mipmap: usize) -> Result<(Vec<u8>, usize, usize), String> {
+        let mipmap_data: Vec<u8> = match self.pixel_data.iter().nth(mipmap) {
+            Some(mipmap_data) => mipmap_data.iter().map(|byte| *byte).collect(),
+            None => Err(format!(
+                "TXTR only contains {} mipmaps",
+                self.pixel_data.len()
+            ))?,
+        };
+
+        let w = self.width as usize >> mipmap;
+        let h = self.height as usize >> mipmap;
+        let (block_w, block_h) = self.format.block_dimensions();
+        if w % block_w != 0 || h % block_h != 0 {
+            Err(format!(
+                "Mipmap {} ({}x{}) is smaller than a block of the texture's format",
+                mipmap, w, h
+            ))?
+        }
+
+        Ok((self.format.decode_mipmap(&mipmap_data[..], w, h), w, h))
+    }
+
+    /// Encodes a TXTR from row-major RGBA mipmaps, given as `(pixels, width, height)` starting
+    /// with the full size image. Each mipmap must be half the size of the previous one. The
+    /// palette of paletted formats is computed from the colors of every mipmap.
+    pub fn from_rgba(
+        mut format: TxtrFormat,
+        mipmaps: Vec<(Vec<u8>, usize, usize)>,
+    ) -> Result<Txtr<'static>, String> {
+        let (w, h) = match mipmaps.first() {
+            Some((_, w, h)) => (*w, *h),
+            None => Err("A TXTR needs at least one mipmap".to_string())?,
+        };
+        if w > u16::MAX as usize || h > u16::MAX as usize {
+            Err(format!("Image dimensions ({}, {}) are too large", w, h))?
+        }
+
+        let (block_w, block_h) = format.block_dimensions();
+        for (i, (pixels, mip_w, mip_h)) in mipmaps.iter().enumerate() {
+            if (*mip_w, *mip_h) != (w >> i, h >> i) {
+                Err(format!(
+                    "Mipmap {} is {}x{}, expected {}x{}",
+                    i,
+                    mip_w,
+                    mip_h,
+                    w >> i,
+                    h >> i
+                ))?
+            }
+            if mip_w % block_w != 0 || mip_h % block_h != 0 || *mip_w == 0 || *mip_h == 0 {
+                Err(format!(
+                    "The width and height ({}, {}) of mipmap {} must be a multiple of the chosen \
+                     format's block dimensions ({}, {})",
+                    mip_w, mip_h, i, block_w, block_h
+                ))?
+            }
+            if pixels.len() != mip_w * mip_h * 4 {
+                Err(format!(
+                    "Expected {} bytes of RGBA data for mipmap {}, got {}",
+                    mip_w * mip_h * 4,
+                    i,
+                    pixels.len()
+                ))?
+            }
+        }
+
+        if format.is_paletted() {
+            let all_pixels: Vec<u8> = mipmaps
+                .iter()
+                .flat_map(|(pixels, _, _)| pixels.iter().copied())
+                .collect();
+            format.compute_palette(&all_pixels[..])?;
+        }
+
+        let pixel_data: Vec<LazyArray<'static, u8>> = mipmaps
+            .iter()
+            .map(|(pixels, w, h)| format.encode_mipmap(&pixels[..], *w, *h).into())
+            .collect();
+
+        Ok(Txtr {
+            format,
+            width: w as u16,
+            height: h as u16,
+            pixel_data: pixel_data.into(),
+        })
+    }
+}
+
+/// Halves the dimensions of a row-major image by averaging each 2x2 square of pixels. If
+/// `discretize_alpha` is set, any partially transparent pixel becomes fully opaque.
+pub fn box_filter_pixels(
+    pixels: &[u8],
+    w: usize,
+    h: usize,
+    chan_count: usize,
+    discretize_alpha: bool,
+) -> Vec<u8> {
+    let mut output = Vec::with_capacity(w * h * chan_count / 4);
+    for iy in 0..h / 2 {
+        for ix in 0..w / 2 {
+            let y = iy * 2;
+            let x = ix * 2;
+            for c in 0..chan_count {
+                output.push(
+                    (((pixels[(y * w + x) * chan_count + c] as u16)
+                        + (pixels[(y * w + x + 1) * chan_count + c] as u16)
+                        + (pixels[((y + 1) * w + x) * chan_count + c] as u16)
+                        + (pixels[((y + 1) * w + x + 1) * chan_count + c] as u16))
+                        / 4) as u8,
+                );
+                if discretize_alpha && c == chan_count - 1 {
+                    let last = output.last_mut().unwrap();
+                    if *last > 0 {
+                        *last = 0xff;
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+fn palette_indices(palette: &[u8]) -> HashMap<[u8; 2], u8> {
+    let mut map = HashMap::with_capacity(palette.len() / 2);
+    for (i, entry) in palette.chunks(2).enumerate() {
+        map.entry([entry[0], entry[1]]).or_insert(i as u8);
+    }
+    map
+}
+
+// Same weights as the `image` crate uses when converting to grayscale
+fn rgba_to_intensity(pixel: &[u8]) -> u8 {
+    ((pixel[0] as u32 * 2126 + pixel[1] as u32 * 7152 + pixel[2] as u32 * 722) / 10000) as u8
+}
+
+fn intensity_to_rgba(i: u8, a: u8) -> [u8; 4] {
+    [i, i, i, a]
+}
+
+// XXX The following conversion functions are borrowed from URDE https://github.com/AxioDL/urde/blob/master/DataSpec/DNACommon/TXTR.cpp
+fn convert3to8(v: u8) -> u8 {
+    (v << 5) | (v << 2) | (v >> 1)
+}
+
+fn convert4to8(v: u8) -> u8 {
+    (v << 4) | v
+}
+
+fn convert5to8(v: u8) -> u8 {
+    (v << 3) | (v >> 2)
+}
+
+fn convert6to8(v: u8) -> u8 {
+    (v << 2) | (v >> 4)
+}
+
+pub fn encode_ia8(pixel: [u8; 4]) -> [u8; 2] {
+    [pixel[3], rgba_to_intensity(&pixel)]
+}
+
+pub fn decode_ia8(texel: [u8; 2]) -> [u8; 4] {
+    intensity_to_rgba(texel[1], texel[0])
+}
+
+pub fn encode_rgb5a3(pixel: [u8; 4]) -> [u8; 2] {
+    let v = if pixel[3] == 0xff {
+        0x8000
+            | ((pixel[0] as u16 >> 3) << 10)
+            | ((pixel[1] as u16 >> 3) << 5)
+            | (pixel[2] as u16 >> 3)
+    } else {
+        ((pixel[0] as u16 >> 4) << 8)
+            | ((pixel[1] as u16 >> 4) << 4)
+            | (pixel[2] as u16 >> 4)
+            | ((pixel[3] as u16 >> 5) << 12)
+    };
+    v.to_be_bytes()
+}
+
+pub fn encode_rgb565(pixel: [u8; 3]) -> [u8; 2] {
+    let v = ((pixel[0] as u16 >> 3) << 11) | ((pixel[1] as u16 >> 2) << 5) | (pixel[2] as u16 >> 3);
+    v.to_be_bytes()
+}
+
+pub fn decode_rgb5a3(texel: [u8; 2]) -> [u8; 4] {
+    let v = u16::from_be_bytes(texel);
+    if v & 0x8000 != 0 {
+        [
+            convert5to8(((v >> 10) & 0x1f) as u8),
+            convert5to8(((v >> 5) & 0x1f) as u8),
+            convert5to8((v & 0x1f) as u8),
+            0xff,
+        ]
+    } else {
+        [
+            convert4to8(((v >> 8) & 0xf) as u8),
+            convert4to8(((v >> 4) & 0xf) as u8),
+            convert4to8((v & 0xf) as u8),
+            convert3to8((v >> 12 & 0x7) as u8),
+        ]
+    }
+}
+
+pub fn decode_rgb565(texel: [u8; 2]) -> [u8; 3] {
+    let v = u16::from_be_bytes(texel);
+    [
+        convert5to8(((v >> 11) & 0x1f) as u8),
+        convert6to8(((v >> 5) & 0x3f) as u8),
+        convert5to8((v & 0x1f) as u8),
+    ]
+}
+
+pub fn compress_dxt1gcn_block(rgba: [[u8; 4]; 16], block: &mut [u8]) {
+    Format::Bc1.compress_block_masked(
+        rgba,
+        0xFFFF,
+        texpresso::Params {
+            algorithm: texpresso::Algorithm::IterativeClusterFit,
+            ..Default::default()
+        },
+        block,
+    );
+    block.swap(0, 1);
+    block.swap(2, 3);
+    for byte in block[4..8].iter_mut() {
+        *byte = reverse_byte(*byte);
+    }
+}
+
+pub fn decompress_dxt1gcn_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut compressed = [0u8; 8];
+    compressed[0] = block[1];
+    compressed[1] = block[0];
+    compressed[2] = block[3];
+    compressed[3] = block[2];
+    for (byte, val) in compressed[4..8].iter_mut().zip(block[4..8].iter()) {
+        *byte = reverse_byte(*val);
+    }
+    Format::Bc1.decompress_block(&compressed)
+}
+
+fn reverse_byte(byte: u8) -> u8 {
+    (byte & 0b00000011) << 6
+        | (byte & 0b00001100) << 2
+        | (byte & 0b00110000) >> 2
+        | (byte & 0b11000000) >> 6
+}
+
+// A 16x16 image made of 4x4 squares of distinct colors, so that every row differs and block
+// compression is lossless apart from quantization
+#[cfg(test)]
+fn test_image(gray: bool, translucent: bool) -> Vec<u8> {
+    let mut pixels = vec![];
+    for y in 0..16 {
+        for x in 0..16 {
+            let (bx, by) = (x / 4, y / 4);
+            let v = (by * 4 + bx) as u8 * 17;
+            let a = if translucent && bx % 2 == 1 {
+                0x60
+            } else {
+                0xff
+            };
+            if gray {
+                pixels.extend_from_slice(&[v, v, v, a]);
+            } else {
+                pixels.extend_from_slice(&[v, 0xff - v, by as u8 * 85, a]);
+            }
+        }
+    }
+    pixels
+}
+
+#[test]
+fn test_txtr_format_round_trip() {
+    use reader_writer::Writable;
+
+    let c4 = |fmt| TxtrFormat::C4(fmt, Default::default());
+    let c8 = |fmt| TxtrFormat::C8(fmt, Default::default());
+    // (format, grayscale image, translucent image, max error per channel)
+    let cases = [
+        (TxtrFormat::I4, true, false, 16),
+        (TxtrFormat::I8, true, false, 0),
+        (TxtrFormat::Ia4, true, true, 16),
+        (TxtrFormat::Ia8, true, true, 0),
+        (TxtrFormat::Rgb565, false, false, 7),
+        (TxtrFormat::Rgb5A3, false, false, 7),
+        (TxtrFormat::Rgb5A3, false, true, 36),
+        (TxtrFormat::Rgba8, false, true, 0),
+        (c4(TxtrPaletteFormat::Ia8), true, true, 0),
+        (c4(TxtrPaletteFormat::Rgb565), false, false, 7),
+        (c8(TxtrPaletteFormat::Rgb5A3), false, true, 36),
+        (TxtrFormat::Cmpr, false, false, 7),
+    ];
+    for (format, gray, translucent, max_error) in cases {
+        let pixels = test_image(gray, translucent);
+        let txtr = Txtr::from_rgba(format.clone(), vec![(pixels.clone(), 16, 16)]).unwrap();
+
+        let mut bytes = vec![];
+        txtr.write_to(&mut bytes).unwrap();
+        let txtr = Txtr::try_read(&mut Reader::new(&bytes[..])).unwrap();
+        let (decoded, w, h) = txtr.to_rgba(0).unwrap();
+        assert_eq!((w, h), (16, 16));
+
+        for (i, (a, b)) in pixels.iter().zip(decoded.iter()).enumerate() {
+            assert!(
+                (*a as i32 - *b as i32).abs() <= max_error,
+                "{:?}: byte {} of pixel ({}, {}) decoded as {}, expected {}",
+                format,
+                i % 4,
+                i / 4 % 16,
+                i / 64,
+                b,
+                a
+            );
+        }
+
+        // Decoded pixels are exactly representable, so encoding them again is lossless
+        let again = Txtr::from_rgba(format.clone(), vec![(decoded.clone(), 16, 16)]).unwrap();
+        assert_eq!(again.to_rgba(0).unwrap().0, decoded, "{:?}", format);
+    }
+}
+
+#[test]
+fn test_txtr_try_read_errors() {
+    // Format 0x6 (C14X2) isn't supported
+    let mut bytes = vec![0, 0, 0, 6, 0, 8, 0, 8, 0, 0, 0, 1];
+    assert!(Txtr::try_read(&mut Reader::new(&bytes[..])).is_err());
+
+    // An 8x8 I8 texture needs 64 bytes of pixel data
+    bytes[3] = 1;
+    bytes.extend_from_slice(&[0; 63]);
+    assert!(Txtr::try_read(&mut Reader::new(&bytes[..])).is_err());
+    bytes.push(0);
+    assert!(Txtr::try_read(&mut Reader::new(&bytes[..])).is_ok());
 }